@@ -25,6 +25,7 @@ pub const SCOPE_FS_DELETE: &str = "fs:delete";
 pub const SCOPE_FS_WATCH: &str = "fs:watch";
 pub const SCOPE_FS_UPLOAD: &str = "fs:upload";
 pub const SCOPE_PUSH_REGISTER: &str = "push:register";
+pub const SCOPE_FS_CONFIG: &str = "fs:config";
 
 pub fn default_scopes() -> Vec<String> {
     [
@@ -43,6 +44,10 @@ pub fn default_scopes() -> Vec<String> {
     .collect()
 }
 
+/// Scopes that are never part of a new pairing and must be granted with
+/// `mobilecli credentials grant`.
+pub const EXPLICIT_SCOPES: &[&str] = &[SCOPE_FS_CONFIG];
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AuthCredential {
     pub credential_id: String,
@@ -66,6 +71,15 @@ impl AuthCredential {
             self.revoked_at = Some(chrono::Utc::now().to_rfc3339());
         }
     }
+
+    /// Add `scope`, returning false if the credential already had it.
+    pub fn grant_scope(&mut self, scope: &str) -> bool {
+        if self.scopes.iter().any(|s| s == scope) {
+            return false;
+        }
+        self.scopes.push(scope.to_string());
+        true
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -218,6 +232,23 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn new_pairings_need_an_explicit_grant_for_fs_config() {
+        let mut credential = generate_pairing_credential("phone").credential;
+        assert!(!credential.scopes.iter().any(|s| s == SCOPE_FS_CONFIG));
+
+        assert!(credential.grant_scope(SCOPE_FS_CONFIG));
+        assert!(!credential.grant_scope(SCOPE_FS_CONFIG));
+        assert_eq!(
+            credential
+                .scopes
+                .iter()
+                .filter(|s| *s == SCOPE_FS_CONFIG)
+                .count(),
+            1
+        );
+    }
+
     #[test]
     fn verifier_derivation_is_deterministic_and_token_bound() {
         let token = "sample-token";
@@ -2,52 +2,75 @@
 //!
 //! This is intentionally best-effort: if systemd/launchd commands are unavailable,
 //! we still write the service file and print manual next steps.
+//!
+//! On Linux, systemd user units are preferred. When `systemctl --user` does not
+//! work (Void, Alpine, Gentoo, ...), a runit, OpenRC, or s6 service is written
+//! instead and the enable commands are printed.
 
 use crate::{daemon, platform};
 use clap::Subcommand;
 use colored::Colorize;
+use serde::Serialize;
 use std::path::PathBuf;
 use std::process::{Command, Stdio};
 
 #[derive(Subcommand, Debug, Clone)]
 pub enum AutostartCommand {
     /// Install and enable daemon autostart on login
-    Install,
+    Install {
+        /// Port the autostarted daemon listens on
+        #[arg(short, long, default_value_t = daemon::DEFAULT_PORT)]
+        port: u16,
+    },
     /// Disable and remove daemon autostart
     Uninstall,
     /// Show whether autostart is installed and whether the daemon is currently running
-    Status,
+    Status {
+        /// Print machine-readable JSON instead of human-readable text
+        #[arg(long)]
+        json: bool,
+    },
 }
 
 pub fn run(cmd: AutostartCommand) -> Result<(), Box<dyn std::error::Error>> {
     match cmd {
-        AutostartCommand::Install => install(),
+        AutostartCommand::Install { port } => install(port),
         AutostartCommand::Uninstall => uninstall(),
-        AutostartCommand::Status => status(),
+        AutostartCommand::Status { json: false } => status(),
+        AutostartCommand::Status { json: true } => {
+            println!("{}", serde_json::to_string_pretty(&collect_status())?);
+            Ok(())
+        }
     }
 }
 
-fn install() -> Result<(), Box<dyn std::error::Error>> {
+fn install(port: u16) -> Result<(), Box<dyn std::error::Error>> {
     #[cfg(target_os = "linux")]
     {
-        install_systemd_user()?;
+        match detect_linux_init() {
+            LinuxInit::Systemd => install_systemd_user(port)?,
+            LinuxInit::Runit => install_runit_service(port)?,
+            LinuxInit::OpenRc => install_openrc_service(port)?,
+            LinuxInit::S6 => install_s6_service(port)?,
+        }
         Ok(())
     }
 
     #[cfg(target_os = "macos")]
     {
-        install_launchd_agent()?;
+        install_launchd_agent(port)?;
         Ok(())
     }
 
     #[cfg(target_os = "windows")]
     {
-        install_windows_task()?;
+        install_windows_task(port)?;
         Ok(())
     }
 
     #[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
     {
+        let _ = port;
         eprintln!("{} Autostart is unsupported on this OS.", "✗".red());
         Ok(())
     }
@@ -56,7 +79,11 @@ fn install() -> Result<(), Box<dyn std::error::Error>> {
 fn uninstall() -> Result<(), Box<dyn std::error::Error>> {
     #[cfg(target_os = "linux")]
     {
-        uninstall_systemd_user()?;
+        let init = detect_linux_init();
+        if init == LinuxInit::Systemd || systemd_unit_path().exists() {
+            uninstall_systemd_user()?;
+        }
+        uninstall_alt_init_services(init)?;
         Ok(())
     }
 
@@ -93,6 +120,15 @@ fn status() -> Result<(), Box<dyn std::error::Error>> {
         if unit_path.exists() {
             println!("{} Autostart: installed (systemd user unit)", "✓".green());
             println!("  Unit: {}", unit_path.display().to_string().dimmed());
+            print_service_port(&unit_path);
+        } else if let Some((init, path)) = installed_alt_init_service() {
+            println!(
+                "{} Autostart: installed ({} service)",
+                "✓".green(),
+                init.as_str()
+            );
+            println!("  Service: {}", path.display().to_string().dimmed());
+            print_service_port(&path);
         } else {
             println!("{} Autostart: not installed", "○".dimmed());
         }
@@ -104,6 +140,7 @@ fn status() -> Result<(), Box<dyn std::error::Error>> {
         if plist_path.exists() {
             println!("{} Autostart: installed (launchd agent)", "✓".green());
             println!("  Plist: {}", plist_path.display().to_string().dimmed());
+            print_service_port(&plist_path);
         } else {
             println!("{} Autostart: not installed", "○".dimmed());
         }
@@ -115,8 +152,9 @@ fn status() -> Result<(), Box<dyn std::error::Error>> {
         let script_path = windows_task_script_path();
         if task_exists {
             println!("{} Autostart: installed (Task Scheduler)", "✓".green());
-            println!("  Task: {}", WINDOWS_TASK_NAME.cyan());
+            println!("  Task: {}", windows_task_name().cyan());
             println!("  Script: {}", script_path.display().to_string().dimmed());
+            print_service_port(&script_path);
             if !script_path.exists() {
                 println!(
                     "{} Startup script is missing. Re-run {} to repair.",
@@ -137,15 +175,116 @@ fn status() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+/// Machine-readable autostart state for `autostart status --json`.
+#[derive(Debug, Serialize)]
+struct AutostartStatus {
+    daemon_running: bool,
+    autostart_installed: bool,
+    /// `systemd`, `runit`, `openrc`, `s6`, `launchd`, or `task_scheduler`.
+    mechanism: Option<&'static str>,
+    unit_path: Option<String>,
+    port: Option<u16>,
+}
+
+fn collect_status() -> AutostartStatus {
+    let installed = installed_service();
+    let port = installed.as_ref().and_then(|(_, path)| {
+        std::fs::read_to_string(path)
+            .ok()
+            .and_then(|content| parse_service_port(&content))
+    });
+    AutostartStatus {
+        daemon_running: daemon::is_running(),
+        autostart_installed: installed.is_some(),
+        mechanism: installed.as_ref().map(|(mechanism, _)| *mechanism),
+        unit_path: installed.map(|(_, path)| path.display().to_string()),
+        port,
+    }
+}
+
+/// The installed autostart mechanism and its service file, if any.
+fn installed_service() -> Option<(&'static str, PathBuf)> {
+    #[cfg(target_os = "linux")]
+    {
+        let unit_path = systemd_unit_path();
+        if unit_path.exists() {
+            return Some(("systemd", unit_path));
+        }
+        installed_alt_init_service().map(|(init, path)| (init.mechanism(), path))
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        let plist_path = launchd_plist_path();
+        plist_path.exists().then_some(("launchd", plist_path))
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        windows_task_exists().then(|| ("task_scheduler", windows_task_script_path()))
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+    {
+        None
+    }
+}
+
+/// Service name for this instance, so named instances get separate units
+/// instead of overwriting the default one.
+#[cfg(target_os = "linux")]
+fn service_name() -> String {
+    match platform::instance_name() {
+        Some(name) => format!("mobilecli-{}", name),
+        None => "mobilecli".to_string(),
+    }
+}
+
+/// Extra `daemon` arguments that pin an autostarted daemon to this instance.
+#[cfg_attr(
+    not(any(target_os = "linux", target_os = "macos", target_os = "windows")),
+    allow(dead_code)
+)]
+fn instance_args() -> Vec<String> {
+    platform::instance_name()
+        .map(|name| vec!["--instance".to_string(), name])
+        .unwrap_or_default()
+}
+
+/// Read the `daemon --port` argument back out of a generated service file.
+fn parse_service_port(content: &str) -> Option<u16> {
+    let rest = &content[content.find("--port")? + "--port".len()..];
+    let digits: String = rest
+        .trim_start_matches(|c: char| !c.is_ascii_digit())
+        .chars()
+        .take_while(|c| c.is_ascii_digit())
+        .collect();
+    digits.parse().ok()
+}
+
+#[cfg_attr(
+    not(any(target_os = "linux", target_os = "macos", target_os = "windows")),
+    allow(dead_code)
+)]
+fn print_service_port(path: &std::path::Path) {
+    let port = std::fs::read_to_string(path)
+        .ok()
+        .and_then(|content| parse_service_port(&content));
+    match port {
+        Some(port) => println!("  Port: {}", port.to_string().cyan()),
+        None => println!("  Port: {}", "unknown".dimmed()),
+    }
+}
+
 #[cfg(target_os = "linux")]
 fn systemd_unit_path() -> PathBuf {
     platform::home_dir()
         .unwrap_or_else(|| PathBuf::from("."))
-        .join(".config/systemd/user/mobilecli.service")
+        .join(format!(".config/systemd/user/{}.service", service_name()))
 }
 
 #[cfg(target_os = "linux")]
-fn install_systemd_user() -> Result<(), Box<dyn std::error::Error>> {
+fn install_systemd_user(port: u16) -> Result<(), Box<dyn std::error::Error>> {
     let unit_path = systemd_unit_path();
     if let Some(parent) = unit_path.parent() {
         std::fs::create_dir_all(parent)?;
@@ -157,9 +296,13 @@ fn install_systemd_user() -> Result<(), Box<dyn std::error::Error>> {
     let log_file = log_dir.join("daemon.log");
 
     let unit = format!(
-        "[Unit]\nDescription=MobileCLI daemon\nAfter=network-online.target\nWants=network-online.target\n\n[Service]\nExecStart={} daemon --port {}\nRestart=always\nRestartSec=2\nStandardOutput=append:{}\nStandardError=append:{}\n\n[Install]\nWantedBy=default.target\n",
+        "[Unit]\nDescription=MobileCLI daemon\nAfter=network-online.target\nWants=network-online.target\n\n[Service]\nExecStart={} daemon --port {}{}\nRestart=always\nRestartSec=2\nStandardOutput=append:{}\nStandardError=append:{}\n\n[Install]\nWantedBy=default.target\n",
         exe.display(),
-        daemon::DEFAULT_PORT,
+        port,
+        instance_args()
+            .iter()
+            .map(|arg| format!(" {}", arg))
+            .collect::<String>(),
         log_file.display(),
         log_file.display(),
     );
@@ -180,6 +323,7 @@ fn install_systemd_user() -> Result<(), Box<dyn std::error::Error>> {
         .status()
         .is_ok();
 
+    let unit_name = format!("{}.service", service_name());
     if has_systemctl {
         let _ = Command::new("systemctl")
             .arg("--user")
@@ -189,13 +333,13 @@ fn install_systemd_user() -> Result<(), Box<dyn std::error::Error>> {
             .arg("--user")
             .arg("enable")
             .arg("--now")
-            .arg("mobilecli.service")
+            .arg(&unit_name)
             .status();
 
         println!("{} Enabled autostart via systemd", "✓".green());
         println!(
             "  Check: {}",
-            "systemctl --user status mobilecli.service".cyan()
+            format!("systemctl --user status {}", unit_name).cyan()
         );
     } else {
         println!(
@@ -205,7 +349,7 @@ fn install_systemd_user() -> Result<(), Box<dyn std::error::Error>> {
         println!("  Run: {}", "systemctl --user daemon-reload".cyan());
         println!(
             "  Run: {}",
-            "systemctl --user enable --now mobilecli.service".cyan()
+            format!("systemctl --user enable --now {}", unit_name).cyan()
         );
     }
 
@@ -221,7 +365,7 @@ fn uninstall_systemd_user() -> Result<(), Box<dyn std::error::Error>> {
         .arg("--user")
         .arg("disable")
         .arg("--now")
-        .arg("mobilecli.service")
+        .arg(format!("{}.service", service_name()))
         .status();
 
     if unit_path.exists() {
@@ -239,18 +383,300 @@ fn uninstall_systemd_user() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+/// Init system used to autostart the daemon on Linux.
+#[cfg(target_os = "linux")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LinuxInit {
+    Systemd,
+    Runit,
+    OpenRc,
+    S6,
+}
+
+#[cfg(target_os = "linux")]
+impl LinuxInit {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Systemd => "systemd",
+            Self::Runit => "runit",
+            Self::OpenRc => "OpenRC",
+            Self::S6 => "s6",
+        }
+    }
+
+    /// Stable identifier used in `autostart status --json`.
+    fn mechanism(self) -> &'static str {
+        match self {
+            Self::OpenRc => "openrc",
+            other => other.as_str(),
+        }
+    }
+}
+
+/// Prefer systemd whenever the user manager answers; otherwise fall back to
+/// whichever alternative init tooling is installed.
+#[cfg(target_os = "linux")]
+fn detect_linux_init() -> LinuxInit {
+    let systemd_user = Command::new("systemctl")
+        .args(["--user", "show-environment"])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map(|s| s.success())
+        .unwrap_or(false);
+    if systemd_user {
+        LinuxInit::Systemd
+    } else if std::path::Path::new("/sbin/runit").exists() || which::which("runsvdir").is_ok() {
+        LinuxInit::Runit
+    } else if which::which("rc-service").is_ok() {
+        LinuxInit::OpenRc
+    } else if which::which("s6-rc").is_ok() {
+        LinuxInit::S6
+    } else {
+        // Nothing recognizable: write a systemd unit and print manual steps.
+        LinuxInit::Systemd
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn runit_service_dir() -> PathBuf {
+    platform::home_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join(".config/sv")
+        .join(service_name())
+}
+
+#[cfg(target_os = "linux")]
+fn openrc_script_path() -> PathBuf {
+    platform::home_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join(".config/rc/init.d")
+        .join(service_name())
+}
+
+#[cfg(target_os = "linux")]
+fn s6_service_dir() -> PathBuf {
+    platform::home_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join(".config/s6/sv")
+        .join(service_name())
+}
+
+#[cfg(target_os = "linux")]
+fn installed_alt_init_service() -> Option<(LinuxInit, PathBuf)> {
+    [
+        (LinuxInit::Runit, runit_service_dir().join("run")),
+        (LinuxInit::OpenRc, openrc_script_path()),
+        (LinuxInit::S6, s6_service_dir().join("run")),
+    ]
+    .into_iter()
+    .find(|(_, path)| path.exists())
+}
+
+#[cfg(target_os = "linux")]
+fn write_executable(path: &std::path::Path, content: &str) -> std::io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, content)?;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755))
+}
+
+#[cfg(target_os = "linux")]
+fn daemon_exec_line(port: u16) -> Result<String, Box<dyn std::error::Error>> {
+    let exe = std::env::current_exe()?;
+    let log_dir = platform::config_dir();
+    std::fs::create_dir_all(&log_dir)?;
+    Ok(format!(
+        "exec \"{}\" daemon --port {}{} >>\"{}\" 2>&1",
+        exe.display(),
+        port,
+        instance_args()
+            .iter()
+            .map(|arg| format!(" {}", arg))
+            .collect::<String>(),
+        log_dir.join("daemon.log").display(),
+    ))
+}
+
+#[cfg(target_os = "linux")]
+fn install_runit_service(port: u16) -> Result<(), Box<dyn std::error::Error>> {
+    let dir = runit_service_dir();
+    let run_path = dir.join("run");
+    write_executable(
+        &run_path,
+        &format!("#!/bin/sh\n{}\n", daemon_exec_line(port)?),
+    )?;
+    println!("{} Wrote runit service: {}", "✓".green(), dir.display());
+    println!(
+        "  Enable it in your per-user runsvdir (e.g. {}):",
+        "~/service".cyan()
+    );
+    println!(
+        "  Run: {}",
+        format!("ln -s {} ~/service/", dir.display()).cyan()
+    );
+    println!(
+        "  Check: {}",
+        format!("sv status ~/service/{}", service_name()).cyan()
+    );
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+fn install_openrc_service(port: u16) -> Result<(), Box<dyn std::error::Error>> {
+    let exe = std::env::current_exe()?;
+    let log_dir = platform::config_dir();
+    std::fs::create_dir_all(&log_dir)?;
+    let log_file = log_dir.join("daemon.log");
+    let script_path = openrc_script_path();
+    let name = service_name();
+    let script = format!(
+        "#!/sbin/openrc-run\n\ndescription=\"MobileCLI daemon\"\ncommand=\"{}\"\ncommand_args=\"daemon --port {}{}\"\ncommand_background=true\npidfile=\"${{RC_SVCDIR:-/tmp}}/{}.pid\"\noutput_log=\"{}\"\nerror_log=\"{}\"\n\ndepend() {{\n    need net\n}}\n",
+        exe.display(),
+        port,
+        instance_args()
+            .iter()
+            .map(|arg| format!(" {}", arg))
+            .collect::<String>(),
+        name,
+        log_file.display(),
+        log_file.display(),
+    );
+    write_executable(&script_path, &script)?;
+    println!(
+        "{} Wrote OpenRC user service: {}",
+        "✓".green(),
+        script_path.display()
+    );
+    println!(
+        "  Run: {}",
+        format!("rc-update --user add {} default", name).cyan()
+    );
+    println!(
+        "  Run: {}",
+        format!("rc-service --user {} start", name).cyan()
+    );
+    println!(
+        "  Without OpenRC user services, copy it to {} and use {} instead.",
+        format!("/etc/init.d/{}", name).cyan(),
+        format!("sudo rc-update add {} default", name).cyan()
+    );
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+fn install_s6_service(port: u16) -> Result<(), Box<dyn std::error::Error>> {
+    let dir = s6_service_dir();
+    std::fs::create_dir_all(&dir)?;
+    std::fs::write(dir.join("type"), "longrun\n")?;
+    write_executable(
+        &dir.join("run"),
+        &format!("#!/bin/sh\n{}\n", daemon_exec_line(port)?),
+    )?;
+    println!("{} Wrote s6-rc service: {}", "✓".green(), dir.display());
+    println!("  Add it to your user s6-rc source directory and recompile, e.g.:");
+    println!(
+        "  Run: {}",
+        format!(
+            "s6-rc-compile <compiled-dir> {}",
+            dir.parent().unwrap_or(&dir).display()
+        )
+        .cyan()
+    );
+    println!(
+        "  Run: {}",
+        format!("s6-rc -u change {}", service_name()).cyan()
+    );
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+fn uninstall_alt_init_services(init: LinuxInit) -> Result<(), Box<dyn std::error::Error>> {
+    // Best-effort stop for the active init before removing files.
+    let name = service_name();
+    match init {
+        LinuxInit::Runit => {
+            let _ = Command::new("sv")
+                .args(["down", &name])
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .status();
+        }
+        LinuxInit::OpenRc => {
+            let _ = Command::new("rc-service")
+                .args(["--user", &name, "stop"])
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .status();
+            let _ = Command::new("rc-update")
+                .args(["--user", "del", &name])
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .status();
+        }
+        LinuxInit::S6 => {
+            let _ = Command::new("s6-rc")
+                .args(["-d", "change", &name])
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .status();
+        }
+        LinuxInit::Systemd => {}
+    }
+
+    let runit_dir = runit_service_dir();
+    if runit_dir.exists() {
+        std::fs::remove_dir_all(&runit_dir)?;
+        println!(
+            "{} Removed runit service: {}",
+            "✓".green(),
+            runit_dir.display()
+        );
+    }
+    let openrc_script = openrc_script_path();
+    if openrc_script.exists() {
+        std::fs::remove_file(&openrc_script)?;
+        println!(
+            "{} Removed OpenRC service: {}",
+            "✓".green(),
+            openrc_script.display()
+        );
+    }
+    let s6_dir = s6_service_dir();
+    if s6_dir.exists() {
+        std::fs::remove_dir_all(&s6_dir)?;
+        println!("{} Removed s6 service: {}", "✓".green(), s6_dir.display());
+    }
+    if init == LinuxInit::Runit {
+        println!(
+            "{} Remove the runsvdir link too if you created one: {}",
+            "·".dimmed(),
+            format!("rm ~/service/{}", name).cyan()
+        );
+    }
+
+    Ok(())
+}
+
 #[cfg(target_os = "macos")]
-const LAUNCHD_LABEL: &str = "com.mobilecli.daemon";
+fn launchd_label() -> String {
+    match platform::instance_name() {
+        Some(name) => format!("com.mobilecli.daemon.{}", name),
+        None => "com.mobilecli.daemon".to_string(),
+    }
+}
 
 #[cfg(target_os = "macos")]
 fn launchd_plist_path() -> PathBuf {
     platform::home_dir()
         .unwrap_or_else(|| PathBuf::from("."))
-        .join(format!("Library/LaunchAgents/{}.plist", LAUNCHD_LABEL))
+        .join(format!("Library/LaunchAgents/{}.plist", launchd_label()))
 }
 
 #[cfg(target_os = "macos")]
-fn install_launchd_agent() -> Result<(), Box<dyn std::error::Error>> {
+fn install_launchd_agent(port: u16) -> Result<(), Box<dyn std::error::Error>> {
     let plist_path = launchd_plist_path();
     if let Some(parent) = plist_path.parent() {
         std::fs::create_dir_all(parent)?;
@@ -272,7 +698,7 @@ fn install_launchd_agent() -> Result<(), Box<dyn std::error::Error>> {
     <string>{exe}</string>
     <string>daemon</string>
     <string>--port</string>
-    <string>{port}</string>
+    <string>{port}</string>{instance}
   </array>
   <key>RunAtLoad</key><true/>
   <key>KeepAlive</key><true/>
@@ -281,9 +707,13 @@ fn install_launchd_agent() -> Result<(), Box<dyn std::error::Error>> {
 </dict>
 </plist>
 "#,
-        label = LAUNCHD_LABEL,
+        label = launchd_label(),
         exe = exe.display(),
-        port = daemon::DEFAULT_PORT,
+        port = port,
+        instance = instance_args()
+            .iter()
+            .map(|arg| format!("\n    <string>{}</string>", arg))
+            .collect::<String>(),
         log = log_file.display(),
     );
 
@@ -297,7 +727,7 @@ fn install_launchd_agent() -> Result<(), Box<dyn std::error::Error>> {
     // Best-effort enable using modern launchctl first, then legacy fallback.
     let mut enabled = false;
     if let Some(gui_domain) = macos_gui_domain() {
-        let service = format!("{}/{}", gui_domain, LAUNCHD_LABEL);
+        let service = format!("{}/{}", gui_domain, launchd_label());
         let plist_arg = plist_path.to_string_lossy().to_string();
 
         let _ = Command::new("launchctl")
@@ -350,7 +780,7 @@ fn uninstall_launchd_agent() -> Result<(), Box<dyn std::error::Error>> {
     let plist_path = launchd_plist_path();
 
     if let Some(gui_domain) = macos_gui_domain() {
-        let service = format!("{}/{}", gui_domain, LAUNCHD_LABEL);
+        let service = format!("{}/{}", gui_domain, launchd_label());
         let _ = Command::new("launchctl")
             .args(["bootout", &service])
             .status();
@@ -390,7 +820,12 @@ fn macos_gui_domain() -> Option<String> {
 }
 
 #[cfg(target_os = "windows")]
-const WINDOWS_TASK_NAME: &str = "MobileCLIDaemon";
+fn windows_task_name() -> String {
+    match platform::instance_name() {
+        Some(name) => format!("MobileCLIDaemon-{}", name),
+        None => "MobileCLIDaemon".to_string(),
+    }
+}
 
 #[cfg(target_os = "windows")]
 fn windows_task_script_path() -> PathBuf {
@@ -405,19 +840,23 @@ fn escape_powershell_single_quoted(input: &str) -> String {
 }
 
 #[cfg(target_os = "windows")]
-fn build_windows_autostart_script(exe_path: &std::path::Path) -> String {
+fn build_windows_autostart_script(exe_path: &std::path::Path, port: u16) -> String {
     let exe = escape_powershell_single_quoted(&exe_path.to_string_lossy());
     format!(
-        "$ErrorActionPreference = 'SilentlyContinue'\n$exe = '{exe}'\nif (-not (Test-Path -LiteralPath $exe)) {{ exit 1 }}\n$arguments = @('daemon', '--port', '{port}')\nStart-Process -FilePath $exe -ArgumentList $arguments -WindowStyle Hidden\n",
+        "$ErrorActionPreference = 'SilentlyContinue'\n$exe = '{exe}'\nif (-not (Test-Path -LiteralPath $exe)) {{ exit 1 }}\n$arguments = @('daemon', '--port', '{port}'{instance})\nStart-Process -FilePath $exe -ArgumentList $arguments -WindowStyle Hidden\n",
         exe = exe,
-        port = daemon::DEFAULT_PORT,
+        port = port,
+        instance = instance_args()
+            .iter()
+            .map(|arg| format!(", '{}'", escape_powershell_single_quoted(arg)))
+            .collect::<String>(),
     )
 }
 
 #[cfg(target_os = "windows")]
 fn windows_task_exists() -> bool {
     Command::new("schtasks")
-        .args(["/Query", "/TN", WINDOWS_TASK_NAME])
+        .args(["/Query", "/TN", &windows_task_name()])
         .stdout(Stdio::null())
         .stderr(Stdio::null())
         .status()
@@ -437,7 +876,7 @@ fn schtasks_available() -> bool {
 }
 
 #[cfg(target_os = "windows")]
-fn install_windows_task() -> Result<(), Box<dyn std::error::Error>> {
+fn install_windows_task(port: u16) -> Result<(), Box<dyn std::error::Error>> {
     if !schtasks_available() {
         return Err("Windows Task Scheduler (schtasks.exe) is not available".into());
     }
@@ -448,7 +887,7 @@ fn install_windows_task() -> Result<(), Box<dyn std::error::Error>> {
     }
 
     let exe = std::env::current_exe()?;
-    let script = build_windows_autostart_script(&exe);
+    let script = build_windows_autostart_script(&exe, port);
     std::fs::write(&script_path, script)?;
     println!(
         "{} Wrote startup script: {}",
@@ -461,17 +900,10 @@ fn install_windows_task() -> Result<(), Box<dyn std::error::Error>> {
         script_path.display()
     );
 
+    let task_name = windows_task_name();
     let create = Command::new("schtasks")
         .args([
-            "/Create",
-            "/F",
-            "/SC",
-            "ONLOGON",
-            "/RL",
-            "LIMITED",
-            "/TN",
-            WINDOWS_TASK_NAME,
-            "/TR",
+            "/Create", "/F", "/SC", "ONLOGON", "/RL", "LIMITED", "/TN", &task_name, "/TR",
             &task_cmd,
         ])
         .output()?;
@@ -485,7 +917,7 @@ fn install_windows_task() -> Result<(), Box<dyn std::error::Error>> {
 
     // Best-effort run now so users get a daemon immediately without relogin.
     let _ = Command::new("schtasks")
-        .args(["/Run", "/TN", WINDOWS_TASK_NAME])
+        .args(["/Run", "/TN", &task_name])
         .stdout(Stdio::null())
         .stderr(Stdio::null())
         .status();
@@ -496,7 +928,7 @@ fn install_windows_task() -> Result<(), Box<dyn std::error::Error>> {
     );
     println!(
         "  Check: {}",
-        format!("schtasks /Query /TN {}", WINDOWS_TASK_NAME).cyan()
+        format!("schtasks /Query /TN {}", task_name).cyan()
     );
 
     Ok(())
@@ -504,15 +936,16 @@ fn install_windows_task() -> Result<(), Box<dyn std::error::Error>> {
 
 #[cfg(target_os = "windows")]
 fn uninstall_windows_task() -> Result<(), Box<dyn std::error::Error>> {
+    let task_name = windows_task_name();
     if windows_task_exists() {
         let delete = Command::new("schtasks")
-            .args(["/Delete", "/TN", WINDOWS_TASK_NAME, "/F"])
+            .args(["/Delete", "/TN", &task_name, "/F"])
             .output()?;
         if delete.status.success() {
             println!(
                 "{} Removed scheduled task: {}",
                 "✓".green(),
-                WINDOWS_TASK_NAME.cyan()
+                task_name.cyan()
             );
         } else {
             let stderr = String::from_utf8_lossy(&delete.stderr).trim().to_string();
@@ -528,7 +961,7 @@ fn uninstall_windows_task() -> Result<(), Box<dyn std::error::Error>> {
         println!(
             "{} Scheduled task not found: {}",
             "○".dimmed(),
-            WINDOWS_TASK_NAME.cyan()
+            task_name.cyan()
         );
     }
 
@@ -553,3 +986,30 @@ fn uninstall_windows_task() -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::parse_service_port;
+
+    #[test]
+    fn service_port_is_read_back_from_each_service_format() {
+        assert_eq!(
+            parse_service_port("ExecStart=/usr/bin/mobilecli daemon --port 9900\n"),
+            Some(9900)
+        );
+        assert_eq!(
+            parse_service_port(
+                "<string>daemon</string>\n    <string>--port</string>\n    <string>9848</string>"
+            ),
+            Some(9848)
+        );
+        assert_eq!(
+            parse_service_port("$arguments = @('daemon', '--port', '9847')"),
+            Some(9847)
+        );
+        assert_eq!(
+            parse_service_port("ExecStart=/usr/bin/mobilecli daemon"),
+            None
+        );
+    }
+}
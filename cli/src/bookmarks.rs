@@ -0,0 +1,158 @@
+//! Directory bookmarks
+//!
+//! User favorites shared by every paired device. Unlike allowed roots these
+//! grant no access; a bookmark outside the current roots simply fails to open.
+
+use crate::platform;
+use crate::protocol::Bookmark;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Serializes load-modify-save cycles from concurrent clients.
+static BOOKMARKS_LOCK: Mutex<()> = Mutex::new(());
+
+/// Most bookmarks kept; adding another path past this fails.
+pub const MAX_BOOKMARKS: usize = 200;
+/// Labels are cut to this many characters.
+const MAX_LABEL_CHARS: usize = 80;
+
+#[derive(Debug)]
+pub enum BookmarkError {
+    /// Already at `MAX_BOOKMARKS`.
+    Full,
+    Io(std::io::Error),
+}
+
+impl std::fmt::Display for BookmarkError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BookmarkError::Full => write!(f, "Bookmark limit reached ({})", MAX_BOOKMARKS),
+            BookmarkError::Io(e) => e.fmt(f),
+        }
+    }
+}
+
+impl From<std::io::Error> for BookmarkError {
+    fn from(e: std::io::Error) -> Self {
+        BookmarkError::Io(e)
+    }
+}
+
+/// Get the bookmarks file path (cross-platform)
+fn bookmarks_file() -> PathBuf {
+    platform::config_dir().join("bookmarks.json")
+}
+
+/// Load all bookmarks from file
+pub fn load_bookmarks() -> Vec<Bookmark> {
+    let path = bookmarks_file();
+    if !path.exists() {
+        return Vec::new();
+    }
+
+    fs::read_to_string(&path)
+        .ok()
+        .and_then(|data| serde_json::from_str(&data).ok())
+        .unwrap_or_default()
+}
+
+fn save_bookmarks(bookmarks: &[Bookmark]) -> std::io::Result<()> {
+    let path = bookmarks_file();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let data = serde_json::to_string_pretty(bookmarks)?;
+    fs::write(path, data)
+}
+
+/// Add a bookmark (or relabel an existing one) and return the updated list.
+pub fn add_bookmark(path: &str, label: Option<&str>) -> Result<Vec<Bookmark>, BookmarkError> {
+    let _guard = BOOKMARKS_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let mut bookmarks = load_bookmarks();
+    if !upsert(&mut bookmarks, path, label) {
+        return Err(BookmarkError::Full);
+    }
+    save_bookmarks(&bookmarks)?;
+    Ok(bookmarks)
+}
+
+/// Remove the bookmark for `path`, if any, and return the updated list.
+pub fn remove_bookmark(path: &str) -> std::io::Result<Vec<Bookmark>> {
+    let _guard = BOOKMARKS_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let mut bookmarks = load_bookmarks();
+    let before = bookmarks.len();
+    bookmarks.retain(|b| b.path != path);
+    if bookmarks.len() != before {
+        save_bookmarks(&bookmarks)?;
+    }
+    Ok(bookmarks)
+}
+
+/// Returns false, changing nothing, if `path` is new and the list is full.
+fn upsert(bookmarks: &mut Vec<Bookmark>, path: &str, label: Option<&str>) -> bool {
+    let label = label
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+        .map(str::to_string)
+        .unwrap_or_else(|| default_label(path));
+    let label = match label.char_indices().nth(MAX_LABEL_CHARS) {
+        Some((cut, _)) => label[..cut].trim_end().to_string(),
+        None => label,
+    };
+    if let Some(existing) = bookmarks.iter_mut().find(|b| b.path == path) {
+        existing.label = label;
+        return true;
+    }
+    if bookmarks.len() >= MAX_BOOKMARKS {
+        return false;
+    }
+    bookmarks.push(Bookmark {
+        path: path.to_string(),
+        label,
+        created_at: chrono::Utc::now().to_rfc3339(),
+    });
+    true
+}
+
+fn default_label(path: &str) -> String {
+    Path::new(path)
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{upsert, MAX_BOOKMARKS, MAX_LABEL_CHARS};
+
+    #[test]
+    fn upsert_relabels_instead_of_duplicating() {
+        let mut bookmarks = Vec::new();
+        upsert(&mut bookmarks, "/work/app", None);
+        assert_eq!(bookmarks[0].label, "app");
+
+        upsert(&mut bookmarks, "/work/app", Some(" Main app "));
+        upsert(&mut bookmarks, "/work/docs", Some(""));
+        assert_eq!(bookmarks.len(), 2);
+        assert_eq!(bookmarks[0].label, "Main app");
+        assert_eq!(bookmarks[1].label, "docs");
+    }
+
+    #[test]
+    fn upsert_caps_labels_and_list_length() {
+        let mut bookmarks = Vec::new();
+        let long = "é".repeat(MAX_LABEL_CHARS + 10);
+        assert!(upsert(&mut bookmarks, "/work/0", Some(&long)));
+        assert_eq!(bookmarks[0].label.chars().count(), MAX_LABEL_CHARS);
+
+        for i in 1..MAX_BOOKMARKS {
+            assert!(upsert(&mut bookmarks, &format!("/work/{i}"), None));
+        }
+        assert!(!upsert(&mut bookmarks, "/work/extra", None));
+        assert_eq!(bookmarks.len(), MAX_BOOKMARKS);
+        // Relabelling an existing bookmark still works when full.
+        assert!(upsert(&mut bookmarks, "/work/0", Some("first")));
+        assert_eq!(bookmarks[0].label, "first");
+    }
+}
@@ -5,29 +5,48 @@
 
 use crate::auth::{self, AuthenticatedClient};
 use crate::detection::{
-    detect_wait_event, strip_ansi_and_normalize, ApprovalModel, CliTracker, CliType, WaitType,
+    detect_wait_event, load_custom_patterns, strip_ansi_and_normalize, ApprovalModel, CliTracker,
+    CliType, CustomPattern, WaitEvent, WaitType,
+};
+use crate::filesystem::{
+    config::{
+        DeniedPatternOverride, FileSystemConfig, MAX_ARCHIVE_SIZE_LIMIT, MAX_LIST_ENTRIES_LIMIT,
+        MAX_READ_SIZE_LIMIT, MAX_SEARCH_FILE_SIZE_LIMIT, MAX_SEARCH_RESULTS_LIMIT,
+        MAX_WRITE_SIZE_LIMIT,
+    },
+    operations::ListOptions,
+    rate_limit::RateLimiter,
+    search::{SearchControl, SearchOptions},
+    security::PathValidator,
+    FileSystemService,
 };
-use crate::filesystem::{config::FileSystemConfig, rate_limit::RateLimiter, FileSystemService};
 use crate::platform;
 use crate::protocol::{
-    ChangeType, ClientMessage, FileEncoding, FileSystemError, PtyResizeReason, ServerMessage,
-    SessionListItem, TmuxViewportAction,
+    encode_binary_pty_frame, BroadcastFilter, ChangeType, ClientMessage, FileChanged, FileEncoding,
+    FileEntry, FileSystemError, FsLimits, GitStatus, NotificationRecord, ProjectSessions,
+    PtyResizeReason, ServerMessage, SessionListItem, TmuxViewportAction, PROTOCOL_VERSION,
 };
+use crate::screen;
 use crate::session::{self, SessionInfo};
 use crate::tmux::sanitize_tmux_token;
 use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
 use chrono::Utc;
 use futures_util::{SinkExt, StreamExt};
+use sha2::{Digest, Sha256};
 use std::collections::{BTreeSet, HashMap, VecDeque};
 use std::net::SocketAddr;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, OnceLock};
 use std::time::Duration;
 use tokio::net::{TcpListener, TcpStream};
 use tokio::sync::{broadcast, mpsc, RwLock};
 use tokio_tungstenite::{
-    accept_async_with_config,
-    tungstenite::{protocol::WebSocketConfig, Message},
+    accept_hdr_async_with_config,
+    tungstenite::{
+        handshake::server::{Callback, ErrorResponse, Request, Response},
+        protocol::WebSocketConfig,
+        Message,
+    },
 };
 
 /// Shared HTTP client for push notifications (lazy initialized with timeout)
@@ -46,12 +65,17 @@ pub const DEFAULT_PORT: u16 = 9847;
 
 /// PID file path (cross-platform)
 fn pid_file() -> PathBuf {
-    platform::config_dir().join("daemon.pid")
+    platform::base_config_dir().join(platform::instance_file_name("daemon", "pid"))
 }
 
 /// Port file path (cross-platform)
 fn port_file() -> PathBuf {
-    platform::config_dir().join("daemon.port")
+    platform::base_config_dir().join(platform::instance_file_name("daemon", "port"))
+}
+
+/// Log file a backgrounded daemon writes to (cross-platform)
+pub fn log_file() -> PathBuf {
+    platform::config_dir().join("daemon.log")
 }
 
 /// Get the running daemon's port (reads from port file)
@@ -164,9 +188,28 @@ const DEFAULT_SCROLLBACK_MAX_BYTES: usize = 8 * 1024 * 1024;
 const SUBSCRIBE_SCROLLBACK_LINES: usize = 10_000;
 const HISTORY_SCROLLBACK_LINES: usize = 200_000;
 
+/// `GetSessionScreen` replays at most this much recent output, which covers
+/// any screen a preview card needs. Kept per PTY session apart from
+/// scrollback, which leaves out alternate-screen output.
+const SCREEN_RENDER_TAIL_BYTES: usize = 256 * 1024;
+
 const ALT_ENTER_SEQS: &[&[u8]] = &[b"\x1b[?1049h", b"\x1b[?1047h", b"\x1b[?47h"];
 const ALT_LEAVE_SEQS: &[&[u8]] = &[b"\x1b[?1049l", b"\x1b[?1047l", b"\x1b[?47l"];
-const ALT_TRACK_TAIL_BYTES: usize = 7;
+const BRACKETED_PASTE_ON: &[u8] = b"\x1b[?2004h";
+const BRACKETED_PASTE_OFF: &[u8] = b"\x1b[?2004l";
+const PASTE_START: &str = "\x1b[200~";
+const PASTE_END: &str = "\x1b[201~";
+/// How many recent `SendInput` client_msg_ids each session remembers.
+const INPUT_DEDUPE_WINDOW: usize = 64;
+/// Quiet period before a mobile resize is forwarded to the wrapper.
+const RESIZE_DEBOUNCE: Duration = Duration::from_millis(80);
+/// DEC private modes that enable mouse reporting (X10, normal, highlight,
+/// button-event, any-event) or select its encoding (UTF-8, SGR, urxvt, pixels).
+const MOUSE_TRACKING_MODES: &[&[u8]] = &[
+    b"9", b"1000", b"1001", b"1002", b"1003", b"1005", b"1006", b"1015", b"1016",
+];
+/// Longest partial CSI sequence held back until the next PTY chunk.
+const MOUSE_FILTER_MAX_PENDING: usize = 32;
 const SNAPSHOT_CHUNK_BYTES: usize = 48 * 1024;
 const CLIENT_CAP_ATTACH_V2: u32 = 1 << 0;
 const TMUX_VIEWPORT_MIN_MAJOR: u32 = 3;
@@ -174,6 +217,51 @@ const TMUX_VIEWPORT_DEFAULT_COUNT: u16 = 1;
 const TMUX_VIEWPORT_MAX_COUNT: u16 = 20;
 const FIRST_MESSAGE_TIMEOUT: Duration = Duration::from_secs(10);
 const FIRST_MESSAGE_MAX_BYTES: usize = 128 * 1024;
+/// Largest `file_appended` payload; bigger appends are split across messages.
+const TAIL_APPEND_MAX_BYTES: u64 = 256 * 1024;
+/// How long a `DeletePreview` token stays valid.
+const DELETE_CONFIRM_TTL: Duration = Duration::from_secs(120);
+/// How long a `GrantPathOnce` read grant lasts.
+const PATH_GRANT_TTL: Duration = Duration::from_secs(5 * 60);
+/// How long a disconnected client's watches keep buffering events, so a
+/// quick reconnect can replay them with `since_seq`.
+const WATCH_REPLAY_GRACE: Duration = Duration::from_secs(60);
+/// How often a running `SearchFiles` reports `search_progress`.
+const SEARCH_PROGRESS_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Default time a PTY wrapper socket may stay silent before its session is
+/// reaped. Wrappers send a heartbeat every few seconds, so only sessions whose
+/// wrapper died without a `session_ended` (e.g. SIGKILL) ever hit this.
+const DEFAULT_SESSION_REAP_TIMEOUT_SECS: u64 = 90;
+
+/// Default cap on concurrent sessions, each holding a PTY and scrollback.
+const DEFAULT_MAX_SESSIONS: usize = 64;
+
+/// Default cap on file operations one mobile client may have running at once.
+const DEFAULT_MAX_FILE_OPS_PER_CLIENT: usize = 8;
+
+/// Default length, in characters, of the recent output kept per session for
+/// wait-state detection. Long multi-line approval prompts must fit whole.
+const DEFAULT_WAIT_BUFFER_CHARS: usize = 16_000;
+
+/// Longest a `RunCommand` may run before it is killed.
+const RUN_COMMAND_TIMEOUT: Duration = Duration::from_secs(30);
+/// Output kept per stream (stdout, stderr) of a `RunCommand`.
+const RUN_COMMAND_OUTPUT_MAX_BYTES: usize = 256 * 1024;
+/// `RunCommand`s one mobile client may have running at once.
+const MAX_RUN_COMMANDS_PER_CLIENT: usize = 2;
+
+/// Commands kept per session for `GetInputHistory`.
+const INPUT_HISTORY_LEN: usize = 50;
+
+/// Wait-state notifications kept in memory for `GetNotificationHistory`.
+const NOTIFICATION_HISTORY_LEN: usize = 200;
+
+/// Default interval between daemon-initiated WebSocket pings to mobile clients.
+const DEFAULT_CLIENT_PING_INTERVAL_SECS: u64 = 30;
+/// Default time a mobile socket may stay silent (no pong or other frame)
+/// before it is treated as half-open and dropped.
+const DEFAULT_CLIENT_TIMEOUT_SECS: u64 = 90;
 
 #[derive(Debug, Clone)]
 struct AuthStartRequest {
@@ -254,6 +342,95 @@ fn env_flag(name: &str, default: bool) -> bool {
     }
 }
 
+fn env_u64(name: &str, default: u64) -> u64 {
+    std::env::var(name)
+        .ok()
+        .and_then(|raw| raw.trim().parse::<u64>().ok())
+        .unwrap_or(default)
+}
+
+/// Silence threshold for the stale-session reaper.
+///
+/// Configured with `MOBILECLI_SESSION_REAP_TIMEOUT_SECS`; `0` disables reaping.
+fn session_reap_timeout() -> Option<Duration> {
+    match env_u64(
+        "MOBILECLI_SESSION_REAP_TIMEOUT_SECS",
+        DEFAULT_SESSION_REAP_TIMEOUT_SECS,
+    ) {
+        0 => None,
+        secs => Some(Duration::from_secs(secs)),
+    }
+}
+
+/// Whether notifications are also appended to `notifications.jsonl`
+/// (`MOBILECLI_NOTIFICATION_LOG`).
+fn notification_log_enabled() -> bool {
+    env_flag("MOBILECLI_NOTIFICATION_LOG", false)
+}
+
+/// Session cap from `MOBILECLI_MAX_SESSIONS`; `0` means unlimited.
+fn max_sessions_from_env() -> usize {
+    env_u64("MOBILECLI_MAX_SESSIONS", DEFAULT_MAX_SESSIONS as u64) as usize
+}
+
+/// Per-client file operation cap from `MOBILECLI_MAX_FILE_OPS_PER_CLIENT`;
+/// `0` means unlimited.
+fn max_file_ops_per_client_from_env() -> usize {
+    env_u64(
+        "MOBILECLI_MAX_FILE_OPS_PER_CLIENT",
+        DEFAULT_MAX_FILE_OPS_PER_CLIENT as u64,
+    ) as usize
+}
+
+/// Wait-detection buffer length from `MOBILECLI_WAIT_BUFFER_CHARS`; at least
+/// one character.
+fn wait_buffer_chars_from_env() -> usize {
+    (env_u64(
+        "MOBILECLI_WAIT_BUFFER_CHARS",
+        DEFAULT_WAIT_BUFFER_CHARS as u64,
+    ) as usize)
+        .max(1)
+}
+
+/// Whether registering a new session would exceed the cap. Re-registering an
+/// existing session id (wrapper reconnect) never counts against it.
+fn session_limit_reached(st: &DaemonState, session_id: Option<&str>) -> bool {
+    if st.max_sessions == 0 {
+        return false;
+    }
+    if session_id.is_some_and(|id| st.sessions.contains_key(id)) {
+        return false;
+    }
+    st.sessions.len() >= st.max_sessions
+}
+
+/// Ping interval and silence timeout for mobile client sockets.
+///
+/// Configured with `MOBILECLI_CLIENT_PING_INTERVAL_SECS` (`0` disables the
+/// keepalive) and `MOBILECLI_CLIENT_TIMEOUT_SECS`.
+fn client_keepalive() -> Option<(Duration, Duration)> {
+    keepalive_settings(
+        env_u64(
+            "MOBILECLI_CLIENT_PING_INTERVAL_SECS",
+            DEFAULT_CLIENT_PING_INTERVAL_SECS,
+        ),
+        env_u64("MOBILECLI_CLIENT_TIMEOUT_SECS", DEFAULT_CLIENT_TIMEOUT_SECS),
+    )
+}
+
+fn keepalive_settings(interval_secs: u64, timeout_secs: u64) -> Option<(Duration, Duration)> {
+    if interval_secs == 0 {
+        return None;
+    }
+    // A timeout shorter than the ping interval would drop healthy clients
+    // before they ever had a chance to answer.
+    let timeout_secs = timeout_secs.max(interval_secs.saturating_mul(2));
+    Some((
+        Duration::from_secs(interval_secs),
+        Duration::from_secs(timeout_secs),
+    ))
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct ResizeRequest {
     pub cols: u16,
@@ -262,6 +439,14 @@ pub struct ResizeRequest {
     pub reason: PtyResizeReason,
 }
 
+/// Out-of-band commands the daemon sends to a PTY wrapper.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WrapperControl {
+    /// Detach the wrapper's tmux client, leaving the tmux session running.
+    /// `requested_by` hears about it if the wrapper fails to detach.
+    Detach { requested_by: SocketAddr },
+}
+
 /// Active PTY session
 pub struct PtySession {
     pub session_id: String,
@@ -275,11 +460,17 @@ pub struct PtySession {
     pub command: String,
     pub project_path: String,
     pub started_at: chrono::DateTime<Utc>,
+    /// Last PTY output or mobile input, for ordering sessions by activity.
+    pub last_activity: chrono::DateTime<Utc>,
     pub input_tx: mpsc::UnboundedSender<Vec<u8>>,
     pub resize_tx: mpsc::UnboundedSender<ResizeRequest>,
+    pub control_tx: mpsc::UnboundedSender<WrapperControl>,
     pub waiting_state: Option<WaitingState>,
     pub cli_tracker: CliTracker,
     pub last_wait_hash: Option<u64>,
+    /// Prompt the user dismissed from mobile; it is not raised again while it
+    /// stays on screen.
+    pub dismissed_wait_hash: Option<u64>,
     /// Scrollback buffer for session history (for linked terminals)
     /// Uses VecDeque for efficient front truncation when buffer is full
     pub scrollback: VecDeque<u8>,
@@ -290,19 +481,65 @@ pub struct PtySession {
     /// Tail bytes from prior chunk used to detect alt-screen escape sequences
     /// split across PTY read boundaries.
     pub alt_track_tail: Vec<u8>,
+    /// Raw recent output, alternate-screen frames included, that
+    /// `GetSessionScreen` renders for non-tmux sessions.
+    pub screen_tail: VecDeque<u8>,
     /// Latest accepted resize epoch from mobile (for stale resize rejection).
     pub last_resize_epoch: u64,
     /// Last dimensions acknowledged by the PTY wrapper.
     pub last_applied_size: Option<(u16, u16)>,
+    /// Latest mobile resize waiting out `RESIZE_DEBOUNCE` before it is sent
+    /// to the wrapper.
+    pub pending_resize: Option<ResizeRequest>,
+    /// Bumped on every queued resize so only the newest debounce timer fires.
+    pub resize_debounce_gen: u64,
     /// Sequence number for live PTY chunks (Phase 0 observability scaffold).
     pub live_seq: u64,
     /// Tail for raw mobile input filtering to handle escape sequences split
     /// across websocket messages.
     pub raw_input_tail: Vec<u8>,
+    /// Whether the foreground program enabled bracketed paste (`?2004h`).
+    pub bracketed_paste: bool,
+    /// Tail bytes from the prior chunk for bracketed-paste mode switches
+    /// split across PTY read boundaries.
+    pub paste_track_tail: Vec<u8>,
+    /// Termios echo state last reported by the wrapper (`None` until known).
+    pub local_echo: Option<bool>,
+    /// Most recent `SendInput` (sender, client_msg_id) pairs, for dropping
+    /// resends. Ids are only unique per sender.
+    pub recent_input_ids: VecDeque<(String, String)>,
+    /// Newline-terminated inputs from mobile, oldest first, for re-running.
+    pub input_history: VecDeque<String>,
     /// Whether a desktop PTY wrapper is attached to this session. When true,
     /// the desktop terminal controls the PTY dimensions and mobile resize
     /// requests are suppressed to prevent dimension fights between viewers.
     pub has_desktop_wrapper: bool,
+    /// Last time any frame (heartbeat, output, resize ack) arrived from the
+    /// wrapper socket. Used by the stale-session reaper.
+    pub last_heartbeat: std::time::Instant,
+    /// Whether the wrapper has sent a `heartbeat` frame. Wrappers from before
+    /// heartbeats never do, so the reaper leaves their sessions alone.
+    pub sends_heartbeats: bool,
+    /// Tells this registration apart from a later one under the same id, so
+    /// a superseded or reaped wrapper socket closing can't end its successor.
+    pub registration_id: u64,
+}
+
+/// A recursive delete previewed for a client, awaiting its confirmation.
+pub struct PendingDelete {
+    pub addr: SocketAddr,
+    /// The path exactly as the client sent it
+    pub path: String,
+    pub expires_at: std::time::Instant,
+}
+
+/// A file followed by `TailFile`; the task streams appends until aborted.
+pub struct FileTail {
+    /// The tailed file, as reported to the client.
+    pub path: String,
+    /// Watcher key (the file's parent directory) shared via `file_watch_counts`.
+    pub watch_path: String,
+    pub task: tokio::task::AbortHandle,
 }
 
 /// Daemon shared state
@@ -311,16 +548,32 @@ pub struct DaemonState {
     pub overhaul_flags: OverhaulFlags,
     pub tmux_viewport_supported: bool,
     pub next_attach_id: u64,
+    pub next_registration_id: u64,
     pub mobile_clients: HashMap<SocketAddr, mpsc::Sender<Message>>,
     pub mobile_client_capabilities: HashMap<SocketAddr, u32>,
+    /// Mobile clients that negotiated binary frames for live PTY output.
+    pub mobile_binary_pty: std::collections::HashSet<SocketAddr>,
+    /// Protocol version each mobile client announced in `Hello`.
+    pub mobile_protocol_versions: HashMap<SocketAddr, u32>,
+    /// Mobile clients that can't send mouse events; PTY output to them has
+    /// mouse-tracking sequences stripped.
+    pub mobile_mouse_filtered: std::collections::HashSet<SocketAddr>,
+    /// Live-output mouse filter per filtered client and session, holding any
+    /// sequence split across chunks.
+    pub mobile_mouse_filters: HashMap<SocketAddr, HashMap<String, MouseSequenceFilter>>,
     pub mobile_auth: HashMap<SocketAddr, AuthenticatedClient>,
     pub mobile_attach_ids: HashMap<SocketAddr, HashMap<String, u64>>,
+    /// Live sequence each client's `AttachSession` history ended at, per
+    /// session; older chunks still in flight are not sent again.
+    pub mobile_replayed_seqs: HashMap<SocketAddr, HashMap<String, u64>>,
     /// Mapping from logical mobile sender ID to current socket address.
     /// Used to evict stale/replaced websocket addresses on reconnect.
     pub mobile_sender_addrs: HashMap<String, SocketAddr>,
     pub pty_broadcast: broadcast::Sender<(String, u64, Vec<u8>)>,
     pub port: u16, // The actual port the daemon is running on
     pub push_tokens: Vec<PushToken>,
+    /// Most recent wait-state notifications, oldest first.
+    pub notification_history: VecDeque<NotificationRecord>,
     pub mobile_views: HashMap<SocketAddr, std::collections::HashSet<String>>,
     /// Current mobile controller socket for tmux shared viewport per session.
     pub tmux_viewport_controllers: HashMap<String, SocketAddr>,
@@ -331,13 +584,35 @@ pub struct DaemonState {
     pub file_system: std::sync::Arc<FileSystemService>,
     pub file_watch_subscriptions: HashMap<SocketAddr, std::collections::HashSet<String>>,
     pub file_watch_counts: HashMap<String, usize>,
+    /// Watched directories per client whose ignored paths it skips
+    /// (`respect_ignore`).
+    pub file_watch_skip_ignored: HashMap<SocketAddr, std::collections::HashSet<String>>,
+    /// Active `TailFile` subscriptions per client, keyed by request id.
+    pub file_tails: HashMap<SocketAddr, HashMap<String, FileTail>>,
+    /// Running `SearchFiles` walks per client, keyed by request id.
+    pub active_searches: HashMap<SocketAddr, HashMap<String, Arc<SearchControl>>>,
+    /// Outstanding `DeletePreview` tokens.
+    pub pending_deletes: HashMap<String, PendingDelete>,
     pub file_rate_limiters: HashMap<SocketAddr, RateLimiter>,
+    /// Per-client permits bounding concurrent file operations; the rate
+    /// limiter above bounds how fast they start, this how many run at once.
+    pub file_op_permits: HashMap<SocketAddr, Arc<tokio::sync::Semaphore>>,
+    /// Permits per client; 0 means unlimited.
+    pub max_file_ops_per_client: usize,
+    /// Per-client permits bounding concurrent `RunCommand`s.
+    pub run_command_permits: HashMap<SocketAddr, Arc<tokio::sync::Semaphore>>,
     /// Device UUID (for multi-device support)
     pub device_id: Option<String>,
     /// Device name (hostname)
     pub device_name: Option<String>,
     /// Stable auth server identity.
     pub server_id: Option<String>,
+    /// When the daemon started, for uptime reporting.
+    pub started_at: std::time::Instant,
+    /// Maximum concurrent sessions; 0 means unlimited.
+    pub max_sessions: usize,
+    /// Wait patterns from `detection.toml`, read at startup and on reload.
+    pub detection_patterns: Arc<Vec<CustomPattern>>,
 }
 
 impl DaemonState {
@@ -373,25 +648,42 @@ impl DaemonState {
             overhaul_flags,
             tmux_viewport_supported,
             next_attach_id: 1,
+            next_registration_id: 1,
             mobile_clients: HashMap::new(),
             mobile_client_capabilities: HashMap::new(),
+            mobile_binary_pty: std::collections::HashSet::new(),
+            mobile_protocol_versions: HashMap::new(),
+            mobile_mouse_filtered: std::collections::HashSet::new(),
+            mobile_mouse_filters: HashMap::new(),
             mobile_auth: HashMap::new(),
             mobile_attach_ids: HashMap::new(),
+            mobile_replayed_seqs: HashMap::new(),
             mobile_sender_addrs: HashMap::new(),
             pty_broadcast,
             port,
             push_tokens: Vec::new(),
+            notification_history: VecDeque::new(),
             mobile_views: HashMap::new(),
             tmux_viewport_controllers: HashMap::new(),
             session_view_counts: HashMap::new(),
             pty_scroll_offsets: HashMap::new(),
             file_system,
             file_watch_subscriptions: HashMap::new(),
+            file_watch_skip_ignored: HashMap::new(),
             file_watch_counts: HashMap::new(),
+            file_tails: HashMap::new(),
+            active_searches: HashMap::new(),
+            pending_deletes: HashMap::new(),
             file_rate_limiters: HashMap::new(),
+            file_op_permits: HashMap::new(),
+            max_file_ops_per_client: max_file_ops_per_client_from_env(),
+            run_command_permits: HashMap::new(),
             device_id,
             device_name,
             server_id,
+            started_at: std::time::Instant::now(),
+            max_sessions: max_sessions_from_env(),
+            detection_patterns: Arc::default(),
         }
     }
 }
@@ -427,9 +719,131 @@ fn file_system_config_from_setup_and_projects<'a>(
     if !roots.is_empty() {
         fs_config.allowed_roots = roots;
     }
+    // Overrides only take effect for roots that are actually allowed.
+    let canonical_roots: Vec<PathBuf> = fs_config
+        .allowed_roots
+        .iter()
+        .map(|root| root.canonicalize().unwrap_or_else(|_| root.clone()))
+        .collect();
+    if let Some(ms) = cfg.filesystem.watch_debounce_ms {
+        fs_config.watch_debounce_ms = crate::filesystem::watcher::clamp_debounce_ms(ms);
+    }
+    fs_config.atomic_backup = cfg.filesystem.atomic_backup;
+    // Hand-edited values are held to the same ceilings as `SetFsConfig`.
+    let fs = &cfg.filesystem;
+    if let Some(bytes) = fs.search_max_file_size {
+        fs_config.max_search_file_size = bytes.clamp(1, MAX_SEARCH_FILE_SIZE_LIMIT);
+    }
+    if let Some(bytes) = fs.max_read_size {
+        fs_config.max_read_size = bytes.clamp(1, MAX_READ_SIZE_LIMIT);
+    }
+    if let Some(bytes) = fs.max_write_size {
+        fs_config.max_write_size = bytes.clamp(1, MAX_WRITE_SIZE_LIMIT);
+    }
+    if let Some(count) = fs.max_list_entries {
+        fs_config.max_list_entries = count.clamp(1, MAX_LIST_ENTRIES_LIMIT);
+    }
+    if let Some(count) = fs.max_search_results {
+        fs_config.max_search_results = count.clamp(1, MAX_SEARCH_RESULTS_LIMIT);
+    }
+    if let Some(bytes) = fs.max_archive_size {
+        fs_config.max_archive_size = bytes.clamp(1, MAX_ARCHIVE_SIZE_LIMIT);
+    }
+    fs_config.denied_pattern_overrides = cfg
+        .filesystem
+        .denied_pattern_overrides
+        .iter()
+        .filter_map(|(root, patterns)| {
+            let root = PathBuf::from(root);
+            let canonical = root.canonicalize().unwrap_or_else(|_| root.clone());
+            canonical_roots
+                .contains(&canonical)
+                .then(|| DeniedPatternOverride {
+                    root,
+                    allowed_patterns: patterns.clone(),
+                })
+        })
+        .collect();
     fs_config
 }
 
+/// The app-adjustable limits of a live filesystem config.
+fn current_fs_limits(config: &FileSystemConfig) -> FsLimits {
+    FsLimits {
+        max_read_size: Some(config.max_read_size),
+        max_write_size: Some(config.max_write_size),
+        max_list_entries: Some(config.max_list_entries),
+        max_search_results: Some(config.max_search_results),
+        max_search_file_size: Some(config.max_search_file_size),
+        watch_debounce_ms: Some(config.watch_debounce_ms),
+    }
+}
+
+/// Copy the limits set in `update` into the saved config, rejecting zero and
+/// anything above the hard ceilings. Nothing changes unless all are valid.
+fn apply_fs_limits(
+    target: &mut crate::setup::FileSystemAccessConfig,
+    update: &FsLimits,
+) -> Result<(), String> {
+    fn check<T: PartialOrd + std::fmt::Display + Copy>(
+        name: &str,
+        value: Option<T>,
+        min: T,
+        max: T,
+    ) -> Result<Option<T>, String> {
+        match value {
+            Some(v) if v < min || v > max => {
+                Err(format!("{} must be between {} and {}", name, min, max))
+            }
+            other => Ok(other),
+        }
+    }
+    let max_read_size = check(
+        "max_read_size",
+        update.max_read_size,
+        1,
+        MAX_READ_SIZE_LIMIT,
+    )?;
+    let max_write_size = check(
+        "max_write_size",
+        update.max_write_size,
+        1,
+        MAX_WRITE_SIZE_LIMIT,
+    )?;
+    let max_list_entries = check(
+        "max_list_entries",
+        update.max_list_entries,
+        1,
+        MAX_LIST_ENTRIES_LIMIT,
+    )?;
+    let max_search_results = check(
+        "max_search_results",
+        update.max_search_results,
+        1,
+        MAX_SEARCH_RESULTS_LIMIT,
+    )?;
+    let max_search_file_size = check(
+        "max_search_file_size",
+        update.max_search_file_size,
+        1,
+        MAX_SEARCH_FILE_SIZE_LIMIT,
+    )?;
+    let watch_debounce_ms = check(
+        "watch_debounce_ms",
+        update.watch_debounce_ms,
+        crate::filesystem::watcher::MIN_DEBOUNCE_MS,
+        crate::filesystem::watcher::MAX_DEBOUNCE_MS,
+    )?;
+
+    target.max_read_size = max_read_size.or(target.max_read_size);
+    target.max_write_size = max_write_size.or(target.max_write_size);
+    target.max_list_entries = max_list_entries.or(target.max_list_entries);
+    target.max_search_results = max_search_results.or(target.max_search_results);
+    target.search_max_file_size = max_search_file_size.or(target.search_max_file_size);
+    target.watch_debounce_ms = watch_debounce_ms.or(target.watch_debounce_ms);
+    Ok(())
+}
+
 fn is_safe_session_project_root(cfg: &crate::setup::Config, path: &str) -> bool {
     let path = PathBuf::from(path);
     if !path.is_absolute() {
@@ -473,15 +887,98 @@ fn refresh_file_system_roots(st: &mut DaemonState) {
         .map(|session| session.project_path.clone())
         .filter(|path| !path.trim().is_empty())
         .collect();
-    st.file_system = std::sync::Arc::new(FileSystemService::new(
-        file_system_config_from_setup_and_projects(&cfg, project_paths.iter()),
-    ));
+    let mut config = file_system_config_from_setup_and_projects(&cfg, project_paths.iter());
+    // Outstanding read grants and chunked uploads survive the rebuild.
+    config.path_grants = st.file_system.config().path_grants.clone();
+    st.file_system = std::sync::Arc::new(st.file_system.rebuild(config));
+}
+
+/// What differs between two filesystem configs, one line per change, for the
+/// reload log.
+fn fs_config_changes(before: &FileSystemConfig, after: &FileSystemConfig) -> Vec<String> {
+    fn diff<T: PartialEq>(
+        changes: &mut Vec<String>,
+        what: &str,
+        before: &[T],
+        after: &[T],
+        show: impl Fn(&T) -> String,
+    ) {
+        for item in after.iter().filter(|item| !before.contains(item)) {
+            changes.push(format!("{what} added: {}", show(item)));
+        }
+        for item in before.iter().filter(|item| !after.contains(item)) {
+            changes.push(format!("{what} removed: {}", show(item)));
+        }
+    }
+
+    let mut changes = Vec::new();
+    diff(
+        &mut changes,
+        "allowed root",
+        &before.allowed_roots,
+        &after.allowed_roots,
+        |root| root.display().to_string(),
+    );
+    diff(
+        &mut changes,
+        "denied pattern",
+        &before.denied_patterns,
+        &after.denied_patterns,
+        String::clone,
+    );
+    diff(
+        &mut changes,
+        "read-only pattern",
+        &before.read_only_patterns,
+        &after.read_only_patterns,
+        String::clone,
+    );
+    let limits = current_fs_limits(after);
+    if current_fs_limits(before) != limits {
+        changes.push(format!("limits now {limits:?}"));
+    }
+    if before.atomic_backup != after.atomic_backup {
+        changes.push(format!("atomic_backup now {}", after.atomic_backup));
+    }
+    changes
+}
+
+/// Re-read config.json into the live filesystem config and log what changed.
+/// Credentials and device details are read from disk on every use, so they
+/// are already current. `detection.toml` is re-read for sessions started
+/// afterwards. The filesystem service is rebuilt, but sessions,
+/// clients, read grants, chunked uploads and directory watches (with their
+/// replay buffers) carry over; watches already running keep their debounce
+/// interval.
+async fn reload_config(state: &SharedState) -> Result<Vec<String>, String> {
+    if crate::setup::load_config().is_none() {
+        return Err("Daemon config not found; run mobilecli setup".to_string());
+    }
+    let mut st = state.write().await;
+    let before = st.file_system.config().clone();
+    refresh_file_system_roots(&mut st);
+    let changes = fs_config_changes(&before, st.file_system.config());
+    st.detection_patterns = Arc::new(load_custom_patterns());
+    drop(st);
+    if changes.is_empty() {
+        tracing::info!("Config reloaded; nothing changed");
+    } else {
+        tracing::info!(changes = ?changes, "Config reloaded");
+    }
+    Ok(changes)
 }
 
 pub type SharedState = Arc<RwLock<DaemonState>>;
 
 /// Start the daemon (blocking - run in background)
-pub async fn run(port: u16) -> std::io::Result<()> {
+///
+/// `bind` overrides the configured `bind_address` for the mobile listener and
+/// `max_sessions` overrides `MOBILECLI_MAX_SESSIONS`.
+pub async fn run(
+    port: u16,
+    bind: Option<std::net::IpAddr>,
+    max_sessions: Option<usize>,
+) -> std::io::Result<()> {
     if crate::setup::load_config().is_some() {
         crate::setup::ensure_desktop_link_token()?;
     }
@@ -499,7 +996,11 @@ pub async fn run(port: u16) -> std::io::Result<()> {
 
     let state: SharedState = Arc::new(RwLock::new(DaemonState::new(port)));
     {
-        let st = state.read().await;
+        let mut st = state.write().await;
+        if let Some(max_sessions) = max_sessions {
+            st.max_sessions = max_sessions;
+        }
+        st.detection_patterns = Arc::new(load_custom_patterns());
         tracing::info!(
             target: "overhaul.phase0",
             attach_protocol = st.overhaul_flags.attach_protocol.as_str(),
@@ -511,10 +1012,14 @@ pub async fn run(port: u16) -> std::io::Result<()> {
         );
     }
 
+    if let Some(timeout) = session_reap_timeout() {
+        spawn_session_reaper(state.clone(), timeout);
+    }
+
     // Limit concurrent connections to prevent resource exhaustion
     let conn_limit = Arc::new(tokio::sync::Semaphore::new(64));
 
-    let listeners = bind_configured_listeners(port).await?;
+    let listeners = bind_configured_listeners(port, bind).await?;
     tracing::info!(
         listener_count = listeners.len(),
         port,
@@ -534,7 +1039,81 @@ pub async fn run(port: u16) -> std::io::Result<()> {
     Ok(())
 }
 
-async fn bind_configured_listeners(port: u16) -> std::io::Result<Vec<TcpListener>> {
+/// Periodically remove sessions whose wrapper socket has gone silent.
+///
+/// A wrapper killed without sending `session_ended` (SIGKILL, OOM, host
+/// terminal crash) leaves its session registered forever. Live wrappers send
+/// heartbeats, so this only reaps sessions that stopped talking entirely;
+/// quiet-but-alive sessions are never touched.
+fn spawn_session_reaper(state: SharedState, timeout: Duration) {
+    let period = (timeout / 3).max(Duration::from_secs(1));
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(period);
+        ticker.tick().await;
+        loop {
+            ticker.tick().await;
+            reap_stale_sessions(&state, timeout).await;
+        }
+    });
+}
+
+fn stale_session_ids(st: &DaemonState, now: std::time::Instant, timeout: Duration) -> Vec<String> {
+    st.sessions
+        .values()
+        .filter(|session| {
+            session.sends_heartbeats
+                && now.saturating_duration_since(session.last_heartbeat) >= timeout
+        })
+        .map(|session| session.session_id.clone())
+        .collect()
+}
+
+async fn reap_stale_sessions(state: &SharedState, timeout: Duration) {
+    let reaped = {
+        let mut st = state.write().await;
+        let stale = stale_session_ids(&st, std::time::Instant::now(), timeout);
+        for session_id in &stale {
+            // Dropping input_tx/resize_tx lets the PTY task exit if it is
+            // still parked on a half-open socket.
+            st.sessions.remove(session_id);
+            st.session_view_counts.remove(session_id);
+            st.tmux_viewport_controllers.remove(session_id);
+            clear_mobile_attach_for_session(&mut st, session_id);
+            for views in st.mobile_views.values_mut() {
+                views.remove(session_id);
+            }
+        }
+        if !stale.is_empty() {
+            refresh_file_system_roots(&mut st);
+            for session_id in &stale {
+                let end_msg = ServerMessage::SessionEnded {
+                    session_id: session_id.clone(),
+                    exit_code: -1,
+                };
+                broadcast_to_active_clients(&st, &end_msg);
+            }
+        }
+        stale
+    };
+
+    if reaped.is_empty() {
+        return;
+    }
+    for session_id in &reaped {
+        tracing::warn!(
+            session_id = %session_id,
+            timeout_secs = timeout.as_secs(),
+            "Reaped session after wrapper heartbeat went silent"
+        );
+    }
+    broadcast_sessions_update(state).await;
+    persist_sessions_to_file(state).await;
+}
+
+async fn bind_configured_listeners(
+    port: u16,
+    bind: Option<std::net::IpAddr>,
+) -> std::io::Result<Vec<TcpListener>> {
     let cfg = crate::setup::load_config().unwrap_or_default();
     let mut addrs = BTreeSet::new();
     addrs.insert(format!("127.0.0.1:{}", port));
@@ -546,6 +1125,17 @@ async fn bind_configured_listeners(port: u16) -> std::io::Result<Vec<TcpListener
         return bind_listeners(addrs).await;
     }
 
+    if let Some(ip) = bind.or(cfg.bind_address) {
+        if ip.is_unspecified() {
+            tracing::warn!(
+                bind_address = %ip,
+                "Binding all interfaces; rely on auth and network isolation to limit exposure"
+            );
+        }
+        tracing::info!(bind_address = %ip, "Using explicit mobile bind address");
+        return bind_listeners(explicit_bind_addrs(port, ip)).await;
+    }
+
     match &cfg.connection_mode {
         crate::setup::ConnectionMode::Local => {
             if let Some(ip) = crate::setup::get_local_ip().or(cfg.local_ip.clone()) {
@@ -575,7 +1165,25 @@ async fn bind_configured_listeners(port: u16) -> std::io::Result<Vec<TcpListener
     bind_listeners(addrs).await
 }
 
+/// Listener addresses for an explicit bind address. A wildcard address
+/// already covers loopback (and binding both would collide on the port).
+fn explicit_bind_addrs(port: u16, ip: std::net::IpAddr) -> BTreeSet<String> {
+    let mut addrs = BTreeSet::new();
+    if !ip.is_unspecified() {
+        addrs.insert(format!("127.0.0.1:{}", port));
+    }
+    addrs.insert(SocketAddr::new(ip, port).to_string());
+    addrs
+}
+
 async fn bind_listeners(addrs: BTreeSet<String>) -> std::io::Result<Vec<TcpListener>> {
+    // Without a loopback listener the desktop wrapper can't connect, so the
+    // listener covering loopback must bind or the daemon fails to start.
+    let required = addrs
+        .iter()
+        .find(|addr| addr.starts_with("127.0.0.1:"))
+        .or_else(|| addrs.iter().next())
+        .cloned();
     let mut listeners = Vec::new();
     for addr in addrs {
         match TcpListener::bind(&addr).await {
@@ -585,7 +1193,7 @@ async fn bind_listeners(addrs: BTreeSet<String>) -> std::io::Result<Vec<TcpListe
             }
             Err(err) => {
                 tracing::warn!(addr = %addr, error = %err, "Failed to bind daemon listener");
-                if addr.starts_with("127.0.0.1:") {
+                if required.as_deref() == Some(addr.as_str()) {
                     return Err(err);
                 }
             }
@@ -616,14 +1224,33 @@ async fn run_server_loop_unix(
         return;
     }
     let mut sigterm = sigterm_result.unwrap();
-    let handles = spawn_accept_loops(listeners, state, conn_limit);
+    // SIGHUP reloads config.json; without it a config change needs a restart.
+    let mut sighup = signal(SignalKind::hangup())
+        .map_err(|e| tracing::warn!("Failed to set up SIGHUP handler: {:?}", e))
+        .ok();
+    let handles = spawn_accept_loops(listeners, state.clone(), conn_limit);
 
-    tokio::select! {
-        _ = tokio::signal::ctrl_c() => {
-            tracing::info!("Daemon shutting down (Ctrl+C)");
-        }
-        _ = sigterm.recv() => {
-            tracing::info!("Daemon shutting down (SIGTERM)");
+    loop {
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => {
+                tracing::info!("Daemon shutting down (Ctrl+C)");
+                break;
+            }
+            _ = sigterm.recv() => {
+                tracing::info!("Daemon shutting down (SIGTERM)");
+                break;
+            }
+            Some(()) = async {
+                match sighup.as_mut() {
+                    Some(sighup) => sighup.recv().await,
+                    None => std::future::pending().await,
+                }
+            } => {
+                tracing::info!("Reloading config (SIGHUP)");
+                if let Err(message) = reload_config(&state).await {
+                    tracing::warn!(error = %message, "Config reload failed");
+                }
+            }
         }
     }
     for handle in handles {
@@ -669,7 +1296,7 @@ fn spawn_accept_loops(
                                     });
                                 }
                                 Err(_) => {
-                                    tracing::warn!("Connection limit reached, rejecting {}", addr);
+                                    tracing::warn!(addr = %addr, "Connection limit reached, rejecting");
                                 }
                             }
                         }
@@ -690,6 +1317,16 @@ async fn handle_connection(
     addr: SocketAddr,
     state: SharedState,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let (cidrs, trusted_proxies) = crate::setup::load_config()
+        .map(|cfg| (cfg.allowed_client_cidrs, cfg.trusted_proxies))
+        .unwrap_or_default();
+    // Loopback covers the desktop wrapper and PTY registrations; everything
+    // else is a mobile client and must come from an allowed range.
+    if !addr.ip().is_loopback() && !client_ip_allowed(addr.ip(), &cidrs) {
+        tracing::warn!(addr = %addr, "Rejecting connection outside allowed_client_cidrs");
+        return Ok(());
+    }
+
     // Keep inbound messages bounded before authentication. Mobile attachments
     // are capped to fit inside this after base64 encoding.
     let ws_config = WebSocketConfig {
@@ -697,9 +1334,30 @@ async fn handle_connection(
         max_frame_size: Some(24 * 1024 * 1024),
         ..Default::default()
     };
-    let ws = accept_async_with_config(stream, Some(ws_config)).await?;
+    let mut forwarded = ForwardedHeaders::default();
+    // No permessage-deflate: tungstenite 0.24 does not implement it, so the
+    // handshake never accepts the extension and clients that offer it fall
+    // back to uncompressed frames.
+    let ws = accept_hdr_async_with_config(stream, &mut forwarded, Some(ws_config)).await?;
     let (mut tx, mut rx) = ws.split();
 
+    // Behind a trusted reverse proxy, the client is whoever the proxy says it
+    // is. The peer port is kept so each connection still has its own key.
+    let peer = addr;
+    let addr = match forwarded.client_ip(peer.ip(), &trusted_proxies) {
+        Some(ip) if ip != peer.ip() => {
+            let addr = SocketAddr::new(ip, peer.port());
+            tracing::debug!(peer = %peer, addr = %addr, "Using forwarded client address");
+            if !ip.is_loopback() && !client_ip_allowed(ip, &cidrs) {
+                tracing::warn!(addr = %addr, "Rejecting connection outside allowed_client_cidrs");
+                let _ = tx.send(Message::Close(None)).await;
+                return Ok(());
+            }
+            addr
+        }
+        _ => peer,
+    };
+
     // Wait briefly for the first message to determine client type. Without
     // this timeout, idle unauthenticated sockets can hold all connection slots.
     let first_msg = match tokio::time::timeout(FIRST_MESSAGE_TIMEOUT, rx.next()).await {
@@ -718,11 +1376,12 @@ async fn handle_connection(
             }
             if let Ok(msg) = serde_json::from_str::<serde_json::Value>(&text) {
                 if msg.get("type").and_then(|v| v.as_str()) == Some("register_pty") {
-                    // This is a PTY session registering
-                    if !addr.ip().is_loopback() {
+                    // This is a PTY session registering. A proxied client
+                    // is remote even though the proxy connects over loopback.
+                    if !pty_registration_allowed(peer, &forwarded, &trusted_proxies) {
                         tracing::warn!(
-                            "Rejecting PTY registration from non-loopback address: {}",
-                            addr
+                            addr = %addr,
+                            "Rejecting PTY registration from non-loopback address"
                         );
                         return Ok(());
                     }
@@ -755,6 +1414,121 @@ async fn handle_connection(
     }
 }
 
+/// Whether `ip` falls inside one of the configured client ranges. An empty
+/// list allows everything; entries that don't parse match nothing, so a typo
+/// narrows access instead of opening it.
+fn client_ip_allowed(ip: std::net::IpAddr, cidrs: &[String]) -> bool {
+    cidrs.is_empty() || ip_in_ranges(ip, cidrs)
+}
+
+/// Whether `ip` matches one of `ranges` (CIDR ranges or single addresses).
+fn ip_in_ranges(ip: std::net::IpAddr, ranges: &[String]) -> bool {
+    // Dual-stack listeners report IPv4 peers as IPv4-mapped IPv6.
+    let ip = match ip {
+        std::net::IpAddr::V6(v6) => v6.to_ipv4_mapped().map(std::net::IpAddr::V4).unwrap_or(ip),
+        v4 => v4,
+    };
+    ranges.iter().any(|range| {
+        if let Ok(net) = range.parse::<ipnet::IpNet>() {
+            net.contains(&ip)
+        } else {
+            range.parse::<std::net::IpAddr>() == Ok(ip)
+        }
+    })
+}
+
+/// Client-address headers captured during the WebSocket handshake.
+#[derive(Debug, Default)]
+struct ForwardedHeaders {
+    /// RFC 7239 `Forwarded`, preferred when present
+    forwarded: Option<String>,
+    x_forwarded_for: Option<String>,
+}
+
+impl ForwardedHeaders {
+    fn from_request(request: &Request) -> Self {
+        // Repeated headers form one list, in order.
+        let joined = |name: &str| {
+            let values: Vec<&str> = request
+                .headers()
+                .get_all(name)
+                .iter()
+                .filter_map(|v| v.to_str().ok())
+                .collect();
+            (!values.is_empty()).then(|| values.join(","))
+        };
+        Self {
+            forwarded: joined("forwarded"),
+            x_forwarded_for: joined("x-forwarded-for"),
+        }
+    }
+
+    /// The real client address, when `peer` is a trusted proxy and the
+    /// headers name one. The hop list is read right to left, skipping our own
+    /// trusted proxies, so a client can't spoof its address by sending the
+    /// header itself. An entry that isn't an IP ends the search.
+    fn client_ip(&self, peer: std::net::IpAddr, trusted: &[String]) -> Option<std::net::IpAddr> {
+        if trusted.is_empty() || !ip_in_ranges(peer, trusted) {
+            return None;
+        }
+        let hops: Vec<&str> = if let Some(forwarded) = &self.forwarded {
+            forwarded
+                .split([',', ';'])
+                .filter_map(|pair| {
+                    let (key, value) = pair.split_once('=')?;
+                    key.trim()
+                        .eq_ignore_ascii_case("for")
+                        .then(|| value.trim().trim_matches('"'))
+                })
+                .collect()
+        } else {
+            self.x_forwarded_for.as_deref()?.split(',').collect()
+        };
+        let mut client = None;
+        for hop in hops.iter().rev() {
+            let ip = parse_forwarded_ip(hop)?;
+            client = Some(ip);
+            if !ip_in_ranges(ip, trusted) {
+                break;
+            }
+        }
+        client
+    }
+}
+
+/// Whether a connection may register a PTY session: a direct loopback
+/// connection. Anything that came through a proxy is refused, whatever its
+/// headers claim, and so is every connection from a trusted proxy address.
+fn pty_registration_allowed(
+    peer: SocketAddr,
+    forwarded: &ForwardedHeaders,
+    trusted_proxies: &[String],
+) -> bool {
+    peer.ip().is_loopback()
+        && forwarded.forwarded.is_none()
+        && forwarded.x_forwarded_for.is_none()
+        && !ip_in_ranges(peer.ip(), trusted_proxies)
+}
+
+impl Callback for &mut ForwardedHeaders {
+    fn on_request(self, request: &Request, response: Response) -> Result<Response, ErrorResponse> {
+        *self = ForwardedHeaders::from_request(request);
+        Ok(response)
+    }
+}
+
+/// An address from a forwarding header: `1.2.3.4`, `1.2.3.4:5678`, `::1`,
+/// or `[::1]:5678`.
+fn parse_forwarded_ip(raw: &str) -> Option<std::net::IpAddr> {
+    let raw = raw.trim();
+    if let Some(rest) = raw.strip_prefix('[') {
+        return rest.split_once(']')?.0.parse().ok();
+    }
+    raw.parse()
+        .ok()
+        .or_else(|| raw.parse::<SocketAddr>().ok().map(|addr| addr.ip()))
+}
+
 async fn send_auth_error(
     tx: &mut futures_util::stream::SplitSink<
         tokio_tungstenite::WebSocketStream<TcpStream>,
@@ -1112,11 +1886,7 @@ async fn handle_mobile_client(
                 {
                     break 'watch;
                 }
-                let watched = match st.file_watch_subscriptions.get(&addr) {
-                    Some(paths) => paths,
-                    None => continue,
-                };
-                if !is_path_watched(&change.path, watched) {
+                if !watch_delivers(&st, &addr, &change) {
                     continue;
                 }
                 st.file_system.clone()
@@ -1148,6 +1918,7 @@ async fn handle_mobile_client(
                 path: change.path.clone(),
                 change_type: change.change_type.clone(),
                 new_entry,
+                seq: change.seq,
             };
             if let Ok(text) = serde_json::to_string(&msg) {
                 if watch_tx.try_send(Message::Text(text)).is_err() {
@@ -1176,6 +1947,7 @@ async fn handle_mobile_client(
     };
     let welcome = ServerMessage::Welcome {
         server_version: env!("CARGO_PKG_VERSION").to_string(),
+        protocol_version: PROTOCOL_VERSION,
         device_id,
         device_name,
         server_id,
@@ -1190,40 +1962,82 @@ async fn handle_mobile_client(
     // Send current waiting states for all sessions (for late-joining clients)
     send_waiting_states(&state, &mut tx).await?;
 
+    // Proactive keepalive: half-open sockets (phone asleep, network switch)
+    // are otherwise only noticed when a later write fails.
+    let keepalive = client_keepalive();
+    let mut keepalive_timer = tokio::time::interval(
+        keepalive
+            .map(|(interval, _)| interval)
+            .unwrap_or(Duration::from_secs(3600)),
+    );
+    keepalive_timer.tick().await;
+    let mut last_client_activity = std::time::Instant::now();
+
     loop {
         tokio::select! {
+            // Keepalive ping / silence check
+            _ = keepalive_timer.tick(), if keepalive.is_some() => {
+                let Some((_, timeout)) = keepalive else { continue };
+                if last_client_activity.elapsed() >= timeout {
+                    tracing::warn!(
+                        addr = %addr,
+                        silent_secs = last_client_activity.elapsed().as_secs(),
+                        "Dropping unresponsive mobile client"
+                    );
+                    break;
+                }
+                if tx.send(Message::Ping(Vec::new())).await.is_err() {
+                    break;
+                }
+            }
+
             // PTY output
             result = pty_rx.recv() => {
                 match result {
                     Ok((session_id, seq, data)) => {
-                        let (flags, capabilities, attach_id, is_viewing, is_client_active) = {
+                        let (flags, capabilities, attach_id, is_viewing, binary_pty, strip_mouse, is_client_active) = {
                             let st = state.read().await;
+                            // Every client sees every session's output on the
+                            // broadcast; drop what this one hasn't subscribed to
+                            // before any per-chunk work (credential check, mouse
+                            // filtering, encoding).
+                            let Some((attach_id, is_viewing)) =
+                                pty_output_subscription(&st, &addr, &session_id)
+                            else {
+                                continue;
+                            };
+                            if pty_chunk_replayed(&st, &addr, &session_id, seq) {
+                                continue;
+                            }
                             let active_ids = active_credential_ids_on_disk();
                             let caps = st.mobile_client_capabilities.get(&addr).copied().unwrap_or(0);
-                            let attach_id = st
-                                .mobile_attach_ids
-                                .get(&addr)
-                                .and_then(|sessions| sessions.get(&session_id).copied());
-                            let is_viewing = st
-                                .mobile_views
-                                .get(&addr)
-                                .is_some_and(|sessions| sessions.contains(&session_id));
                             (
                                 st.overhaul_flags,
                                 caps,
                                 attach_id,
                                 is_viewing,
+                                st.mobile_binary_pty.contains(&addr),
+                                st.mobile_mouse_filtered.contains(&addr),
                                 is_mobile_client_active(&st, &addr, &active_ids),
                             )
                         };
                         if !is_client_active {
                             break;
                         }
+                        let data = if strip_mouse {
+                            let mut st = state.write().await;
+                            st.mobile_mouse_filters
+                                .entry(addr)
+                                .or_default()
+                                .entry(session_id.clone())
+                                .or_default()
+                                .filter(&data)
+                        } else {
+                            data
+                        };
 
-                        let msg = if should_use_attach_v2(flags, capabilities) {
-                            let Some(attach_id) = attach_id else {
-                                continue;
-                            };
+                        let v2_attach_id = attach_id.filter(|_| should_use_attach_v2(flags, capabilities));
+                        let msg = if let Some(attach_id) = v2_attach_id {
                             ServerMessage::PtyChunk {
                                 session_id,
                                 attach_id,
@@ -1232,12 +2046,21 @@ async fn handle_mobile_client(
                                 timestamp_ms: Utc::now().timestamp_millis().max(0) as u64,
                             }
                         } else {
-                            if !is_viewing {
+                            if !is_viewing || data.is_empty() {
                                 continue;
                             }
+                            if binary_pty {
+                                if let Some(frame) = encode_binary_pty_frame(&session_id, &data) {
+                                    if tx.send(Message::Binary(frame)).await.is_err() {
+                                        break;
+                                    }
+                                    continue;
+                                }
+                            }
                             ServerMessage::PtyBytes {
                                 session_id,
                                 data: BASE64.encode(&data),
+                                seq: Some(seq),
                             }
                         };
 
@@ -1267,12 +2090,29 @@ async fn handle_mobile_client(
 
             // Client messages
             result = rx.next() => {
+                if matches!(result, Some(Ok(_))) {
+                    last_client_activity = std::time::Instant::now();
+                }
                 match result {
                     Some(Ok(Message::Text(text))) => {
                         match serde_json::from_str::<ClientMessage>(&text) {
+                            Ok(ClientMessage::Goodbye { reason }) => {
+                                tracing::info!(
+                                    addr = %addr,
+                                    reason = reason.as_deref().unwrap_or("none"),
+                                    "Mobile client said goodbye"
+                                );
+                                // Release views before acking so PTY sizes are
+                                // restored by the time the app sees the ack.
+                                cleanup_client_state(&state, addr).await;
+                                let ack = serde_json::to_string(&ServerMessage::GoodbyeAck)?;
+                                let _ = tx.send(Message::Text(ack)).await;
+                                let _ = tx.send(Message::Close(None)).await;
+                                break;
+                            }
                             Ok(msg) => process_client_msg(msg, &state, &mut tx, addr).await?,
                             Err(e) => {
-                                tracing::debug!("Ignoring unparsable client message from {}: {}", addr, e);
+                                tracing::debug!(addr = %addr, error = %e, "Ignoring unparsable client message");
                             }
                         }
                     }
@@ -1290,12 +2130,108 @@ async fn handle_mobile_client(
         let mut st = state.write().await;
         st.mobile_clients.remove(&addr);
         st.file_rate_limiters.remove(&addr);
+        st.file_op_permits.remove(&addr);
+        st.run_command_permits.remove(&addr);
     }
     cleanup_client_state(&state, addr).await;
-    tracing::info!("Mobile client disconnected: {}", addr);
+    tracing::info!(addr = %addr, "Mobile client disconnected");
     Ok(())
 }
 
+/// How `addr` is subscribed to `session_id`'s live output: its attach-v2 id
+/// and whether the session is in its views. `None` when it is neither, so
+/// the output is not for this client.
+fn pty_output_subscription(
+    st: &DaemonState,
+    addr: &SocketAddr,
+    session_id: &str,
+) -> Option<(Option<u64>, bool)> {
+    let attach_id = st
+        .mobile_attach_ids
+        .get(addr)
+        .and_then(|sessions| sessions.get(session_id).copied());
+    let is_viewing = st
+        .mobile_views
+        .get(addr)
+        .is_some_and(|sessions| sessions.contains(session_id));
+    (is_viewing || attach_id.is_some()).then_some((attach_id, is_viewing))
+}
+
+/// Whether live chunk `seq` of `session_id` was already part of the history
+/// `addr` got from `AttachSession`.
+fn pty_chunk_replayed(st: &DaemonState, addr: &SocketAddr, session_id: &str, seq: u64) -> bool {
+    st.mobile_replayed_seqs
+        .get(addr)
+        .and_then(|sessions| sessions.get(session_id))
+        .is_some_and(|&replayed| seq <= replayed)
+}
+
+/// Snapshot `session_id`'s scrollback tail and live sequence and make `addr`
+/// a viewer, all under the caller's write lock, so no live chunk falls
+/// between the history and the stream. Chunks up to the returned sequence
+/// that are still in flight are dropped by `pty_chunk_replayed`.
+fn attach_session_snapshot(
+    st: &mut DaemonState,
+    addr: SocketAddr,
+    session_id: &str,
+    history_bytes: Option<usize>,
+) -> Option<(Vec<u8>, usize, u64)> {
+    let session = st.sessions.get(session_id)?;
+    let seq = session.live_seq;
+    let strip_mouse = st.mobile_mouse_filtered.contains(&addr);
+    let (bytes, total_bytes) =
+        if should_treat_as_tui_for_mobile(&session.runtime, session.in_alt_screen) {
+            // Alternate-screen sessions are live-only.
+            (Vec::new(), 0)
+        } else {
+            tail_scrollback_bytes(
+                session,
+                history_bytes.unwrap_or(session.scrollback_max_bytes),
+            )
+        };
+    let bytes = strip_mouse_from_replay(bytes, strip_mouse);
+    register_session_view(st, addr, session_id);
+    // Output continues as sequenced `PtyBytes`, not attach-v2 chunks.
+    if let Some(attach_by_session) = st.mobile_attach_ids.get_mut(&addr) {
+        attach_by_session.remove(session_id);
+    }
+    st.mobile_replayed_seqs
+        .entry(addr)
+        .or_default()
+        .insert(session_id.to_string(), seq);
+    Some((bytes, total_bytes, seq))
+}
+
+/// Register `session`, replacing any earlier one under the same id. A
+/// re-registered session numbers its output from 0 again, so replay marks
+/// left by attaches to the old one are dropped before they swallow it.
+fn insert_pty_session(st: &mut DaemonState, session: PtySession) -> Option<PtySession> {
+    forget_replayed_seqs_for_session(st, &session.session_id);
+    st.sessions.insert(session.session_id.clone(), session)
+}
+
+/// Remove `session_id` if it is still the registration `registration_id`
+/// made. Ending a session is then a no-op once something else removed or
+/// replaced it.
+fn remove_registered_session(
+    st: &mut DaemonState,
+    session_id: &str,
+    registration_id: u64,
+) -> Option<PtySession> {
+    st.sessions
+        .get(session_id)
+        .filter(|session| session.registration_id == registration_id)?;
+    st.sessions.remove(session_id)
+}
+
+/// Drop every client's replay mark for `session_id`.
+fn forget_replayed_seqs_for_session(st: &mut DaemonState, session_id: &str) {
+    st.mobile_replayed_seqs.retain(|_, replayed| {
+        replayed.remove(session_id);
+        !replayed.is_empty()
+    });
+}
+
 /// Handle PTY session registration
 async fn handle_pty_session(
     reg_msg: serde_json::Value,
@@ -1310,9 +2246,11 @@ async fn handle_pty_session(
         .filter(|s| !s.is_empty())
         .ok_or("Missing or empty session_id in registration")?
         .to_string();
-    let name = reg_msg["name"].as_str().unwrap_or("Terminal").to_string();
     let command = reg_msg["command"].as_str().unwrap_or("shell").to_string();
     let project_path = reg_msg["project_path"].as_str().unwrap_or("").to_string();
+    // A rename from the app outlives the session id, which changes on restart.
+    let name = session::saved_session_name(&project_path, &command)
+        .unwrap_or_else(|| reg_msg["name"].as_str().unwrap_or("Terminal").to_string());
     let runtime = reg_msg["runtime"].as_str().unwrap_or("pty").to_lowercase();
     let has_desktop = reg_msg["desktop"].as_bool().unwrap_or(false);
     let (tmux_socket, tmux_session) = if runtime == "tmux" {
@@ -1324,23 +2262,48 @@ async fn handle_pty_session(
     };
 
     tracing::info!(
-        "PTY session registered: {} ({}) runtime={}",
-        name,
-        session_id,
-        runtime
+        session_id = %session_id,
+        name = %name,
+        runtime = %runtime,
+        "PTY session registered"
     );
 
     let (input_tx, mut input_rx) = mpsc::unbounded_channel::<Vec<u8>>();
     let (resize_tx, mut resize_rx) = mpsc::unbounded_channel::<ResizeRequest>();
+    let (control_tx, mut control_rx) = mpsc::unbounded_channel::<WrapperControl>();
+    let mut detached = false;
+    let mut detach_requesters: Vec<SocketAddr> = Vec::new();
 
     // Register session
-    let pty_broadcast = {
-        let mut cli_tracker = CliTracker::new();
-        cli_tracker.update_from_command(&command);
-
+    let (pty_broadcast, registration_id) = {
         let mut st = state.write().await;
-        st.sessions.insert(
-            session_id.clone(),
+        let mut cli_tracker = CliTracker::new().with_custom_patterns(st.detection_patterns.clone());
+        cli_tracker.update_from_command(&command);
+        if session_limit_reached(&st, Some(&session_id)) {
+            let max_sessions = st.max_sessions;
+            drop(st);
+            tracing::warn!(
+                session_id = %session_id,
+                max_sessions,
+                "Rejecting PTY registration: session limit reached"
+            );
+            let _ = tx
+                .send(Message::Text(
+                    serde_json::json!({
+                        "type": "error",
+                        "code": "session_limit_reached",
+                        "message": format!("session limit reached ({} sessions)", max_sessions)
+                    })
+                    .to_string(),
+                ))
+                .await;
+            let _ = tx.send(Message::Close(None)).await;
+            return Ok(());
+        }
+        let registration_id = st.next_registration_id;
+        st.next_registration_id = st.next_registration_id.saturating_add(1);
+        let previous = insert_pty_session(
+            &mut st,
             PtySession {
                 session_id: session_id.clone(),
                 runtime: runtime.clone(),
@@ -1350,25 +2313,48 @@ async fn handle_pty_session(
                 command,
                 project_path,
                 started_at: Utc::now(),
+                last_activity: Utc::now(),
                 input_tx,
                 resize_tx,
+                control_tx,
                 waiting_state: None,
                 cli_tracker,
                 last_wait_hash: None,
+                dismissed_wait_hash: None,
                 scrollback: VecDeque::new(),
                 scrollback_max_bytes: DEFAULT_SCROLLBACK_MAX_BYTES,
                 in_alt_screen: false,
                 alt_track_tail: Vec::new(),
+                screen_tail: VecDeque::new(),
                 last_resize_epoch: 0,
                 last_applied_size: None,
+                pending_resize: None,
+                resize_debounce_gen: 0,
                 live_seq: 0,
                 raw_input_tail: Vec::new(),
+                bracketed_paste: false,
+                paste_track_tail: Vec::new(),
+                local_echo: None,
+                recent_input_ids: VecDeque::new(),
+                input_history: VecDeque::new(),
                 has_desktop_wrapper: has_desktop,
+                last_heartbeat: std::time::Instant::now(),
+                sends_heartbeats: false,
+                registration_id,
             },
         );
         refresh_file_system_roots(&mut st);
-        st.pty_broadcast.clone()
-    };
+        // A wrapper reconnecting under the same id is not a new session.
+        if previous.is_none() {
+            if let Some(session) = st.sessions.get(&session_id) {
+                let msg = ServerMessage::SessionStarted {
+                    session: session_list_item(session, st.port),
+                };
+                broadcast_to_active_clients(&st, &msg);
+            }
+        }
+        (st.pty_broadcast.clone(), registration_id)
+    };
 
     // Notify mobile clients and persist to file
     broadcast_sessions_update(&state).await;
@@ -1380,7 +2366,7 @@ async fn handle_pty_session(
 
     // Buffer for detecting waiting state patterns (ANSI-stripped, normalized)
     let mut output_buffer = String::new();
-    const BUFFER_MAX_CHARS: usize = 4000; // Keep last N chars for pattern matching
+    let wait_buffer_chars = wait_buffer_chars_from_env();
 
     loop {
         tokio::select! {
@@ -1397,30 +2383,11 @@ async fn handle_pty_session(
                                         {
                                             let mut st = state.write().await;
                                             if let Some(session) = st.sessions.get_mut(&session_id) {
+                                                session.last_heartbeat = std::time::Instant::now();
                                                 session.live_seq = session.live_seq.saturating_add(1);
+                                                session.last_activity = Utc::now();
                                                 live_seq = Some(session.live_seq);
-                                                session.scrollback.extend(bytes.iter().copied());
-                                                // Truncate from front if over limit (VecDeque is O(1) per pop)
-                                                while session.scrollback.len() > session.scrollback_max_bytes {
-                                                    session.scrollback.pop_front();
-                                                }
-                                                // tmux runtime runs with alternate-screen disabled
-                                                // during bootstrap, so raw 1049/1047 bytes from
-                                                // frame CLIs are not a reliable signal for
-                                                // mobile-side alt-screen policy.
-                                                if session.runtime == "tmux" {
-                                                    session.in_alt_screen = false;
-                                                    session.alt_track_tail.clear();
-                                                } else {
-                                                    // Track alt-screen transitions across chunk
-                                                    // boundaries so subscribe_ack reflects the
-                                                    // current rendering mode reliably.
-                                                    update_alt_screen_state(
-                                                        &mut session.in_alt_screen,
-                                                        &mut session.alt_track_tail,
-                                                        &bytes,
-                                                    );
-                                                }
+                                                record_pty_output(session, &bytes);
                                             }
                                         }
                                         if let Some(seq) = live_seq {
@@ -1440,44 +2407,29 @@ async fn handle_pty_session(
                                         let normalized_chunk = strip_ansi_and_normalize(&text);
 
                                         if !normalized_chunk.is_empty() {
-                                            output_buffer.push_str(&normalized_chunk);
-                                            truncate_to_max_chars(&mut output_buffer, BUFFER_MAX_CHARS);
+                                            push_wait_buffer(&mut output_buffer, &normalized_chunk, wait_buffer_chars);
 
                                             // Update CLI tracker based on output
-                                            let cli_type = {
+                                            let (cli_type, custom_patterns) = {
                                                 let mut st = state.write().await;
                                                 if let Some(session) = st.sessions.get_mut(&session_id) {
                                                     session.cli_tracker.update_from_output(&normalized_chunk);
-                                                    session.cli_tracker.current()
+                                                    session.cli_tracker.observe_output(&normalized_chunk, Utc::now());
+                                                    (session.cli_tracker.current(), session.cli_tracker.custom_patterns())
                                                 } else {
-                                                    CliType::Terminal
+                                                    (CliType::Terminal, Default::default())
                                                 }
                                             };
 
                                             // Check for waiting state patterns
                                             tracing::debug!("Checking for wait event, cli_type: {:?}, buffer_len: {}", cli_type, output_buffer.len());
-                                            if let Some(wait_event) = detect_wait_event(&output_buffer, cli_type) {
+                                            if let Some(wait_event) = detect_wait_event(&output_buffer, cli_type, &custom_patterns) {
                                                 tracing::info!("Detected wait event: {:?} for session {}", wait_event.wait_type, session_id);
                                                 let should_notify = {
                                                     let mut st = state.write().await;
-                                                    if let Some(session) = st.sessions.get_mut(&session_id) {
-                                                        let is_new = session.waiting_state.as_ref().map(|w| {
-                                                            w.prompt_hash != wait_event.prompt_hash || w.wait_type != wait_event.wait_type
-                                                        }).unwrap_or(true);
-                                                        if is_new {
-                                                            session.waiting_state = Some(WaitingState {
-                                                                wait_type: wait_event.wait_type,
-                                                                prompt_content: wait_event.prompt.clone(),
-                                                                timestamp: Utc::now(),
-                                                                approval_model: wait_event.approval_model,
-                                                                prompt_hash: wait_event.prompt_hash,
-                                                            });
-                                                            session.last_wait_hash = Some(wait_event.prompt_hash);
-                                                        }
-                                                        is_new
-                                                    } else {
-                                                        false
-                                                    }
+                                                    st.sessions
+                                                        .get_mut(&session_id)
+                                                        .is_some_and(|session| raise_waiting(session, &wait_event))
                                                 };
 
                                                 if should_notify {
@@ -1492,26 +2444,29 @@ async fn handle_pty_session(
                                                     };
                                                     let session_id_clone = session_id.clone();
                                                     let name_clone = name.clone();
+                                                    let state_clone = state.clone();
                                                     tokio::spawn(async move {
                                                         let (title, body) = build_notification_text(cli_type, &name_clone, &wait_event);
-                                                        send_push_notifications(&tokens, &title, &body, &session_id_clone).await;
+                                                        let record = NotificationRecord {
+                                                            timestamp: Utc::now().to_rfc3339(),
+                                                            session_id: session_id_clone.clone(),
+                                                            session_name: name_clone.clone(),
+                                                            wait_type: wait_event.wait_type.as_str().to_string(),
+                                                            title: title.clone(),
+                                                            prompt: wait_event.prompt.clone(),
+                                                        };
+                                                        record_notification(&state_clone, record).await;
+                                                        send_push_notifications(&state_clone, &tokens, &title, &body, &session_id_clone).await;
                                                     });
                                                 }
                                             } else {
-                                                // If previously waiting, clear on meaningful output that is not a waiting prompt
+                                                // The recent output no longer matches any wait
+                                                // pattern, so the prompt was answered or went away.
                                                 let should_clear = {
                                                     let mut st = state.write().await;
-                                                    if let Some(session) = st.sessions.get_mut(&session_id) {
-                                                        if session.waiting_state.is_some() && normalized_chunk.trim().chars().count() >= 10 {
-                                                            session.waiting_state = None;
-                                                            session.last_wait_hash = None;
-                                                            true
-                                                        } else {
-                                                            false
-                                                        }
-                                                    } else {
-                                                        false
-                                                    }
+                                                    st.sessions
+                                                        .get_mut(&session_id)
+                                                        .is_some_and(clear_waiting_after_output)
                                                 };
 
                                                 if should_clear {
@@ -1528,13 +2483,53 @@ async fn handle_pty_session(
                                 {
                                     let mut st = state.write().await;
                                     if let Some(session) = st.sessions.get_mut(&session_id) {
+                                        session.last_heartbeat = std::time::Instant::now();
                                         session.last_applied_size = Some((cols, rows));
                                     }
                                 }
                                 broadcast_pty_resized(&state, &session_id, cols, rows, epoch).await;
+                            } else if msg["type"].as_str() == Some("echo_mode") {
+                                let Some(local_echo) = msg["local_echo"].as_bool() else {
+                                    continue;
+                                };
+                                let changed = {
+                                    let mut st = state.write().await;
+                                    st.sessions.get_mut(&session_id).is_some_and(|session| {
+                                        session.last_heartbeat = std::time::Instant::now();
+                                        session.local_echo.replace(local_echo) != Some(local_echo)
+                                    })
+                                };
+                                if changed {
+                                    let st = state.read().await;
+                                    let msg = ServerMessage::EchoMode {
+                                        session_id: session_id.clone(),
+                                        local_echo,
+                                    };
+                                    broadcast_to_active_clients(&st, &msg);
+                                }
+                            } else if msg["type"].as_str() == Some("heartbeat") {
+                                let mut st = state.write().await;
+                                if let Some(session) = st.sessions.get_mut(&session_id) {
+                                    session.last_heartbeat = std::time::Instant::now();
+                                    session.sends_heartbeats = true;
+                                }
+                            } else if msg["type"].as_str() == Some("detached") {
+                                detached = true;
+                                tracing::info!(session_id = %session_id, "PTY session detached by mobile client");
+                                break;
+                            } else if msg["type"].as_str() == Some("detach_failed") {
+                                let message = msg["message"].as_str().unwrap_or("tmux detach failed");
+                                tracing::warn!(session_id = %session_id, error = %message, "Wrapper failed to detach");
+                                let err = ServerMessage::Error {
+                                    code: "detach_failed".to_string(),
+                                    message: format!("Session {} could not be detached: {}", session_id, message),
+                                };
+                                for addr in detach_requesters.drain(..) {
+                                    queue_to_client(&state, addr, &err).await;
+                                }
                             } else if msg["type"].as_str() == Some("session_ended") {
                                 exit_code = msg["exit_code"].as_i64().unwrap_or(0) as i32;
-                                tracing::info!("PTY session {} ended (exit_code={})", session_id, exit_code);
+                                tracing::info!(session_id = %session_id, exit_code, "PTY session ended");
                                 break;
                             }
                         }
@@ -1543,7 +2538,9 @@ async fn handle_pty_session(
                         let seq = {
                             let mut st = state.write().await;
                             if let Some(session) = st.sessions.get_mut(&session_id) {
+                                session.last_heartbeat = std::time::Instant::now();
                                 session.live_seq = session.live_seq.saturating_add(1);
+                                session.last_activity = Utc::now();
                                 session.live_seq
                             } else {
                                 0
@@ -1627,28 +2624,46 @@ async fn handle_pty_session(
                     None => break,
                 }
             }
+
+            // Control requests from mobile (e.g. DetachSession)
+            result = control_rx.recv() => {
+                match result {
+                    Some(WrapperControl::Detach { requested_by }) => {
+                        // The session stays registered until the wrapper
+                        // answers `detached` or `detach_failed`.
+                        let msg = serde_json::json!({ "type": "detach" });
+                        if tx.send(Message::Text(msg.to_string())).await.is_err() {
+                            break;
+                        }
+                        detach_requesters.push(requested_by);
+                    }
+                    None => break,
+                }
+            }
         }
     }
 
-    // Unregister session (skip if already removed by CloseSession)
+    // Unregister session (skip if already removed by CloseSession, the
+    // reaper, or a newer registration under the same id)
     let was_present = {
         let mut st = state.write().await;
-        if st.sessions.remove(&session_id).is_some() {
+        if let Some(session) = remove_registered_session(&mut st, &session_id, registration_id) {
             st.tmux_viewport_controllers.remove(&session_id);
             clear_mobile_attach_for_session(&mut st, &session_id);
             refresh_file_system_roots(&mut st);
-            // Notify about session end
-            let msg = ServerMessage::SessionEnded {
-                session_id: session_id.clone(),
-                exit_code,
+            // Notify about session end, or that the tmux session lives on
+            let msg = match (detached, session.tmux_socket, session.tmux_session) {
+                (true, Some(tmux_socket), Some(tmux_session)) => ServerMessage::SessionDetached {
+                    session_id: session_id.clone(),
+                    tmux_socket,
+                    tmux_session,
+                },
+                _ => ServerMessage::SessionEnded {
+                    session_id: session_id.clone(),
+                    exit_code,
+                },
             };
-            let msg_str = serde_json::to_string(&msg)?;
-            let active_ids = active_credential_ids_on_disk();
-            for (addr, client) in &st.mobile_clients {
-                if is_mobile_client_active(&st, addr, &active_ids) {
-                    let _ = client.try_send(Message::Text(msg_str.clone()));
-                }
-            }
+            broadcast_to_active_clients(&st, &msg);
             true
         } else {
             false
@@ -1663,52 +2678,63 @@ async fn handle_pty_session(
         persist_sessions_to_file(&state).await;
     }
 
-    tracing::info!("PTY session ended: {}", session_id);
+    tracing::info!(session_id = %session_id, "PTY session ended");
     Ok(())
 }
 
+/// Built-in commands mobile may always spawn. Users extend this list with
+/// `spawn.allowed_commands` in `~/.mobilecli/config.json`.
+const ALLOWED_COMMANDS: &[&str] = &[
+    "claude",
+    "codex",
+    "gemini",
+    "opencode",
+    "shell",
+    "bash",
+    "zsh",
+    "sh",
+    "fish",
+    "nu",
+    "pwsh",
+    "powershell",
+    "python",
+    "python3",
+    "node",
+    "ruby",
+];
+
+/// Extra spawnable commands configured by the user.
+fn configured_spawn_commands() -> Vec<String> {
+    crate::setup::load_config()
+        .map(|cfg| cfg.spawn.allowed_commands)
+        .unwrap_or_default()
+}
+
 /// Validate command name - only allow known safe CLI commands
-fn is_allowed_command(command: &str) -> bool {
+fn is_allowed_command(command: &str, extra_commands: &[String]) -> bool {
     let path = std::path::Path::new(command);
     if path.is_absolute() || command.contains('/') || command.contains('\\') {
         return false;
     }
-    const ALLOWED_COMMANDS: &[&str] = &[
-        "claude",
-        "codex",
-        "gemini",
-        "opencode",
-        "shell",
-        "bash",
-        "zsh",
-        "sh",
-        "fish",
-        "nu",
-        "pwsh",
-        "powershell",
-        "python",
-        "python3",
-        "node",
-        "ruby",
-    ];
     // Get base command name (handle paths like /usr/bin/bash)
     let base = std::path::Path::new(command)
         .file_name()
         .and_then(|s| s.to_str())
         .unwrap_or(command);
-    ALLOWED_COMMANDS.contains(&base)
+    ALLOWED_COMMANDS.contains(&base) || extra_commands.iter().any(|c| c == base)
 }
 
 fn normalize_mobile_spawn_request(
     command: &str,
     args: &[String],
+    extra_commands: &[String],
 ) -> Result<(String, Vec<String>), Box<dyn std::error::Error + Send + Sync>> {
     if !args.is_empty() {
         return Err(
             "Mobile-spawn arguments are not accepted; choose a supported spawn profile".into(),
         );
     }
-    if !is_allowed_command(command) {
+    if !is_allowed_command(command, extra_commands) {
         return Err(format!("Command '{}' is not in the allowed profile list", command).into());
     }
     let normalized = match command {
@@ -1716,7 +2742,7 @@ fn normalize_mobile_spawn_request(
         "shell" | "bash" | "sh" | "zsh" | "fish" | "nu" | "pwsh" | "powershell" => {
             let default_shell = platform::default_shell();
             let base = shell_base_name(&default_shell);
-            if is_allowed_command(&base) {
+            if is_allowed_command(&base, extra_commands) {
                 base
             } else if cfg!(windows) {
                 "powershell".to_string()
@@ -1724,6 +2750,7 @@ fn normalize_mobile_spawn_request(
                 "sh".to_string()
             }
         }
+        _ if extra_commands.iter().any(|c| c == command) => command.to_string(),
         _ => return Err("Unsupported mobile spawn profile".into()),
     };
     Ok((normalized, Vec::new()))
@@ -1739,6 +2766,40 @@ fn is_shell_safe(s: &str) -> bool {
         && !s.contains("$(")
 }
 
+/// Environment variable name prefixes a mobile spawn may not set, because
+/// they change how the wrapper or the dynamic loader behave rather than the CLI.
+const RESERVED_SPAWN_ENV_PREFIXES: &[&str] = &["MOBILECLI_", "LD_", "DYLD_"];
+
+/// Validate that an env var name matches `[A-Z_][A-Z0-9_]*`
+fn is_valid_env_key(key: &str) -> bool {
+    let mut chars = key.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_uppercase() || c == '_')
+        && chars.all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_')
+}
+
+/// Validate mobile-supplied environment variables before they reach a spawn.
+fn validate_spawn_env(
+    env: &[(String, String)],
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    for (key, value) in env {
+        if !is_valid_env_key(key) {
+            return Err(format!("Environment variable name '{}' is not allowed", key).into());
+        }
+        if RESERVED_SPAWN_ENV_PREFIXES
+            .iter()
+            .any(|prefix| key.starts_with(prefix))
+        {
+            return Err(format!("Environment variable '{}' is reserved", key).into());
+        }
+        if !is_shell_safe(value) {
+            return Err(
+                format!("Environment variable '{}' contains unsafe characters", key).into(),
+            );
+        }
+    }
+    Ok(())
+}
+
 /// POSIX-safe single-quote wrapper for shell tokens.
 #[cfg(not(windows))]
 fn shell_quote_posix(s: &str) -> String {
@@ -1875,6 +2936,7 @@ fn spawn_session_windows(
     args: &[String],
     name: Option<&str>,
     working_dir: Option<&str>,
+    env: &[(String, String)],
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     use std::os::windows::process::CommandExt;
 
@@ -1903,6 +2965,7 @@ fn spawn_session_windows(
     }
     cmd.arg(effective_command);
     cmd.args(effective_args);
+    cmd.envs(env.iter().map(|(k, v)| (k, v)));
     // Keep host desktop geometry stable for Windows demo sessions even if
     // the machine has a legacy mirror policy set in the environment.
     cmd.env("MOBILECLI_DESKTOP_RESIZE_POLICY", "preserve");
@@ -1916,19 +2979,160 @@ fn spawn_session_windows(
     Ok(())
 }
 
+/// Output of a `RunCommand`, each stream cut at the cap.
+#[derive(Debug, Default)]
+struct CapturedRun {
+    exit_code: Option<i32>,
+    stdout: String,
+    stderr: String,
+    truncated: bool,
+}
+
+/// Shells and interpreters a spawn may start but `RunCommand` may not: with
+/// arguments passed through, `bash -c` or `python -c` would run any code.
+const RUN_COMMAND_DENIED_COMMANDS: &[&str] = &[
+    "shell",
+    "bash",
+    "zsh",
+    "sh",
+    "dash",
+    "ksh",
+    "csh",
+    "tcsh",
+    "fish",
+    "nu",
+    "pwsh",
+    "powershell",
+    "cmd",
+    "python",
+    "python3",
+    "node",
+    "deno",
+    "bun",
+    "ruby",
+    "perl",
+    "php",
+    "lua",
+    "env",
+];
+
+/// Check a mobile `RunCommand` against the spawn allowlist. Unlike a spawn,
+/// arguments are passed through, so shells and interpreters are refused.
+fn validate_run_command(
+    command: &str,
+    args: &[String],
+    extra_commands: &[String],
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    if !is_allowed_command(command, extra_commands) {
+        return Err(format!("Command '{}' is not in the allowed list", command).into());
+    }
+    if RUN_COMMAND_DENIED_COMMANDS.contains(&shell_base_name(command).as_str()) {
+        return Err(format!("Command '{}' cannot be run without a terminal", command).into());
+    }
+    if !is_shell_safe(command) {
+        return Err("Command contains unsafe characters".into());
+    }
+    if args.iter().any(|arg| !is_shell_safe(arg)) {
+        return Err("Argument contains unsafe characters".into());
+    }
+    Ok(())
+}
+
+/// Validate a mobile `RunCommand`, then run it without a terminal. The
+/// command is executed directly, never through a shell, in `working_dir` (or
+/// the default spawn directory) once the file system validator accepts it.
+async fn run_command_from_mobile(
+    validator: &PathValidator,
+    command: &str,
+    args: &[String],
+    working_dir: Option<&str>,
+) -> Result<CapturedRun, Box<dyn std::error::Error + Send + Sync>> {
+    validate_run_command(command, args, &configured_spawn_commands())?;
+    let dir = match working_dir {
+        Some(dir) => dir.to_string(),
+        None => default_mobile_spawn_working_dir()?,
+    };
+    let dir = resolve_mobile_spawn_working_dir(validator, Some(&dir), None)?.unwrap_or(dir);
+    run_captured(
+        command,
+        args,
+        Path::new(&dir),
+        RUN_COMMAND_OUTPUT_MAX_BYTES,
+        RUN_COMMAND_TIMEOUT,
+    )
+    .await
+}
+
+/// Run `command` with no stdin, keeping up to `max_bytes` of each output
+/// stream. Past the cap the pipe is closed, so a chatty command usually
+/// ends on SIGPIPE; one still running at `timeout` is killed.
+async fn run_captured(
+    command: &str,
+    args: &[String],
+    dir: &Path,
+    max_bytes: usize,
+    timeout: Duration,
+) -> Result<CapturedRun, Box<dyn std::error::Error + Send + Sync>> {
+    use tokio::io::AsyncReadExt;
+
+    let mut child = tokio::process::Command::new(command)
+        .args(args)
+        .current_dir(dir)
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
+
+    async fn read_capped<R: tokio::io::AsyncRead + Unpin>(
+        pipe: Option<R>,
+        max_bytes: usize,
+    ) -> (String, bool) {
+        let mut buf = Vec::new();
+        if let Some(pipe) = pipe {
+            let _ = pipe.take(max_bytes as u64 + 1).read_to_end(&mut buf).await;
+        }
+        let truncated = buf.len() > max_bytes;
+        buf.truncate(max_bytes);
+        (String::from_utf8_lossy(&buf).into_owned(), truncated)
+    }
+
+    let (stdout, stderr) = (child.stdout.take(), child.stderr.take());
+    let run = async {
+        let ((stdout, out_cut), (stderr, err_cut)) = tokio::join!(
+            read_capped(stdout, max_bytes),
+            read_capped(stderr, max_bytes)
+        );
+        let status = child.wait().await?;
+        Ok::<_, std::io::Error>(CapturedRun {
+            exit_code: status.code(),
+            stdout,
+            stderr,
+            truncated: out_cut || err_cut,
+        })
+    };
+    match tokio::time::timeout(timeout, run).await {
+        Ok(result) => Ok(result?),
+        Err(_) => Err(format!("Command timed out after {}s", timeout.as_secs()).into()),
+    }
+}
+
 /// Spawn a new session from mobile request
 async fn spawn_session_from_mobile(
     command: &str,
     args: &[String],
     name: Option<&str>,
     working_dir: Option<&str>,
+    env: &[(String, String)],
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    let (command, normalized_args) = normalize_mobile_spawn_request(command, args)?;
+    let extra_commands = configured_spawn_commands();
+    let (command, normalized_args) =
+        normalize_mobile_spawn_request(command, args, &extra_commands)?;
     let command = command.as_str();
     let args = normalized_args.as_slice();
 
     // Security: Validate command is in allowlist
-    if !is_allowed_command(command) {
+    if !is_allowed_command(command, &extra_commands) {
         return Err(format!("Command '{}' is not in the allowed list", command).into());
     }
 
@@ -1946,6 +3150,7 @@ async fn spawn_session_from_mobile(
             return Err("Name contains unsafe characters".into());
         }
     }
+    validate_spawn_env(env)?;
     let effective_working_dir = if let Some(dir) = working_dir {
         if !is_shell_safe(dir) {
             return Err("Working directory contains unsafe characters".into());
@@ -1969,7 +3174,7 @@ async fn spawn_session_from_mobile(
 
     #[cfg(windows)]
     {
-        return spawn_session_windows(command, args, name, effective_working_dir);
+        return spawn_session_windows(command, args, name, effective_working_dir, env);
     }
 
     #[cfg(not(windows))]
@@ -2092,7 +3297,13 @@ async fn spawn_session_from_mobile(
                 let shell_cmd = shell_command_line(&shell, &shell_args);
                 tracing::info!("Spawning session headless (tmux): {}", wrap_cmd);
                 let mut c = std::process::Command::new("tmux");
-                c.args(["new-session", "-d", "-s", &tmux_name, &shell_cmd]);
+                c.args(["new-session", "-d", "-s", &tmux_name]);
+                // A running tmux server does not inherit the client's env,
+                // so pass spawn env explicitly to the new session.
+                for (key, value) in env {
+                    c.arg("-e").arg(format!("{}={}", key, value));
+                }
+                c.arg(&shell_cmd);
                 c
             } else {
                 // Direct spawn: mobilecli pty-wrap creates its own PTY
@@ -2107,6 +3318,7 @@ async fn spawn_session_from_mobile(
         if let Some(dir) = effective_working_dir {
             cmd.current_dir(dir);
         }
+        cmd.envs(env.iter().map(|(k, v)| (k, v)));
 
         // Spawn detached
         #[cfg(unix)]
@@ -2132,6 +3344,63 @@ async fn spawn_session_from_mobile(
     }
 }
 
+/// Resolve a mobile-supplied spawn directory to an absolute path.
+///
+/// Relative paths are joined onto `base`, or onto the default mobile spawn
+/// root. Either way the result must be an existing directory the file system
+/// validator accepts: inside the jail and clear of denied patterns.
+fn resolve_mobile_spawn_working_dir(
+    validator: &PathValidator,
+    working_dir: Option<&str>,
+    base: Option<&str>,
+) -> Result<Option<String>, Box<dyn std::error::Error + Send + Sync>> {
+    let Some(dir) = working_dir else {
+        return Ok(None);
+    };
+    if !is_shell_safe(dir) {
+        return Err("Working directory contains unsafe characters".into());
+    }
+    let joined = if std::path::Path::new(dir).is_absolute() {
+        std::path::PathBuf::from(dir)
+    } else {
+        let base = match base {
+            Some(base) => {
+                if !is_shell_safe(base) {
+                    return Err("Base directory contains unsafe characters".into());
+                }
+                if !std::path::Path::new(base).is_absolute() {
+                    return Err("Base directory must be an absolute path".into());
+                }
+                base.to_string()
+            }
+            None => default_mobile_spawn_working_dir()?,
+        };
+        std::path::Path::new(&base).join(dir)
+    };
+    let canonical = validator
+        .validate_existing(&joined.to_string_lossy())
+        .map_err(|e| match e {
+            FileSystemError::PathTraversal { .. } => {
+                "Working directory escapes the approved filesystem roots".to_string()
+            }
+            FileSystemError::PermissionDenied { reason, .. } => reason,
+            FileSystemError::OutsideAllowedRoots { .. } => {
+                "Working directory is outside the approved filesystem roots".to_string()
+            }
+            FileSystemError::DeniedByPattern { pattern, .. } => {
+                format!("Working directory matches denied pattern: {}", pattern)
+            }
+            FileSystemError::IoError { message } => {
+                format!("Working directory could not be resolved: {}", message)
+            }
+            _ => "Working directory is not accessible".to_string(),
+        })?;
+    if !canonical.is_dir() {
+        return Err("Working directory does not exist or is not a directory".into());
+    }
+    Ok(Some(canonical.to_string_lossy().to_string()))
+}
+
 fn is_path_within_approved_roots(path: &std::path::Path) -> bool {
     let Ok(canonical_path) = path.canonicalize() else {
         return false;
@@ -2300,6 +3569,19 @@ async fn process_client_msg(
         }
     }
 
+    // Held until the operation finishes; work spawned off the connection
+    // loop takes it along. Cancelling must not queue behind what it cancels.
+    let file_op_permit = match required_scope_for_message(&msg) {
+        Some(scope) if scope.starts_with("fs:") => {
+            if matches!(msg, ClientMessage::CancelRequest { .. }) {
+                None
+            } else {
+                acquire_file_op_permit(state, addr).await
+            }
+        }
+        _ => None,
+    };
+
     match msg {
         ClientMessage::AuthStart { .. } | ClientMessage::AuthResponse { .. } => {
             let err = ServerMessage::Error {
@@ -2310,11 +3592,36 @@ async fn process_client_msg(
         }
         ClientMessage::Hello {
             client_version,
+            protocol_version,
             sender_id,
             client_capabilities,
+            binary_pty,
+            mouse_capable,
         } => {
             // Already sent Welcome on connect, but log the client version
-            tracing::debug!("Client hello, version: {}", client_version);
+            tracing::debug!(
+                client_version = %client_version,
+                protocol_version = protocol_version.unwrap_or(1),
+                "Client hello"
+            );
+            {
+                let mut st = state.write().await;
+                st.mobile_protocol_versions.insert(
+                    addr,
+                    protocol_version.unwrap_or(1).clamp(1, PROTOCOL_VERSION),
+                );
+                if binary_pty {
+                    st.mobile_binary_pty.insert(addr);
+                } else {
+                    st.mobile_binary_pty.remove(&addr);
+                }
+                if mouse_capable == Some(false) {
+                    st.mobile_mouse_filtered.insert(addr);
+                } else {
+                    st.mobile_mouse_filtered.remove(&addr);
+                    st.mobile_mouse_filters.remove(&addr);
+                }
+            }
             if let Some(sender_id) = sender_id
                 .map(|s| s.trim().to_string())
                 .filter(|s| !s.is_empty())
@@ -2352,544 +3659,153 @@ async fn process_client_msg(
             last_seen_seq,
             client_capabilities,
         } => {
-            tracing::debug!("Client subscribed to session: {}", session_id);
-            let attach_started = std::time::Instant::now();
-            let mut st = state.write().await;
-            if let Some(capabilities) = client_capabilities {
-                st.mobile_client_capabilities.insert(addr, capabilities);
+            subscribe_session(
+                state,
+                tx,
+                addr,
+                session_id,
+                last_seen_seq,
+                client_capabilities,
+            )
+            .await;
+        }
+        ClientMessage::SubscribeAll {
+            client_capabilities,
+        } => {
+            let mut session_ids: Vec<String> =
+                { state.read().await.sessions.keys().cloned().collect() };
+            session_ids.sort();
+            for session_id in session_ids {
+                subscribe_session(state, tx, addr, session_id, None, client_capabilities).await;
             }
-            let attach_id = st.next_attach_id;
-            st.next_attach_id = st.next_attach_id.saturating_add(1);
-            let overhaul_flags = st.overhaul_flags;
-            let capabilities = st
-                .mobile_client_capabilities
-                .get(&addr)
-                .copied()
-                .unwrap_or(0);
-            let use_attach_v2 = should_use_attach_v2(overhaul_flags, capabilities);
-
-            // Collect session state under one lock, then drop it before sending.
-            let (
-                scrollback_bytes,
-                render_as_tui,
-                mobile_in_alt_screen,
-                scrollback_len,
-                runtime,
-                tmux_snapshot_req,
-                initial_live_seq,
-                ready_cols,
-                ready_rows,
-            ) = if let Some(session) = st.sessions.get(&session_id) {
-                let render_as_tui =
-                    should_treat_as_tui_for_mobile(&session.runtime, session.in_alt_screen);
-                let mobile_in_alt_screen =
-                    should_mobile_enter_alt_screen(&session.runtime, session.in_alt_screen);
-                let has_scrollback = !session.scrollback.is_empty();
-                let runtime = session.runtime.clone();
-                // tmux sessions always replay from capture-pane. Alternate-screen
-                // sessions replay only the visible pane; main-screen sessions may
-                // include scrollback.
-                let tmux_snapshot_req = if runtime == "tmux" {
-                    match (&session.tmux_socket, &session.tmux_session) {
-                        (Some(socket), Some(name)) => Some((
-                            socket.clone(),
-                            name.clone(),
-                            session.scrollback_max_bytes,
-                            !session.in_alt_screen,
-                        )),
-                        _ => None,
+        }
+        ClientMessage::UnsubscribeAll => {
+            unsubscribe_all_sessions(state, addr).await;
+        }
+        ClientMessage::AttachSession {
+            session_id,
+            history_bytes,
+        } => {
+            let attached = {
+                let mut st = state.write().await;
+                attach_session_snapshot(&mut st, addr, &session_id, history_bytes)
+            };
+            let msg = match attached {
+                Some((bytes, total_bytes, seq)) => {
+                    tracing::debug!(addr = %addr, session_id = %session_id, seq, "Client attached to session");
+                    ServerMessage::SessionAttached {
+                        session_id,
+                        data: BASE64.encode(&bytes),
+                        total_bytes,
+                        seq,
                     }
-                } else {
-                    None
-                };
-                let sb = if runtime != "tmux" && !render_as_tui && has_scrollback {
-                    Some(session.scrollback.iter().copied().collect::<Vec<u8>>())
-                } else {
-                    None
-                };
-                let (ready_cols, ready_rows) = session.last_applied_size.unwrap_or((0, 0));
-                (
-                    sb,
-                    render_as_tui,
-                    mobile_in_alt_screen,
-                    session.scrollback.len(),
-                    runtime,
-                    tmux_snapshot_req,
-                    session.live_seq,
-                    ready_cols,
-                    ready_rows,
-                )
-            } else {
-                (None, false, false, 0, "pty".to_string(), None, 0, 0, 0)
+                }
+                None => ServerMessage::Error {
+                    code: "session_not_found".to_string(),
+                    message: format!("Session {} not found", session_id),
+                },
             };
-
-            tracing::debug!(
-                session_id = %session_id,
-                addr = %addr,
-                attach_id,
-                runtime = %runtime,
-                capabilities,
-                use_attach_v2,
-                last_seen_seq = ?last_seen_seq,
-                render_as_tui,
-                mobile_in_alt_screen,
-                scrollback_len,
-                text_replay_bytes = scrollback_bytes.as_ref().map(|b| b.len()).unwrap_or(0),
-                "Subscribe replay decision"
-            );
-            drop(st);
-            tracing::info!(
-                target: "overhaul.attach",
-                session_id = %session_id,
-                addr = %addr,
-                attach_id,
-                runtime = %runtime,
-                protocol = overhaul_flags.attach_protocol.as_str(),
-                use_attach_v2,
-                last_seen_seq = ?last_seen_seq,
-                "Attach sequence started"
-            );
-            let clear_mode = if mobile_in_alt_screen {
-                "alternate_screen"
-            } else {
-                "main_screen"
+            tx.send(Message::Text(serde_json::to_string(&msg)?)).await?;
+        }
+        ClientMessage::Unsubscribe { session_id } => {
+            tracing::debug!(addr = %addr, session_id = %session_id, "Client unsubscribed from session");
+            unsubscribe_session(state, addr, &session_id).await;
+        }
+        ClientMessage::SendInput {
+            session_id,
+            text,
+            raw,
+            client_msg_id,
+        } => {
+            let mut st = state.write().await;
+            let sender = input_sender(&st, &addr);
+            let Some(session) = st.sessions.get_mut(&session_id) else {
+                return Ok(());
             };
-            let mut replay_events: Vec<(&'static str, usize)> = Vec::new();
-            if use_attach_v2 {
-                let begin = ServerMessage::AttachBegin {
-                    session_id: session_id.clone(),
-                    attach_id,
-                    runtime: runtime.clone(),
-                    mode: if last_seen_seq.is_some() {
-                        "reconnect".to_string()
-                    } else {
-                        "fresh".to_string()
-                    },
-                    in_alt_screen: mobile_in_alt_screen,
+            session.last_activity = Utc::now();
+            let is_resend = client_msg_id
+                .as_deref()
+                .is_some_and(|id| !remember_input_id(&mut session.recent_input_ids, &sender, id));
+            if is_resend {
+                tracing::debug!(
+                    session_id = %session_id,
+                    client_msg_id = ?client_msg_id,
+                    "Dropping resent mobile input"
+                );
+            } else {
+                deliver_mobile_input(session, &text, raw);
+            }
+            if let Some(client_msg_id) = client_msg_id {
+                let ack = ServerMessage::InputAck {
+                    session_id,
+                    client_msg_id,
                 };
-                if let Ok(text) = serde_json::to_string(&begin) {
-                    let _ = tx.send(Message::Text(text)).await;
+                let understood = client_understands(&st, &addr, &ack);
+                drop(st);
+                if understood {
+                    tx.send(Message::Text(serde_json::to_string(&ack)?)).await?;
                 }
             }
-
-            // ── Step 1: wipe mobile's xterm.js buffer ──────────────────
-            //
-            // On every (re-)subscribe we first clear the client terminal so
-            // that replay data doesn't append to stale content from a prior
-            // subscribe. Sessions in mobile alternate-screen use 1049h clear.
-            // Text main-buffer sessions erase display+scrollback so the next
-            // replay starts from a deterministic baseline.
-            //
-            {
-                if use_attach_v2 {
-                    let clear = ServerMessage::AttachClear {
-                        session_id: session_id.clone(),
-                        attach_id,
-                    };
-                    if let Ok(text) = serde_json::to_string(&clear) {
-                        let _ = tx.send(Message::Text(text)).await;
-                    }
-                } else {
-                    let clear: &[u8] = if mobile_in_alt_screen {
-                        // \x1b[?1049h  enter alternate screen
-                        // \x1b[2J      erase display
-                        // \x1b[H       cursor home
-                        b"\x1b[?1049h\x1b[2J\x1b[H"
-                    } else {
-                        // \x1b[2J      erase display
-                        // \x1b[3J      erase scrollback (xterm extension, supported by xterm.js)
-                        // \x1b[H       cursor home
-                        b"\x1b[2J\x1b[3J\x1b[H"
-                    };
-                    let clear_msg = ServerMessage::PtyBytes {
-                        session_id: session_id.clone(),
-                        data: BASE64.encode(clear),
-                    };
-                    if let Ok(text) = serde_json::to_string(&clear_msg) {
-                        let _ = tx.send(Message::Text(text)).await;
-                    }
+        }
+        ClientMessage::BroadcastInput { text, raw, filter } => {
+            let delivered = {
+                let mut st = state.write().await;
+                broadcast_mobile_input(&mut st, addr, &text, raw, filter.as_ref())
+            };
+            tracing::info!(addr = %addr, delivered, "Broadcast mobile input");
+            let msg = ServerMessage::BroadcastResult { delivered };
+            tx.send(Message::Text(serde_json::to_string(&msg)?)).await?;
+        }
+        ClientMessage::PasteInput { session_id, text } => {
+            let st = state.read().await;
+            if let Some(session) = st.sessions.get(&session_id) {
+                let payload = paste_payload(&text, session.bracketed_paste);
+                if !payload.is_empty() {
+                    let _ = session.input_tx.send(payload);
                 }
-                tracing::info!(
-                    target: "overhaul.attach",
+            }
+        }
+        ClientMessage::PtyResize {
+            session_id,
+            cols,
+            rows,
+            epoch,
+            reason,
+        } => {
+            let reason = resolve_resize_reason(cols, rows, reason);
+            let mut st = state.write().await;
+            let resize_simplified = st.overhaul_flags.resize_simplified;
+            let is_restore = reason == PtyResizeReason::DetachRestore;
+            let viewer_count = st
+                .session_view_counts
+                .get(&session_id)
+                .copied()
+                .unwrap_or(0);
+            let sender_is_viewing = st
+                .mobile_views
+                .get(&addr)
+                .map(|views| views.contains(&session_id))
+                .unwrap_or(false);
+            let mut synthetic_ack: Option<(u16, u16, Option<u64>)> = None;
+
+            let ignore_no_viewer_resize =
+                should_ignore_resize_without_viewers(is_restore, viewer_count, reason);
+            if ignore_no_viewer_resize {
+                tracing::debug!(
+                    target: "overhaul.resize",
                     session_id = %session_id,
-                    attach_id,
-                    clear_mode,
-                    clear_bytes = if use_attach_v2 { 0 } else if mobile_in_alt_screen { b"\x1b[?1049h\x1b[2J\x1b[H".len() } else { b"\x1b[2J\x1b[3J\x1b[H".len() },
-                    "Attach clear frame sent"
+                    cols,
+                    rows,
+                    epoch = ?epoch,
+                    reason = reason.as_str(),
+                    viewer_count,
+                    sender_is_viewing,
+                    resize_simplified,
+                    bootstrap_reason = matches!(reason, PtyResizeReason::AttachInit | PtyResizeReason::ReconnectSync),
+                    decision = "ignored_no_active_viewers",
+                    "Ignoring PTY resize with no active mobile viewers"
                 );
-            }
-
-            // ── Step 2: replay history ────────────────────────────────────
-            //
-            // PTY text sessions replay daemon scrollback.
-            if !render_as_tui {
-                if let Some(bytes) = scrollback_bytes {
-                    replay_events.push(("daemon_scrollback", bytes.len()));
-                    if use_attach_v2 {
-                        let chunks = chunk_snapshot_payload(&bytes);
-                        let total_chunks = chunks.len().max(1) as u32;
-                        if chunks.is_empty() {
-                            let msg = ServerMessage::AttachSnapshotChunk {
-                                session_id: session_id.clone(),
-                                attach_id,
-                                chunk_seq: 1,
-                                total_chunks,
-                                is_last: true,
-                                data: String::new(),
-                            };
-                            if let Ok(text) = serde_json::to_string(&msg) {
-                                let _ = tx.send(Message::Text(text)).await;
-                            }
-                        } else {
-                            for (idx, chunk) in chunks.into_iter().enumerate() {
-                                let msg = ServerMessage::AttachSnapshotChunk {
-                                    session_id: session_id.clone(),
-                                    attach_id,
-                                    chunk_seq: (idx + 1) as u32,
-                                    total_chunks,
-                                    is_last: idx + 1 == total_chunks as usize,
-                                    data: chunk,
-                                };
-                                if let Ok(text) = serde_json::to_string(&msg) {
-                                    let _ = tx.send(Message::Text(text)).await;
-                                }
-                            }
-                        }
-                    } else {
-                        let msg = ServerMessage::PtyBytes {
-                            session_id: session_id.clone(),
-                            data: BASE64.encode(&bytes),
-                        };
-                        if let Ok(text) = serde_json::to_string(&msg) {
-                            let _ = tx.send(Message::Text(text)).await;
-                        }
-                    }
-                    tracing::info!(
-                        target: "overhaul.attach",
-                        session_id = %session_id,
-                        attach_id,
-                        replay_source = "daemon_scrollback",
-                        replay_bytes = bytes.len(),
-                        "Attach replay sent"
-                    );
-                }
-            }
-
-            // tmux sessions always get an authoritative pane snapshot on
-            // subscribe.
-            if let Some((socket, name, max_bytes, include_scrollback)) = tmux_snapshot_req {
-                let max_lines = if include_scrollback {
-                    SUBSCRIBE_SCROLLBACK_LINES
-                } else {
-                    0
-                };
-                if let Some(snapshot) =
-                    capture_tmux_history_with_retry(socket, name, include_scrollback, max_lines)
-                        .await
-                {
-                    let total_bytes = snapshot.len();
-                    let skip = total_bytes.saturating_sub(max_bytes);
-                    let bytes = &snapshot[skip..];
-                    replay_events.push(("tmux_capture_pane", bytes.len()));
-                    if use_attach_v2 {
-                        let chunks = chunk_snapshot_payload(bytes);
-                        let total_chunks = chunks.len().max(1) as u32;
-                        if chunks.is_empty() {
-                            let msg = ServerMessage::AttachSnapshotChunk {
-                                session_id: session_id.clone(),
-                                attach_id,
-                                chunk_seq: 1,
-                                total_chunks,
-                                is_last: true,
-                                data: String::new(),
-                            };
-                            if let Ok(text) = serde_json::to_string(&msg) {
-                                let _ = tx.send(Message::Text(text)).await;
-                            }
-                        } else {
-                            for (idx, chunk) in chunks.into_iter().enumerate() {
-                                let msg = ServerMessage::AttachSnapshotChunk {
-                                    session_id: session_id.clone(),
-                                    attach_id,
-                                    chunk_seq: (idx + 1) as u32,
-                                    total_chunks,
-                                    is_last: idx + 1 == total_chunks as usize,
-                                    data: chunk,
-                                };
-                                if let Ok(text) = serde_json::to_string(&msg) {
-                                    let _ = tx.send(Message::Text(text)).await;
-                                }
-                            }
-                        }
-                    } else {
-                        let msg = ServerMessage::SessionHistory {
-                            session_id: session_id.clone(),
-                            data: BASE64.encode(bytes),
-                            total_bytes,
-                        };
-                        if let Ok(text) = serde_json::to_string(&msg) {
-                            let _ = tx.send(Message::Text(text)).await;
-                        }
-                    }
-                    tracing::info!(
-                        target: "overhaul.attach",
-                        session_id = %session_id,
-                        attach_id,
-                        replay_source = "tmux_capture_pane",
-                        replay_bytes = bytes.len(),
-                        total_snapshot_bytes = total_bytes,
-                        "Attach replay sent"
-                    );
-                } else {
-                    tracing::warn!(
-                        target: "overhaul.attach",
-                        session_id = %session_id,
-                        attach_id,
-                        include_scrollback,
-                        "tmux capture-pane unavailable during attach replay"
-                    );
-                }
-            }
-
-            // Always send SubscribeAck so mobile knows whether to suppress
-            // stale bytes and enter alt-screen mode before resizing (v1 path).
-            let runtime_for_log = runtime.clone();
-            if use_attach_v2 {
-                let ready_live_seq = {
-                    let st = state.read().await;
-                    st.sessions
-                        .get(&session_id)
-                        .map(|session| session.live_seq)
-                        .unwrap_or(initial_live_seq)
-                };
-                tracing::debug!(
-                    target: "overhaul.sequence",
-                    session_id = %session_id,
-                    attach_id,
-                    initial_live_seq,
-                    ready_live_seq,
-                    "Attach ready sequence barrier"
-                );
-                let ready = ServerMessage::AttachReady {
-                    session_id: session_id.clone(),
-                    attach_id,
-                    last_live_seq: ready_live_seq,
-                    cols: ready_cols,
-                    rows: ready_rows,
-                };
-                if let Ok(text) = serde_json::to_string(&ready) {
-                    let _ = tx.send(Message::Text(text)).await;
-                }
-            } else {
-                let ack = ServerMessage::SubscribeAck {
-                    session_id: session_id.clone(),
-                    in_alt_screen: mobile_in_alt_screen,
-                    runtime: Some(runtime),
-                };
-                if let Ok(text) = serde_json::to_string(&ack) {
-                    let _ = tx.send(Message::Text(text)).await;
-                }
-            }
-
-            // Register active view after initial clear/replay/ack sequence so
-            // live PTY stream can't interleave with bootstrap replay bytes.
-            let mut st = state.write().await;
-            let entry = st.mobile_views.entry(addr).or_default();
-            if entry.insert(session_id.clone()) {
-                let count = st
-                    .session_view_counts
-                    .entry(session_id.clone())
-                    .or_insert(0);
-                *count += 1;
-            }
-            if st.tmux_viewport_supported && runtime_for_log == "tmux" {
-                let active_controller = st.tmux_viewport_controllers.get(&session_id).copied();
-                match active_controller {
-                    Some(controller) if controller != addr => {
-                        let controller_is_viewing = st
-                            .mobile_views
-                            .get(&controller)
-                            .map(|views| views.contains(&session_id))
-                            .unwrap_or(false);
-                        if controller_is_viewing {
-                            tracing::debug!(
-                                session_id = %session_id,
-                                active_controller = %controller,
-                                requester = %addr,
-                                "Keeping existing tmux viewport controller on subscribe"
-                            );
-                        } else {
-                            st.tmux_viewport_controllers
-                                .insert(session_id.clone(), addr);
-                            tracing::debug!(
-                                session_id = %session_id,
-                                stale_controller = %controller,
-                                new_controller = %addr,
-                                "Reassigned stale tmux viewport controller on subscribe"
-                            );
-                        }
-                    }
-                    Some(_) => {}
-                    None => {
-                        st.tmux_viewport_controllers
-                            .insert(session_id.clone(), addr);
-                    }
-                }
-            }
-            if use_attach_v2 {
-                st.mobile_attach_ids
-                    .entry(addr)
-                    .or_default()
-                    .insert(session_id.clone(), attach_id);
-            } else {
-                let mut remove_addr = false;
-                if let Some(attach_by_session) = st.mobile_attach_ids.get_mut(&addr) {
-                    attach_by_session.remove(&session_id);
-                    remove_addr = attach_by_session.is_empty();
-                }
-                if remove_addr {
-                    st.mobile_attach_ids.remove(&addr);
-                }
-            }
-            let replay_sources = if replay_events.is_empty() {
-                "none".to_string()
-            } else {
-                replay_events
-                    .iter()
-                    .map(|(source, _)| *source)
-                    .collect::<Vec<_>>()
-                    .join(",")
-            };
-            let replay_bytes_total: usize = replay_events.iter().map(|(_, bytes)| *bytes).sum();
-            tracing::info!(
-                target: "overhaul.attach",
-                session_id = %session_id,
-                addr = %addr,
-                attach_id,
-                runtime = %runtime_for_log,
-                protocol = overhaul_flags.attach_protocol.as_str(),
-                use_attach_v2,
-                clear_mode,
-                replay_sources = %replay_sources,
-                replay_events = replay_events.len(),
-                replay_bytes_total,
-                duration_ms = attach_started.elapsed().as_millis() as u64,
-                "Attach sequence completed"
-            );
-        }
-        ClientMessage::Unsubscribe { session_id } => {
-            tracing::debug!("Client unsubscribed from session: {}", session_id);
-            let mut st = state.write().await;
-            let mut remove_attach_addr = false;
-            let mut should_restore = false;
-            if let Some(attach_by_session) = st.mobile_attach_ids.get_mut(&addr) {
-                attach_by_session.remove(&session_id);
-                remove_attach_addr = attach_by_session.is_empty();
-            }
-            if remove_attach_addr {
-                st.mobile_attach_ids.remove(&addr);
-            }
-            if let Some(entry) = st.mobile_views.get_mut(&addr) {
-                if entry.remove(&session_id) {
-                    if let Some(count) = st.session_view_counts.get_mut(&session_id) {
-                        if *count > 0 {
-                            *count -= 1;
-                        }
-                        if *count == 0 {
-                            st.session_view_counts.remove(&session_id);
-                            should_restore = true;
-                        }
-                    }
-                }
-            }
-            if st.tmux_viewport_controllers.get(&session_id).copied() == Some(addr) {
-                st.tmux_viewport_controllers.remove(&session_id);
-                if let Some(next_addr) = st.mobile_views.iter().find_map(|(candidate, views)| {
-                    views.contains(&session_id).then_some(*candidate)
-                }) {
-                    st.tmux_viewport_controllers
-                        .insert(session_id.clone(), next_addr);
-                }
-            }
-            drop(st);
-            if should_restore {
-                restore_pty_size(state, &session_id).await;
-                return Ok(());
-            }
-        }
-        ClientMessage::SendInput {
-            session_id,
-            text,
-            raw,
-            ..
-        } => {
-            let mut st = state.write().await;
-            if let Some(session) = st.sessions.get_mut(&session_id) {
-                let mut payload = text.into_bytes();
-                if raw {
-                    let (filtered, dropped) = strip_terminal_report_sequences_stateful(
-                        &mut session.raw_input_tail,
-                        &payload,
-                    );
-                    if dropped > 0 {
-                        tracing::debug!(
-                            session_id = %session_id,
-                            runtime = %session.runtime,
-                            dropped_sequences = dropped,
-                            original_len = payload.len(),
-                            filtered_len = filtered.len(),
-                            "Dropped terminal-report reply sequences from mobile raw input"
-                        );
-                    }
-                    payload = filtered;
-                }
-
-                if !payload.is_empty() {
-                    let _ = session.input_tx.send(payload);
-                }
-            }
-        }
-        ClientMessage::PtyResize {
-            session_id,
-            cols,
-            rows,
-            epoch,
-            reason,
-        } => {
-            let reason = resolve_resize_reason(cols, rows, reason);
-            let mut st = state.write().await;
-            let resize_simplified = st.overhaul_flags.resize_simplified;
-            let is_restore = reason == PtyResizeReason::DetachRestore;
-            let viewer_count = st
-                .session_view_counts
-                .get(&session_id)
-                .copied()
-                .unwrap_or(0);
-            let sender_is_viewing = st
-                .mobile_views
-                .get(&addr)
-                .map(|views| views.contains(&session_id))
-                .unwrap_or(false);
-            let mut synthetic_ack: Option<(u16, u16, Option<u64>)> = None;
-
-            let ignore_no_viewer_resize =
-                should_ignore_resize_without_viewers(is_restore, viewer_count, reason);
-            if ignore_no_viewer_resize {
-                tracing::debug!(
-                    target: "overhaul.resize",
-                    session_id = %session_id,
-                    cols,
-                    rows,
-                    epoch = ?epoch,
-                    reason = reason.as_str(),
-                    viewer_count,
-                    sender_is_viewing,
-                    resize_simplified,
-                    bootstrap_reason = matches!(reason, PtyResizeReason::AttachInit | PtyResizeReason::ReconnectSync),
-                    decision = "ignored_no_active_viewers",
-                    "Ignoring PTY resize with no active mobile viewers"
-                );
-                return Ok(());
+                return Ok(());
             }
 
             // A restore request (0x0) must not override active dimensions while
@@ -2976,6 +3892,10 @@ async fn process_client_msg(
                     );
                     synthetic_ack = Some((ack_dims.0, ack_dims.1, epoch));
                 } else if is_noop_resize(session.last_applied_size, cols, rows) {
+                    // The keyboard settled back on the current size; drop
+                    // whatever intermediate size was still waiting.
+                    session.pending_resize = None;
+                    session.resize_debounce_gen = session.resize_debounce_gen.wrapping_add(1);
                     tracing::debug!(
                         target: "overhaul.resize",
                         session_id = %session_id,
@@ -3003,15 +3923,22 @@ async fn process_client_msg(
                         sender_is_viewing,
                         alt_screen = session.in_alt_screen,
                         resize_simplified,
-                        decision = "forwarded",
+                        decision = if is_restore { "forwarded" } else { "debounced" },
                         "Forwarding PTY resize to wrapper"
                     );
-                    let _ = session.resize_tx.send(ResizeRequest {
+                    let request = ResizeRequest {
                         cols,
                         rows,
                         epoch,
                         reason,
-                    });
+                    };
+                    if is_restore {
+                        session.pending_resize = None;
+                        session.resize_debounce_gen = session.resize_debounce_gen.wrapping_add(1);
+                        let _ = session.resize_tx.send(request);
+                    } else {
+                        queue_debounced_resize(state, session, request);
+                    }
                 }
             }
             drop(st);
@@ -3126,6 +4053,7 @@ async fn process_client_msg(
                             let pty_msg = ServerMessage::PtyBytes {
                                 session_id: session_id.clone(),
                                 data: BASE64.encode(&payload),
+                                seq: None,
                             };
                             if let Ok(json) = serde_json::to_string(&pty_msg) {
                                 let _ = tx.send(Message::Text(json)).await;
@@ -3227,6 +4155,7 @@ async fn process_client_msg(
                         let pty_msg = ServerMessage::PtyBytes {
                             session_id: session_id.clone(),
                             data: BASE64.encode(&payload),
+                            seq: None,
                         };
                         if let Ok(json) = serde_json::to_string(&pty_msg) {
                             let _ = tx.send(Message::Text(json)).await;
@@ -3249,24 +4178,37 @@ async fn process_client_msg(
             tx.send(Message::Text(serde_json::to_string(&ServerMessage::Pong)?))
                 .await?;
         }
+        ClientMessage::Goodbye { .. } => {
+            // Handled by the connection loop, which owns the socket lifetime.
+        }
         ClientMessage::GetSessions => {
             send_sessions_list(state, tx).await?;
         }
+        ClientMessage::GetSessionsByProject { request_id } => {
+            let groups = {
+                let st = state.read().await;
+                group_sessions_by_project(st.sessions.values(), st.port)
+            };
+            let msg = ServerMessage::SessionsByProject { request_id, groups };
+            tx.send(Message::Text(serde_json::to_string(&msg)?)).await?;
+        }
         ClientMessage::RenameSession {
             session_id,
             new_name,
         } => {
             let renamed = {
                 let mut st = state.write().await;
-                if let Some(session) = st.sessions.get_mut(&session_id) {
+                st.sessions.get_mut(&session_id).map(|session| {
                     session.name = new_name.clone();
-                    true
-                } else {
-                    false
-                }
+                    (session.project_path.clone(), session.command.clone())
+                })
             };
 
-            if renamed {
+            if let Some((project_path, command)) = renamed {
+                if let Err(e) = session::save_session_name(&project_path, &command, &new_name) {
+                    tracing::warn!("Failed to persist session name: {}", e);
+                }
+
                 // Send confirmation
                 let msg = ServerMessage::SessionRenamed {
                     session_id: session_id.clone(),
@@ -3280,7 +4222,7 @@ async fn process_client_msg(
                 // Update persisted sessions file
                 persist_sessions_to_file(state).await;
 
-                tracing::info!("Session {} renamed to '{}'", session_id, new_name);
+                tracing::info!(session_id = %session_id, new_name = %new_name, "Session renamed");
             } else {
                 let msg = ServerMessage::Error {
                     code: "session_not_found".to_string(),
@@ -3289,6 +4231,50 @@ async fn process_client_msg(
                 tx.send(Message::Text(serde_json::to_string(&msg)?)).await?;
             }
         }
+        ClientMessage::ClearHistory { session_id } => {
+            let tmux_target = {
+                let mut st = state.write().await;
+                match st.sessions.get_mut(&session_id) {
+                    Some(session) => {
+                        session.scrollback.clear();
+                        let target = session
+                            .tmux_socket
+                            .clone()
+                            .zip(session.tmux_session.clone());
+                        st.pty_scroll_offsets.remove(&session_id);
+                        Ok(target)
+                    }
+                    None => Err(()),
+                }
+            };
+            match tmux_target {
+                Ok(target) => {
+                    // tmux sessions replay from capture-pane, so its own history
+                    // has to go too or the next subscribe brings it back.
+                    if let Some((socket, name)) = target {
+                        let cleared = tokio::task::spawn_blocking(move || {
+                            tmux_targets(&name).iter().any(|target| {
+                                run_tmux_viewport_cmd(&socket, target, &["clear-history"]).is_ok()
+                            })
+                        })
+                        .await
+                        .unwrap_or(false);
+                        if !cleared {
+                            tracing::warn!(session_id = %session_id, "tmux clear-history failed");
+                        }
+                    }
+                    let st = state.read().await;
+                    broadcast_to_active_clients(&st, &ServerMessage::HistoryCleared { session_id });
+                }
+                Err(()) => {
+                    let msg = ServerMessage::Error {
+                        code: "session_not_found".to_string(),
+                        message: format!("Session {} not found", session_id),
+                    };
+                    tx.send(Message::Text(serde_json::to_string(&msg)?)).await?;
+                }
+            }
+        }
         ClientMessage::CloseSession { session_id } => {
             // Drop the session's channels so the PTY task exits naturally,
             // then remove it from state and notify all clients.
@@ -3325,20 +4311,14 @@ async fn process_client_msg(
                         session_id: session_id.clone(),
                         exit_code: -1,
                     };
-                    let end_str = serde_json::to_string(&end_msg)?;
-                    let active_ids = active_credential_ids_on_disk();
-                    for (addr, client) in &st.mobile_clients {
-                        if is_mobile_client_active(&st, addr, &active_ids) {
-                            let _ = client.try_send(Message::Text(end_str.clone()));
-                        }
-                    }
+                    broadcast_to_active_clients(&st, &end_msg);
                 }
 
                 // Broadcast updated sessions list and persist
                 broadcast_sessions_update(state).await;
                 persist_sessions_to_file(state).await;
 
-                tracing::info!("Session {} closed by mobile client", session_id);
+                tracing::info!(session_id = %session_id, "Session closed by mobile client");
             } else {
                 let msg = ServerMessage::Error {
                     code: "session_not_found".to_string(),
@@ -3347,6 +4327,39 @@ async fn process_client_msg(
                 tx.send(Message::Text(serde_json::to_string(&msg)?)).await?;
             }
         }
+        ClientMessage::DetachSession { session_id } => {
+            let result = {
+                let st = state.read().await;
+                match st.sessions.get(&session_id) {
+                    None => Err((
+                        "session_not_found",
+                        format!("Session {} not found", session_id),
+                    )),
+                    Some(session) if session.tmux_session.is_none() => Err((
+                        "detach_unsupported",
+                        "Only tmux-backed sessions can be detached".to_string(),
+                    )),
+                    Some(session) => session
+                        .control_tx
+                        .send(WrapperControl::Detach { requested_by: addr })
+                        .map_err(|_| {
+                            (
+                                "session_not_found",
+                                format!("Session {} is no longer connected", session_id),
+                            )
+                        }),
+                }
+            };
+            // Success is reported via the SessionDetached broadcast once the
+            // wrapper confirms it detached; a failure comes back as an error.
+            if let Err((code, message)) = result {
+                let msg = ServerMessage::Error {
+                    code: code.to_string(),
+                    message,
+                };
+                tx.send(Message::Text(serde_json::to_string(&msg)?)).await?;
+            }
+        }
         ClientMessage::RegisterPushToken {
             token,
             token_type,
@@ -3411,6 +4424,124 @@ async fn process_client_msg(
                 before.saturating_sub(after)
             );
         }
+        ClientMessage::GetInputHistory { session_id, limit } => {
+            let entries = {
+                let st = state.read().await;
+                st.sessions
+                    .get(&session_id)
+                    .map(|session| {
+                        session
+                            .input_history
+                            .iter()
+                            .rev()
+                            .take(limit.unwrap_or(INPUT_HISTORY_LEN))
+                            .cloned()
+                            .collect()
+                    })
+                    .unwrap_or_default()
+            };
+            let msg = ServerMessage::InputHistory {
+                session_id,
+                entries,
+            };
+            tx.send(Message::Text(serde_json::to_string(&msg)?)).await?;
+        }
+        ClientMessage::GetNotificationHistory { request_id, limit } => {
+            let notifications = {
+                let st = state.read().await;
+                st.notification_history
+                    .iter()
+                    .rev()
+                    .take(limit.unwrap_or(NOTIFICATION_HISTORY_LEN))
+                    .cloned()
+                    .collect()
+            };
+            let msg = ServerMessage::NotificationHistory {
+                request_id,
+                notifications,
+            };
+            tx.send(Message::Text(serde_json::to_string(&msg)?)).await?;
+        }
+        ClientMessage::TestNotification { request_id } => {
+            let tokens: Vec<PushToken> = {
+                let mut st = state.write().await;
+                retain_active_push_tokens(&mut st);
+                match st.mobile_auth.get(&addr) {
+                    Some(client) => st
+                        .push_tokens
+                        .iter()
+                        .filter(|t| {
+                            t.credential_id == client.credential_id
+                                && t.mobile_installation_id == client.mobile_installation_id
+                        })
+                        .cloned()
+                        .collect(),
+                    None => Vec::new(),
+                }
+            };
+            let outcomes = if tokens.is_empty() {
+                Vec::new()
+            } else {
+                let data = serde_json::json!({ "type": "test_notification" });
+                send_push_messages(
+                    state,
+                    &tokens,
+                    "MobileCLI test notification",
+                    "Push notifications are working.",
+                    data,
+                )
+                .await
+            };
+            let delivered = outcomes.iter().filter(|o| o.result.is_ok()).count();
+            let summary = outcomes
+                .iter()
+                .map(|o| match &o.result {
+                    Ok(()) => format!("{}: delivered", o.token_type),
+                    Err(reason) => format!("{}: failed: {}", o.token_type, reason),
+                })
+                .collect::<Vec<_>>()
+                .join("; ");
+            let msg = if outcomes.is_empty() {
+                ServerMessage::Error {
+                    code: "no_push_tokens".to_string(),
+                    message: "No push tokens registered for this device".to_string(),
+                }
+            } else if delivered == 0 {
+                ServerMessage::Error {
+                    code: "push_delivery_failed".to_string(),
+                    message: summary,
+                }
+            } else {
+                ServerMessage::OperationSuccess {
+                    request_id,
+                    operation: "test_notification".to_string(),
+                    path: String::new(),
+                    message: Some(summary),
+                    entry: None,
+                }
+            };
+            tx.send(Message::Text(serde_json::to_string(&msg)?)).await?;
+        }
+        ClientMessage::DismissWaiting { session_id } => {
+            let dismissed = {
+                let mut st = state.write().await;
+                st.sessions.get_mut(&session_id).map(dismiss_waiting)
+            };
+            match dismissed {
+                Some(true) => {
+                    tracing::info!(session_id = %session_id, "Waiting state dismissed from mobile");
+                    broadcast_waiting_cleared(state, &session_id).await;
+                }
+                Some(false) => {}
+                None => {
+                    let msg = ServerMessage::Error {
+                        code: "session_not_found".to_string(),
+                        message: format!("Session {} not found", session_id),
+                    };
+                    tx.send(Message::Text(serde_json::to_string(&msg)?)).await?;
+                }
+            }
+        }
         ClientMessage::ToolApproval {
             session_id,
             response,
@@ -3440,8 +4571,8 @@ async fn process_client_msg(
                 }
             } else {
                 tracing::warn!(
-                    "Tool approval ignored (no applicable approval model) for session {}",
-                    session_id
+                    session_id = %session_id,
+                    "Tool approval ignored (no applicable approval model)"
                 );
             }
 
@@ -3449,11 +4580,57 @@ async fn process_client_msg(
                 broadcast_waiting_cleared(state, &session_id).await;
             }
         }
+        ClientMessage::GetSessionScreen {
+            session_id,
+            cols,
+            rows,
+        } => {
+            let source = {
+                let st = state.read().await;
+                st.sessions.get(&session_id).map(|session| {
+                    match (&session.tmux_socket, &session.tmux_session) {
+                        (Some(socket), Some(name)) if session.runtime == "tmux" => {
+                            Err((socket.clone(), name.clone()))
+                        }
+                        _ => Ok(session.screen_tail.iter().copied().collect::<Vec<u8>>()),
+                    }
+                })
+            };
+            let bytes = match source {
+                Some(Ok(bytes)) => bytes,
+                // tmux scrollback is redraw traffic; its visible pane is the
+                // better source.
+                Some(Err((socket, name))) => {
+                    capture_tmux_history_with_retry(socket, name, false, 0)
+                        .await
+                        .unwrap_or_default()
+                }
+                None => {
+                    let msg = ServerMessage::Error {
+                        code: "session_not_found".to_string(),
+                        message: format!("Session {} not found", session_id),
+                    };
+                    tx.send(Message::Text(serde_json::to_string(&msg)?)).await?;
+                    return Ok(());
+                }
+            };
+            let rendered = screen::render(&bytes, cols, rows);
+            let msg = ServerMessage::SessionScreen {
+                session_id,
+                cols: cols.clamp(1, screen::MAX_SCREEN_DIMENSION),
+                rows: rows.clamp(1, screen::MAX_SCREEN_DIMENSION),
+                lines: rendered.lines,
+                cursor_row: rendered.cursor_row,
+                cursor_col: rendered.cursor_col,
+            };
+            tx.send(Message::Text(serde_json::to_string(&msg)?)).await?;
+        }
         ClientMessage::GetSessionHistory {
             session_id,
             max_bytes,
         } => {
             let mut tmux_capture_req: Option<(String, String, usize, bool)> = None;
+            let strip_mouse = { state.read().await.mobile_mouse_filtered.contains(&addr) };
             let (fallback_bytes, fallback_total_bytes) = {
                 let st = state.read().await;
                 if let Some(session) = st.sessions.get(&session_id) {
@@ -3493,7 +4670,8 @@ async fn process_client_msg(
                     {
                         let total = snapshot.len();
                         let skip = total.saturating_sub(max);
-                        (BASE64.encode(&snapshot[skip..]), total)
+                        let bytes = strip_mouse_from_replay(snapshot[skip..].to_vec(), strip_mouse);
+                        (BASE64.encode(&bytes), total)
                     } else {
                         tracing::warn!(
                             session_id = %session_id,
@@ -3507,7 +4685,8 @@ async fn process_client_msg(
                         fallback_total_bytes = fallback_total_bytes,
                         "Using daemon scrollback replay for session_history"
                     );
-                    (BASE64.encode(&fallback_bytes), fallback_total_bytes)
+                    let bytes = strip_mouse_from_replay(fallback_bytes, strip_mouse);
+                    (BASE64.encode(&bytes), fallback_total_bytes)
                 };
 
             let msg = ServerMessage::SessionHistory {
@@ -3522,10 +4701,31 @@ async fn process_client_msg(
             args,
             name,
             working_dir,
+            base,
+            env,
         } => {
-            let result =
-                spawn_session_from_mobile(&command, &args, name.as_deref(), working_dir.as_deref())
-                    .await;
+            let (fs, limit_reached) = {
+                let st = state.read().await;
+                (st.file_system.clone(), session_limit_reached(&st, None))
+            };
+            let result = match resolve_mobile_spawn_working_dir(
+                fs.validator(),
+                working_dir.as_deref(),
+                base.as_deref(),
+            ) {
+                Ok(_) if limit_reached => Err("session limit reached".into()),
+                Ok(working_dir) => {
+                    spawn_session_from_mobile(
+                        &command,
+                        &args,
+                        name.as_deref(),
+                        working_dir.as_deref(),
+                        &env,
+                    )
+                    .await
+                }
+                Err(e) => Err(e),
+            };
             let msg = match result {
                 Ok(()) => ServerMessage::SpawnResult {
                     success: true,
@@ -3540,12 +4740,73 @@ async fn process_client_msg(
             };
             tx.send(Message::Text(serde_json::to_string(&msg)?)).await?;
         }
+        ClientMessage::RunCommand {
+            request_id,
+            command,
+            args,
+            working_dir,
+        } => {
+            let Some(permit) = try_acquire_run_command_permit(state, addr).await else {
+                let msg = ServerMessage::CommandOutput {
+                    request_id,
+                    exit_code: None,
+                    stdout: String::new(),
+                    stderr: String::new(),
+                    truncated: false,
+                    error: Some(format!(
+                        "Too many commands running; at most {} at once",
+                        MAX_RUN_COMMANDS_PER_CLIENT
+                    )),
+                };
+                tx.send(Message::Text(serde_json::to_string(&msg)?)).await?;
+                return Ok(());
+            };
+            let fs = { state.read().await.file_system.clone() };
+            // Off the connection loop; a slow command must not stall the terminal.
+            let state = state.clone();
+            tokio::spawn(async move {
+                let _permit = permit;
+                let msg = match run_command_from_mobile(
+                    fs.validator(),
+                    &command,
+                    &args,
+                    working_dir.as_deref(),
+                )
+                .await
+                {
+                    Ok(run) => ServerMessage::CommandOutput {
+                        request_id,
+                        exit_code: run.exit_code,
+                        stdout: run.stdout,
+                        stderr: run.stderr,
+                        truncated: run.truncated,
+                        error: None,
+                    },
+                    Err(e) => ServerMessage::CommandOutput {
+                        request_id,
+                        exit_code: None,
+                        stdout: String::new(),
+                        stderr: String::new(),
+                        truncated: false,
+                        error: Some(e.to_string()),
+                    },
+                };
+                queue_to_client(&state, addr, &msg).await;
+            });
+        }
         ClientMessage::ListDirectory {
             request_id,
             path,
             include_hidden,
             sort_by,
             sort_order,
+            name_filter,
+            extensions,
+            offset,
+            limit,
+            compact,
+            respect_gitignore,
+            hide_ignored_dirs,
         } => {
             tracing::info!(request_id = %request_id, path = %path, "ListDirectory request");
             if let Err(retry_after_ms) = check_fs_rate_limit(state, addr).await {
@@ -3563,19 +4824,33 @@ async fn process_client_msg(
             tracing::info!(request_id = %request_id, "ListDirectory calling fs.ops()");
             let fs = { state.read().await.file_system.clone() };
             tracing::info!(request_id = %request_id, "ListDirectory got fs, calling list_directory");
-            match fs
-                .ops()
-                .list_directory(&path, include_hidden, sort_by, sort_order)
-                .await
-            {
-                Ok((path, entries, total_count, truncated)) => {
-                    tracing::info!(request_id = %request_id, total_count = total_count, "ListDirectory success");
+            let options = ListOptions {
+                include_hidden,
+                sort_by,
+                sort_order,
+                name_filter,
+                extensions,
+                offset: offset.unwrap_or(0),
+                limit,
+                compact,
+                respect_gitignore,
+                hide_ignored_dirs,
+            };
+            match fs.ops().list_directory(&path, &options).await {
+                Ok(mut page) => {
+                    tracing::info!(request_id = %request_id, total_count = page.total_count, "ListDirectory success");
+                    let protocol_version = {
+                        let st = state.read().await;
+                        st.mobile_protocol_versions.get(&addr).copied().unwrap_or(1)
+                    };
+                    downgrade_git_statuses(&mut page.entries, protocol_version);
                     let msg = ServerMessage::DirectoryListing {
                         request_id,
-                        path,
-                        entries,
-                        total_count,
-                        truncated,
+                        path: page.path,
+                        entries: page.entries,
+                        total_count: page.total_count,
+                        truncated: page.truncated,
+                        has_more: page.has_more,
                     };
                     tx.send(Message::Text(serde_json::to_string(&msg)?)).await?;
                 }
@@ -3585,12 +4860,66 @@ async fn process_client_msg(
                 }
             }
         }
+        ClientMessage::ListDirectoryStream {
+            request_id,
+            path,
+            include_hidden,
+        } => {
+            if let Err(retry_after_ms) = check_fs_rate_limit(state, addr).await {
+                send_fs_error(
+                    tx,
+                    request_id,
+                    "list_directory_stream",
+                    &path,
+                    FileSystemError::RateLimited { retry_after_ms },
+                )
+                .await?;
+                return Ok(());
+            }
+            let ops = { state.read().await.file_system.ops().clone() };
+            // Bounded so a slow socket applies backpressure to the directory walk.
+            let (entry_tx, mut entry_rx) = mpsc::channel::<FileEntry>(64);
+            let listing = {
+                let path = path.clone();
+                tokio::spawn(async move {
+                    ops.list_directory_stream(&path, include_hidden, entry_tx)
+                        .await
+                })
+            };
+            while let Some(entry) = entry_rx.recv().await {
+                let msg = ServerMessage::DirectoryEntry {
+                    request_id: request_id.clone(),
+                    entry,
+                };
+                tx.send(Message::Text(serde_json::to_string(&msg)?)).await?;
+            }
+            match listing.await {
+                Ok(Ok((listed_path, total_count))) => {
+                    let msg = ServerMessage::DirectoryListingDone {
+                        request_id,
+                        path: listed_path,
+                        total_count,
+                    };
+                    tx.send(Message::Text(serde_json::to_string(&msg)?)).await?;
+                }
+                Ok(Err(e)) => {
+                    send_fs_error(tx, request_id, "list_directory_stream", &path, e).await?;
+                }
+                Err(e) => {
+                    let err = FileSystemError::IoError {
+                        message: e.to_string(),
+                    };
+                    send_fs_error(tx, request_id, "list_directory_stream", &path, err).await?;
+                }
+            }
+        }
         ClientMessage::ReadFile {
             request_id,
             path,
             offset,
             length,
             encoding,
+            force_text,
         } => {
             if let Err(retry_after_ms) = check_fs_rate_limit(state, addr).await {
                 send_fs_error(
@@ -3604,7 +4933,12 @@ async fn process_client_msg(
                 return Ok(());
             }
             let fs = { state.read().await.file_system.clone() };
-            match fs.ops().read_file(&path, offset, length, encoding).await {
+            match fs
+                .ops()
+                .for_client(addr)
+                .read_file(&path, offset, length, encoding, force_text)
+                .await
+            {
                 Ok(file) => {
                     let msg = ServerMessage::FileContent {
                         request_id,
@@ -3615,6 +4949,10 @@ async fn process_client_msg(
                         size: file.size,
                         modified: file.modified,
                         truncated_at: file.truncated_at,
+                        detected_encoding: file.detected_encoding,
+                        language: file.language,
+                        line_ending: file.line_ending,
+                        indent: file.indent,
                     };
                     tx.send(Message::Text(serde_json::to_string(&msg)?)).await?;
                 }
@@ -3623,17 +4961,17 @@ async fn process_client_msg(
                 }
             }
         }
-        ClientMessage::ReadFileChunk {
+        ClientMessage::ReadFileLines {
             request_id,
             path,
-            chunk_index,
-            chunk_size,
+            start_line,
+            end_line,
         } => {
             if let Err(retry_after_ms) = check_fs_rate_limit(state, addr).await {
                 send_fs_error(
                     tx,
                     request_id,
-                    "read_file_chunk",
+                    "read_file_lines",
                     &path,
                     FileSystemError::RateLimited { retry_after_ms },
                 )
@@ -3641,38 +4979,38 @@ async fn process_client_msg(
                 return Ok(());
             }
             let fs = { state.read().await.file_system.clone() };
-            let size = chunk_size.unwrap_or(262_144);
-            match fs.ops().read_file_chunk(&path, chunk_index, size).await {
-                Ok((path, total_chunks, total_size, chunk_index, data, checksum, is_last)) => {
-                    let msg = ServerMessage::FileChunk {
+            match fs
+                .ops()
+                .for_client(addr)
+                .read_file_lines(&path, start_line, end_line)
+                .await
+            {
+                Ok(file) => {
+                    let msg = ServerMessage::FileLines {
                         request_id,
-                        path,
-                        chunk_index,
-                        total_chunks,
-                        total_size,
-                        data,
-                        checksum,
-                        is_last,
+                        path: file.path,
+                        start_line: file.start_line,
+                        lines: file.lines,
+                        total_lines: file.total_lines,
+                        truncated: file.truncated,
                     };
                     tx.send(Message::Text(serde_json::to_string(&msg)?)).await?;
                 }
                 Err(e) => {
-                    send_fs_error(tx, request_id, "read_file_chunk", &path, e).await?;
+                    send_fs_error(tx, request_id, "read_file_lines", &path, e).await?;
                 }
             }
         }
-        ClientMessage::WriteFile {
+        ClientMessage::TailFile {
             request_id,
             path,
-            content,
-            encoding,
-            create_parents,
+            from_end_bytes,
         } => {
             if let Err(retry_after_ms) = check_fs_rate_limit(state, addr).await {
                 send_fs_error(
                     tx,
                     request_id,
-                    "write_file",
+                    "tail_file",
                     &path,
                     FileSystemError::RateLimited { retry_after_ms },
                 )
@@ -3680,108 +5018,191 @@ async fn process_client_msg(
                 return Ok(());
             }
             let fs = { state.read().await.file_system.clone() };
-            match fs
+            // Subscribe before reading so appends between the read and the
+            // spawn below are not missed.
+            let mut changes = fs.watcher().subscribe();
+            let (file_path, data, size) = match fs
                 .ops()
-                .write_file(&path, &content, encoding, create_parents)
+                .for_client(addr)
+                .tail_file(&path, from_end_bytes)
                 .await
             {
-                Ok(()) => {
-                    let msg = ServerMessage::OperationSuccess {
-                        request_id,
-                        operation: "write_file".to_string(),
-                        path: path.clone(),
-                        message: None,
-                    };
-                    tx.send(Message::Text(serde_json::to_string(&msg)?)).await?;
-                }
+                Ok(tail) => tail,
                 Err(e) => {
-                    send_fs_error(tx, request_id, "write_file", &path, e).await?;
+                    send_fs_error(tx, request_id, "tail_file", &path, e).await?;
+                    return Ok(());
+                }
+            };
+            let watch_path = std::path::Path::new(&file_path)
+                .parent()
+                .map(crate::filesystem::path_utils::to_protocol_path)
+                .unwrap_or_else(|| file_path.clone());
+
+            let should_watch = {
+                let mut st = state.write().await;
+                let count = st.file_watch_counts.entry(watch_path.clone()).or_insert(0);
+                *count += 1;
+                *count == 1
+            };
+            if should_watch {
+                if let Err(e) = fs.watcher().watch(&watch_path, None) {
+                    release_file_watch(state, &watch_path).await;
+                    send_fs_error(tx, request_id, "tail_file", &path, e).await?;
+                    return Ok(());
+                }
+            }
+
+            let msg = ServerMessage::FileChunk {
+                request_id: request_id.clone(),
+                path: file_path.clone(),
+                chunk_index: 0,
+                total_chunks: 1,
+                total_size: size,
+                checksum: format!("{:x}", md5::compute(&data)),
+                data: BASE64.encode(&data),
+                is_last: true,
+            };
+            tx.send(Message::Text(serde_json::to_string(&msg)?)).await?;
+
+            let tail_state = state.clone();
+            let tail_request_id = request_id.clone();
+            let tailed_path = file_path.clone();
+            let task = tokio::spawn(async move {
+                let mut offset = size;
+                loop {
+                    let change = match changes.recv().await {
+                        Ok(change) => change,
+                        Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                        Err(_) => break,
+                    };
+                    if change.path != file_path {
+                        continue;
+                    }
+                    if matches!(change.change_type, ChangeType::Deleted) {
+                        // Rotated away; the replacement is read from its start.
+                        offset = 0;
+                        continue;
+                    }
+                    loop {
+                        let (data, next) = match fs
+                            .ops()
+                            .for_client(addr)
+                            .read_appended(&file_path, offset, TAIL_APPEND_MAX_BYTES)
+                            .await
+                        {
+                            Ok(read) => read,
+                            Err(_) => break,
+                        };
+                        offset = next;
+                        if data.is_empty() {
+                            break;
+                        }
+                        let client_tx = {
+                            let st = tail_state.read().await;
+                            st.mobile_clients.get(&addr).cloned()
+                        };
+                        let Some(client_tx) = client_tx else {
+                            return;
+                        };
+                        let msg = ServerMessage::FileAppended {
+                            request_id: tail_request_id.clone(),
+                            path: file_path.clone(),
+                            data: BASE64.encode(&data),
+                        };
+                        let Ok(text) = serde_json::to_string(&msg) else {
+                            break;
+                        };
+                        if client_tx.send(Message::Text(text)).await.is_err() {
+                            return;
+                        }
+                    }
                 }
+            });
+
+            let replaced = {
+                let mut st = state.write().await;
+                st.file_tails.entry(addr).or_default().insert(
+                    request_id,
+                    FileTail {
+                        path: tailed_path,
+                        watch_path,
+                        task: task.abort_handle(),
+                    },
+                )
+            };
+            if let Some(previous) = replaced {
+                previous.task.abort();
+                release_file_watch(state, &previous.watch_path).await;
             }
         }
-        ClientMessage::UploadFile {
+        ClientMessage::UntailFile { request_id } => {
+            let removed = {
+                let mut st = state.write().await;
+                st.file_tails
+                    .get_mut(&addr)
+                    .and_then(|tails| tails.remove(&request_id))
+            };
+            if let Some(tail) = &removed {
+                tail.task.abort();
+                release_file_watch(state, &tail.watch_path).await;
+            }
+            let msg = ServerMessage::OperationSuccess {
+                request_id,
+                operation: "untail_file".to_string(),
+                path: removed.map(|tail| tail.path).unwrap_or_default(),
+                message: None,
+                entry: None,
+            };
+            tx.send(Message::Text(serde_json::to_string(&msg)?)).await?;
+        }
+        ClientMessage::GetFileChecksum {
             request_id,
-            session_id,
-            file_name,
-            content_base64,
-            mime_type,
+            path,
+            algorithm,
         } => {
-            tracing::debug!(
-                "UploadFile request: session_id={}, file_name={}, bytes_base64={}",
-                session_id,
-                file_name,
-                content_base64.len()
-            );
             if let Err(retry_after_ms) = check_fs_rate_limit(state, addr).await {
                 send_fs_error(
                     tx,
                     request_id,
-                    "upload_file",
-                    &session_id,
+                    "get_file_checksum",
+                    &path,
                     FileSystemError::RateLimited { retry_after_ms },
                 )
                 .await?;
                 return Ok(());
             }
-
-            let (project_path, fs) = {
-                let st = state.read().await;
-                let Some(session) = st.sessions.get(&session_id) else {
-                    send_fs_error(
-                        tx,
-                        request_id,
-                        "upload_file",
-                        &session_id,
-                        FileSystemError::NotFound {
-                            path: format!("session:{}", session_id),
-                        },
-                    )
-                    .await?;
-                    return Ok(());
-                };
-                (session.project_path.clone(), st.file_system.clone())
-            };
-
-            let destination =
-                build_upload_destination_path(&project_path, sanitize_upload_file_name(&file_name));
-            let destination_path = crate::filesystem::path_utils::to_protocol_path(&destination);
-
+            let fs = { state.read().await.file_system.clone() };
             match fs
                 .ops()
-                .write_file(
-                    &destination_path,
-                    &content_base64,
-                    FileEncoding::Base64,
-                    true,
-                )
+                .for_client(addr)
+                .file_checksum(&path, algorithm)
                 .await
             {
-                Ok(()) => {
-                    tracing::debug!("UploadFile success: {}", destination_path);
-                    let msg = ServerMessage::OperationSuccess {
+                Ok((path, checksum, size)) => {
+                    let msg = ServerMessage::FileChecksum {
                         request_id,
-                        operation: "upload_file".to_string(),
-                        path: destination_path,
-                        message: mime_type,
+                        path,
+                        algorithm,
+                        checksum,
+                        size,
                     };
                     tx.send(Message::Text(serde_json::to_string(&msg)?)).await?;
                 }
                 Err(e) => {
-                    tracing::debug!("UploadFile error for {}: {:?}", project_path, e);
-                    send_fs_error(tx, request_id, "upload_file", &project_path, e).await?;
+                    send_fs_error(tx, request_id, "get_file_checksum", &path, e).await?;
                 }
             }
         }
-        ClientMessage::CreateDirectory {
+        ClientMessage::GetBinaryPreview {
             request_id,
             path,
-            recursive,
+            max_bytes,
         } => {
             if let Err(retry_after_ms) = check_fs_rate_limit(state, addr).await {
                 send_fs_error(
                     tx,
                     request_id,
-                    "create_directory",
+                    "get_binary_preview",
                     &path,
                     FileSystemError::RateLimited { retry_after_ms },
                 )
@@ -3789,45 +5210,81 @@ async fn process_client_msg(
                 return Ok(());
             }
             let fs = { state.read().await.file_system.clone() };
-            match fs.ops().create_directory(&path, recursive).await {
-                Ok(()) => {
-                    let msg = ServerMessage::OperationSuccess {
+            match fs
+                .ops()
+                .for_client(addr)
+                .binary_preview(&path, max_bytes)
+                .await
+            {
+                Ok(preview) => {
+                    let msg = ServerMessage::BinaryPreview {
                         request_id,
-                        operation: "create_directory".to_string(),
-                        path: path.clone(),
-                        message: None,
+                        path: preview.path,
+                        hex: preview.hex,
+                        ascii: preview.ascii,
+                        total_size: preview.total_size,
+                        mime_type: preview.mime_type,
                     };
                     tx.send(Message::Text(serde_json::to_string(&msg)?)).await?;
                 }
                 Err(e) => {
-                    send_fs_error(tx, request_id, "create_directory", &path, e).await?;
+                    send_fs_error(tx, request_id, "get_binary_preview", &path, e).await?;
                 }
             }
         }
-        ClientMessage::DeletePath {
+        ClientMessage::ReadFileChunk {
             request_id,
             path,
-            recursive,
+            chunk_index,
+            chunk_size,
         } => {
-            if !destructive_operations_enabled() {
+            if let Err(retry_after_ms) = check_fs_rate_limit(state, addr).await {
                 send_fs_error(
                     tx,
                     request_id,
-                    "delete_path",
+                    "read_file_chunk",
                     &path,
-                    FileSystemError::PermissionDenied {
-                        path: path.clone(),
-                        reason: "Destructive filesystem operations are disabled".to_string(),
-                    },
+                    FileSystemError::RateLimited { retry_after_ms },
                 )
                 .await?;
                 return Ok(());
             }
+            let fs = { state.read().await.file_system.clone() };
+            let size = chunk_size.unwrap_or(262_144);
+            match fs
+                .ops()
+                .for_client(addr)
+                .read_file_chunk(&path, chunk_index, size)
+                .await
+            {
+                Ok((path, total_chunks, total_size, chunk_index, data, checksum, is_last)) => {
+                    let msg = ServerMessage::FileChunk {
+                        request_id,
+                        path,
+                        chunk_index,
+                        total_chunks,
+                        total_size,
+                        data,
+                        checksum,
+                        is_last,
+                    };
+                    tx.send(Message::Text(serde_json::to_string(&msg)?)).await?;
+                }
+                Err(e) => {
+                    send_fs_error(tx, request_id, "read_file_chunk", &path, e).await?;
+                }
+            }
+        }
+        ClientMessage::GetFileManifest {
+            request_id,
+            path,
+            chunk_size,
+        } => {
             if let Err(retry_after_ms) = check_fs_rate_limit(state, addr).await {
                 send_fs_error(
                     tx,
                     request_id,
-                    "delete_path",
+                    "get_file_manifest",
                     &path,
                     FileSystemError::RateLimited { retry_after_ms },
                 )
@@ -3835,106 +5292,182 @@ async fn process_client_msg(
                 return Ok(());
             }
             let fs = { state.read().await.file_system.clone() };
-            match fs.ops().delete_path(&path, recursive).await {
-                Ok(()) => {
-                    let msg = ServerMessage::OperationSuccess {
+            let chunk_size = chunk_size.unwrap_or(262_144);
+            match fs
+                .ops()
+                .for_client(addr)
+                .file_manifest(&path, chunk_size)
+                .await
+            {
+                Ok((path, total_size, checksums)) => {
+                    let msg = ServerMessage::FileManifest {
                         request_id,
-                        operation: "delete_path".to_string(),
-                        path: path.clone(),
-                        message: None,
+                        path,
+                        total_size,
+                        total_chunks: checksums.len() as u64,
+                        chunk_size,
+                        checksums,
                     };
                     tx.send(Message::Text(serde_json::to_string(&msg)?)).await?;
                 }
                 Err(e) => {
-                    send_fs_error(tx, request_id, "delete_path", &path, e).await?;
+                    send_fs_error(tx, request_id, "get_file_manifest", &path, e).await?;
                 }
             }
         }
-        ClientMessage::RenamePath {
+        ClientMessage::GetRecentFiles {
             request_id,
-            old_path,
-            new_path,
+            path,
+            limit,
+            since_ms,
+            respect_gitignore,
         } => {
-            if !destructive_operations_enabled() {
+            if let Err(retry_after_ms) = check_fs_rate_limit(state, addr).await {
                 send_fs_error(
                     tx,
                     request_id,
-                    "rename_path",
-                    &old_path,
-                    FileSystemError::PermissionDenied {
-                        path: old_path.clone(),
-                        reason: "Destructive filesystem operations are disabled".to_string(),
-                    },
+                    "get_recent_files",
+                    &path,
+                    FileSystemError::RateLimited { retry_after_ms },
                 )
                 .await?;
                 return Ok(());
             }
+            let fs = { state.read().await.file_system.clone() };
+            let limit = limit.unwrap_or(crate::filesystem::search::DEFAULT_RECENT_FILES);
+            match fs
+                .search()
+                .recent_files(&path, limit, since_ms, respect_gitignore)
+                .await
+            {
+                Ok((path, entries, truncated)) => {
+                    let msg = ServerMessage::RecentFiles {
+                        request_id,
+                        path,
+                        entries,
+                        truncated,
+                    };
+                    tx.send(Message::Text(serde_json::to_string(&msg)?)).await?;
+                }
+                Err(e) => {
+                    send_fs_error(tx, request_id, "get_recent_files", &path, e).await?;
+                }
+            }
+        }
+        ClientMessage::DownloadArchive {
+            request_id,
+            path,
+            format,
+            chunk_size,
+        } => {
+            use tokio::io::AsyncReadExt;
+
             if let Err(retry_after_ms) = check_fs_rate_limit(state, addr).await {
                 send_fs_error(
                     tx,
                     request_id,
-                    "rename_path",
-                    &old_path,
+                    "download_archive",
+                    &path,
                     FileSystemError::RateLimited { retry_after_ms },
                 )
                 .await?;
                 return Ok(());
             }
             let fs = { state.read().await.file_system.clone() };
-            match fs.ops().rename_path(&old_path, &new_path).await {
-                Ok(()) => {
-                    let msg = ServerMessage::OperationSuccess {
-                        request_id,
-                        operation: "rename_path".to_string(),
-                        path: old_path.clone(),
-                        message: Some(new_path),
-                    };
-                    tx.send(Message::Text(serde_json::to_string(&msg)?)).await?;
+            let archive = match fs.ops().create_archive(&path, format).await {
+                Ok(archive) => archive,
+                Err(e) => {
+                    send_fs_error(tx, request_id, "download_archive", &path, e).await?;
+                    return Ok(());
                 }
+            };
+            if archive.skipped > 0 {
+                tracing::debug!(
+                    path = %archive.name,
+                    skipped = archive.skipped,
+                    "Denied entries left out of archive"
+                );
+            }
+            let chunk_size = chunk_size.unwrap_or(262_144).max(1);
+            let total_chunks = ((archive.size + chunk_size - 1) / chunk_size).max(1);
+            let mut file = match tokio::fs::File::open(&archive.file_path).await {
+                Ok(file) => file,
                 Err(e) => {
-                    send_fs_error(tx, request_id, "rename_path", &old_path, e).await?;
+                    let err = FileSystemError::IoError {
+                        message: e.to_string(),
+                    };
+                    send_fs_error(tx, request_id, "download_archive", &path, err).await?;
+                    return Ok(());
+                }
+            };
+            for chunk_index in 0..total_chunks {
+                let mut data = Vec::with_capacity(chunk_size.min(archive.size) as usize);
+                if let Err(e) = (&mut file).take(chunk_size).read_to_end(&mut data).await {
+                    let err = FileSystemError::IoError {
+                        message: e.to_string(),
+                    };
+                    send_fs_error(tx, request_id, "download_archive", &path, err).await?;
+                    return Ok(());
                 }
+                let msg = ServerMessage::FileChunk {
+                    request_id: request_id.clone(),
+                    path: archive.name.clone(),
+                    chunk_index,
+                    total_chunks,
+                    total_size: archive.size,
+                    checksum: format!("{:x}", md5::compute(&data)),
+                    data: BASE64.encode(&data),
+                    is_last: chunk_index + 1 == total_chunks,
+                };
+                tx.send(Message::Text(serde_json::to_string(&msg)?)).await?;
             }
         }
-        ClientMessage::CopyPath {
+        ClientMessage::WriteFile {
             request_id,
-            source,
-            destination,
-            recursive,
+            path,
+            content,
+            encoding,
+            create_parents,
+            expected_modified,
         } => {
             if let Err(retry_after_ms) = check_fs_rate_limit(state, addr).await {
                 send_fs_error(
                     tx,
                     request_id,
-                    "copy_path",
-                    &source,
+                    "write_file",
+                    &path,
                     FileSystemError::RateLimited { retry_after_ms },
                 )
                 .await?;
                 return Ok(());
             }
             let fs = { state.read().await.file_system.clone() };
-            match fs.ops().copy_path(&source, &destination, recursive).await {
+            match fs
+                .ops()
+                .write_file(&path, &content, encoding, create_parents, expected_modified)
+                .await
+            {
                 Ok(()) => {
                     let msg = ServerMessage::OperationSuccess {
                         request_id,
-                        operation: "copy_path".to_string(),
-                        path: source.clone(),
-                        message: Some(destination),
+                        operation: "write_file".to_string(),
+                        path: path.clone(),
+                        message: None,
+                        entry: fs.ops().get_file_info(&path).await.ok(),
                     };
                     tx.send(Message::Text(serde_json::to_string(&msg)?)).await?;
                 }
                 Err(e) => {
-                    send_fs_error(tx, request_id, "copy_path", &source, e).await?;
+                    send_fs_error(tx, request_id, "write_file", &path, e).await?;
                 }
             }
         }
-        ClientMessage::GetFileInfo { request_id, path } => {
+        ClientMessage::PathExists { request_id, path } => {
             if let Err(retry_after_ms) = check_fs_rate_limit(state, addr).await {
                 send_fs_error(
                     tx,
                     request_id,
-                    "get_file_info",
+                    "path_exists",
                     &path,
                     FileSystemError::RateLimited { retry_after_ms },
                 )
@@ -3942,33 +5475,36 @@ async fn process_client_msg(
                 return Ok(());
             }
             let fs = { state.read().await.file_system.clone() };
-            match fs.ops().get_file_info(&path).await {
-                Ok(entry) => {
-                    let msg = ServerMessage::FileInfo {
+            match fs.ops().path_exists(&path).await {
+                Ok(presence) => {
+                    let msg = ServerMessage::PathExistsResult {
                         request_id,
-                        path,
-                        entry,
+                        path: presence.path,
+                        exists: presence.exists,
+                        is_directory: presence.is_directory,
+                        is_file: presence.is_file,
                     };
                     tx.send(Message::Text(serde_json::to_string(&msg)?)).await?;
                 }
                 Err(e) => {
-                    send_fs_error(tx, request_id, "get_file_info", &path, e).await?;
+                    send_fs_error(tx, request_id, "path_exists", &path, e).await?;
                 }
             }
         }
-        ClientMessage::SearchFiles {
+        ClientMessage::WriteFileChunk {
             request_id,
             path,
-            pattern,
-            content_pattern,
-            max_depth,
-            max_results,
+            upload_id,
+            chunk_index,
+            total_chunks,
+            data,
+            checksum,
         } => {
             if let Err(retry_after_ms) = check_fs_rate_limit(state, addr).await {
                 send_fs_error(
                     tx,
                     request_id,
-                    "search_files",
+                    "write_file_chunk",
                     &path,
                     FileSystemError::RateLimited { retry_after_ms },
                 )
@@ -3976,119 +5512,824 @@ async fn process_client_msg(
                 return Ok(());
             }
             let fs = { state.read().await.file_system.clone() };
-            let max_results = max_results
-                .unwrap_or(fs.config().max_search_results)
-                .min(fs.config().max_search_results);
             match fs
-                .search()
-                .search_files(
+                .ops()
+                .write_file_chunk(
                     &path,
-                    &pattern,
-                    content_pattern.as_deref(),
-                    max_depth,
-                    max_results,
+                    &upload_id,
+                    chunk_index,
+                    total_chunks,
+                    &data,
+                    &checksum,
                 )
                 .await
             {
-                Ok((root, matches, truncated)) => {
-                    let msg = ServerMessage::SearchResults {
+                Ok(progress) => {
+                    let msg = ServerMessage::FileChunkWritten {
                         request_id,
-                        query: pattern,
-                        path: root,
-                        matches,
-                        truncated,
+                        path: progress.path,
+                        upload_id,
+                        chunk_index,
+                        total_chunks,
+                        bytes_written: progress.bytes_written,
+                        is_complete: progress.is_complete,
                     };
                     tx.send(Message::Text(serde_json::to_string(&msg)?)).await?;
                 }
                 Err(e) => {
-                    send_fs_error(tx, request_id, "search_files", &path, e).await?;
+                    send_fs_error(tx, request_id, "write_file_chunk", &path, e).await?;
                 }
             }
         }
-        ClientMessage::WatchDirectory { request_id, path } => {
+        ClientMessage::UploadFile {
+            request_id,
+            session_id,
+            file_name,
+            content_base64,
+            mime_type,
+            dedup,
+        } => {
+            tracing::debug!(
+                session_id = %session_id,
+                file_name = %file_name,
+                bytes_base64 = content_base64.len(),
+                "UploadFile request"
+            );
             if let Err(retry_after_ms) = check_fs_rate_limit(state, addr).await {
                 send_fs_error(
                     tx,
                     request_id,
-                    "watch_directory",
-                    &path,
+                    "upload_file",
+                    &session_id,
                     FileSystemError::RateLimited { retry_after_ms },
                 )
                 .await?;
                 return Ok(());
             }
-            let fs = { state.read().await.file_system.clone() };
-            match fs.validator().validate_existing(&path) {
-                Ok(canonical) => {
-                    if !canonical.is_dir() {
-                        send_fs_error(
-                            tx,
-                            request_id,
-                            "watch_directory",
-                            &path,
-                            FileSystemError::NotADirectory {
-                                path: crate::filesystem::path_utils::to_protocol_path(&canonical),
-                            },
-                        )
-                        .await?;
-                        return Ok(());
-                    }
-
-                    // Use protocol-normalized paths for watcher keys and subscriptions so the
-                    // mobile app can match file_changed events reliably across OSes.
-                    let watch_path = crate::filesystem::path_utils::to_protocol_path(&canonical);
-                    let should_watch = {
-                        let mut st = state.write().await;
-                        let entry = st.file_watch_subscriptions.entry(addr).or_default();
-                        if entry.insert(watch_path.clone()) {
-                            let count = st.file_watch_counts.entry(watch_path.clone()).or_insert(0);
-                            *count += 1;
-                            *count == 1
-                        } else {
-                            false
-                        }
-                    };
 
-                    if should_watch {
-                        if let Err(e) = fs.watcher().watch(&watch_path) {
-                            send_fs_error(tx, request_id, "watch_directory", &path, e).await?;
-                            return Ok(());
-                        }
-                    }
+            let (project_path, fs) = {
+                let st = state.read().await;
+                let Some(session) = st.sessions.get(&session_id) else {
+                    send_fs_error(
+                        tx,
+                        request_id,
+                        "upload_file",
+                        &session_id,
+                        FileSystemError::NotFound {
+                            path: format!("session:{}", session_id),
+                        },
+                    )
+                    .await?;
+                    return Ok(());
+                };
+                (session.project_path.clone(), st.file_system.clone())
+            };
 
+            // Invalid base64 skips dedup; write_file reports the decode error.
+            let content_hash = if dedup {
+                BASE64
+                    .decode(&content_base64)
+                    .ok()
+                    .map(|bytes| format!("{:x}", Sha256::digest(&bytes)))
+            } else {
+                None
+            };
+            if let Some(hash) = content_hash.as_deref() {
+                if let Some(existing) = find_deduped_upload(&upload_dir(&project_path), hash).await
+                {
+                    let existing_path = crate::filesystem::path_utils::to_protocol_path(&existing);
+                    tracing::debug!("UploadFile reused existing upload: {}", existing_path);
                     let msg = ServerMessage::OperationSuccess {
                         request_id,
-                        operation: "watch_directory".to_string(),
-                        path: watch_path,
-                        message: None,
+                        operation: "upload_file".to_string(),
+                        path: existing_path,
+                        message: mime_type,
+                        entry: None,
                     };
                     tx.send(Message::Text(serde_json::to_string(&msg)?)).await?;
+                    return Ok(());
                 }
-                Err(e) => {
-                    send_fs_error(tx, request_id, "watch_directory", &path, e).await?;
-                }
-            }
-        }
-        ClientMessage::UnwatchDirectory { request_id, path } => {
-            if let Err(retry_after_ms) = check_fs_rate_limit(state, addr).await {
-                send_fs_error(
-                    tx,
-                    request_id,
-                    "unwatch_directory",
-                    &path,
-                    FileSystemError::RateLimited { retry_after_ms },
-                )
-                .await?;
-                return Ok(());
             }
-            let fs = { state.read().await.file_system.clone() };
-            let watch_path = match fs.validator().validate_existing(&path) {
-                Ok(canonical) => crate::filesystem::path_utils::to_protocol_path(&canonical),
-                Err(_) => path.clone(),
+
+            let destination =
+                build_upload_destination_path(&project_path, sanitize_upload_file_name(&file_name));
+            let destination_path = crate::filesystem::path_utils::to_protocol_path(&destination);
+
+            match fs
+                .ops()
+                .write_file(
+                    &destination_path,
+                    &content_base64,
+                    FileEncoding::Base64,
+                    true,
+                    None,
+                )
+                .await
+            {
+                Ok(()) => {
+                    tracing::debug!("UploadFile success: {}", destination_path);
+                    if let Some(hash) = content_hash.as_deref() {
+                        if let Err(e) = record_upload_hash(&destination, hash).await {
+                            tracing::warn!("Failed to update upload index: {}", e);
+                        }
+                    }
+                    let msg = ServerMessage::OperationSuccess {
+                        request_id,
+                        operation: "upload_file".to_string(),
+                        path: destination_path,
+                        message: mime_type,
+                        entry: None,
+                    };
+                    tx.send(Message::Text(serde_json::to_string(&msg)?)).await?;
+                }
+                Err(e) => {
+                    tracing::debug!("UploadFile error for {}: {:?}", project_path, e);
+                    send_fs_error(tx, request_id, "upload_file", &project_path, e).await?;
+                }
+            }
+        }
+        ClientMessage::UploadToPath {
+            request_id,
+            destination_dir,
+            file_name,
+            content_base64,
+        } => {
+            if let Err(retry_after_ms) = check_fs_rate_limit(state, addr).await {
+                send_fs_error(
+                    tx,
+                    request_id,
+                    "upload_to_path",
+                    &destination_dir,
+                    FileSystemError::RateLimited { retry_after_ms },
+                )
+                .await?;
+                return Ok(());
+            }
+            // Base64 is 4 bytes per 3; close enough to refuse before decoding.
+            let approx_size = (content_base64.len() / 4 * 3) as u64;
+            if approx_size > MAX_UPLOAD_TO_PATH_BYTES {
+                send_fs_error(
+                    tx,
+                    request_id,
+                    "upload_to_path",
+                    &destination_dir,
+                    FileSystemError::FileTooLarge {
+                        path: destination_dir.clone(),
+                        size: approx_size,
+                        max_size: MAX_UPLOAD_TO_PATH_BYTES,
+                    },
+                )
+                .await?;
+                return Ok(());
+            }
+            let fs = { state.read().await.file_system.clone() };
+            let dir = match fs.validator().validate_existing(&destination_dir) {
+                Ok(dir) if dir.is_dir() => dir,
+                Ok(dir) => {
+                    let error = FileSystemError::NotADirectory {
+                        path: crate::filesystem::path_utils::to_protocol_path(&dir),
+                    };
+                    send_fs_error(tx, request_id, "upload_to_path", &destination_dir, error)
+                        .await?;
+                    return Ok(());
+                }
+                Err(e) => {
+                    send_fs_error(tx, request_id, "upload_to_path", &destination_dir, e).await?;
+                    return Ok(());
+                }
+            };
+
+            let file_name = sanitize_upload_file_name(&file_name);
+            // Another upload can claim the free name first; move on to the
+            // next one rather than failing or overwriting it.
+            let mut attempts = 0;
+            let (destination_path, result) = loop {
+                let destination = free_upload_path(&dir, &file_name);
+                let destination_path =
+                    crate::filesystem::path_utils::to_protocol_path(&destination);
+                let result = fs
+                    .ops()
+                    .create_file(&destination_path, &content_base64, FileEncoding::Base64)
+                    .await;
+                attempts += 1;
+                match result {
+                    Err(FileSystemError::AlreadyExists { .. })
+                        if attempts < UPLOAD_TO_PATH_ATTEMPTS => {}
+                    result => break (destination_path, result),
+                }
+            };
+            match result {
+                Ok(()) => {
+                    let msg = ServerMessage::OperationSuccess {
+                        request_id,
+                        operation: "upload_to_path".to_string(),
+                        path: destination_path,
+                        message: None,
+                        entry: None,
+                    };
+                    tx.send(Message::Text(serde_json::to_string(&msg)?)).await?;
+                }
+                Err(e) => {
+                    send_fs_error(tx, request_id, "upload_to_path", &destination_path, e).await?;
+                }
+            }
+        }
+        ClientMessage::ListUploads {
+            request_id,
+            session_id,
+        } => {
+            if let Err(retry_after_ms) = check_fs_rate_limit(state, addr).await {
+                send_fs_error(
+                    tx,
+                    request_id,
+                    "list_uploads",
+                    &session_id,
+                    FileSystemError::RateLimited { retry_after_ms },
+                )
+                .await?;
+                return Ok(());
+            }
+            let (project_path, fs) = {
+                let st = state.read().await;
+                let Some(session) = st.sessions.get(&session_id) else {
+                    send_fs_error(
+                        tx,
+                        request_id,
+                        "list_uploads",
+                        &session_id,
+                        FileSystemError::NotFound {
+                            path: format!("session:{}", session_id),
+                        },
+                    )
+                    .await?;
+                    return Ok(());
+                };
+                (session.project_path.clone(), st.file_system.clone())
+            };
+
+            let dir = crate::filesystem::path_utils::to_protocol_path(&upload_dir(&project_path));
+            let options = ListOptions {
+                include_hidden: true,
+                sort_by: Some(crate::protocol::SortField::Modified),
+                sort_order: Some(crate::protocol::SortOrder::Desc),
+                ..Default::default()
+            };
+            let uploads = match fs.ops().list_directory(&dir, &options).await {
+                // Skip directories, the dedup index, and in-flight atomic-write temp files.
+                Ok(page) => page
+                    .entries
+                    .into_iter()
+                    .filter(|entry| {
+                        !entry.is_directory
+                            && !entry.name.starts_with('.')
+                            && !entry.name.contains(".tmp-")
+                    })
+                    .collect(),
+                // Nothing uploaded yet.
+                Err(FileSystemError::NotFound { .. }) => Vec::new(),
+                Err(e) => {
+                    send_fs_error(tx, request_id, "list_uploads", &dir, e).await?;
+                    return Ok(());
+                }
+            };
+            let msg = ServerMessage::UploadList {
+                request_id,
+                uploads,
+            };
+            tx.send(Message::Text(serde_json::to_string(&msg)?)).await?;
+        }
+        ClientMessage::CreateDirectory {
+            request_id,
+            path,
+            recursive,
+        } => {
+            if let Err(retry_after_ms) = check_fs_rate_limit(state, addr).await {
+                send_fs_error(
+                    tx,
+                    request_id,
+                    "create_directory",
+                    &path,
+                    FileSystemError::RateLimited { retry_after_ms },
+                )
+                .await?;
+                return Ok(());
+            }
+            let fs = { state.read().await.file_system.clone() };
+            match fs.ops().create_directory(&path, recursive).await {
+                Ok(()) => {
+                    let msg = ServerMessage::OperationSuccess {
+                        request_id,
+                        operation: "create_directory".to_string(),
+                        path: path.clone(),
+                        message: None,
+                        entry: None,
+                    };
+                    tx.send(Message::Text(serde_json::to_string(&msg)?)).await?;
+                }
+                Err(e) => {
+                    send_fs_error(tx, request_id, "create_directory", &path, e).await?;
+                }
+            }
+        }
+        ClientMessage::DeletePath {
+            request_id,
+            path,
+            recursive,
+            token,
+            dry_run,
+        } => {
+            if !destructive_operations_enabled() {
+                send_fs_error(
+                    tx,
+                    request_id,
+                    "delete_path",
+                    &path,
+                    FileSystemError::PermissionDenied {
+                        path: path.clone(),
+                        reason: "Destructive filesystem operations are disabled".to_string(),
+                    },
+                )
+                .await?;
+                return Ok(());
+            }
+            if let Err(retry_after_ms) = check_fs_rate_limit(state, addr).await {
+                send_fs_error(
+                    tx,
+                    request_id,
+                    "delete_path",
+                    &path,
+                    FileSystemError::RateLimited { retry_after_ms },
+                )
+                .await?;
+                return Ok(());
+            }
+            if dry_run {
+                let fs = { state.read().await.file_system.clone() };
+                let result = fs.ops().delete_dry_run(&path, recursive).await;
+                send_dry_run_result(tx, request_id, "delete_path", &path, result).await?;
+                return Ok(());
+            }
+            if recursive && recursive_delete_confirmation_required() {
+                if let Some(token) = token {
+                    let confirmed = {
+                        let mut st = state.write().await;
+                        take_pending_delete(&mut st, &token, addr, &path)
+                    };
+                    if !confirmed {
+                        let err = FileSystemError::PermissionDenied {
+                            path: path.clone(),
+                            reason: "Delete confirmation token is invalid or expired".to_string(),
+                        };
+                        send_fs_error(tx, request_id, "delete_path", &path, err).await?;
+                        return Ok(());
+                    }
+                } else {
+                    let fs = { state.read().await.file_system.clone() };
+                    match fs.ops().delete_preview(&path).await {
+                        // Single files are deleted straight away.
+                        Ok(preview) if !preview.is_directory => {}
+                        Ok(preview) => {
+                            let token = uuid::Uuid::new_v4().to_string();
+                            state.write().await.pending_deletes.insert(
+                                token.clone(),
+                                PendingDelete {
+                                    addr,
+                                    path: path.clone(),
+                                    expires_at: std::time::Instant::now() + DELETE_CONFIRM_TTL,
+                                },
+                            );
+                            let msg = ServerMessage::DeletePreview {
+                                request_id,
+                                path: preview.path,
+                                entry_count: preview.entry_count,
+                                total_bytes: preview.total_bytes,
+                                token,
+                            };
+                            tx.send(Message::Text(serde_json::to_string(&msg)?)).await?;
+                            return Ok(());
+                        }
+                        Err(e) => {
+                            send_fs_error(tx, request_id, "delete_path", &path, e).await?;
+                            return Ok(());
+                        }
+                    }
+                }
+            }
+            let fs = { state.read().await.file_system.clone() };
+            match fs.ops().delete_path(&path, recursive).await {
+                Ok(()) => {
+                    let msg = ServerMessage::OperationSuccess {
+                        request_id,
+                        operation: "delete_path".to_string(),
+                        path: path.clone(),
+                        message: None,
+                        entry: None,
+                    };
+                    tx.send(Message::Text(serde_json::to_string(&msg)?)).await?;
+                }
+                Err(e) => {
+                    send_fs_error(tx, request_id, "delete_path", &path, e).await?;
+                }
+            }
+        }
+        ClientMessage::RenamePath {
+            request_id,
+            old_path,
+            new_path,
+            dry_run,
+        } => {
+            if !destructive_operations_enabled() {
+                send_fs_error(
+                    tx,
+                    request_id,
+                    "rename_path",
+                    &old_path,
+                    FileSystemError::PermissionDenied {
+                        path: old_path.clone(),
+                        reason: "Destructive filesystem operations are disabled".to_string(),
+                    },
+                )
+                .await?;
+                return Ok(());
+            }
+            if let Err(retry_after_ms) = check_fs_rate_limit(state, addr).await {
+                send_fs_error(
+                    tx,
+                    request_id,
+                    "rename_path",
+                    &old_path,
+                    FileSystemError::RateLimited { retry_after_ms },
+                )
+                .await?;
+                return Ok(());
+            }
+            let fs = { state.read().await.file_system.clone() };
+            if dry_run {
+                let result = fs.ops().rename_dry_run(&old_path, &new_path);
+                send_dry_run_result(tx, request_id, "rename_path", &old_path, result).await?;
+                return Ok(());
+            }
+            match fs.ops().rename_path(&old_path, &new_path).await {
+                Ok(()) => {
+                    let msg = ServerMessage::OperationSuccess {
+                        request_id,
+                        operation: "rename_path".to_string(),
+                        path: old_path.clone(),
+                        message: Some(new_path),
+                        entry: None,
+                    };
+                    tx.send(Message::Text(serde_json::to_string(&msg)?)).await?;
+                }
+                Err(e) => {
+                    send_fs_error(tx, request_id, "rename_path", &old_path, e).await?;
+                }
+            }
+        }
+        ClientMessage::CopyPath {
+            request_id,
+            source,
+            destination,
+            recursive,
+            preserve_metadata,
+            dry_run,
+        } => {
+            if let Err(retry_after_ms) = check_fs_rate_limit(state, addr).await {
+                send_fs_error(
+                    tx,
+                    request_id,
+                    "copy_path",
+                    &source,
+                    FileSystemError::RateLimited { retry_after_ms },
+                )
+                .await?;
+                return Ok(());
+            }
+            let fs = { state.read().await.file_system.clone() };
+            if dry_run {
+                let result = fs
+                    .ops()
+                    .copy_dry_run(&source, &destination, recursive)
+                    .await;
+                send_dry_run_result(tx, request_id, "copy_path", &source, result).await?;
+                return Ok(());
+            }
+            match fs
+                .ops()
+                .copy_path(&source, &destination, recursive, preserve_metadata)
+                .await
+            {
+                Ok(()) => {
+                    let msg = ServerMessage::OperationSuccess {
+                        request_id,
+                        operation: "copy_path".to_string(),
+                        path: source.clone(),
+                        message: Some(destination),
+                        entry: None,
+                    };
+                    tx.send(Message::Text(serde_json::to_string(&msg)?)).await?;
+                }
+                Err(e) => {
+                    send_fs_error(tx, request_id, "copy_path", &source, e).await?;
+                }
+            }
+        }
+        ClientMessage::ExtractArchive {
+            request_id,
+            path,
+            destination,
+        } => {
+            if let Err(retry_after_ms) = check_fs_rate_limit(state, addr).await {
+                send_fs_error(
+                    tx,
+                    request_id,
+                    "extract_archive",
+                    &path,
+                    FileSystemError::RateLimited { retry_after_ms },
+                )
+                .await?;
+                return Ok(());
+            }
+            let fs = { state.read().await.file_system.clone() };
+            match fs.ops().extract_archive(&path, &destination).await {
+                Ok(extracted) => {
+                    let msg = ServerMessage::ArchiveExtracted {
+                        request_id,
+                        path,
+                        destination: extracted.destination,
+                        files: extracted.files,
+                    };
+                    tx.send(Message::Text(serde_json::to_string(&msg)?)).await?;
+                }
+                Err(e) => {
+                    send_fs_error(tx, request_id, "extract_archive", &path, e).await?;
+                }
+            }
+        }
+        ClientMessage::GetFileInfo { request_id, path } => {
+            if let Err(retry_after_ms) = check_fs_rate_limit(state, addr).await {
+                send_fs_error(
+                    tx,
+                    request_id,
+                    "get_file_info",
+                    &path,
+                    FileSystemError::RateLimited { retry_after_ms },
+                )
+                .await?;
+                return Ok(());
+            }
+            let fs = { state.read().await.file_system.clone() };
+            match fs.ops().for_client(addr).get_file_info(&path).await {
+                Ok(entry) => {
+                    let msg = ServerMessage::FileInfo {
+                        request_id,
+                        path,
+                        entry,
+                    };
+                    tx.send(Message::Text(serde_json::to_string(&msg)?)).await?;
+                }
+                Err(e) => {
+                    send_fs_error(tx, request_id, "get_file_info", &path, e).await?;
+                }
+            }
+        }
+        ClientMessage::SearchFiles {
+            request_id,
+            path,
+            pattern,
+            content_pattern,
+            max_depth,
+            max_results,
+            sort_by_relevance,
+            fuzzy,
+            extensions,
+        } => {
+            if let Err(retry_after_ms) = check_fs_rate_limit(state, addr).await {
+                send_fs_error(
+                    tx,
+                    request_id,
+                    "search_files",
+                    &path,
+                    FileSystemError::RateLimited { retry_after_ms },
+                )
+                .await?;
+                return Ok(());
+            }
+            let fs = { state.read().await.file_system.clone() };
+            let max_results = max_results
+                .unwrap_or(fs.config().max_search_results)
+                .min(fs.config().max_search_results);
+            let control = Arc::new(SearchControl::default());
+            let options = SearchOptions {
+                content_pattern,
+                max_depth,
+                max_results,
+                sort_by_relevance,
+                fuzzy,
+                extensions,
+                control: Some(control.clone()),
+            };
+            {
+                let mut st = state.write().await;
+                st.active_searches
+                    .entry(addr)
+                    .or_default()
+                    .insert(request_id.clone(), control.clone());
+            }
+            // Run off the connection loop so `CancelRequest` can reach it.
+            let state = state.clone();
+            tokio::spawn(async move {
+                let _permit = file_op_permit;
+                let mut walk = {
+                    let search = fs.search().clone();
+                    let path = path.clone();
+                    let pattern = pattern.clone();
+                    tokio::spawn(
+                        async move { search.search_files(&path, &pattern, &options).await },
+                    )
+                };
+                let mut progress = tokio::time::interval(SEARCH_PROGRESS_INTERVAL);
+                progress.tick().await;
+                let result = loop {
+                    tokio::select! {
+                        result = &mut walk => break result,
+                        _ = progress.tick() => {
+                            let msg = ServerMessage::SearchProgress {
+                                request_id: request_id.clone(),
+                                scanned: control.scanned.load(std::sync::atomic::Ordering::Relaxed),
+                                matched: control.matched.load(std::sync::atomic::Ordering::Relaxed),
+                            };
+                            queue_to_client(&state, addr, &msg).await;
+                        }
+                    }
+                };
+                {
+                    let mut st = state.write().await;
+                    if let Some(searches) = st.active_searches.get_mut(&addr) {
+                        searches.remove(&request_id);
+                    }
+                }
+                let msg = match result {
+                    Ok(Ok(outcome)) => ServerMessage::SearchResults {
+                        request_id,
+                        query: pattern,
+                        path: outcome.path,
+                        matches: outcome.matches,
+                        truncated: outcome.truncated,
+                        cancelled: outcome.cancelled,
+                    },
+                    Ok(Err(error)) => ServerMessage::OperationError {
+                        request_id,
+                        operation: "search_files".to_string(),
+                        path,
+                        error,
+                    },
+                    Err(e) => ServerMessage::OperationError {
+                        request_id,
+                        operation: "search_files".to_string(),
+                        path,
+                        error: FileSystemError::IoError {
+                            message: e.to_string(),
+                        },
+                    },
+                };
+                queue_to_client(&state, addr, &msg).await;
+            });
+        }
+        ClientMessage::CancelRequest { request_id } => {
+            let st = state.read().await;
+            if let Some(control) = st
+                .active_searches
+                .get(&addr)
+                .and_then(|searches| searches.get(&request_id))
+            {
+                control.cancel();
+            }
+        }
+        ClientMessage::WatchDirectory {
+            request_id,
+            path,
+            debounce_ms,
+            since_seq,
+            respect_ignore,
+        } => {
+            if let Err(retry_after_ms) = check_fs_rate_limit(state, addr).await {
+                send_fs_error(
+                    tx,
+                    request_id,
+                    "watch_directory",
+                    &path,
+                    FileSystemError::RateLimited { retry_after_ms },
+                )
+                .await?;
+                return Ok(());
+            }
+            let fs = { state.read().await.file_system.clone() };
+            match fs.validator().validate_existing(&path) {
+                Ok(canonical) => {
+                    if !canonical.is_dir() {
+                        send_fs_error(
+                            tx,
+                            request_id,
+                            "watch_directory",
+                            &path,
+                            FileSystemError::NotADirectory {
+                                path: crate::filesystem::path_utils::to_protocol_path(&canonical),
+                            },
+                        )
+                        .await?;
+                        return Ok(());
+                    }
+
+                    // Use protocol-normalized paths for watcher keys and subscriptions so the
+                    // mobile app can match file_changed events reliably across OSes.
+                    let watch_path = crate::filesystem::path_utils::to_protocol_path(&canonical);
+                    // Checked before `watch` so a directory nobody was watching
+                    // during the gap reads as incomplete.
+                    let replay = since_seq.map(|seq| fs.watcher().events_since(&watch_path, seq));
+                    let should_watch = {
+                        let mut st = state.write().await;
+                        let skip_ignored = st.file_watch_skip_ignored.entry(addr).or_default();
+                        if respect_ignore {
+                            skip_ignored.insert(watch_path.clone());
+                        } else {
+                            skip_ignored.remove(&watch_path);
+                        }
+                        let entry = st.file_watch_subscriptions.entry(addr).or_default();
+                        if entry.insert(watch_path.clone()) {
+                            let count = st.file_watch_counts.entry(watch_path.clone()).or_insert(0);
+                            *count += 1;
+                            *count == 1
+                        } else {
+                            false
+                        }
+                    };
+
+                    if should_watch {
+                        if let Err(e) = fs.watcher().watch(&watch_path, debounce_ms) {
+                            send_fs_error(tx, request_id, "watch_directory", &path, e).await?;
+                            return Ok(());
+                        }
+                    }
+
+                    let msg = ServerMessage::OperationSuccess {
+                        request_id,
+                        operation: "watch_directory".to_string(),
+                        path: watch_path,
+                        message: replay
+                            .as_ref()
+                            .filter(|replay| replay.incomplete)
+                            .map(|_| "resync".to_string()),
+                        entry: None,
+                    };
+                    tx.send(Message::Text(serde_json::to_string(&msg)?)).await?;
+                    let replayed = replay.map(|replay| replay.events).unwrap_or_default();
+                    for change in replayed
+                        .into_iter()
+                        .filter(|change| !(respect_ignore && change.ignored))
+                    {
+                        let new_entry = match change.change_type {
+                            ChangeType::Created | ChangeType::Modified => {
+                                fs.ops().get_file_info(&change.path).await.ok()
+                            }
+                            _ => None,
+                        };
+                        let msg = ServerMessage::FileChanged {
+                            path: change.path,
+                            change_type: change.change_type,
+                            new_entry,
+                            seq: change.seq,
+                        };
+                        tx.send(Message::Text(serde_json::to_string(&msg)?)).await?;
+                    }
+                }
+                Err(e) => {
+                    send_fs_error(tx, request_id, "watch_directory", &path, e).await?;
+                }
+            }
+        }
+        ClientMessage::UnwatchDirectory { request_id, path } => {
+            if let Err(retry_after_ms) = check_fs_rate_limit(state, addr).await {
+                send_fs_error(
+                    tx,
+                    request_id,
+                    "unwatch_directory",
+                    &path,
+                    FileSystemError::RateLimited { retry_after_ms },
+                )
+                .await?;
+                return Ok(());
+            }
+            let fs = { state.read().await.file_system.clone() };
+            let watch_path = match fs.validator().validate_existing(&path) {
+                Ok(canonical) => crate::filesystem::path_utils::to_protocol_path(&canonical),
+                Err(_) => path.clone(),
             };
 
             let should_unwatch = {
                 let mut st = state.write().await;
+                if let Some(skip_ignored) = st.file_watch_skip_ignored.get_mut(&addr) {
+                    skip_ignored.remove(&watch_path);
+                }
                 let entry = st.file_watch_subscriptions.entry(addr).or_default();
                 let removed = entry.remove(&watch_path);
                 if removed {
@@ -4119,6 +6360,37 @@ async fn process_client_msg(
                 operation: "unwatch_directory".to_string(),
                 path: watch_path,
                 message: None,
+                entry: None,
+            };
+            tx.send(Message::Text(serde_json::to_string(&msg)?)).await?;
+        }
+        ClientMessage::GetDaemonStatus { request_id } => {
+            // Read-only snapshot of in-memory counters; cheap enough to skip
+            // rate limiting so the app can poll it for a live health panel.
+            let msg = {
+                let st = state.read().await;
+                ServerMessage::DaemonStatus {
+                    request_id,
+                    version: env!("CARGO_PKG_VERSION").to_string(),
+                    uptime_seconds: st.started_at.elapsed().as_secs(),
+                    session_count: st.sessions.len(),
+                    max_sessions: st.max_sessions,
+                    connected_clients: st.mobile_clients.len(),
+                    push_token_count: st.push_tokens.len(),
+                    port: st.port,
+                }
+            };
+            tx.send(Message::Text(serde_json::to_string(&msg)?)).await?;
+        }
+        ClientMessage::GetPaths { request_id } => {
+            let native = |path: PathBuf| path.to_string_lossy().to_string();
+            let msg = ServerMessage::Paths {
+                request_id,
+                config_dir: native(platform::config_dir()),
+                pid_file: native(pid_file()),
+                port_file: native(port_file()),
+                sessions_file: native(session::sessions_file()),
+                log_file: native(log_file()),
             };
             tx.send(Message::Text(serde_json::to_string(&msg)?)).await?;
         }
@@ -4167,15 +6439,212 @@ async fn process_client_msg(
                 .await?;
                 return Ok(());
             }
-            let fs = { state.read().await.file_system.clone() };
-            let roots = fs
-                .config()
-                .allowed_roots
-                .iter()
-                .map(|p| crate::filesystem::path_utils::to_protocol_path(p))
-                .collect();
-            let msg = ServerMessage::AllowedRoots { request_id, roots };
-            tx.send(Message::Text(serde_json::to_string(&msg)?)).await?;
+            let fs = { state.read().await.file_system.clone() };
+            let roots = fs
+                .config()
+                .allowed_roots
+                .iter()
+                .map(|p| crate::filesystem::path_utils::to_protocol_path(p))
+                .collect();
+            let msg = ServerMessage::AllowedRoots { request_id, roots };
+            tx.send(Message::Text(serde_json::to_string(&msg)?)).await?;
+        }
+        ClientMessage::GetFsConfig { request_id } => {
+            let fs = { state.read().await.file_system.clone() };
+            let msg = ServerMessage::FsConfig {
+                request_id,
+                allowed_roots: fs
+                    .config()
+                    .allowed_roots
+                    .iter()
+                    .map(|p| crate::filesystem::path_utils::to_protocol_path(p))
+                    .collect(),
+                limits: current_fs_limits(fs.config()),
+            };
+            tx.send(Message::Text(serde_json::to_string(&msg)?)).await?;
+        }
+        ClientMessage::ReloadConfig { request_id } => {
+            // A paired phone manages access through set_fs_config; rewriting
+            // the whole config is for the desktop user.
+            let result = if addr.ip().is_loopback() {
+                reload_config(state).await
+            } else {
+                Err("reload_config is only accepted from this machine".to_string())
+            };
+            let msg = match result {
+                Ok(changes) => ServerMessage::OperationSuccess {
+                    request_id,
+                    operation: "reload_config".to_string(),
+                    path: String::new(),
+                    message: Some(if changes.is_empty() {
+                        "No changes".to_string()
+                    } else {
+                        changes.join("; ")
+                    }),
+                    entry: None,
+                },
+                Err(message) => ServerMessage::OperationError {
+                    request_id,
+                    operation: "reload_config".to_string(),
+                    path: String::new(),
+                    error: FileSystemError::IoError { message },
+                },
+            };
+            tx.send(Message::Text(serde_json::to_string(&msg)?)).await?;
+        }
+        ClientMessage::SetFsConfig { request_id, limits } => {
+            let saved = match crate::setup::load_config() {
+                Some(mut cfg) => apply_fs_limits(&mut cfg.filesystem, &limits)
+                    .map_err(|message| FileSystemError::InvalidArgument { message })
+                    .and_then(|()| {
+                        crate::setup::save_config(&cfg).map_err(|e| FileSystemError::IoError {
+                            message: e.to_string(),
+                        })
+                    }),
+                None => Err(FileSystemError::IoError {
+                    message: "Daemon config not found; run mobilecli setup".to_string(),
+                }),
+            };
+            if let Err(error) = saved {
+                let msg = ServerMessage::OperationError {
+                    request_id,
+                    operation: "set_fs_config".to_string(),
+                    path: String::new(),
+                    error,
+                };
+                tx.send(Message::Text(serde_json::to_string(&msg)?)).await?;
+                return Ok(());
+            }
+            let fs = {
+                let mut st = state.write().await;
+                refresh_file_system_roots(&mut st);
+                st.file_system.clone()
+            };
+            tracing::info!(addr = %addr, limits = ?current_fs_limits(fs.config()), "Filesystem limits changed from mobile");
+            let msg = ServerMessage::FsConfig {
+                request_id,
+                allowed_roots: fs
+                    .config()
+                    .allowed_roots
+                    .iter()
+                    .map(|p| crate::filesystem::path_utils::to_protocol_path(p))
+                    .collect(),
+                limits: current_fs_limits(fs.config()),
+            };
+            tx.send(Message::Text(serde_json::to_string(&msg)?)).await?;
+        }
+        ClientMessage::GrantPathOnce { request_id, path } => {
+            let fs = { state.read().await.file_system.clone() };
+            match fs.validator().grant_read(&path, addr, PATH_GRANT_TTL) {
+                Ok(canonical) => {
+                    let granted = crate::filesystem::path_utils::to_protocol_path(&canonical);
+                    let credential_id = state
+                        .read()
+                        .await
+                        .mobile_auth
+                        .get(&addr)
+                        .map(|client| client.credential_id.clone());
+                    tracing::warn!(
+                        addr = %addr,
+                        credential_id = ?credential_id,
+                        path = %granted,
+                        ttl_secs = PATH_GRANT_TTL.as_secs(),
+                        "Granted temporary read access outside allowed roots"
+                    );
+                    let expires_at = Utc::now()
+                        + chrono::Duration::from_std(PATH_GRANT_TTL)
+                            .unwrap_or_else(|_| chrono::Duration::zero());
+                    let msg = ServerMessage::PathGranted {
+                        request_id,
+                        path: granted,
+                        expires_at: expires_at.to_rfc3339(),
+                    };
+                    tx.send(Message::Text(serde_json::to_string(&msg)?)).await?;
+                }
+                Err(e) => {
+                    send_fs_error(tx, request_id, "grant_path_once", &path, e).await?;
+                }
+            }
+        }
+        ClientMessage::GetBookmarks { request_id } => {
+            let bookmarks = crate::bookmarks::load_bookmarks();
+            let msg = ServerMessage::Bookmarks {
+                request_id,
+                bookmarks,
+            };
+            tx.send(Message::Text(serde_json::to_string(&msg)?)).await?;
+        }
+        ClientMessage::AddBookmark {
+            request_id,
+            path,
+            label,
+        } => {
+            if let Err(retry_after_ms) = check_fs_rate_limit(state, addr).await {
+                send_fs_error(
+                    tx,
+                    request_id,
+                    "add_bookmark",
+                    &path,
+                    FileSystemError::RateLimited { retry_after_ms },
+                )
+                .await?;
+                return Ok(());
+            }
+            let fs = { state.read().await.file_system.clone() };
+            let canonical = match fs.validator().validate_existing(&path) {
+                Ok(canonical) if canonical.is_dir() => canonical,
+                Ok(canonical) => {
+                    let error = FileSystemError::NotADirectory {
+                        path: crate::filesystem::path_utils::to_protocol_path(&canonical),
+                    };
+                    send_fs_error(tx, request_id, "add_bookmark", &path, error).await?;
+                    return Ok(());
+                }
+                Err(e) => {
+                    send_fs_error(tx, request_id, "add_bookmark", &path, e).await?;
+                    return Ok(());
+                }
+            };
+            let bookmark_path = crate::filesystem::path_utils::to_protocol_path(&canonical);
+            match crate::bookmarks::add_bookmark(&bookmark_path, label.as_deref()) {
+                Ok(bookmarks) => {
+                    let msg = ServerMessage::Bookmarks {
+                        request_id,
+                        bookmarks,
+                    };
+                    tx.send(Message::Text(serde_json::to_string(&msg)?)).await?;
+                }
+                Err(crate::bookmarks::BookmarkError::Full) => {
+                    let error = FileSystemError::PermissionDenied {
+                        path: bookmark_path,
+                        reason: crate::bookmarks::BookmarkError::Full.to_string(),
+                    };
+                    send_fs_error(tx, request_id, "add_bookmark", &path, error).await?;
+                }
+                Err(crate::bookmarks::BookmarkError::Io(e)) => {
+                    let error = FileSystemError::IoError {
+                        message: e.to_string(),
+                    };
+                    send_fs_error(tx, request_id, "add_bookmark", &path, error).await?;
+                }
+            }
+        }
+        ClientMessage::RemoveBookmark { request_id, path } => {
+            match crate::bookmarks::remove_bookmark(&path) {
+                Ok(bookmarks) => {
+                    let msg = ServerMessage::Bookmarks {
+                        request_id,
+                        bookmarks,
+                    };
+                    tx.send(Message::Text(serde_json::to_string(&msg)?)).await?;
+                }
+                Err(e) => {
+                    let error = FileSystemError::IoError {
+                        message: e.to_string(),
+                    };
+                    send_fs_error(tx, request_id, "remove_bookmark", &path, error).await?;
+                }
+            }
         }
     }
     Ok(())
@@ -4186,47 +6655,90 @@ fn required_scope_for_message(msg: &ClientMessage) -> Option<&'static str> {
         ClientMessage::AuthStart { .. }
         | ClientMessage::AuthResponse { .. }
         | ClientMessage::Hello { .. }
-        | ClientMessage::Ping => None,
+        | ClientMessage::Ping
+        | ClientMessage::Goodbye { .. } => None,
         ClientMessage::GetSessions
+        | ClientMessage::GetSessionsByProject { .. }
+        | ClientMessage::GetDaemonStatus { .. }
+        | ClientMessage::GetPaths { .. }
         | ClientMessage::Subscribe { .. }
         | ClientMessage::Unsubscribe { .. }
-        | ClientMessage::GetSessionHistory { .. } => Some(auth::SCOPE_SESSION_READ),
+        | ClientMessage::SubscribeAll { .. }
+        | ClientMessage::AttachSession { .. }
+        | ClientMessage::UnsubscribeAll
+        | ClientMessage::GetSessionHistory { .. }
+        | ClientMessage::GetSessionScreen { .. }
+        | ClientMessage::GetInputHistory { .. }
+        | ClientMessage::GetNotificationHistory { .. } => Some(auth::SCOPE_SESSION_READ),
         ClientMessage::SendInput { .. }
+        | ClientMessage::BroadcastInput { .. }
+        | ClientMessage::PasteInput { .. }
         | ClientMessage::PtyResize { .. }
         | ClientMessage::TmuxViewport { .. }
         | ClientMessage::RenameSession { .. }
+        | ClientMessage::ClearHistory { .. }
         | ClientMessage::CloseSession { .. }
-        | ClientMessage::ToolApproval { .. } => Some(auth::SCOPE_SESSION_CONTROL),
-        ClientMessage::SpawnSession { .. } => Some(auth::SCOPE_SESSION_SPAWN),
-        ClientMessage::RegisterPushToken { .. } | ClientMessage::UnregisterPushToken { .. } => {
-            Some(auth::SCOPE_PUSH_REGISTER)
+        | ClientMessage::DetachSession { .. }
+        | ClientMessage::ToolApproval { .. }
+        | ClientMessage::DismissWaiting { .. } => Some(auth::SCOPE_SESSION_CONTROL),
+        ClientMessage::SpawnSession { .. } | ClientMessage::RunCommand { .. } => {
+            Some(auth::SCOPE_SESSION_SPAWN)
         }
+        ClientMessage::RegisterPushToken { .. }
+        | ClientMessage::UnregisterPushToken { .. }
+        | ClientMessage::TestNotification { .. } => Some(auth::SCOPE_PUSH_REGISTER),
         ClientMessage::ListDirectory { .. }
+        | ClientMessage::ListDirectoryStream { .. }
         | ClientMessage::ReadFile { .. }
         | ClientMessage::ReadFileChunk { .. }
+        | ClientMessage::DownloadArchive { .. }
+        | ClientMessage::GetFileChecksum { .. }
+        | ClientMessage::GetBinaryPreview { .. }
+        | ClientMessage::GetFileManifest { .. }
+        | ClientMessage::ListUploads { .. }
+        | ClientMessage::PathExists { .. }
+        | ClientMessage::TailFile { .. }
+        | ClientMessage::UntailFile { .. }
+        | ClientMessage::ReadFileLines { .. }
         | ClientMessage::GetFileInfo { .. }
         | ClientMessage::SearchFiles { .. }
+        | ClientMessage::GetRecentFiles { .. }
+        | ClientMessage::CancelRequest { .. }
         | ClientMessage::GetHomeDirectory { .. }
-        | ClientMessage::GetAllowedRoots { .. } => Some(auth::SCOPE_FS_READ),
+        | ClientMessage::GetAllowedRoots { .. }
+        | ClientMessage::GetFsConfig { .. }
+        | ClientMessage::GetBookmarks { .. } => Some(auth::SCOPE_FS_READ),
         ClientMessage::WriteFile { .. }
+        | ClientMessage::AddBookmark { .. }
+        | ClientMessage::RemoveBookmark { .. }
+        | ClientMessage::WriteFileChunk { .. }
         | ClientMessage::CreateDirectory { .. }
         | ClientMessage::RenamePath { .. }
-        | ClientMessage::CopyPath { .. } => Some(auth::SCOPE_FS_WRITE),
+        | ClientMessage::CopyPath { .. }
+        | ClientMessage::ExtractArchive { .. } => Some(auth::SCOPE_FS_WRITE),
         ClientMessage::DeletePath { .. } => Some(auth::SCOPE_FS_DELETE),
+        ClientMessage::SetFsConfig { .. }
+        | ClientMessage::GrantPathOnce { .. }
+        | ClientMessage::ReloadConfig { .. } => Some(auth::SCOPE_FS_CONFIG),
         ClientMessage::WatchDirectory { .. } | ClientMessage::UnwatchDirectory { .. } => {
             Some(auth::SCOPE_FS_WATCH)
         }
-        ClientMessage::UploadFile { .. } => Some(auth::SCOPE_FS_UPLOAD),
+        ClientMessage::UploadFile { .. } | ClientMessage::UploadToPath { .. } => {
+            Some(auth::SCOPE_FS_UPLOAD)
+        }
     }
 }
 
 fn session_control_target(msg: &ClientMessage) -> Option<&str> {
     match msg {
         ClientMessage::SendInput { session_id, .. }
+        | ClientMessage::PasteInput { session_id, .. }
         | ClientMessage::PtyResize { session_id, .. }
         | ClientMessage::TmuxViewport { session_id, .. }
         | ClientMessage::RenameSession { session_id, .. }
+        | ClientMessage::ClearHistory { session_id }
         | ClientMessage::CloseSession { session_id }
+        | ClientMessage::DetachSession { session_id }
         | ClientMessage::ToolApproval { session_id, .. } => Some(session_id.as_str()),
         _ => None,
     }
@@ -4298,6 +6810,52 @@ fn is_mobile_client_active(
         .is_some_and(|client| active_ids.contains(&client.credential_id))
 }
 
+/// Whether the client's negotiated protocol version can parse `msg`. Clients
+/// that never announced a version are treated as version 1.
+fn client_understands(st: &DaemonState, addr: &SocketAddr, msg: &ServerMessage) -> bool {
+    st.mobile_protocol_versions.get(addr).copied().unwrap_or(1) >= msg.min_protocol_version()
+}
+
+/// Show git statuses newer than the client's protocol as `Modified`, which
+/// every app version can deserialize.
+fn downgrade_git_statuses(entries: &mut [FileEntry], protocol_version: u32) {
+    for entry in entries {
+        if entry
+            .git_status
+            .as_ref()
+            .is_some_and(|status| status.min_protocol_version() > protocol_version)
+        {
+            entry.git_status = Some(GitStatus::Modified);
+        }
+    }
+}
+
+/// Queue `msg` for one mobile client, for replies produced outside its
+/// connection loop. Returns false once the client has gone away.
+async fn queue_to_client(state: &SharedState, addr: SocketAddr, msg: &ServerMessage) -> bool {
+    let Ok(text) = serde_json::to_string(msg) else {
+        return false;
+    };
+    let client = { state.read().await.mobile_clients.get(&addr).cloned() };
+    match client {
+        Some(client) => client.send(Message::Text(text)).await.is_ok(),
+        None => false,
+    }
+}
+
+/// Send `msg` to every active mobile client that understands it.
+fn broadcast_to_active_clients(st: &DaemonState, msg: &ServerMessage) {
+    let Ok(msg_str) = serde_json::to_string(msg) else {
+        return;
+    };
+    let active_ids = active_credential_ids_on_disk();
+    for (addr, client) in &st.mobile_clients {
+        if is_mobile_client_active(st, addr, &active_ids) && client_understands(st, addr, msg) {
+            let _ = client.try_send(Message::Text(msg_str.clone()));
+        }
+    }
+}
+
 fn is_valid_push_token(token_type: &str, token: &str) -> bool {
     let token = token.trim();
     if token.is_empty() || token.len() > 4096 || token.chars().any(char::is_whitespace) {
@@ -4321,6 +6879,23 @@ fn destructive_operations_enabled() -> bool {
         .unwrap_or(false)
 }
 
+fn recursive_delete_confirmation_required() -> bool {
+    crate::setup::load_config()
+        .map(|cfg| cfg.filesystem.confirm_recursive_delete)
+        .unwrap_or(false)
+}
+
+/// Redeem a `DeletePreview` token. Tokens are single-use and only good for
+/// the client and path they were issued to, until they expire.
+fn take_pending_delete(st: &mut DaemonState, token: &str, addr: SocketAddr, path: &str) -> bool {
+    let now = std::time::Instant::now();
+    st.pending_deletes
+        .retain(|_, pending| pending.expires_at > now);
+    st.pending_deletes
+        .remove(token)
+        .is_some_and(|pending| pending.addr == addr && pending.path == path)
+}
+
 async fn send_fs_error(
     tx: &mut futures_util::stream::SplitSink<
         tokio_tungstenite::WebSocketStream<TcpStream>,
@@ -4341,6 +6916,39 @@ async fn send_fs_error(
     Ok(())
 }
 
+/// Reply to a `dry_run` rename, copy or delete.
+async fn send_dry_run_result(
+    tx: &mut futures_util::stream::SplitSink<
+        tokio_tungstenite::WebSocketStream<TcpStream>,
+        Message,
+    >,
+    request_id: String,
+    operation: &str,
+    path: &str,
+    result: Result<crate::filesystem::operations::DryRun, FileSystemError>,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    match result {
+        Ok(dry_run) => {
+            let msg = ServerMessage::DryRunResult {
+                request_id,
+                operation: operation.to_string(),
+                affected_paths: dry_run.affected_paths,
+                conflicts: dry_run.conflicts,
+                truncated: dry_run.truncated,
+            };
+            tx.send(Message::Text(serde_json::to_string(&msg)?)).await?;
+            Ok(())
+        }
+        Err(e) => send_fs_error(tx, request_id, operation, path, e).await,
+    }
+}
+
+/// Largest file accepted by `UploadToPath`, whatever `max_write_size` says.
+const MAX_UPLOAD_TO_PATH_BYTES: u64 = 50 * 1024 * 1024;
+
+/// Free names `UploadToPath` tries before giving up on a busy directory.
+const UPLOAD_TO_PATH_ATTEMPTS: usize = 8;
+
 // Uploads are stored as:
 //   <project>/.mobilecli/uploads/<timestamp>-<short_uuid>-<sanitized_name>
 // and then written atomically via FileOperations::write_file, which appends
@@ -4472,10 +7080,83 @@ fn is_windows_reserved_device_name(name: &str) -> bool {
     false
 }
 
+fn upload_dir(project_path: &str) -> PathBuf {
+    PathBuf::from(project_path)
+        .join(".mobilecli")
+        .join("uploads")
+}
+
+/// Content-hash index (sha256 hex -> file name) used by `UploadFile { dedup: true }`.
+const UPLOAD_INDEX_FILE: &str = ".index.json";
+
+/// Serializes read-modify-write cycles of upload indexes from concurrent uploads.
+static UPLOAD_INDEX_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+async fn read_upload_index(dir: &Path) -> HashMap<String, String> {
+    match tokio::fs::read(dir.join(UPLOAD_INDEX_FILE)).await {
+        Ok(bytes) => serde_json::from_slice(&bytes).unwrap_or_default(),
+        Err(_) => HashMap::new(),
+    }
+}
+
+/// Return the earlier upload with this content hash, if it is still on disk
+/// with the same contents. An upload edited since it was indexed is not reused.
+async fn find_deduped_upload(dir: &Path, hash: &str) -> Option<PathBuf> {
+    let index = read_upload_index(dir).await;
+    let name = index.get(hash)?;
+    // The index lives in the project tree; only accept bare file names.
+    if Path::new(name).file_name() != Some(std::ffi::OsStr::new(name)) {
+        return None;
+    }
+    let path = dir.join(name);
+    let contents = tokio::fs::read(&path).await.ok()?;
+    (format!("{:x}", Sha256::digest(&contents)) == hash).then_some(path)
+}
+
+async fn record_upload_hash(destination: &Path, hash: &str) -> std::io::Result<()> {
+    let (Some(dir), Some(name)) = (destination.parent(), destination.file_name()) else {
+        return Ok(());
+    };
+    let _guard = UPLOAD_INDEX_LOCK.lock().await;
+    let mut index = read_upload_index(dir).await;
+    index.insert(hash.to_string(), name.to_string_lossy().into_owned());
+    let json = serde_json::to_vec_pretty(&index)?;
+    let temp = dir.join(format!(
+        "{}.tmp-{}",
+        UPLOAD_INDEX_FILE,
+        uuid::Uuid::new_v4()
+    ));
+    tokio::fs::write(&temp, json).await?;
+    tokio::fs::rename(&temp, dir.join(UPLOAD_INDEX_FILE)).await
+}
+
+/// `dir/file_name`, or the first free `name (n).ext` next to it.
+fn free_upload_path(dir: &Path, file_name: &str) -> PathBuf {
+    let candidate = dir.join(file_name);
+    if !candidate.exists() {
+        return candidate;
+    }
+    let name = Path::new(file_name);
+    let stem = name
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_else(|| file_name.to_string());
+    let extension = name
+        .extension()
+        .map(|ext| format!(".{}", ext.to_string_lossy()))
+        .unwrap_or_default();
+    for n in 1..1000 {
+        let candidate = dir.join(format!("{} ({}){}", stem, n, extension));
+        if !candidate.exists() {
+            return candidate;
+        }
+    }
+    let suffix = uuid::Uuid::new_v4().to_string();
+    dir.join(format!("{}-{}{}", stem, &suffix[..8], extension))
+}
+
 fn build_upload_destination_path(project_path: &str, file_name: String) -> PathBuf {
-    let mut path = PathBuf::from(project_path);
-    path.push(".mobilecli");
-    path.push("uploads");
+    let mut path = upload_dir(project_path);
     let stamp = chrono::Utc::now().format("%Y%m%d-%H%M%S");
     let suffix = uuid::Uuid::new_v4().to_string();
     let short_suffix = &suffix[..8];
@@ -4483,6 +7164,41 @@ fn build_upload_destination_path(project_path: &str, file_name: String) -> PathB
     path
 }
 
+/// Wait for one of the client's file operation slots. `None` when the cap
+/// is disabled.
+async fn acquire_file_op_permit(
+    state: &SharedState,
+    addr: SocketAddr,
+) -> Option<tokio::sync::OwnedSemaphorePermit> {
+    let permits = {
+        let mut st = state.write().await;
+        let max = st.max_file_ops_per_client;
+        if max == 0 {
+            return None;
+        }
+        st.file_op_permits
+            .entry(addr)
+            .or_insert_with(|| Arc::new(tokio::sync::Semaphore::new(max)))
+            .clone()
+    };
+    permits.acquire_owned().await.ok()
+}
+
+/// Take one of the client's `RunCommand` slots, or `None` when all are busy.
+async fn try_acquire_run_command_permit(
+    state: &SharedState,
+    addr: SocketAddr,
+) -> Option<tokio::sync::OwnedSemaphorePermit> {
+    let permits = {
+        let mut st = state.write().await;
+        st.run_command_permits
+            .entry(addr)
+            .or_insert_with(|| Arc::new(tokio::sync::Semaphore::new(MAX_RUN_COMMANDS_PER_CLIENT)))
+            .clone()
+    };
+    permits.try_acquire_owned().ok()
+}
+
 async fn check_fs_rate_limit(state: &SharedState, addr: SocketAddr) -> Result<(), u64> {
     const REQUESTS_PER_SECOND: u32 = 100;
     const BURST_SIZE: u32 = 50;
@@ -4494,6 +7210,55 @@ async fn check_fs_rate_limit(state: &SharedState, addr: SocketAddr) -> Result<()
     limiter.allow()
 }
 
+fn session_list_item(s: &PtySession, port: u16) -> SessionListItem {
+    SessionListItem {
+        session_id: s.session_id.clone(),
+        name: s.name.clone(),
+        command: s.command.clone(),
+        project_path: s.project_path.clone(),
+        ws_port: port,
+        started_at: s.started_at.to_rfc3339(),
+        cli_type: s.cli_tracker.current().as_str().to_string(),
+        runtime: Some(s.runtime.clone()),
+        activity_state: s
+            .cli_tracker
+            .activity_state(s.waiting_state.is_some(), Utc::now())
+            .as_str()
+            .to_string(),
+    }
+}
+
+/// Group sessions by `project_path`. Groups and the sessions inside them are
+/// ordered by most recent activity.
+fn group_sessions_by_project<'a>(
+    sessions: impl Iterator<Item = &'a PtySession>,
+    port: u16,
+) -> Vec<ProjectSessions> {
+    let mut by_project: HashMap<&str, Vec<&PtySession>> = HashMap::new();
+    for session in sessions {
+        by_project
+            .entry(session.project_path.as_str())
+            .or_default()
+            .push(session);
+    }
+
+    let mut groups: Vec<(chrono::DateTime<Utc>, ProjectSessions)> = by_project
+        .into_iter()
+        .map(|(project_path, mut members)| {
+            members.sort_by_key(|s| std::cmp::Reverse(s.last_activity));
+            let last_activity = members[0].last_activity;
+            let group = ProjectSessions {
+                project_path: project_path.to_string(),
+                last_activity: last_activity.to_rfc3339(),
+                sessions: members.iter().map(|s| session_list_item(s, port)).collect(),
+            };
+            (last_activity, group)
+        })
+        .collect();
+    groups.sort_by_key(|(last_activity, _)| std::cmp::Reverse(*last_activity));
+    groups.into_iter().map(|(_, group)| group).collect()
+}
+
 /// Send sessions list to a client
 async fn send_sessions_list(
     state: &SharedState,
@@ -4507,16 +7272,7 @@ async fn send_sessions_list(
     let items: Vec<SessionListItem> = st
         .sessions
         .values()
-        .map(|s| SessionListItem {
-            session_id: s.session_id.clone(),
-            name: s.name.clone(),
-            command: s.command.clone(),
-            project_path: s.project_path.clone(),
-            ws_port: port,
-            started_at: s.started_at.to_rfc3339(),
-            cli_type: s.cli_tracker.current().as_str().to_string(),
-            runtime: Some(s.runtime.clone()),
-        })
+        .map(|s| session_list_item(s, port))
         .collect();
     let msg = ServerMessage::Sessions { sessions: items };
     tx.send(Message::Text(serde_json::to_string(&msg)?)).await?;
@@ -4530,26 +7286,10 @@ async fn broadcast_sessions_update(state: &SharedState) {
     let items: Vec<SessionListItem> = st
         .sessions
         .values()
-        .map(|s| SessionListItem {
-            session_id: s.session_id.clone(),
-            name: s.name.clone(),
-            command: s.command.clone(),
-            project_path: s.project_path.clone(),
-            ws_port: port,
-            started_at: s.started_at.to_rfc3339(),
-            cli_type: s.cli_tracker.current().as_str().to_string(),
-            runtime: Some(s.runtime.clone()),
-        })
+        .map(|s| session_list_item(s, port))
         .collect();
     let msg = ServerMessage::Sessions { sessions: items };
-    if let Ok(msg_str) = serde_json::to_string(&msg) {
-        let active_ids = active_credential_ids_on_disk();
-        for (addr, client) in &st.mobile_clients {
-            if is_mobile_client_active(&st, addr, &active_ids) {
-                let _ = client.try_send(Message::Text(msg_str.clone()));
-            }
-        }
-    }
+    broadcast_to_active_clients(&st, &msg);
 }
 
 /// Persist daemon sessions to file for status command
@@ -4594,14 +7334,53 @@ async fn broadcast_waiting_for_input(state: &SharedState, session_id: &str) {
         wait_type: waiting.wait_type.as_str().to_string(),
         cli_type: session.cli_tracker.current().as_str().to_string(),
     };
-    if let Ok(msg_str) = serde_json::to_string(&msg) {
-        let active_ids = active_credential_ids_on_disk();
-        for (addr, client) in &st.mobile_clients {
-            if is_mobile_client_active(&st, addr, &active_ids) {
-                let _ = client.try_send(Message::Text(msg_str.clone()));
-            }
-        }
+    broadcast_to_active_clients(&st, &msg);
+}
+
+/// Record a detected prompt as the session's waiting state. Returns whether it
+/// should notify: a prompt redrawing itself (spinner, status line) while still
+/// waiting, or one the user dismissed, is not new.
+fn raise_waiting(session: &mut PtySession, event: &WaitEvent) -> bool {
+    let is_new = session.dismissed_wait_hash != Some(event.prompt_hash)
+        && session
+            .waiting_state
+            .as_ref()
+            .map(|w| w.prompt_hash != event.prompt_hash || w.wait_type != event.wait_type)
+            .unwrap_or(true);
+    if is_new {
+        session.waiting_state = Some(WaitingState {
+            wait_type: event.wait_type,
+            prompt_content: event.prompt.clone(),
+            timestamp: Utc::now(),
+            approval_model: event.approval_model,
+            prompt_hash: event.prompt_hash,
+        });
+        session.last_wait_hash = Some(event.prompt_hash);
+    }
+    is_new
+}
+
+/// The session's recent output no longer matches any wait pattern: drop its
+/// waiting state and any dismissed prompt. Returns whether it was waiting.
+fn clear_waiting_after_output(session: &mut PtySession) -> bool {
+    session.dismissed_wait_hash = None;
+    if session.waiting_state.take().is_none() {
+        return false;
     }
+    session.last_wait_hash = None;
+    true
+}
+
+/// Clear a session's waiting state without touching the PTY, remembering the
+/// prompt so the output it stays visible in doesn't raise it again. Returns
+/// whether the session was waiting.
+fn dismiss_waiting(session: &mut PtySession) -> bool {
+    let Some(waiting) = session.waiting_state.take() else {
+        return false;
+    };
+    session.last_wait_hash = None;
+    session.dismissed_wait_hash = Some(waiting.prompt_hash);
+    true
 }
 
 /// Broadcast waiting_cleared to all mobile clients
@@ -4611,14 +7390,7 @@ async fn broadcast_waiting_cleared(state: &SharedState, session_id: &str) {
         session_id: session_id.to_string(),
         timestamp: Utc::now().to_rfc3339(),
     };
-    if let Ok(msg_str) = serde_json::to_string(&msg) {
-        let active_ids = active_credential_ids_on_disk();
-        for (addr, client) in &st.mobile_clients {
-            if is_mobile_client_active(&st, addr, &active_ids) {
-                let _ = client.try_send(Message::Text(msg_str.clone()));
-            }
-        }
-    }
+    broadcast_to_active_clients(&st, &msg);
 }
 
 /// Broadcast pty_resized to clients actively viewing this session.
@@ -4726,13 +7498,26 @@ fn approval_input_for(model: ApprovalModel, response: &str) -> Option<&'static s
     }
 }
 
-fn truncate_to_max_chars(input: &mut String, max_chars: usize) {
-    let len = input.chars().count();
+/// Append `chunk` to the wait-detection buffer and keep at most its last
+/// `max_chars` characters. The kept text starts at a line boundary, so the
+/// first line the pattern matcher sees is never a cut-off half of a prompt.
+fn push_wait_buffer(buffer: &mut String, chunk: &str, max_chars: usize) {
+    buffer.push_str(chunk);
+    let len = buffer.chars().count();
     if len <= max_chars {
         return;
     }
-    let trimmed: String = input.chars().skip(len - max_chars).collect();
-    *input = trimmed;
+    let cut = buffer
+        .char_indices()
+        .nth(len - max_chars)
+        .map_or(buffer.len(), |(idx, _)| idx);
+    let start = if buffer[..cut].ends_with('\n') {
+        cut
+    } else {
+        // A single line longer than the buffer is kept cut.
+        buffer[cut..].find('\n').map_or(cut, |idx| cut + idx + 1)
+    };
+    buffer.drain(..start);
 }
 
 fn tail_scrollback_bytes(session: &PtySession, max_bytes: usize) -> (Vec<u8>, usize) {
@@ -4770,6 +7555,11 @@ fn clear_mobile_attach_for_session(st: &mut DaemonState, session_id: &str) {
     for addr in remove_addrs {
         st.mobile_attach_ids.remove(&addr);
     }
+    forget_replayed_seqs_for_session(st, session_id);
+    st.mobile_mouse_filters.retain(|_, filters| {
+        filters.remove(session_id);
+        !filters.is_empty()
+    });
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -5114,6 +7904,165 @@ fn strip_terminal_report_sequences(input: &[u8]) -> (Vec<u8>, usize) {
     (out, dropped)
 }
 
+/// Record `sender`'s `id` in a session's recent input ids. Returns false
+/// when it was already there, i.e. the input is a retry of one already
+/// delivered.
+fn remember_input_id(recent: &mut VecDeque<(String, String)>, sender: &str, id: &str) -> bool {
+    if recent
+        .iter()
+        .any(|(seen_sender, seen_id)| seen_sender == sender && seen_id == id)
+    {
+        return false;
+    }
+    if recent.len() >= INPUT_DEDUPE_WINDOW {
+        recent.pop_front();
+    }
+    recent.push_back((sender.to_string(), id.to_string()));
+    true
+}
+
+/// Who sent input from `addr`, stable across reconnects so a retry over a
+/// new socket is still recognized: the app's `sender_id`, else its paired
+/// installation, else the socket itself.
+fn input_sender(st: &DaemonState, addr: &SocketAddr) -> String {
+    if let Some((sender_id, _)) = st
+        .mobile_sender_addrs
+        .iter()
+        .find(|(_, sender_addr)| *sender_addr == addr)
+    {
+        return format!("sender:{}", sender_id);
+    }
+    match st.mobile_auth.get(addr) {
+        Some(client) => format!(
+            "install:{}:{}",
+            client.credential_id, client.mobile_installation_id
+        ),
+        None => format!("addr:{}", addr),
+    }
+}
+
+/// Type `text` from a mobile client into `session`: toolbar key names are
+/// translated unless `raw`, and raw input loses stray terminal report
+/// replies. Returns whether any bytes were sent.
+fn deliver_mobile_input(session: &mut PtySession, text: &str, raw: bool) -> bool {
+    // Nothing typed at a no-echo (password) prompt is kept.
+    if session.local_echo != Some(false) {
+        record_input_history(&mut session.input_history, text);
+    }
+    let mut payload = match named_key_bytes(text) {
+        Some(bytes) if !raw => bytes,
+        _ => text.as_bytes().to_vec(),
+    };
+    if raw {
+        let (filtered, dropped) =
+            strip_terminal_report_sequences_stateful(&mut session.raw_input_tail, &payload);
+        if dropped > 0 {
+            tracing::debug!(
+                session_id = %session.session_id,
+                runtime = %session.runtime,
+                dropped_sequences = dropped,
+                original_len = payload.len(),
+                filtered_len = filtered.len(),
+                "Dropped terminal-report reply sequences from mobile raw input"
+            );
+        }
+        payload = filtered;
+    }
+
+    !payload.is_empty() && session.input_tx.send(payload).is_ok()
+}
+
+/// Type `text` into every session `addr` is subscribed to that matches
+/// `filter`, the same sessions `SendInput` would accept from it. Returns how
+/// many received it.
+fn broadcast_mobile_input(
+    st: &mut DaemonState,
+    addr: SocketAddr,
+    text: &str,
+    raw: bool,
+    filter: Option<&BroadcastFilter>,
+) -> usize {
+    let Some(views) = st.mobile_views.get(&addr) else {
+        return 0;
+    };
+    let mut delivered = 0;
+    for (session_id, session) in st.sessions.iter_mut() {
+        // Never type into a password prompt nobody is looking at.
+        if !views.contains(session_id)
+            || session.local_echo == Some(false)
+            || !broadcast_filter_matches(filter, session)
+        {
+            continue;
+        }
+        session.last_activity = Utc::now();
+        if deliver_mobile_input(session, text, raw) {
+            delivered += 1;
+        }
+    }
+    delivered
+}
+
+fn broadcast_filter_matches(filter: Option<&BroadcastFilter>, session: &PtySession) -> bool {
+    let Some(filter) = filter else {
+        return true;
+    };
+    filter
+        .cli_type
+        .as_deref()
+        .map_or(true, |cli| session.cli_tracker.current().as_str() == cli)
+        && filter
+            .project_path
+            .as_deref()
+            .map_or(true, |path| session.project_path == path)
+}
+
+/// Keep a submitted command (input ending in a newline) for
+/// `GetInputHistory`. Blank lines and an immediate repeat are skipped.
+fn record_input_history(history: &mut VecDeque<String>, text: &str) {
+    if !text.ends_with(['\n', '\r']) {
+        return;
+    }
+    let command = text.trim_end_matches(['\n', '\r']);
+    if command.trim().is_empty() || history.back().is_some_and(|last| last == command) {
+        return;
+    }
+    if history.len() >= INPUT_HISTORY_LEN {
+        history.pop_front();
+    }
+    history.push_back(command.to_string());
+}
+
+/// Bytes for a toolbar key name sent as the whole text of a non-raw
+/// `SendInput` (`"Escape"`, `"Up"`, `"Ctrl+C"`, ...). Cursor keys use normal
+/// (not application) mode, which shells and most TUIs accept.
+fn named_key_bytes(name: &str) -> Option<Vec<u8>> {
+    let bytes: &[u8] = match name {
+        "Escape" | "Esc" => b"\x1b",
+        "Enter" | "Return" => b"\r",
+        "Tab" => b"\t",
+        "BackTab" | "Shift+Tab" => b"\x1b[Z",
+        "Backspace" => b"\x7f",
+        "Delete" => b"\x1b[3~",
+        "Insert" => b"\x1b[2~",
+        "Up" => b"\x1b[A",
+        "Down" => b"\x1b[B",
+        "Right" => b"\x1b[C",
+        "Left" => b"\x1b[D",
+        "Home" => b"\x1b[H",
+        "End" => b"\x1b[F",
+        "PageUp" => b"\x1b[5~",
+        "PageDown" => b"\x1b[6~",
+        _ => {
+            let letter = name.strip_prefix("Ctrl+")?;
+            return match letter.as_bytes() {
+                [c] if c.is_ascii_alphabetic() => Some(vec![c.to_ascii_uppercase() - b'@']),
+                _ => None,
+            };
+        }
+    };
+    Some(bytes.to_vec())
+}
+
 fn strip_terminal_report_sequences_stateful(tail: &mut Vec<u8>, input: &[u8]) -> (Vec<u8>, usize) {
     let mut scan = Vec::with_capacity(tail.len() + input.len());
     scan.extend_from_slice(tail);
@@ -5207,6 +8156,30 @@ fn resolve_resize_reason(
     }
 }
 
+/// Hold a mobile resize for `RESIZE_DEBOUNCE`, replacing any resize still
+/// waiting, so a keyboard animation reaches the wrapper as one final resize
+/// instead of a redraw storm.
+fn queue_debounced_resize(state: &SharedState, session: &mut PtySession, request: ResizeRequest) {
+    session.pending_resize = Some(request);
+    session.resize_debounce_gen = session.resize_debounce_gen.wrapping_add(1);
+    let generation = session.resize_debounce_gen;
+    let session_id = session.session_id.clone();
+    let state = Arc::clone(state);
+    tokio::spawn(async move {
+        tokio::time::sleep(RESIZE_DEBOUNCE).await;
+        let mut st = state.write().await;
+        let Some(session) = st.sessions.get_mut(&session_id) else {
+            return;
+        };
+        if session.resize_debounce_gen != generation {
+            return;
+        }
+        if let Some(request) = session.pending_resize.take() {
+            let _ = session.resize_tx.send(request);
+        }
+    });
+}
+
 fn is_noop_resize(last_applied: Option<(u16, u16)>, cols: u16, rows: u16) -> bool {
     last_applied == Some((cols, rows))
 }
@@ -5242,38 +8215,246 @@ fn should_mobile_enter_alt_screen(runtime: &str, in_alt_screen: bool) -> bool {
     if runtime == "tmux" {
         return false;
     }
-    in_alt_screen
+    in_alt_screen
+}
+
+fn is_stale_resize_epoch(last_epoch: u64, incoming_epoch: Option<u64>) -> bool {
+    incoming_epoch.is_some_and(|epoch| epoch <= last_epoch)
+}
+
+/// Per-client, per-session filter that drops mouse-tracking mode switches
+/// (`CSI ? 1000 h` and friends) and echoed SGR mouse reports (`CSI < b;x;y M`)
+/// from PTY output. A CSI sequence split across chunks is held back until
+/// it completes.
+#[derive(Default)]
+pub(crate) struct MouseSequenceFilter {
+    pending: Vec<u8>,
+}
+
+enum CsiScan {
+    Complete(usize),
+    Incomplete,
+    NotCsi,
+}
+
+impl MouseSequenceFilter {
+    fn filter(&mut self, chunk: &[u8]) -> Vec<u8> {
+        let mut input = std::mem::take(&mut self.pending);
+        input.extend_from_slice(chunk);
+
+        let mut out = Vec::with_capacity(input.len());
+        let mut i = 0;
+        while i < input.len() {
+            if input[i] != 0x1b {
+                out.push(input[i]);
+                i += 1;
+                continue;
+            }
+            match scan_csi(&input[i..]) {
+                CsiScan::Complete(len) => {
+                    push_unless_mouse_sequence(&input[i..i + len], &mut out);
+                    i += len;
+                }
+                CsiScan::Incomplete if input.len() - i <= MOUSE_FILTER_MAX_PENDING => {
+                    self.pending.extend_from_slice(&input[i..]);
+                    break;
+                }
+                _ => {
+                    out.push(input[i]);
+                    i += 1;
+                }
+            }
+        }
+        out
+    }
+}
+
+/// Strip mouse-tracking sequences from a history replay for a client that
+/// can't send mouse events. A replay is self-contained, so it gets a fresh
+/// filter; a sequence cut off at the very end is dropped.
+fn strip_mouse_from_replay(bytes: Vec<u8>, strip: bool) -> Vec<u8> {
+    if strip {
+        MouseSequenceFilter::default().filter(&bytes)
+    } else {
+        bytes
+    }
+}
+
+/// Length of the CSI sequence at the start of `bytes` (which begins with ESC).
+fn scan_csi(bytes: &[u8]) -> CsiScan {
+    match bytes.get(1) {
+        None => return CsiScan::Incomplete,
+        Some(b'[') => {}
+        Some(_) => return CsiScan::NotCsi,
+    }
+    for (idx, &byte) in bytes.iter().enumerate().skip(2) {
+        match byte {
+            0x20..=0x3f => {}
+            0x40..=0x7e => return CsiScan::Complete(idx + 1),
+            _ => return CsiScan::NotCsi,
+        }
+    }
+    CsiScan::Incomplete
+}
+
+fn push_unless_mouse_sequence(seq: &[u8], out: &mut Vec<u8>) {
+    let body = &seq[2..seq.len() - 1];
+    let final_byte = seq[seq.len() - 1];
+
+    if body.first() == Some(&b'<') && matches!(final_byte, b'M' | b'm') {
+        return;
+    }
+    let is_mode_switch = matches!(final_byte, b'h' | b'l')
+        && body.first() == Some(&b'?')
+        && body[1..].iter().all(|b| b.is_ascii_digit() || *b == b';');
+    if !is_mode_switch {
+        out.extend_from_slice(seq);
+        return;
+    }
+
+    let params: Vec<&[u8]> = body[1..].split(|b| *b == b';').collect();
+    let kept: Vec<&[u8]> = params
+        .iter()
+        .copied()
+        .filter(|p| !MOUSE_TRACKING_MODES.contains(p))
+        .collect();
+    if kept.len() == params.len() {
+        out.extend_from_slice(seq);
+    } else if !kept.is_empty() {
+        // Mixed switches like `CSI ? 25 ; 1000 h` keep their other modes.
+        out.extend_from_slice(b"\x1b[?");
+        out.extend_from_slice(&kept.join(&b';'));
+        out.push(final_byte);
+    }
+}
+
+/// Track bracketed-paste mode from a PTY chunk, including mode switches
+/// split across chunk boundaries. The last switch in the chunk wins.
+fn update_bracketed_paste_state(enabled: &mut bool, tail: &mut Vec<u8>, chunk: &[u8]) {
+    if chunk.is_empty() {
+        return;
+    }
+
+    let mut scan = Vec::with_capacity(tail.len() + chunk.len());
+    scan.extend_from_slice(tail);
+    scan.extend_from_slice(chunk);
+
+    let last_on = scan
+        .windows(BRACKETED_PASTE_ON.len())
+        .rposition(|w| w == BRACKETED_PASTE_ON);
+    let last_off = scan
+        .windows(BRACKETED_PASTE_OFF.len())
+        .rposition(|w| w == BRACKETED_PASTE_OFF);
+    match (last_on, last_off) {
+        (Some(on), Some(off)) => *enabled = on > off,
+        (Some(_), None) => *enabled = true,
+        (None, Some(_)) => *enabled = false,
+        (None, None) => {}
+    }
+
+    let keep = (BRACKETED_PASTE_ON.len() - 1).min(scan.len());
+    tail.clear();
+    tail.extend_from_slice(&scan[scan.len() - keep..]);
+}
+
+/// Bytes to send for a mobile paste. With bracketed paste on, the text is
+/// framed so the program treats embedded newlines as text, and any end
+/// marker inside it is removed so the paste can't break out of the frame.
+fn paste_payload(text: &str, bracketed: bool) -> Vec<u8> {
+    if !bracketed {
+        return text.as_bytes().to_vec();
+    }
+    let mut body = text.to_string();
+    while body.contains(PASTE_END) {
+        body = body.replace(PASTE_END, "");
+    }
+    format!("{PASTE_START}{body}{PASTE_END}").into_bytes()
 }
 
-fn is_stale_resize_epoch(last_epoch: u64, incoming_epoch: Option<u64>) -> bool {
-    incoming_epoch.is_some_and(|epoch| epoch <= last_epoch)
+/// Fold one chunk of PTY output into the session's scrollback and render
+/// state.
+fn record_pty_output(session: &mut PtySession, bytes: &[u8]) {
+    // tmux runtime runs with alternate-screen disabled during bootstrap, so
+    // raw 1049/1047 bytes from frame CLIs are not a reliable signal for
+    // mobile-side alt-screen policy.
+    if session.runtime == "tmux" {
+        session.in_alt_screen = false;
+        session.alt_track_tail.clear();
+        session.scrollback.extend(bytes.iter().copied());
+    } else {
+        // Track alt-screen transitions across chunk boundaries so
+        // subscribe_ack reflects the current rendering mode reliably, and
+        // keep full-screen TUI frames out of history.
+        let main_screen = update_alt_screen_state(
+            &mut session.in_alt_screen,
+            &mut session.alt_track_tail,
+            bytes,
+        );
+        session.scrollback.extend(main_screen);
+        // History drops those frames, so the screen preview keeps its own
+        // copy of everything.
+        session.screen_tail.extend(bytes.iter().copied());
+        let excess = session
+            .screen_tail
+            .len()
+            .saturating_sub(SCREEN_RENDER_TAIL_BYTES);
+        session.screen_tail.drain(..excess);
+    }
+    update_bracketed_paste_state(
+        &mut session.bracketed_paste,
+        &mut session.paste_track_tail,
+        bytes,
+    );
+    // Truncate from front if over limit (VecDeque is O(1) per pop)
+    while session.scrollback.len() > session.scrollback_max_bytes {
+        session.scrollback.pop_front();
+    }
 }
 
 /// Update alternate-screen state from a PTY chunk, including sequences split
 /// across chunk boundaries.
-fn update_alt_screen_state(in_alt_screen: &mut bool, tail: &mut Vec<u8>, chunk: &[u8]) {
+///
+/// Returns the chunk's main-screen bytes (everything outside the alternate
+/// screen, without the enter/leave sequences) so full-screen TUI frames stay
+/// out of scrollback history. Trailing bytes that could still become an
+/// enter/leave sequence are held in `tail` and only returned once the next
+/// chunk shows what they are.
+fn update_alt_screen_state(in_alt_screen: &mut bool, tail: &mut Vec<u8>, chunk: &[u8]) -> Vec<u8> {
     if chunk.is_empty() {
-        return;
+        return Vec::new();
     }
 
-    let mut scan = Vec::with_capacity(tail.len() + chunk.len());
-    scan.extend_from_slice(tail);
+    let mut scan = std::mem::take(tail);
     scan.extend_from_slice(chunk);
 
-    for i in 0..scan.len() {
+    let mut main_screen = Vec::with_capacity(scan.len());
+    let mut i = 0;
+    while i < scan.len() {
         let rem = &scan[i..];
-        if ALT_ENTER_SEQS.iter().any(|seq| rem.starts_with(seq)) {
+        if let Some(seq) = ALT_ENTER_SEQS.iter().find(|seq| rem.starts_with(seq)) {
             *in_alt_screen = true;
+            i += seq.len();
             continue;
         }
-        if ALT_LEAVE_SEQS.iter().any(|seq| rem.starts_with(seq)) {
+        if let Some(seq) = ALT_LEAVE_SEQS.iter().find(|seq| rem.starts_with(seq)) {
             *in_alt_screen = false;
+            i += seq.len();
+            continue;
+        }
+        let unresolved = ALT_ENTER_SEQS
+            .iter()
+            .chain(ALT_LEAVE_SEQS)
+            .any(|seq| seq.starts_with(rem));
+        if unresolved {
+            tail.extend_from_slice(rem);
+            break;
         }
+        if !*in_alt_screen {
+            main_screen.push(scan[i]);
+        }
+        i += 1;
     }
-
-    let keep = ALT_TRACK_TAIL_BYTES.min(scan.len());
-    tail.clear();
-    tail.extend_from_slice(&scan[scan.len() - keep..]);
+    main_screen
 }
 
 fn build_notification_text(
@@ -5324,31 +8505,39 @@ async fn cleanup_client_state(state: &SharedState, addr: SocketAddr) {
             st.mobile_sender_addrs.remove(&sender_id);
         }
         st.mobile_client_capabilities.remove(&addr);
+        st.mobile_binary_pty.remove(&addr);
+        st.mobile_protocol_versions.remove(&addr);
+        st.mobile_mouse_filtered.remove(&addr);
+        st.mobile_mouse_filters.remove(&addr);
         st.mobile_auth.remove(&addr);
         st.mobile_attach_ids.remove(&addr);
+        st.mobile_replayed_seqs.remove(&addr);
+        st.file_system.config().path_grants.revoke_all(addr);
+        let tails = st.file_tails.remove(&addr).unwrap_or_default();
+        for control in st
+            .active_searches
+            .remove(&addr)
+            .unwrap_or_default()
+            .values()
+        {
+            control.cancel();
+        }
+        st.pending_deletes.retain(|_, pending| pending.addr != addr);
 
-        let (sessions_to_restore, sessions_detached) = match st.mobile_views.remove(&addr) {
-            Some(sessions) => {
-                let mut restore = Vec::new();
-                let mut detached = Vec::new();
-                for session_id in sessions {
-                    detached.push(session_id.clone());
-                    if let Some(count) = st.session_view_counts.get_mut(&session_id) {
-                        if *count > 0 {
-                            *count -= 1;
-                        }
-                        if *count == 0 {
-                            st.session_view_counts.remove(&session_id);
-                            restore.push(session_id);
-                        }
-                    }
-                }
-                (restore, detached)
-            }
-            None => (Vec::new(), Vec::new()),
-        };
+        let sessions_detached: Vec<String> = st
+            .mobile_views
+            .get(&addr)
+            .map(|views| views.iter().cloned().collect())
+            .unwrap_or_default();
+        let sessions_to_restore: Vec<String> = sessions_detached
+            .iter()
+            .filter(|session_id| release_session_view(&mut st, addr, session_id))
+            .cloned()
+            .collect();
+        st.mobile_views.remove(&addr);
 
-        let to_unwatch = match st.file_watch_subscriptions.remove(&addr) {
+        st.file_watch_skip_ignored.remove(&addr);
+        let mut to_unwatch = match st.file_watch_subscriptions.remove(&addr) {
             Some(paths) => {
                 let mut to_unwatch = Vec::new();
                 for path in paths {
@@ -5366,6 +8555,16 @@ async fn cleanup_client_state(state: &SharedState, addr: SocketAddr) {
             }
             None => Vec::new(),
         };
+        for tail in tails.into_values() {
+            tail.task.abort();
+            if let Some(count) = st.file_watch_counts.get_mut(&tail.watch_path) {
+                *count = count.saturating_sub(1);
+                if *count == 0 {
+                    st.file_watch_counts.remove(&tail.watch_path);
+                    to_unwatch.push(tail.watch_path);
+                }
+            }
+        }
 
         for session_id in &sessions_detached {
             if st.tmux_viewport_controllers.get(session_id).copied() == Some(addr) {
@@ -5376,48 +8575,653 @@ async fn cleanup_client_state(state: &SharedState, addr: SocketAddr) {
                     .find_map(|(candidate, views)| views.contains(session_id).then_some(*candidate))
                 {
                     st.tmux_viewport_controllers
-                        .insert(session_id.clone(), next_addr);
+                        .insert(session_id.clone(), next_addr);
+                }
+            }
+        }
+
+        (sessions_to_restore, to_unwatch)
+    };
+
+    if !to_unwatch.is_empty() {
+        let state = state.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(WATCH_REPLAY_GRACE).await;
+            let (fs, idle): (_, Vec<String>) = {
+                let st = state.read().await;
+                let idle = to_unwatch
+                    .into_iter()
+                    .filter(|path| !st.file_watch_counts.contains_key(path))
+                    .collect();
+                (st.file_system.clone(), idle)
+            };
+            for path in idle {
+                let _ = fs.watcher().unwatch(&path);
+            }
+        });
+    }
+
+    for session_id in sessions_to_restore {
+        restore_pty_size(state, &session_id).await;
+    }
+}
+
+/// Drop one reference to a watched directory, stopping the watcher at zero.
+async fn release_file_watch(state: &SharedState, watch_path: &str) {
+    let should_unwatch = {
+        let mut st = state.write().await;
+        match st.file_watch_counts.get_mut(watch_path) {
+            Some(count) => {
+                *count = count.saturating_sub(1);
+                if *count == 0 {
+                    st.file_watch_counts.remove(watch_path);
+                    true
+                } else {
+                    false
+                }
+            }
+            None => false,
+        }
+    };
+    if should_unwatch {
+        let fs = { state.read().await.file_system.clone() };
+        let _ = fs.watcher().unwatch(watch_path);
+    }
+}
+
+/// Evict a stale mobile websocket address and clean all associated view/watch state.
+async fn evict_mobile_addr(state: &SharedState, addr: SocketAddr) {
+    let stale_tx = {
+        let mut st = state.write().await;
+        st.file_rate_limiters.remove(&addr);
+        st.file_op_permits.remove(&addr);
+        st.run_command_permits.remove(&addr);
+        st.mobile_clients.remove(&addr)
+    };
+    if let Some(tx) = stale_tx {
+        let _ = tx.try_send(Message::Close(None));
+    }
+    cleanup_client_state(state, addr).await;
+}
+
+/// The watched directory `changed_path` falls under: the path itself or its
+/// parent, since watches are not recursive.
+fn watched_dir<'a>(
+    changed_path: &str,
+    watched: &'a std::collections::HashSet<String>,
+) -> Option<&'a String> {
+    if let Some(dir) = watched.get(changed_path) {
+        return Some(dir);
+    }
+    let parent = std::path::Path::new(changed_path)
+        .parent()
+        .map(crate::filesystem::path_utils::to_protocol_path)?;
+    watched.get(&parent)
+}
+
+/// Whether `change` goes to `addr`: it is in a directory the client watches,
+/// and not an ignored path in a watch where the client skips those.
+fn watch_delivers(st: &DaemonState, addr: &SocketAddr, change: &FileChanged) -> bool {
+    let Some(dir) = st
+        .file_watch_subscriptions
+        .get(addr)
+        .and_then(|watched| watched_dir(&change.path, watched))
+    else {
+        return false;
+    };
+    !(change.ignored
+        && st
+            .file_watch_skip_ignored
+            .get(addr)
+            .is_some_and(|skip| skip.contains(dir)))
+}
+
+/// Record that `addr` is viewing `session_id`.
+fn register_session_view(st: &mut DaemonState, addr: SocketAddr, session_id: &str) {
+    let entry = st.mobile_views.entry(addr).or_default();
+    if entry.insert(session_id.to_string()) {
+        let count = st
+            .session_view_counts
+            .entry(session_id.to_string())
+            .or_insert(0);
+        *count += 1;
+    }
+}
+
+/// Drop `addr`'s view of `session_id`. Returns true when that was the last
+/// mobile viewer, i.e. the PTY should go back to the wrapper's natural size.
+///
+/// Remaining viewers are derived from `mobile_views` rather than trusting
+/// `session_view_counts`, so a missing or drifted counter can neither skip
+/// the restore nor restore underneath another viewer.
+fn release_session_view(st: &mut DaemonState, addr: SocketAddr, session_id: &str) -> bool {
+    let was_viewing = st
+        .mobile_views
+        .get_mut(&addr)
+        .is_some_and(|views| views.remove(session_id));
+    if !was_viewing {
+        return false;
+    }
+    let remaining = st
+        .mobile_views
+        .values()
+        .filter(|views| views.contains(session_id))
+        .count();
+    if remaining == 0 {
+        st.session_view_counts.remove(session_id);
+        true
+    } else {
+        st.session_view_counts
+            .insert(session_id.to_string(), remaining);
+        false
+    }
+}
+
+/// Attach `addr` to a session: clear the app's terminal, replay history, ack,
+/// then register the view so live output follows the replay.
+async fn subscribe_session(
+    state: &SharedState,
+    tx: &mut futures_util::stream::SplitSink<
+        tokio_tungstenite::WebSocketStream<TcpStream>,
+        Message,
+    >,
+    addr: SocketAddr,
+    session_id: String,
+    last_seen_seq: Option<u64>,
+    client_capabilities: Option<u32>,
+) {
+    tracing::debug!(addr = %addr, session_id = %session_id, "Client subscribed to session");
+    let attach_started = std::time::Instant::now();
+    let mut st = state.write().await;
+    if let Some(capabilities) = client_capabilities {
+        st.mobile_client_capabilities.insert(addr, capabilities);
+    }
+    let attach_id = st.next_attach_id;
+    st.next_attach_id = st.next_attach_id.saturating_add(1);
+    let overhaul_flags = st.overhaul_flags;
+    let capabilities = st
+        .mobile_client_capabilities
+        .get(&addr)
+        .copied()
+        .unwrap_or(0);
+    let use_attach_v2 = should_use_attach_v2(overhaul_flags, capabilities);
+    let strip_mouse = st.mobile_mouse_filtered.contains(&addr);
+
+    // Collect session state under one lock, then drop it before sending.
+    let (
+        scrollback_bytes,
+        render_as_tui,
+        mobile_in_alt_screen,
+        scrollback_len,
+        runtime,
+        tmux_snapshot_req,
+        initial_live_seq,
+        ready_cols,
+        ready_rows,
+    ) = if let Some(session) = st.sessions.get(&session_id) {
+        let render_as_tui = should_treat_as_tui_for_mobile(&session.runtime, session.in_alt_screen);
+        let mobile_in_alt_screen =
+            should_mobile_enter_alt_screen(&session.runtime, session.in_alt_screen);
+        let has_scrollback = !session.scrollback.is_empty();
+        let runtime = session.runtime.clone();
+        // tmux sessions always replay from capture-pane. Alternate-screen
+        // sessions replay only the visible pane; main-screen sessions may
+        // include scrollback.
+        let tmux_snapshot_req = if runtime == "tmux" {
+            match (&session.tmux_socket, &session.tmux_session) {
+                (Some(socket), Some(name)) => Some((
+                    socket.clone(),
+                    name.clone(),
+                    session.scrollback_max_bytes,
+                    !session.in_alt_screen,
+                )),
+                _ => None,
+            }
+        } else {
+            None
+        };
+        let sb = if runtime != "tmux" && !render_as_tui && has_scrollback {
+            let bytes = session.scrollback.iter().copied().collect::<Vec<u8>>();
+            Some(strip_mouse_from_replay(bytes, strip_mouse))
+        } else {
+            None
+        };
+        let (ready_cols, ready_rows) = session.last_applied_size.unwrap_or((0, 0));
+        (
+            sb,
+            render_as_tui,
+            mobile_in_alt_screen,
+            session.scrollback.len(),
+            runtime,
+            tmux_snapshot_req,
+            session.live_seq,
+            ready_cols,
+            ready_rows,
+        )
+    } else {
+        (None, false, false, 0, "pty".to_string(), None, 0, 0, 0)
+    };
+
+    tracing::debug!(
+        session_id = %session_id,
+        addr = %addr,
+        attach_id,
+        runtime = %runtime,
+        capabilities,
+        use_attach_v2,
+        last_seen_seq = ?last_seen_seq,
+        render_as_tui,
+        mobile_in_alt_screen,
+        scrollback_len,
+        text_replay_bytes = scrollback_bytes.as_ref().map(|b| b.len()).unwrap_or(0),
+        "Subscribe replay decision"
+    );
+    drop(st);
+    tracing::info!(
+        target: "overhaul.attach",
+        session_id = %session_id,
+        addr = %addr,
+        attach_id,
+        runtime = %runtime,
+        protocol = overhaul_flags.attach_protocol.as_str(),
+        use_attach_v2,
+        last_seen_seq = ?last_seen_seq,
+        "Attach sequence started"
+    );
+    let clear_mode = if mobile_in_alt_screen {
+        "alternate_screen"
+    } else {
+        "main_screen"
+    };
+    let mut replay_events: Vec<(&'static str, usize)> = Vec::new();
+    if use_attach_v2 {
+        let begin = ServerMessage::AttachBegin {
+            session_id: session_id.clone(),
+            attach_id,
+            runtime: runtime.clone(),
+            mode: if last_seen_seq.is_some() {
+                "reconnect".to_string()
+            } else {
+                "fresh".to_string()
+            },
+            in_alt_screen: mobile_in_alt_screen,
+        };
+        if let Ok(text) = serde_json::to_string(&begin) {
+            let _ = tx.send(Message::Text(text)).await;
+        }
+    }
+
+    // ── Step 1: wipe mobile's xterm.js buffer ──────────────────
+    //
+    // On every (re-)subscribe we first clear the client terminal so
+    // that replay data doesn't append to stale content from a prior
+    // subscribe. Sessions in mobile alternate-screen use 1049h clear.
+    // Text main-buffer sessions erase display+scrollback so the next
+    // replay starts from a deterministic baseline.
+    //
+    {
+        if use_attach_v2 {
+            let clear = ServerMessage::AttachClear {
+                session_id: session_id.clone(),
+                attach_id,
+            };
+            if let Ok(text) = serde_json::to_string(&clear) {
+                let _ = tx.send(Message::Text(text)).await;
+            }
+        } else {
+            let clear: &[u8] = if mobile_in_alt_screen {
+                // \x1b[?1049h  enter alternate screen
+                // \x1b[2J      erase display
+                // \x1b[H       cursor home
+                b"\x1b[?1049h\x1b[2J\x1b[H"
+            } else {
+                // \x1b[2J      erase display
+                // \x1b[3J      erase scrollback (xterm extension, supported by xterm.js)
+                // \x1b[H       cursor home
+                b"\x1b[2J\x1b[3J\x1b[H"
+            };
+            let clear_msg = ServerMessage::PtyBytes {
+                session_id: session_id.clone(),
+                data: BASE64.encode(clear),
+                seq: None,
+            };
+            if let Ok(text) = serde_json::to_string(&clear_msg) {
+                let _ = tx.send(Message::Text(text)).await;
+            }
+        }
+        tracing::info!(
+            target: "overhaul.attach",
+            session_id = %session_id,
+            attach_id,
+            clear_mode,
+            clear_bytes = if use_attach_v2 { 0 } else if mobile_in_alt_screen { b"\x1b[?1049h\x1b[2J\x1b[H".len() } else { b"\x1b[2J\x1b[3J\x1b[H".len() },
+            "Attach clear frame sent"
+        );
+    }
+
+    // ── Step 2: replay history ────────────────────────────────────
+    //
+    // PTY text sessions replay daemon scrollback.
+    if !render_as_tui {
+        if let Some(bytes) = scrollback_bytes {
+            replay_events.push(("daemon_scrollback", bytes.len()));
+            if use_attach_v2 {
+                let chunks = chunk_snapshot_payload(&bytes);
+                let total_chunks = chunks.len().max(1) as u32;
+                if chunks.is_empty() {
+                    let msg = ServerMessage::AttachSnapshotChunk {
+                        session_id: session_id.clone(),
+                        attach_id,
+                        chunk_seq: 1,
+                        total_chunks,
+                        is_last: true,
+                        data: String::new(),
+                    };
+                    if let Ok(text) = serde_json::to_string(&msg) {
+                        let _ = tx.send(Message::Text(text)).await;
+                    }
+                } else {
+                    for (idx, chunk) in chunks.into_iter().enumerate() {
+                        let msg = ServerMessage::AttachSnapshotChunk {
+                            session_id: session_id.clone(),
+                            attach_id,
+                            chunk_seq: (idx + 1) as u32,
+                            total_chunks,
+                            is_last: idx + 1 == total_chunks as usize,
+                            data: chunk,
+                        };
+                        if let Ok(text) = serde_json::to_string(&msg) {
+                            let _ = tx.send(Message::Text(text)).await;
+                        }
+                    }
+                }
+            } else {
+                let msg = ServerMessage::PtyBytes {
+                    session_id: session_id.clone(),
+                    data: BASE64.encode(&bytes),
+                    seq: None,
+                };
+                if let Ok(text) = serde_json::to_string(&msg) {
+                    let _ = tx.send(Message::Text(text)).await;
+                }
+            }
+            tracing::info!(
+                target: "overhaul.attach",
+                session_id = %session_id,
+                attach_id,
+                replay_source = "daemon_scrollback",
+                replay_bytes = bytes.len(),
+                "Attach replay sent"
+            );
+        }
+    }
+
+    // tmux sessions always get an authoritative pane snapshot on
+    // subscribe.
+    if let Some((socket, name, max_bytes, include_scrollback)) = tmux_snapshot_req {
+        let max_lines = if include_scrollback {
+            SUBSCRIBE_SCROLLBACK_LINES
+        } else {
+            0
+        };
+        if let Some(snapshot) =
+            capture_tmux_history_with_retry(socket, name, include_scrollback, max_lines).await
+        {
+            let total_bytes = snapshot.len();
+            let skip = total_bytes.saturating_sub(max_bytes);
+            let replay = strip_mouse_from_replay(snapshot[skip..].to_vec(), strip_mouse);
+            let bytes = replay.as_slice();
+            replay_events.push(("tmux_capture_pane", bytes.len()));
+            if use_attach_v2 {
+                let chunks = chunk_snapshot_payload(bytes);
+                let total_chunks = chunks.len().max(1) as u32;
+                if chunks.is_empty() {
+                    let msg = ServerMessage::AttachSnapshotChunk {
+                        session_id: session_id.clone(),
+                        attach_id,
+                        chunk_seq: 1,
+                        total_chunks,
+                        is_last: true,
+                        data: String::new(),
+                    };
+                    if let Ok(text) = serde_json::to_string(&msg) {
+                        let _ = tx.send(Message::Text(text)).await;
+                    }
+                } else {
+                    for (idx, chunk) in chunks.into_iter().enumerate() {
+                        let msg = ServerMessage::AttachSnapshotChunk {
+                            session_id: session_id.clone(),
+                            attach_id,
+                            chunk_seq: (idx + 1) as u32,
+                            total_chunks,
+                            is_last: idx + 1 == total_chunks as usize,
+                            data: chunk,
+                        };
+                        if let Ok(text) = serde_json::to_string(&msg) {
+                            let _ = tx.send(Message::Text(text)).await;
+                        }
+                    }
+                }
+            } else {
+                let msg = ServerMessage::SessionHistory {
+                    session_id: session_id.clone(),
+                    data: BASE64.encode(bytes),
+                    total_bytes,
+                };
+                if let Ok(text) = serde_json::to_string(&msg) {
+                    let _ = tx.send(Message::Text(text)).await;
+                }
+            }
+            tracing::info!(
+                target: "overhaul.attach",
+                session_id = %session_id,
+                attach_id,
+                replay_source = "tmux_capture_pane",
+                replay_bytes = bytes.len(),
+                total_snapshot_bytes = total_bytes,
+                "Attach replay sent"
+            );
+        } else {
+            tracing::warn!(
+                target: "overhaul.attach",
+                session_id = %session_id,
+                attach_id,
+                include_scrollback,
+                "tmux capture-pane unavailable during attach replay"
+            );
+        }
+    }
+
+    // Always send SubscribeAck so mobile knows whether to suppress
+    // stale bytes and enter alt-screen mode before resizing (v1 path).
+    let runtime_for_log = runtime.clone();
+    if use_attach_v2 {
+        let ready_live_seq = {
+            let st = state.read().await;
+            st.sessions
+                .get(&session_id)
+                .map(|session| session.live_seq)
+                .unwrap_or(initial_live_seq)
+        };
+        tracing::debug!(
+            target: "overhaul.sequence",
+            session_id = %session_id,
+            attach_id,
+            initial_live_seq,
+            ready_live_seq,
+            "Attach ready sequence barrier"
+        );
+        let ready = ServerMessage::AttachReady {
+            session_id: session_id.clone(),
+            attach_id,
+            last_live_seq: ready_live_seq,
+            cols: ready_cols,
+            rows: ready_rows,
+        };
+        if let Ok(text) = serde_json::to_string(&ready) {
+            let _ = tx.send(Message::Text(text)).await;
+        }
+    } else {
+        let ack = ServerMessage::SubscribeAck {
+            session_id: session_id.clone(),
+            in_alt_screen: mobile_in_alt_screen,
+            runtime: Some(runtime),
+        };
+        if let Ok(text) = serde_json::to_string(&ack) {
+            let _ = tx.send(Message::Text(text)).await;
+        }
+    }
+    let echo = {
+        let st = state.read().await;
+        st.sessions
+            .get(&session_id)
+            .and_then(|s| s.local_echo)
+            .map(|local_echo| ServerMessage::EchoMode {
+                session_id: session_id.clone(),
+                local_echo,
+            })
+            .filter(|echo| client_understands(&st, &addr, echo))
+    };
+    if let Some(echo) = echo {
+        if let Ok(text) = serde_json::to_string(&echo) {
+            let _ = tx.send(Message::Text(text)).await;
+        }
+    }
+
+    // Register active view after initial clear/replay/ack sequence so
+    // live PTY stream can't interleave with bootstrap replay bytes.
+    let mut st = state.write().await;
+    register_session_view(&mut st, addr, &session_id);
+    if st.tmux_viewport_supported && runtime_for_log == "tmux" {
+        let active_controller = st.tmux_viewport_controllers.get(&session_id).copied();
+        match active_controller {
+            Some(controller) if controller != addr => {
+                let controller_is_viewing = st
+                    .mobile_views
+                    .get(&controller)
+                    .map(|views| views.contains(&session_id))
+                    .unwrap_or(false);
+                if controller_is_viewing {
+                    tracing::debug!(
+                        session_id = %session_id,
+                        active_controller = %controller,
+                        requester = %addr,
+                        "Keeping existing tmux viewport controller on subscribe"
+                    );
+                } else {
+                    st.tmux_viewport_controllers
+                        .insert(session_id.clone(), addr);
+                    tracing::debug!(
+                        session_id = %session_id,
+                        stale_controller = %controller,
+                        new_controller = %addr,
+                        "Reassigned stale tmux viewport controller on subscribe"
+                    );
                 }
             }
+            Some(_) => {}
+            None => {
+                st.tmux_viewport_controllers
+                    .insert(session_id.clone(), addr);
+            }
         }
-
-        (sessions_to_restore, to_unwatch)
-    };
-
-    let fs = { state.read().await.file_system.clone() };
-    for path in to_unwatch {
-        let _ = fs.watcher().unwatch(&path);
     }
-
-    for session_id in sessions_to_restore {
-        restore_pty_size(state, &session_id).await;
+    if use_attach_v2 {
+        st.mobile_attach_ids
+            .entry(addr)
+            .or_default()
+            .insert(session_id.clone(), attach_id);
+    } else {
+        let mut remove_addr = false;
+        if let Some(attach_by_session) = st.mobile_attach_ids.get_mut(&addr) {
+            attach_by_session.remove(&session_id);
+            remove_addr = attach_by_session.is_empty();
+        }
+        if remove_addr {
+            st.mobile_attach_ids.remove(&addr);
+        }
     }
+    let replay_sources = if replay_events.is_empty() {
+        "none".to_string()
+    } else {
+        replay_events
+            .iter()
+            .map(|(source, _)| *source)
+            .collect::<Vec<_>>()
+            .join(",")
+    };
+    let replay_bytes_total: usize = replay_events.iter().map(|(_, bytes)| *bytes).sum();
+    tracing::info!(
+        target: "overhaul.attach",
+        session_id = %session_id,
+        addr = %addr,
+        attach_id,
+        runtime = %runtime_for_log,
+        protocol = overhaul_flags.attach_protocol.as_str(),
+        use_attach_v2,
+        clear_mode,
+        replay_sources = %replay_sources,
+        replay_events = replay_events.len(),
+        replay_bytes_total,
+        duration_ms = attach_started.elapsed().as_millis() as u64,
+        "Attach sequence completed"
+    );
 }
 
-/// Evict a stale mobile websocket address and clean all associated view/watch state.
-async fn evict_mobile_addr(state: &SharedState, addr: SocketAddr) {
-    let stale_tx = {
-        let mut st = state.write().await;
-        st.file_rate_limiters.remove(&addr);
-        st.mobile_clients.remove(&addr)
+/// Drop every session `addr` is subscribed to.
+async fn unsubscribe_all_sessions(state: &SharedState, addr: SocketAddr) {
+    let session_ids: Vec<String> = {
+        let st = state.read().await;
+        let mut ids: std::collections::HashSet<String> =
+            st.mobile_views.get(&addr).cloned().unwrap_or_default();
+        if let Some(attach_by_session) = st.mobile_attach_ids.get(&addr) {
+            ids.extend(attach_by_session.keys().cloned());
+        }
+        ids.into_iter().collect()
     };
-    if let Some(tx) = stale_tx {
-        let _ = tx.try_send(Message::Close(None));
+    for session_id in session_ids {
+        unsubscribe_session(state, addr, &session_id).await;
     }
-    cleanup_client_state(state, addr).await;
 }
 
-fn is_path_watched(changed_path: &str, watched: &std::collections::HashSet<String>) -> bool {
-    if watched.contains(changed_path) {
-        return true;
-    }
-    let parent = std::path::Path::new(changed_path)
-        .parent()
-        .map(crate::filesystem::path_utils::to_protocol_path);
-    if let Some(parent) = parent {
-        return watched.contains(&parent);
+/// Handle a mobile client leaving a session's view, restoring the PTY size
+/// once nobody is left watching.
+async fn unsubscribe_session(state: &SharedState, addr: SocketAddr, session_id: &str) {
+    let should_restore = {
+        let mut st = state.write().await;
+        let mut remove_attach_addr = false;
+        if let Some(attach_by_session) = st.mobile_attach_ids.get_mut(&addr) {
+            attach_by_session.remove(session_id);
+            remove_attach_addr = attach_by_session.is_empty();
+        }
+        if remove_attach_addr {
+            st.mobile_attach_ids.remove(&addr);
+        }
+        let mut remove_replayed_addr = false;
+        if let Some(replayed) = st.mobile_replayed_seqs.get_mut(&addr) {
+            replayed.remove(session_id);
+            remove_replayed_addr = replayed.is_empty();
+        }
+        if remove_replayed_addr {
+            st.mobile_replayed_seqs.remove(&addr);
+        }
+        let should_restore = release_session_view(&mut st, addr, session_id);
+        if st.tmux_viewport_controllers.get(session_id).copied() == Some(addr) {
+            st.tmux_viewport_controllers.remove(session_id);
+            if let Some(next_addr) = st
+                .mobile_views
+                .iter()
+                .find_map(|(candidate, views)| views.contains(session_id).then_some(*candidate))
+            {
+                st.tmux_viewport_controllers
+                    .insert(session_id.to_string(), next_addr);
+            }
+        }
+        should_restore
+    };
+    if should_restore {
+        restore_pty_size(state, session_id).await;
     }
-    false
 }
 
 async fn restore_pty_size(state: &SharedState, session_id: &str) {
@@ -5432,26 +9236,123 @@ async fn restore_pty_size(state: &SharedState, session_id: &str) {
     }
 }
 
+/// Remember a notification for `GetNotificationHistory` and, when enabled,
+/// append it to `notifications.jsonl`.
+async fn record_notification(state: &SharedState, record: NotificationRecord) {
+    if notification_log_enabled() {
+        if let Err(e) = append_notification_log(&record).await {
+            tracing::debug!("Failed to append notification log: {}", e);
+        }
+    }
+    let mut st = state.write().await;
+    push_notification_record(&mut st.notification_history, record);
+}
+
+fn push_notification_record(
+    history: &mut VecDeque<NotificationRecord>,
+    record: NotificationRecord,
+) {
+    if history.len() >= NOTIFICATION_HISTORY_LEN {
+        history.pop_front();
+    }
+    history.push_back(record);
+}
+
+async fn append_notification_log(record: &NotificationRecord) -> std::io::Result<()> {
+    use tokio::io::AsyncWriteExt;
+
+    let mut line = serde_json::to_string(record)?;
+    line.push('\n');
+    let mut file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(platform::config_dir().join("notifications.jsonl"))
+        .await?;
+    file.write_all(line.as_bytes()).await
+}
+
 /// Send push notifications to all registered tokens
-async fn send_push_notifications(tokens: &[PushToken], title: &str, body: &str, session_id: &str) {
-    if tokens.is_empty() {
-        return;
+async fn send_push_notifications(
+    state: &SharedState,
+    tokens: &[PushToken],
+    title: &str,
+    body: &str,
+    session_id: &str,
+) {
+    let data = serde_json::json!({
+        "sessionId": session_id,
+        "session_id": session_id,
+        "type": "waiting_for_input"
+    });
+    send_push_messages(state, tokens, title, body, data).await;
+}
+
+/// Provider errors meaning the token will never work again (the app was
+/// uninstalled or the token was revoked): Expo, then APNs, then FCM.
+const UNREGISTERED_PUSH_ERRORS: &[&str] = &[
+    "DeviceNotRegistered",
+    "Unregistered",
+    "BadDeviceToken",
+    "UNREGISTERED",
+    "NotRegistered",
+];
+
+/// What the push service said about one token.
+struct PushOutcome {
+    token: String,
+    token_type: String,
+    result: Result<(), String>,
+}
+
+impl PushOutcome {
+    fn is_unregistered(&self) -> bool {
+        self.result
+            .as_ref()
+            .is_err_and(|reason| UNREGISTERED_PUSH_ERRORS.contains(&reason.as_str()))
     }
+}
+
+/// Drop tokens the provider reported as permanently invalid, so they aren't
+/// retried on every notification.
+fn prune_unregistered_push_tokens(st: &mut DaemonState, outcomes: &[PushOutcome]) -> usize {
+    let dead: BTreeSet<&str> = outcomes
+        .iter()
+        .filter(|o| o.is_unregistered())
+        .map(|o| o.token.as_str())
+        .collect();
+    let before = st.push_tokens.len();
+    st.push_tokens.retain(|t| !dead.contains(t.token.as_str()));
+    before - st.push_tokens.len()
+}
+
+/// Send one push per token and report each token's delivery status.
+async fn send_push_messages(
+    state: &SharedState,
+    tokens: &[PushToken],
+    title: &str,
+    body: &str,
+    data: serde_json::Value,
+) -> Vec<PushOutcome> {
+    let mut outcomes: Vec<PushOutcome> = tokens
+        .iter()
+        .filter(|t| t.token_type != "expo")
+        .map(|t| PushOutcome {
+            token: t.token.clone(),
+            token_type: t.token_type.clone(),
+            result: Err("token type not supported by the daemon".to_string()),
+        })
+        .collect();
 
     // Build Expo push messages
-    let messages: Vec<serde_json::Value> = tokens
+    let expo_tokens: Vec<&PushToken> = tokens.iter().filter(|t| t.token_type == "expo").collect();
+    let messages: Vec<serde_json::Value> = expo_tokens
         .iter()
-        .filter(|t| t.token_type == "expo")
         .map(|t| {
             serde_json::json!({
                 "to": t.token,
                 "title": title,
                 "body": body,
-                "data": {
-                    "sessionId": session_id,
-                    "session_id": session_id,
-                    "type": "waiting_for_input"
-                },
+                "data": data,
                 "sound": "default",
                 "priority": "high"
             })
@@ -5459,11 +9360,11 @@ async fn send_push_notifications(tokens: &[PushToken], title: &str, body: &str,
         .collect();
 
     if messages.is_empty() {
-        return;
+        return outcomes;
     }
 
     // Send to Expo Push API (using shared client with timeout)
-    match http_client()
+    let results = match http_client()
         .post("https://exp.host/--/api/v2/push/send")
         .header("Content-Type", "application/json")
         .json(&messages)
@@ -5473,34 +9374,95 @@ async fn send_push_notifications(tokens: &[PushToken], title: &str, body: &str,
         Ok(resp) => {
             if !resp.status().is_success() {
                 tracing::warn!("Push notification failed: {}", resp.status());
+                vec![Err(format!("push service returned {}", resp.status())); messages.len()]
             } else {
                 tracing::debug!("Push notification sent to {} devices", messages.len());
+                let body = resp.json::<serde_json::Value>().await.unwrap_or_default();
+                parse_expo_tickets(&body, messages.len())
             }
         }
         Err(e) => {
             tracing::warn!("Failed to send push notification: {}", e);
+            vec![Err(e.to_string()); messages.len()]
         }
+    };
+
+    outcomes.extend(
+        expo_tokens
+            .iter()
+            .zip(results)
+            .map(|(t, result)| PushOutcome {
+                token: t.token.clone(),
+                token_type: t.token_type.clone(),
+                result,
+            }),
+    );
+
+    let pruned = prune_unregistered_push_tokens(&mut *state.write().await, &outcomes);
+    if pruned > 0 {
+        tracing::info!("Pruned {} unregistered push token(s)", pruned);
     }
+    outcomes
+}
+
+/// Per-message results from an Expo push response (`data` holds one ticket
+/// per message, in order). Errors prefer the machine-readable
+/// `details.error` (e.g. `DeviceNotRegistered`) over the prose message.
+fn parse_expo_tickets(body: &serde_json::Value, count: usize) -> Vec<Result<(), String>> {
+    let tickets = body["data"].as_array().cloned().unwrap_or_default();
+    (0..count)
+        .map(|i| match tickets.get(i) {
+            Some(ticket) if ticket["status"] == "ok" => Ok(()),
+            Some(ticket) => Err(ticket["details"]["error"]
+                .as_str()
+                .or_else(|| ticket["message"].as_str())
+                .unwrap_or("unknown error")
+                .to_string()),
+            None => Err("no receipt from push service".to_string()),
+        })
+        .collect()
 }
 
 #[cfg(test)]
 mod tests {
     use super::{
-        active_credential_index, build_upload_destination_path, capture_tmux_history,
-        clear_mobile_attach_for_session, file_system_config_from_setup_and_projects,
-        is_noop_resize, is_safe_session_project_root, is_stale_resize_epoch, is_valid_push_token,
-        is_windows_reserved_device_name, normalize_mobile_spawn_request, parse_auth_start_request,
-        pty_resized_ack_clients, resolve_resize_reason, sanitize_upload_file_name,
-        session_control_target, should_ignore_resize_without_viewers, should_ignore_restore_resize,
+        acquire_file_op_permit, active_credential_index, apply_fs_limits, attach_session_snapshot,
+        broadcast_filter_matches, broadcast_mobile_input, build_upload_destination_path,
+        capture_tmux_history, clear_mobile_attach_for_session, clear_waiting_after_output,
+        client_ip_allowed, client_understands, deliver_mobile_input, dismiss_waiting,
+        downgrade_git_statuses, explicit_bind_addrs, file_system_config_from_setup_and_projects,
+        find_deduped_upload, free_upload_path, fs_config_changes, group_sessions_by_project,
+        input_sender, insert_pty_session, is_allowed_command, is_noop_resize,
+        is_safe_session_project_root, is_stale_resize_epoch, is_valid_push_token,
+        is_windows_reserved_device_name, keepalive_settings, named_key_bytes,
+        normalize_mobile_spawn_request, parse_auth_start_request, parse_expo_tickets,
+        paste_payload, prune_unregistered_push_tokens, pty_chunk_replayed, pty_output_subscription,
+        pty_registration_allowed, pty_resized_ack_clients, push_notification_record,
+        push_wait_buffer, queue_debounced_resize, raise_waiting, read_upload_index,
+        record_input_history, record_pty_output, record_upload_hash, register_session_view,
+        remember_input_id, remove_registered_session, required_scope_for_message,
+        resolve_mobile_spawn_working_dir, resolve_resize_reason, run_captured,
+        sanitize_upload_file_name, session_control_target, session_limit_reached,
+        session_list_item, should_ignore_resize_without_viewers, should_ignore_restore_resize,
         should_mobile_enter_alt_screen, should_treat_as_tui_for_mobile, should_use_attach_v2,
-        strip_terminal_report_sequences, strip_terminal_report_sequences_stateful,
-        update_alt_screen_state, validate_auth_response_text, validate_pty_registration_with_token,
-        AttachProtocolMode, AuthStartRequest, AuthenticatedClient, ClientMessage, DaemonState,
-        OverhaulFlags, PtyResizeReason, TmuxViewportAction, CLIENT_CAP_ATTACH_V2,
-        DEFAULT_SCROLLBACK_MAX_BYTES, MAX_UPLOAD_FILE_NAME_BYTES,
+        stale_session_ids, strip_terminal_report_sequences,
+        strip_terminal_report_sequences_stateful, take_pending_delete,
+        try_acquire_run_command_permit, unsubscribe_all_sessions, unsubscribe_session,
+        update_alt_screen_state, update_bracketed_paste_state, upload_dir,
+        validate_auth_response_text, validate_pty_registration_with_token, validate_run_command,
+        validate_spawn_env, watch_delivers, AttachProtocolMode, AuthStartRequest,
+        AuthenticatedClient, BroadcastFilter, ClientMessage, DaemonState, ForwardedHeaders,
+        FsLimits, MouseSequenceFilter, NotificationRecord, OverhaulFlags, PendingDelete,
+        PtyResizeReason, PtySession, PushOutcome, PushToken, ResizeRequest, ServerMessage,
+        SharedState, TmuxViewportAction, CLIENT_CAP_ATTACH_V2, DEFAULT_SCROLLBACK_MAX_BYTES,
+        INPUT_DEDUPE_WINDOW, INPUT_HISTORY_LEN, MAX_RUN_COMMANDS_PER_CLIENT,
+        MAX_UPLOAD_FILE_NAME_BYTES, NOTIFICATION_HISTORY_LEN, PROTOCOL_VERSION, RESIZE_DEBOUNCE,
     };
+    use crate::detection::CliTracker;
+    use crate::filesystem::{config::FileSystemConfig, security::PathValidator};
     use crate::{auth, setup::Config};
-    use std::collections::BTreeSet;
+    use sha2::{Digest, Sha256};
+    use std::collections::{BTreeSet, VecDeque};
     use tempfile::TempDir;
     use tokio::time::Duration;
     use tokio_tungstenite::tungstenite::Message;
@@ -5862,6 +9824,12 @@ mod tests {
             }),
             Some("s3")
         );
+        assert_eq!(
+            session_control_target(&ClientMessage::ClearHistory {
+                session_id: "s5".to_string(),
+            }),
+            Some("s5")
+        );
         assert_eq!(session_control_target(&ClientMessage::GetSessions), None);
         assert_eq!(
             session_control_target(&ClientMessage::GetSessionHistory {
@@ -6048,6 +10016,26 @@ mod tests {
         assert!(out.len() <= MAX_UPLOAD_FILE_NAME_BYTES);
     }
 
+    #[test]
+    fn free_upload_path_numbers_clashing_names() {
+        let temp = TempDir::new().expect("tempdir");
+        assert_eq!(
+            free_upload_path(temp.path(), "photo.png"),
+            temp.path().join("photo.png")
+        );
+        std::fs::write(temp.path().join("photo.png"), b"a").expect("write");
+        std::fs::write(temp.path().join("photo (1).png"), b"b").expect("write");
+        assert_eq!(
+            free_upload_path(temp.path(), "photo.png"),
+            temp.path().join("photo (2).png")
+        );
+        std::fs::write(temp.path().join("notes"), b"c").expect("write");
+        assert_eq!(
+            free_upload_path(temp.path(), "notes"),
+            temp.path().join("notes (1)")
+        );
+    }
+
     #[test]
     fn build_upload_destination_path_uses_expected_folder_structure() {
         let out = build_upload_destination_path("/tmp/project", "image.png".to_string());
@@ -6059,63 +10047,467 @@ mod tests {
         assert!(components.contains(&"uploads".to_string()));
         let last = components.last().map(String::as_str).unwrap_or_default();
         assert!(last.ends_with("-image.png"));
+        assert_eq!(out.parent(), Some(upload_dir("/tmp/project").as_path()));
     }
 
     #[test]
     fn mobile_spawn_rejects_paths_and_interpreter_args() {
-        assert!(normalize_mobile_spawn_request("/tmp/bash", &[]).is_err());
-        assert!(normalize_mobile_spawn_request("bash", &["-c".to_string()]).is_err());
-        assert!(normalize_mobile_spawn_request("python", &["-c".to_string()]).is_err());
-        assert!(normalize_mobile_spawn_request("node", &["-e".to_string()]).is_err());
-        assert!(normalize_mobile_spawn_request("powershell", &["-Command".to_string()]).is_err());
+        assert!(normalize_mobile_spawn_request("/tmp/bash", &[], &[]).is_err());
+        assert!(normalize_mobile_spawn_request("bash", &["-c".to_string()], &[]).is_err());
+        assert!(normalize_mobile_spawn_request("python", &["-c".to_string()], &[]).is_err());
+        assert!(normalize_mobile_spawn_request("node", &["-e".to_string()], &[]).is_err());
+        assert!(
+            normalize_mobile_spawn_request("powershell", &["-Command".to_string()], &[]).is_err()
+        );
     }
 
     #[test]
     fn mobile_spawn_allows_supported_profiles_without_client_args() {
         assert_eq!(
-            normalize_mobile_spawn_request("claude", &[]).expect("claude profile"),
+            normalize_mobile_spawn_request("claude", &[], &[]).expect("claude profile"),
             ("claude".to_string(), Vec::<String>::new())
         );
         assert_eq!(
-            normalize_mobile_spawn_request("codex", &[]).expect("codex profile"),
+            normalize_mobile_spawn_request("codex", &[], &[]).expect("codex profile"),
             ("codex".to_string(), Vec::<String>::new())
         );
     }
 
-    #[tokio::test]
-    async fn unicode_upload_name_stays_within_filesystem_limits() {
-        let temp = TempDir::new().expect("tempdir");
-        let project = temp.path().join("project");
-        tokio::fs::create_dir_all(&project)
-            .await
-            .expect("create project");
+    #[test]
+    fn mobile_spawn_accepts_configured_extra_commands() {
+        let extra = vec!["aider".to_string()];
+        assert!(normalize_mobile_spawn_request("aider", &[], &[]).is_err());
+        assert_eq!(
+            normalize_mobile_spawn_request("aider", &[], &extra).expect("aider profile"),
+            ("aider".to_string(), Vec::<String>::new())
+        );
+        assert!(normalize_mobile_spawn_request("aider", &["--yes".to_string()], &extra).is_err());
+        assert!(!is_allowed_command("/opt/bin/aider", &extra));
+        assert!(normalize_mobile_spawn_request("llm", &[], &extra).is_err());
+    }
+
+    #[test]
+    fn mobile_spawn_resolves_relative_working_dir_inside_roots() {
+        let temp = TempDir::new().expect("tempdir");
+        let root = temp.path().canonicalize().expect("canonical root");
+        std::fs::create_dir_all(root.join("project/src")).expect("create dirs");
+        let validator = PathValidator::new(std::sync::Arc::new(FileSystemConfig {
+            allowed_roots: vec![root.clone()],
+            ..Default::default()
+        }));
+        let base = root.to_string_lossy().to_string();
+
+        let resolved =
+            resolve_mobile_spawn_working_dir(&validator, Some("project/src"), Some(&base))
+                .expect("relative dir resolves");
+        assert_eq!(
+            resolved.as_deref().map(std::path::Path::new),
+            Some(root.join("project/src").as_path())
+        );
+        assert!(resolve_mobile_spawn_working_dir(&validator, Some("../"), Some(&base)).is_err());
+        assert!(
+            resolve_mobile_spawn_working_dir(&validator, Some("missing"), Some(&base)).is_err()
+        );
+        assert!(
+            resolve_mobile_spawn_working_dir(&validator, Some("project"), Some("relative"))
+                .is_err()
+        );
+        assert_eq!(
+            resolve_mobile_spawn_working_dir(&validator, None, Some(&base)).expect("none"),
+            None
+        );
+    }
+
+    #[test]
+    fn mobile_spawn_env_validates_keys_and_values() {
+        let env = |k: &str, v: &str| vec![(k.to_string(), v.to_string())];
+        assert!(validate_spawn_env(&env("NODE_ENV", "production")).is_ok());
+        assert!(validate_spawn_env(&env("_PROFILE2", "work")).is_ok());
+        assert!(validate_spawn_env(&env("node_env", "x")).is_err());
+        assert!(validate_spawn_env(&env("2FA", "x")).is_err());
+        assert!(validate_spawn_env(&env("A-B", "x")).is_err());
+        assert!(validate_spawn_env(&env("", "x")).is_err());
+        assert!(validate_spawn_env(&env("LD_PRELOAD", "/tmp/x.so")).is_err());
+        assert!(validate_spawn_env(&env("MOBILECLI_RUNTIME", "pty")).is_err());
+        assert!(validate_spawn_env(&env("TOKEN", "$(id)")).is_err());
+        assert!(validate_spawn_env(&env("TOKEN", "a\nb")).is_err());
+    }
+
+    #[tokio::test]
+    async fn unicode_upload_name_stays_within_filesystem_limits() {
+        let temp = TempDir::new().expect("tempdir");
+        let project = temp.path().join("project");
+        tokio::fs::create_dir_all(&project)
+            .await
+            .expect("create project");
+
+        let file_name = sanitize_upload_file_name(&(String::from("你").repeat(160) + ".txt"));
+        assert!(file_name.len() <= MAX_UPLOAD_FILE_NAME_BYTES);
+
+        let path = build_upload_destination_path(&project.to_string_lossy(), file_name);
+        let parent = path.parent().expect("parent").to_path_buf();
+        tokio::fs::create_dir_all(parent)
+            .await
+            .expect("create uploads dir");
+
+        tokio::fs::write(&path, b"x")
+            .await
+            .expect("unicode path should be writable");
+    }
+
+    #[test]
+    fn clients_without_protocol_version_get_baseline_messages() {
+        let mut st = DaemonState::new(9847);
+        let legacy: std::net::SocketAddr = "127.0.0.1:5000".parse().unwrap();
+        let current: std::net::SocketAddr = "127.0.0.1:5001".parse().unwrap();
+        st.mobile_protocol_versions
+            .insert(current, PROTOCOL_VERSION);
+        let msg = ServerMessage::Pong;
+
+        assert_eq!(msg.min_protocol_version(), 1);
+        assert!(client_understands(&st, &legacy, &msg));
+        assert!(client_understands(&st, &current, &msg));
+
+        let session = test_pty_session("s1");
+        let newer = [
+            ServerMessage::SessionStarted {
+                session: session_list_item(&session, 9847),
+            },
+            ServerMessage::EchoMode {
+                session_id: "s1".to_string(),
+                local_echo: true,
+            },
+            ServerMessage::HistoryCleared {
+                session_id: "s1".to_string(),
+            },
+            ServerMessage::InputAck {
+                session_id: "s1".to_string(),
+                client_msg_id: "m1".to_string(),
+            },
+        ];
+        for msg in &newer {
+            assert_eq!(msg.min_protocol_version(), 2);
+            assert!(!client_understands(&st, &legacy, msg));
+            assert!(client_understands(&st, &current, msg));
+        }
+        st.mobile_protocol_versions.insert(legacy, 1);
+        assert!(newer
+            .iter()
+            .all(|msg| !client_understands(&st, &legacy, msg)));
+
+        let hello: ClientMessage =
+            serde_json::from_str(r#"{"type":"hello","client_version":"1.0"}"#).unwrap();
+        assert!(matches!(
+            hello,
+            ClientMessage::Hello {
+                protocol_version: None,
+                ..
+            }
+        ));
+    }
+
+    #[tokio::test]
+    async fn upload_index_finds_existing_upload_by_hash() {
+        let temp = TempDir::new().expect("tempdir");
+        let dir = upload_dir(&temp.path().to_string_lossy());
+        tokio::fs::create_dir_all(&dir).await.expect("uploads dir");
+
+        let first = dir.join("20250101-000000-abcd1234-shot.png");
+        tokio::fs::write(&first, b"png").await.expect("write");
+        let hash = format!("{:x}", Sha256::digest(b"png"));
+        record_upload_hash(&first, &hash).await.expect("index");
+
+        assert_eq!(find_deduped_upload(&dir, &hash).await, Some(first.clone()));
+        assert_eq!(find_deduped_upload(&dir, "cafebabe").await, None);
+
+        // An upload edited after it was indexed no longer matches its hash.
+        tokio::fs::write(&first, b"edited").await.expect("edit");
+        assert_eq!(find_deduped_upload(&dir, &hash).await, None);
+
+        // A stale entry whose file was removed is not reused.
+        tokio::fs::remove_file(&first).await.expect("remove");
+        assert_eq!(find_deduped_upload(&dir, &hash).await, None);
+    }
+
+    #[tokio::test]
+    async fn concurrent_upload_index_updates_keep_every_entry() {
+        let temp = TempDir::new().expect("tempdir");
+        let dir = upload_dir(&temp.path().to_string_lossy());
+        tokio::fs::create_dir_all(&dir).await.expect("uploads dir");
+
+        let updates = (0..16).map(|i| {
+            let destination = dir.join(format!("upload-{i}"));
+            tokio::spawn(async move { record_upload_hash(&destination, &format!("hash{i}")).await })
+        });
+        for update in updates.collect::<Vec<_>>() {
+            update.await.expect("join").expect("index");
+        }
+        assert_eq!(read_upload_index(&dir).await.len(), 16);
+    }
+
+    #[test]
+    fn screen_preview_shows_the_alternate_screen_history_leaves_out() {
+        let mut session = test_pty_session("s1");
+        record_pty_output(&mut session, b"$ vim notes.txt\r\n");
+        record_pty_output(&mut session, b"\x1b[?1049h\x1b[H\x1b[2Jline one\r\n~\r\n");
+
+        assert!(session.in_alt_screen);
+        let history: Vec<u8> = session.scrollback.iter().copied().collect();
+        assert_eq!(history, b"$ vim notes.txt\r\n");
+
+        let bytes: Vec<u8> = session.screen_tail.iter().copied().collect();
+        let screen = crate::screen::render(&bytes, 20, 3);
+        assert_eq!(screen.lines, vec!["line one", "~", ""]);
+    }
+
+    #[test]
+    fn update_alt_screen_state_detects_split_enter_and_leave_sequences() {
+        let mut in_alt = false;
+        let mut tail = Vec::new();
+
+        let kept = update_alt_screen_state(&mut in_alt, &mut tail, b"$ vim\x1b[?10");
+        assert_eq!(kept, b"$ vim");
+        assert!(!in_alt);
+
+        update_alt_screen_state(&mut in_alt, &mut tail, b"49h");
+        assert!(in_alt);
+
+        update_alt_screen_state(&mut in_alt, &mut tail, b"\x1b[?1049l");
+        assert!(!in_alt);
+
+        // A held prefix that turns out not to be a switch is still history.
+        let kept = update_alt_screen_state(&mut in_alt, &mut tail, b"\x1b[?1");
+        assert!(kept.is_empty());
+        let kept = update_alt_screen_state(&mut in_alt, &mut tail, b"h ok");
+        assert_eq!(kept, b"\x1b[?1h ok");
+    }
+
+    #[test]
+    fn sessions_group_by_project_in_activity_order() {
+        let now = chrono::Utc::now();
+        let mut sessions = Vec::new();
+        for (id, project, age_secs) in [
+            ("api-old", "/work/api", 300),
+            ("web", "/work/web", 60),
+            ("api-new", "/work/api", 5),
+        ] {
+            let mut session = test_pty_session(id);
+            session.project_path = project.to_string();
+            session.last_activity = now - chrono::Duration::seconds(age_secs);
+            sessions.push(session);
+        }
+
+        let groups = group_sessions_by_project(sessions.iter(), 9847);
+        let order: Vec<(&str, Vec<&str>)> = groups
+            .iter()
+            .map(|g| {
+                let ids = g.sessions.iter().map(|s| s.session_id.as_str()).collect();
+                (g.project_path.as_str(), ids)
+            })
+            .collect();
+        assert_eq!(
+            order,
+            vec![
+                ("/work/api", vec!["api-new", "api-old"]),
+                ("/work/web", vec!["web"]),
+            ]
+        );
+    }
+
+    #[test]
+    fn bookmark_changes_need_fs_write() {
+        use crate::auth::{SCOPE_FS_READ, SCOPE_FS_WRITE};
+
+        let get = ClientMessage::GetBookmarks {
+            request_id: "r1".to_string(),
+        };
+        let add = ClientMessage::AddBookmark {
+            request_id: "r2".to_string(),
+            path: "/work".to_string(),
+            label: None,
+        };
+        let remove = ClientMessage::RemoveBookmark {
+            request_id: "r3".to_string(),
+            path: "/work".to_string(),
+        };
+        assert_eq!(required_scope_for_message(&get), Some(SCOPE_FS_READ));
+        assert_eq!(required_scope_for_message(&add), Some(SCOPE_FS_WRITE));
+        assert_eq!(required_scope_for_message(&remove), Some(SCOPE_FS_WRITE));
+    }
+
+    #[test]
+    fn session_started_carries_the_list_item() {
+        let session = test_pty_session("s1");
+        let msg = ServerMessage::SessionStarted {
+            session: session_list_item(&session, 9847),
+        };
+        let json = serde_json::to_value(&msg).expect("serialize");
+        assert_eq!(json["type"], "session_started");
+        assert_eq!(json["session"]["session_id"], "s1");
+        assert_eq!(json["session"]["ws_port"], 9847);
+        assert_eq!(json["session"]["runtime"], "pty");
+    }
+
+    #[test]
+    fn notification_history_keeps_the_most_recent_records() {
+        let mut history = VecDeque::new();
+        for i in 0..NOTIFICATION_HISTORY_LEN + 5 {
+            push_notification_record(
+                &mut history,
+                NotificationRecord {
+                    timestamp: String::new(),
+                    session_id: format!("s{i}"),
+                    session_name: "Terminal".to_string(),
+                    wait_type: "tool_approval".to_string(),
+                    title: String::new(),
+                    prompt: String::new(),
+                },
+            );
+        }
+        assert_eq!(history.len(), NOTIFICATION_HISTORY_LEN);
+        assert_eq!(history.front().map(|r| r.session_id.as_str()), Some("s5"));
+    }
+
+    #[test]
+    fn unregistered_push_tokens_are_pruned() {
+        let mut st = DaemonState::new(9847);
+        for token in ["ExponentPushToken[gone]", "ExponentPushToken[live]"] {
+            st.push_tokens.push(PushToken {
+                token: token.to_string(),
+                token_type: "expo".to_string(),
+                platform: "ios".to_string(),
+                credential_id: "cred".to_string(),
+                mobile_installation_id: token.to_string(),
+            });
+        }
+        let outcomes = [
+            PushOutcome {
+                token: "ExponentPushToken[gone]".to_string(),
+                token_type: "expo".to_string(),
+                result: Err("DeviceNotRegistered".to_string()),
+            },
+            PushOutcome {
+                token: "ExponentPushToken[live]".to_string(),
+                token_type: "expo".to_string(),
+                result: Err("MessageRateExceeded".to_string()),
+            },
+        ];
+
+        assert_eq!(prune_unregistered_push_tokens(&mut st, &outcomes), 1);
+        assert_eq!(st.push_tokens.len(), 1);
+        assert_eq!(st.push_tokens[0].token, "ExponentPushToken[live]");
+    }
+
+    #[test]
+    fn expo_tickets_report_each_message_status() {
+        let body = serde_json::json!({
+            "data": [
+                { "status": "ok", "id": "abc" },
+                {
+                    "status": "error",
+                    "message": "\"ExponentPushToken[x]\" is not a registered push notification recipient",
+                    "details": { "error": "DeviceNotRegistered" }
+                }
+            ]
+        });
+        assert_eq!(
+            parse_expo_tickets(&body, 3),
+            vec![
+                Ok(()),
+                Err("DeviceNotRegistered".to_string()),
+                Err("no receipt from push service".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn paste_is_bracketed_only_while_the_program_enables_it() {
+        let mut enabled = false;
+        let mut tail = Vec::new();
+        assert_eq!(paste_payload("ls\n", enabled), b"ls\n");
+
+        update_bracketed_paste_state(&mut enabled, &mut tail, b"prompt\x1b[?20");
+        assert!(!enabled);
+        update_bracketed_paste_state(&mut enabled, &mut tail, b"04h$ ");
+        assert!(enabled);
+        assert_eq!(
+            paste_payload("a\x1b[201~\nrm -rf x\n", enabled),
+            b"\x1b[200~a\nrm -rf x\n\x1b[201~"
+        );
+
+        update_bracketed_paste_state(&mut enabled, &mut tail, b"\x1b[?2004h\x1b[?2004l");
+        assert!(!enabled);
+    }
+
+    #[test]
+    fn resent_input_ids_are_recognized_within_the_window() {
+        let mut recent = VecDeque::new();
+        assert!(remember_input_id(&mut recent, "phone", "m1"));
+        assert!(!remember_input_id(&mut recent, "phone", "m1"));
+        // Another client picking the same id is not a resend.
+        assert!(remember_input_id(&mut recent, "tablet", "m1"));
+
+        for i in 0..INPUT_DEDUPE_WINDOW {
+            assert!(remember_input_id(&mut recent, "phone", &format!("n{i}")));
+        }
+        assert_eq!(recent.len(), INPUT_DEDUPE_WINDOW);
+        assert!(remember_input_id(&mut recent, "phone", "m1"));
+    }
+
+    #[test]
+    fn input_sender_survives_a_reconnect() {
+        let mut st = DaemonState::new(9847);
+        let first: std::net::SocketAddr = "127.0.0.1:50040".parse().unwrap();
+        let second: std::net::SocketAddr = "127.0.0.1:50041".parse().unwrap();
 
-        let file_name = sanitize_upload_file_name(&(String::from("你").repeat(160) + ".txt"));
-        assert!(file_name.len() <= MAX_UPLOAD_FILE_NAME_BYTES);
+        st.mobile_sender_addrs.insert("phone-1".to_string(), first);
+        let before = input_sender(&st, &first);
+        st.mobile_sender_addrs.insert("phone-1".to_string(), second);
+        assert_eq!(input_sender(&st, &second), before);
+        assert_ne!(input_sender(&st, &first), before);
+    }
 
-        let path = build_upload_destination_path(&project.to_string_lossy(), file_name);
-        let parent = path.parent().expect("parent").to_path_buf();
-        tokio::fs::create_dir_all(parent)
-            .await
-            .expect("create uploads dir");
+    #[test]
+    fn named_keys_translate_only_exact_names() {
+        assert_eq!(named_key_bytes("Escape").as_deref(), Some(&b"\x1b"[..]));
+        assert_eq!(named_key_bytes("Up").as_deref(), Some(&b"\x1b[A"[..]));
+        assert_eq!(named_key_bytes("Ctrl+c").as_deref(), Some(&[0x03][..]));
+        assert_eq!(named_key_bytes("Ctrl+Z").as_deref(), Some(&[0x1a][..]));
+        assert_eq!(named_key_bytes("up"), None);
+        assert_eq!(named_key_bytes("Ctrl+1"), None);
+        assert_eq!(named_key_bytes("git status\r"), None);
+    }
 
-        tokio::fs::write(&path, b"x")
-            .await
-            .expect("unicode path should be writable");
+    #[test]
+    fn mouse_filter_strips_tracking_sequences_across_chunks() {
+        let mut filter = MouseSequenceFilter::default();
+
+        let out = filter.filter(b"htop\x1b[?1000h\x1b[?10");
+        assert_eq!(out, b"htop");
+        let out = filter.filter(b"06h\x1b[<0;12;4Mcpu\x1b[?25;1002l\x1b[2J");
+        assert_eq!(out, b"cpu\x1b[?25l\x1b[2J");
+        let out = filter.filter(b"\x1b[?1049h\x1bMdone");
+        assert_eq!(out, b"\x1b[?1049h\x1bMdone");
     }
 
     #[test]
-    fn update_alt_screen_state_detects_split_enter_and_leave_sequences() {
+    fn update_alt_screen_state_keeps_alt_frames_out_of_history() {
         let mut in_alt = false;
         let mut tail = Vec::new();
 
-        update_alt_screen_state(&mut in_alt, &mut tail, b"\x1b[?10");
-        assert!(!in_alt);
-
-        update_alt_screen_state(&mut in_alt, &mut tail, b"49h");
+        let kept = update_alt_screen_state(
+            &mut in_alt,
+            &mut tail,
+            b"$ vim\r\n\x1b[?1049h\x1b[2J~ frame",
+        );
+        assert_eq!(kept, b"$ vim\r\n");
         assert!(in_alt);
 
-        update_alt_screen_state(&mut in_alt, &mut tail, b"\x1b[?1049l");
+        let kept = update_alt_screen_state(&mut in_alt, &mut tail, b"~ more frames\x1b[?10");
+        assert!(kept.is_empty());
+
+        let kept = update_alt_screen_state(&mut in_alt, &mut tail, b"49l$ ls\r\n");
+        assert_eq!(kept, b"$ ls\r\n");
         assert!(!in_alt);
     }
 
@@ -6339,4 +10731,853 @@ mod tests {
         assert!(revoked_rx.try_recv().is_err());
         assert!(idle_rx.try_recv().is_err());
     }
+
+    fn test_pty_session(session_id: &str) -> PtySession {
+        let (input_tx, _input_rx) = tokio::sync::mpsc::unbounded_channel();
+        let (resize_tx, _resize_rx) = tokio::sync::mpsc::unbounded_channel();
+        let (control_tx, _control_rx) = tokio::sync::mpsc::unbounded_channel();
+        PtySession {
+            session_id: session_id.to_string(),
+            runtime: "pty".to_string(),
+            tmux_socket: None,
+            tmux_session: None,
+            name: "Terminal".to_string(),
+            command: "bash".to_string(),
+            project_path: String::new(),
+            started_at: chrono::Utc::now(),
+            last_activity: chrono::Utc::now(),
+            input_tx,
+            resize_tx,
+            control_tx,
+            waiting_state: None,
+            cli_tracker: CliTracker::new(),
+            last_wait_hash: None,
+            dismissed_wait_hash: None,
+            scrollback: VecDeque::new(),
+            scrollback_max_bytes: DEFAULT_SCROLLBACK_MAX_BYTES,
+            in_alt_screen: false,
+            alt_track_tail: Vec::new(),
+            screen_tail: VecDeque::new(),
+            last_resize_epoch: 0,
+            last_applied_size: None,
+            pending_resize: None,
+            resize_debounce_gen: 0,
+            live_seq: 0,
+            raw_input_tail: Vec::new(),
+            bracketed_paste: false,
+            paste_track_tail: Vec::new(),
+            local_echo: None,
+            recent_input_ids: VecDeque::new(),
+            input_history: VecDeque::new(),
+            has_desktop_wrapper: true,
+            last_heartbeat: std::time::Instant::now(),
+            sends_heartbeats: false,
+            registration_id: 0,
+        }
+    }
+
+    #[test]
+    fn keepalive_settings_disable_at_zero_and_floor_timeout() {
+        assert!(keepalive_settings(0, 90).is_none());
+        assert_eq!(
+            keepalive_settings(30, 90),
+            Some((Duration::from_secs(30), Duration::from_secs(90)))
+        );
+        assert_eq!(
+            keepalive_settings(30, 10),
+            Some((Duration::from_secs(30), Duration::from_secs(60)))
+        );
+    }
+
+    #[test]
+    fn broadcast_input_reaches_only_subscribed_sessions() {
+        let mut st = DaemonState::new(9847);
+        let addr: std::net::SocketAddr = "127.0.0.1:50050".parse().unwrap();
+        let mut receivers = std::collections::HashMap::new();
+        for id in ["watched", "unwatched"] {
+            let mut session = test_pty_session(id);
+            let (input_tx, input_rx) = tokio::sync::mpsc::unbounded_channel();
+            session.input_tx = input_tx;
+            receivers.insert(id, input_rx);
+            st.sessions.insert(id.to_string(), session);
+        }
+        register_session_view(&mut st, addr, "watched");
+
+        assert_eq!(
+            broadcast_mobile_input(&mut st, addr, "ls\n", false, None),
+            1
+        );
+        assert_eq!(
+            receivers.get_mut("watched").unwrap().try_recv().unwrap(),
+            b"ls\n"
+        );
+        assert!(receivers.get_mut("unwatched").unwrap().try_recv().is_err());
+
+        let stranger: std::net::SocketAddr = "127.0.0.1:50051".parse().unwrap();
+        assert_eq!(
+            broadcast_mobile_input(&mut st, stranger, "ls\n", false, None),
+            0
+        );
+        assert!(receivers.get_mut("watched").unwrap().try_recv().is_err());
+    }
+
+    #[test]
+    fn broadcast_filter_matches_cli_type_and_project() {
+        let mut session = test_pty_session("s1");
+        session.project_path = "/work/api".to_string();
+        session.cli_tracker.update_from_command("claude");
+
+        let filter = |cli_type: Option<&str>, project_path: Option<&str>| BroadcastFilter {
+            cli_type: cli_type.map(str::to_string),
+            project_path: project_path.map(str::to_string),
+        };
+        assert!(broadcast_filter_matches(None, &session));
+        assert!(broadcast_filter_matches(
+            Some(&filter(Some("claude"), None)),
+            &session
+        ));
+        assert!(broadcast_filter_matches(
+            Some(&filter(Some("claude"), Some("/work/api"))),
+            &session
+        ));
+        assert!(!broadcast_filter_matches(
+            Some(&filter(Some("codex"), None)),
+            &session
+        ));
+        assert!(!broadcast_filter_matches(
+            Some(&filter(Some("claude"), Some("/work/web"))),
+            &session
+        ));
+    }
+
+    #[test]
+    fn mobile_input_translates_key_names_unless_raw() {
+        let mut session = test_pty_session("s1");
+        let (input_tx, mut input_rx) = tokio::sync::mpsc::unbounded_channel();
+        session.input_tx = input_tx;
+
+        assert!(deliver_mobile_input(&mut session, "Escape", false));
+        assert_eq!(input_rx.try_recv().unwrap(), b"\x1b");
+        assert!(deliver_mobile_input(&mut session, "Escape", true));
+        assert_eq!(input_rx.try_recv().unwrap(), b"Escape");
+        assert!(!deliver_mobile_input(&mut session, "", false));
+    }
+
+    #[test]
+    fn input_history_keeps_submitted_commands_only() {
+        let mut history = VecDeque::new();
+        for text in [
+            "ls -la\r",
+            "partial",
+            "\r",
+            "ls -la\n",
+            "git status\r\n",
+            "  \n",
+        ] {
+            record_input_history(&mut history, text);
+        }
+        assert_eq!(history, ["ls -la", "git status"]);
+
+        for i in 0..INPUT_HISTORY_LEN + 5 {
+            record_input_history(&mut history, &format!("echo {i}\n"));
+        }
+        assert_eq!(history.len(), INPUT_HISTORY_LEN);
+        assert_eq!(history.front().map(String::as_str), Some("echo 5"));
+    }
+
+    #[test]
+    fn delete_tokens_are_single_use_and_bound_to_client_and_path() {
+        let addr: std::net::SocketAddr = "127.0.0.1:5000".parse().unwrap();
+        let other: std::net::SocketAddr = "127.0.0.1:5001".parse().unwrap();
+        let mut st = DaemonState::new(9847);
+        let pending = |path: &str, expires_at| PendingDelete {
+            addr,
+            path: path.to_string(),
+            expires_at,
+        };
+        let later = std::time::Instant::now() + Duration::from_secs(60);
+        st.pending_deletes
+            .insert("t1".into(), pending("/p/build", later));
+        st.pending_deletes
+            .insert("t2".into(), pending("/p/build", later));
+        st.pending_deletes
+            .insert("t3".into(), pending("/p/build", later));
+        st.pending_deletes
+            .insert("old".into(), pending("/p/build", std::time::Instant::now()));
+
+        assert!(take_pending_delete(&mut st, "t1", addr, "/p/build"));
+        assert!(!take_pending_delete(&mut st, "t1", addr, "/p/build"));
+        assert!(!take_pending_delete(&mut st, "t2", other, "/p/build"));
+        assert!(!take_pending_delete(&mut st, "t3", addr, "/p/src"));
+        assert!(!take_pending_delete(&mut st, "old", addr, "/p/build"));
+        assert!(st.pending_deletes.is_empty());
+    }
+
+    #[tokio::test]
+    async fn rapid_resizes_are_coalesced_into_the_last_one() {
+        let mut session = test_pty_session("s1");
+        let (resize_tx, mut resize_rx) = tokio::sync::mpsc::unbounded_channel();
+        session.resize_tx = resize_tx;
+        let mut st = DaemonState::new(9847);
+        st.sessions.insert("s1".to_string(), session);
+        let state: SharedState = std::sync::Arc::new(tokio::sync::RwLock::new(st));
+
+        for rows in [30, 24, 18] {
+            let mut st = state.write().await;
+            let session = st.sessions.get_mut("s1").expect("session");
+            queue_debounced_resize(
+                &state,
+                session,
+                ResizeRequest {
+                    cols: 80,
+                    rows,
+                    epoch: None,
+                    reason: PtyResizeReason::GeometryChange,
+                },
+            );
+        }
+        assert!(resize_rx.try_recv().is_err());
+
+        tokio::time::sleep(RESIZE_DEBOUNCE * 3).await;
+        let applied = resize_rx.try_recv().expect("debounced resize");
+        assert_eq!((applied.cols, applied.rows), (80, 18));
+        assert!(resize_rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn last_viewer_unsubscribe_restores_pty_size_once() {
+        let mut session = test_pty_session("s1");
+        let (resize_tx, mut resize_rx) = tokio::sync::mpsc::unbounded_channel();
+        session.resize_tx = resize_tx;
+        let mut st = DaemonState::new(9847);
+        st.sessions.insert("s1".to_string(), session);
+        let first: std::net::SocketAddr = "127.0.0.1:50001".parse().expect("first addr");
+        let second: std::net::SocketAddr = "127.0.0.1:50002".parse().expect("second addr");
+        register_session_view(&mut st, first, "s1");
+        register_session_view(&mut st, second, "s1");
+        let state: SharedState = std::sync::Arc::new(tokio::sync::RwLock::new(st));
+
+        unsubscribe_session(&state, first, "s1").await;
+        assert!(resize_rx.try_recv().is_err());
+        assert_eq!(state.read().await.session_view_counts.get("s1"), Some(&1));
+
+        unsubscribe_session(&state, second, "s1").await;
+        let restore = resize_rx.try_recv().expect("restore resize");
+        assert_eq!((restore.cols, restore.rows), (0, 0));
+        assert!(matches!(restore.reason, PtyResizeReason::DetachRestore));
+
+        // A repeated unsubscribe from a client no longer viewing is a no-op.
+        unsubscribe_session(&state, second, "s1").await;
+        assert!(resize_rx.try_recv().is_err());
+
+        // A view whose counter went missing still restores on release.
+        state
+            .write()
+            .await
+            .mobile_views
+            .entry(first)
+            .or_default()
+            .insert("s1".to_string());
+        unsubscribe_session(&state, first, "s1").await;
+        assert!(resize_rx.try_recv().is_ok());
+        assert!(resize_rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn explicit_bind_addrs_keep_loopback_unless_wildcard() {
+        let specific = explicit_bind_addrs(9847, "100.64.0.7".parse().expect("ip"));
+        assert_eq!(
+            specific.into_iter().collect::<Vec<_>>(),
+            vec!["100.64.0.7:9847", "127.0.0.1:9847"]
+        );
+        let loopback = explicit_bind_addrs(9847, "127.0.0.1".parse().expect("ip"));
+        assert_eq!(loopback.len(), 1);
+        let wildcard = explicit_bind_addrs(9847, "::".parse().expect("ip"));
+        assert_eq!(wildcard.into_iter().collect::<Vec<_>>(), vec!["[::]:9847"]);
+    }
+
+    #[test]
+    fn client_ip_allowlist_matches_ranges_and_fails_closed() {
+        let ip = |s: &str| s.parse::<std::net::IpAddr>().expect("ip");
+        assert!(client_ip_allowed(ip("203.0.113.9"), &[]));
+
+        let cidrs = vec![
+            "192.168.1.0/24".to_string(),
+            "100.64.0.0/10".to_string(),
+            "10.0.0.5".to_string(),
+            "not-a-range".to_string(),
+        ];
+        assert!(client_ip_allowed(ip("192.168.1.40"), &cidrs));
+        assert!(client_ip_allowed(ip("100.101.102.103"), &cidrs));
+        assert!(client_ip_allowed(ip("10.0.0.5"), &cidrs));
+        assert!(client_ip_allowed(ip("::ffff:192.168.1.7"), &cidrs));
+        assert!(!client_ip_allowed(ip("192.168.2.1"), &cidrs));
+        assert!(!client_ip_allowed(ip("10.0.0.6"), &cidrs));
+        assert!(!client_ip_allowed(
+            ip("192.168.2.1"),
+            &["garbage".to_string()]
+        ));
+    }
+
+    #[test]
+    fn forwarded_client_ip_only_trusts_configured_proxies() {
+        let ip = |s: &str| s.parse::<std::net::IpAddr>().expect("ip");
+        let trusted = vec!["127.0.0.1".to_string(), "10.0.0.0/8".to_string()];
+        let xff = |value: &str| ForwardedHeaders {
+            forwarded: None,
+            x_forwarded_for: Some(value.to_string()),
+        };
+
+        // Rightmost untrusted hop wins; a spoofed leftmost entry is ignored.
+        let headers = xff("6.6.6.6, 203.0.113.9, 10.1.2.3");
+        assert_eq!(
+            headers.client_ip(ip("127.0.0.1"), &trusted),
+            Some(ip("203.0.113.9"))
+        );
+        // Untrusted peers and an empty trust list leave the peer address alone.
+        assert_eq!(headers.client_ip(ip("198.51.100.7"), &trusted), None);
+        assert_eq!(headers.client_ip(ip("127.0.0.1"), &[]), None);
+        assert_eq!(xff("unknown").client_ip(ip("127.0.0.1"), &trusted), None);
+
+        let headers = ForwardedHeaders {
+            forwarded: Some(r#"for="[2001:db8::7]:4711";proto=https, for=10.0.0.2"#.to_string()),
+            x_forwarded_for: Some("6.6.6.6".to_string()),
+        };
+        assert_eq!(
+            headers.client_ip(ip("127.0.0.1"), &trusted),
+            Some(ip("2001:db8::7"))
+        );
+    }
+
+    #[test]
+    fn pty_registration_refuses_proxied_connections() {
+        let peer: std::net::SocketAddr = "127.0.0.1:40000".parse().expect("addr");
+        let remote: std::net::SocketAddr = "192.168.1.20:40000".parse().expect("addr");
+        let direct = ForwardedHeaders::default();
+        let spoofed = ForwardedHeaders {
+            forwarded: None,
+            x_forwarded_for: Some("127.0.0.1".to_string()),
+        };
+        let rfc = ForwardedHeaders {
+            forwarded: Some("for=127.0.0.1".to_string()),
+            x_forwarded_for: None,
+        };
+        let trusted = vec!["127.0.0.1".to_string()];
+
+        assert!(pty_registration_allowed(peer, &direct, &[]));
+        assert!(!pty_registration_allowed(remote, &direct, &[]));
+        // Forwarding headers mark a proxied request even when every hop is
+        // loopback and trusted.
+        assert!(!pty_registration_allowed(peer, &spoofed, &[]));
+        assert!(!pty_registration_allowed(peer, &spoofed, &trusted));
+        assert!(!pty_registration_allowed(peer, &rfc, &trusted));
+        // A trusted proxy that sends no header is still a proxy.
+        assert!(!pty_registration_allowed(peer, &direct, &trusted));
+    }
+
+    #[test]
+    fn fs_limit_updates_are_validated_before_any_apply() {
+        let mut fs = crate::setup::FileSystemAccessConfig {
+            max_list_entries: Some(500),
+            ..Default::default()
+        };
+        let update = FsLimits {
+            max_read_size: Some(100 * 1024 * 1024),
+            watch_debounce_ms: Some(100),
+            ..Default::default()
+        };
+        apply_fs_limits(&mut fs, &update).expect("valid limits");
+        assert_eq!(fs.max_read_size, Some(100 * 1024 * 1024));
+        assert_eq!(fs.watch_debounce_ms, Some(100));
+        assert_eq!(fs.max_list_entries, Some(500));
+
+        let too_big = FsLimits {
+            max_write_size: Some(8),
+            max_search_results: Some(1_000_000),
+            ..Default::default()
+        };
+        let err = apply_fs_limits(&mut fs, &too_big).unwrap_err();
+        assert!(err.contains("max_search_results"), "{err}");
+        assert_eq!(fs.max_write_size, None);
+        let zero = FsLimits {
+            max_read_size: Some(0),
+            ..Default::default()
+        };
+        assert!(apply_fs_limits(&mut fs, &zero).is_err());
+        assert_eq!(fs.max_read_size, Some(100 * 1024 * 1024));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn run_captured_collects_both_streams_and_caps_them() {
+        let temp = TempDir::new().expect("tempdir");
+        let script = |s: &str| vec!["-c".to_string(), s.to_string()];
+
+        let run = run_captured(
+            "sh",
+            &script("pwd; echo oops >&2; exit 3"),
+            temp.path(),
+            1024,
+            Duration::from_secs(10),
+        )
+        .await
+        .expect("run");
+        assert_eq!(run.exit_code, Some(3));
+        let cwd = temp.path().canonicalize().unwrap();
+        assert_eq!(run.stdout.trim(), cwd.to_string_lossy());
+        assert_eq!(run.stderr, "oops\n");
+        assert!(!run.truncated);
+
+        let run = run_captured(
+            "sh",
+            &script("printf 0123456789"),
+            temp.path(),
+            4,
+            Duration::from_secs(10),
+        )
+        .await
+        .expect("run");
+        assert_eq!(run.stdout, "0123");
+        assert!(run.truncated);
+
+        let err = run_captured(
+            "sh",
+            &script("sleep 5"),
+            temp.path(),
+            1024,
+            Duration::from_millis(100),
+        )
+        .await
+        .unwrap_err();
+        assert!(err.to_string().contains("timed out"));
+    }
+
+    #[test]
+    fn pty_output_only_reaches_subscribed_clients() {
+        let mut st = DaemonState::new(9847);
+        let viewer: std::net::SocketAddr = "127.0.0.1:50010".parse().unwrap();
+        let bystander: std::net::SocketAddr = "127.0.0.1:50011".parse().unwrap();
+        st.mobile_views
+            .entry(viewer)
+            .or_default()
+            .insert("s1".to_string());
+        st.mobile_views
+            .entry(bystander)
+            .or_default()
+            .insert("s2".to_string());
+
+        assert_eq!(
+            pty_output_subscription(&st, &viewer, "s1"),
+            Some((None, true))
+        );
+        assert_eq!(pty_output_subscription(&st, &bystander, "s1"), None);
+
+        st.mobile_attach_ids
+            .entry(bystander)
+            .or_default()
+            .insert("s1".to_string(), 7);
+        assert_eq!(
+            pty_output_subscription(&st, &bystander, "s1"),
+            Some((Some(7), false))
+        );
+    }
+
+    #[test]
+    fn fs_config_changes_lists_what_a_reload_picked_up() {
+        let before = FileSystemConfig {
+            allowed_roots: vec![std::path::PathBuf::from("/work/a")],
+            denied_patterns: vec!["*.pem".to_string()],
+            ..Default::default()
+        };
+        assert!(fs_config_changes(&before, &before.clone()).is_empty());
+
+        let after = FileSystemConfig {
+            allowed_roots: vec![std::path::PathBuf::from("/work/b")],
+            denied_patterns: vec!["*.pem".to_string(), "*.key".to_string()],
+            max_read_size: before.max_read_size * 2,
+            ..before.clone()
+        };
+        let changes = fs_config_changes(&before, &after);
+        assert_eq!(changes.len(), 4, "{changes:?}");
+        assert!(changes.contains(&"allowed root added: /work/b".to_string()));
+        assert!(changes.contains(&"allowed root removed: /work/a".to_string()));
+        assert!(changes.contains(&"denied pattern added: *.key".to_string()));
+        assert!(changes[3].starts_with("limits now"));
+    }
+
+    #[test]
+    fn redrawn_prompt_stays_waiting_until_output_moves_on() {
+        use crate::detection::{detect_wait_event, CliType};
+
+        // Mirrors the PTY output loop: raise on a match, clear otherwise.
+        fn feed(session: &mut PtySession, buffer: &mut String, chunk: &str) {
+            push_wait_buffer(buffer, chunk, 4000);
+            match detect_wait_event(buffer, CliType::Claude, &[]) {
+                Some(event) => {
+                    raise_waiting(session, &event);
+                }
+                None => {
+                    clear_waiting_after_output(session);
+                }
+            }
+        }
+
+        let mut session = test_pty_session("s1");
+        let mut buffer = String::new();
+        feed(
+            &mut session,
+            &mut buffer,
+            "Do you want to run this command?\n1. Yes, proceed\n2. Yes, and don't ask again\n3. No\n",
+        );
+        assert!(session.waiting_state.is_some());
+
+        // A spinner redrawing its status line while the prompt is up.
+        for frame in ["⠋", "⠙", "⠹", "⠸"] {
+            feed(
+                &mut session,
+                &mut buffer,
+                &format!("\r{frame} Waiting for permission (esc to cancel)"),
+            );
+            assert!(session.waiting_state.is_some(), "cleared on redraw {frame}");
+        }
+
+        // The command runs and its output replaces the prompt.
+        for line in 0..8 {
+            feed(&mut session, &mut buffer, &format!("\nrunning step {line}"));
+        }
+        assert!(session.waiting_state.is_none());
+        assert!(!clear_waiting_after_output(&mut session));
+    }
+
+    #[test]
+    fn dismiss_waiting_clears_state_and_remembers_the_prompt() {
+        use crate::detection::{ApprovalModel, WaitType};
+
+        let mut session = test_pty_session("s1");
+        assert!(!dismiss_waiting(&mut session));
+
+        session.waiting_state = Some(super::WaitingState {
+            wait_type: WaitType::ToolApproval,
+            prompt_content: "Do you want to run this command?".to_string(),
+            timestamp: chrono::Utc::now(),
+            approval_model: ApprovalModel::Numbered,
+            prompt_hash: 42,
+        });
+        session.last_wait_hash = Some(42);
+
+        assert!(dismiss_waiting(&mut session));
+        assert!(session.waiting_state.is_none());
+        assert_eq!(session.last_wait_hash, None);
+        assert_eq!(session.dismissed_wait_hash, Some(42));
+    }
+
+    #[test]
+    fn wait_buffer_keeps_a_prompt_after_a_long_burst() {
+        use crate::detection::{detect_wait_event, CliType, WaitType};
+
+        let mut buffer = String::new();
+        let burst = "compiling crate with a long verbose log line\n".repeat(120);
+        assert!(burst.len() > 4000);
+        push_wait_buffer(&mut buffer, &burst, 4000);
+        push_wait_buffer(
+            &mut buffer,
+            "Do you want to run this command?\n1. Yes, proceed\n2. Yes, and don't ask again\n3. No\n",
+            4000,
+        );
+        assert!(buffer.chars().count() <= 4000);
+        assert!(buffer.starts_with("compiling"));
+
+        let event = detect_wait_event(&buffer, CliType::Claude, &[]).expect("prompt detected");
+        assert_eq!(event.wait_type, WaitType::ToolApproval);
+
+        // A single over-long line can't be split on a boundary.
+        let mut line = String::new();
+        push_wait_buffer(&mut line, &"x".repeat(10), 4);
+        assert_eq!(line, "xxxx");
+    }
+
+    #[test]
+    fn attach_session_history_and_live_stream_neither_gap_nor_overlap() {
+        let mut st = DaemonState::new(9847);
+        let addr: std::net::SocketAddr = "127.0.0.1:50030".parse().unwrap();
+        let mut session = test_pty_session("s1");
+        session.scrollback.extend(b"old output\r\n".iter().copied());
+        session.live_seq = 7;
+        st.sessions.insert("s1".to_string(), session);
+
+        let (bytes, total_bytes, seq) =
+            attach_session_snapshot(&mut st, addr, "s1", Some(8)).expect("session exists");
+        assert_eq!(bytes, b"output\r\n");
+        assert_eq!(total_bytes, 12);
+        assert_eq!(seq, 7);
+        assert_eq!(
+            pty_output_subscription(&st, &addr, "s1"),
+            Some((None, true))
+        );
+
+        // Chunk 7 is already in the history; chunk 8 is the first live one.
+        assert!(pty_chunk_replayed(&st, &addr, "s1", 7));
+        assert!(!pty_chunk_replayed(&st, &addr, "s1", 8));
+        assert!(attach_session_snapshot(&mut st, addr, "missing", None).is_none());
+    }
+
+    #[test]
+    fn mouse_filtering_covers_history_and_ends_with_the_session() {
+        let mut st = DaemonState::new(9847);
+        let addr: std::net::SocketAddr = "127.0.0.1:50031".parse().unwrap();
+        let mut session = test_pty_session("s1");
+        session
+            .scrollback
+            .extend(b"\x1b[?1000hmenu\x1b[<0;3;4M\r\n".iter().copied());
+        st.sessions.insert("s1".to_string(), session);
+        st.mobile_mouse_filtered.insert(addr);
+
+        let (bytes, _, _) =
+            attach_session_snapshot(&mut st, addr, "s1", None).expect("session exists");
+        assert_eq!(bytes, b"menu\r\n");
+
+        st.mobile_mouse_filters
+            .entry(addr)
+            .or_default()
+            .insert("s1".to_string(), MouseSequenceFilter::default());
+        clear_mobile_attach_for_session(&mut st, "s1");
+        assert!(!st.mobile_mouse_filters.contains_key(&addr));
+    }
+
+    #[test]
+    fn ignored_watch_events_skip_only_clients_that_respect_ignore() {
+        let mut st = DaemonState::new(9847);
+        let skipping: std::net::SocketAddr = "127.0.0.1:50040".parse().unwrap();
+        let seeing: std::net::SocketAddr = "127.0.0.1:50041".parse().unwrap();
+        for addr in [skipping, seeing] {
+            st.file_watch_subscriptions
+                .entry(addr)
+                .or_default()
+                .insert("/repo".to_string());
+        }
+        st.file_watch_skip_ignored
+            .entry(skipping)
+            .or_default()
+            .insert("/repo".to_string());
+        let change = |path: &str, ignored: bool| crate::protocol::FileChanged {
+            path: path.to_string(),
+            change_type: crate::protocol::ChangeType::Modified,
+            new_entry: None,
+            seq: Some(1),
+            ignored,
+        };
+
+        let ignored = change("/repo/target", true);
+        assert!(!watch_delivers(&st, &skipping, &ignored));
+        assert!(watch_delivers(&st, &seeing, &ignored));
+        let kept = change("/repo/main.rs", false);
+        assert!(watch_delivers(&st, &skipping, &kept));
+        assert!(watch_delivers(&st, &seeing, &kept));
+        assert!(!watch_delivers(
+            &st,
+            &seeing,
+            &change("/other/main.rs", false)
+        ));
+    }
+
+    #[test]
+    fn reregistered_session_output_is_not_taken_for_replayed_history() {
+        let mut st = DaemonState::new(9847);
+        let addr: std::net::SocketAddr = "127.0.0.1:50031".parse().unwrap();
+        let mut session = test_pty_session("s1");
+        session.live_seq = 40;
+        insert_pty_session(&mut st, session);
+        attach_session_snapshot(&mut st, addr, "s1", None).expect("session exists");
+        assert!(pty_chunk_replayed(&st, &addr, "s1", 3));
+
+        // The wrapper reconnects under the same id and starts over at 0.
+        assert!(insert_pty_session(&mut st, test_pty_session("s1")).is_some());
+        assert!(!pty_chunk_replayed(&st, &addr, "s1", 1));
+        assert!(st.mobile_replayed_seqs.is_empty());
+
+        // Ending the session clears the marks as well.
+        attach_session_snapshot(&mut st, addr, "s1", None).expect("session exists");
+        clear_mobile_attach_for_session(&mut st, "s1");
+        assert!(!pty_chunk_replayed(&st, &addr, "s1", 0));
+    }
+
+    #[tokio::test]
+    async fn unsubscribe_all_stops_every_session_for_that_client_only() {
+        let mut st = DaemonState::new(9847);
+        let addr: std::net::SocketAddr = "127.0.0.1:50020".parse().unwrap();
+        let other: std::net::SocketAddr = "127.0.0.1:50021".parse().unwrap();
+        for id in ["s1", "s2"] {
+            st.sessions.insert(id.to_string(), test_pty_session(id));
+            register_session_view(&mut st, addr, id);
+        }
+        register_session_view(&mut st, other, "s1");
+        st.mobile_attach_ids
+            .entry(addr)
+            .or_default()
+            .insert("s2".to_string(), 3);
+        let state: SharedState = std::sync::Arc::new(tokio::sync::RwLock::new(st));
+
+        unsubscribe_all_sessions(&state, addr).await;
+
+        let st = state.read().await;
+        assert_eq!(pty_output_subscription(&st, &addr, "s1"), None);
+        assert_eq!(pty_output_subscription(&st, &addr, "s2"), None);
+        assert_eq!(
+            pty_output_subscription(&st, &other, "s1"),
+            Some((None, true))
+        );
+    }
+
+    #[test]
+    fn run_command_refuses_shells_and_interpreters() {
+        let args = |a: &[&str]| a.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        let extra = vec!["git".to_string(), "perl".to_string()];
+
+        assert!(validate_run_command("git", &args(&["status"]), &extra).is_ok());
+        assert!(validate_run_command("bash", &args(&["-c", "id"]), &extra).is_err());
+        assert!(validate_run_command("python", &args(&["-c", "print(1)"]), &extra).is_err());
+        assert!(validate_run_command("node", &args(&["-e", "1"]), &extra).is_err());
+        // Configuring an interpreter as a spawn command does not open it up.
+        assert!(validate_run_command("perl", &args(&["-e", "1"]), &extra).is_err());
+        assert!(validate_run_command("make", &[], &extra).is_err());
+        assert!(validate_run_command("git", &args(&["log", "$(id)"]), &extra).is_err());
+    }
+
+    #[tokio::test]
+    async fn run_command_permits_refuse_past_the_per_client_cap() {
+        let state: SharedState =
+            std::sync::Arc::new(tokio::sync::RwLock::new(DaemonState::new(9847)));
+        let addr: std::net::SocketAddr = "127.0.0.1:50000".parse().unwrap();
+        let other: std::net::SocketAddr = "127.0.0.1:50001".parse().unwrap();
+
+        let mut held = Vec::new();
+        for _ in 0..MAX_RUN_COMMANDS_PER_CLIENT {
+            held.push(
+                try_acquire_run_command_permit(&state, addr)
+                    .await
+                    .expect("permit"),
+            );
+        }
+        assert!(try_acquire_run_command_permit(&state, addr).await.is_none());
+        assert!(try_acquire_run_command_permit(&state, other)
+            .await
+            .is_some());
+
+        drop(held);
+        assert!(try_acquire_run_command_permit(&state, addr).await.is_some());
+    }
+
+    #[tokio::test]
+    async fn file_op_permits_queue_past_the_per_client_cap() {
+        let mut st = DaemonState::new(9847);
+        st.max_file_ops_per_client = 2;
+        let state: SharedState = std::sync::Arc::new(tokio::sync::RwLock::new(st));
+        let addr: std::net::SocketAddr = "127.0.0.1:50000".parse().unwrap();
+        let other: std::net::SocketAddr = "127.0.0.1:50001".parse().unwrap();
+
+        let first = acquire_file_op_permit(&state, addr).await;
+        let _second = acquire_file_op_permit(&state, addr).await;
+        let third = tokio::time::timeout(
+            Duration::from_millis(50),
+            acquire_file_op_permit(&state, addr),
+        )
+        .await;
+        assert!(third.is_err(), "third operation should wait");
+        // Other clients have their own permits.
+        assert!(acquire_file_op_permit(&state, other).await.is_some());
+
+        drop(first);
+        assert!(acquire_file_op_permit(&state, addr).await.is_some());
+
+        state.write().await.max_file_ops_per_client = 0;
+        assert!(acquire_file_op_permit(&state, addr).await.is_none());
+    }
+
+    #[test]
+    fn session_limit_ignores_reregistration_and_zero_cap() {
+        let mut state = DaemonState::new(9847);
+        state.max_sessions = 2;
+        state
+            .sessions
+            .insert("a".to_string(), test_pty_session("a"));
+        assert!(!session_limit_reached(&state, Some("b")));
+        state
+            .sessions
+            .insert("b".to_string(), test_pty_session("b"));
+        assert!(session_limit_reached(&state, Some("c")));
+        assert!(session_limit_reached(&state, None));
+        assert!(!session_limit_reached(&state, Some("a")));
+        state.max_sessions = 0;
+        assert!(!session_limit_reached(&state, Some("c")));
+    }
+
+    #[test]
+    fn stale_session_ids_only_reports_silent_wrappers() {
+        let mut state = DaemonState::new(9847);
+        let now = std::time::Instant::now();
+        let timeout = Duration::from_secs(90);
+
+        let mut silent = test_pty_session("silent");
+        silent.last_heartbeat = now - Duration::from_secs(120);
+        silent.sends_heartbeats = true;
+        state.sessions.insert("silent".to_string(), silent);
+
+        let mut recent = test_pty_session("recent");
+        recent.last_heartbeat = now - Duration::from_secs(30);
+        recent.sends_heartbeats = true;
+        state.sessions.insert("recent".to_string(), recent);
+
+        // A pre-heartbeat wrapper is quiet only because it never sends any.
+        let mut legacy = test_pty_session("legacy");
+        legacy.last_heartbeat = now - Duration::from_secs(600);
+        state.sessions.insert("legacy".to_string(), legacy);
+
+        assert_eq!(
+            stale_session_ids(&state, now, timeout),
+            vec!["silent".to_string()]
+        );
+    }
+
+    #[test]
+    fn submodule_status_reaches_old_clients_as_modified() {
+        let entry = |status: &str| -> crate::protocol::FileEntry {
+            serde_json::from_value(serde_json::json!({
+                "name": "lib",
+                "path": "/repo/lib",
+                "is_directory": true,
+                "is_symlink": false,
+                "is_hidden": false,
+                "size": 0,
+                "modified": 0,
+                "git_status": status,
+            }))
+            .unwrap()
+        };
+
+        let mut old = vec![entry("submodule"), entry("untracked")];
+        downgrade_git_statuses(&mut old, 1);
+        let json = serde_json::to_value(&old).unwrap();
+        assert_eq!(json[0]["git_status"], "modified");
+        assert_eq!(json[1]["git_status"], "untracked");
+
+        let mut new = vec![entry("submodule")];
+        downgrade_git_statuses(&mut new, 2);
+        assert_eq!(
+            serde_json::to_value(&new).unwrap()[0]["git_status"],
+            "submodule"
+        );
+    }
+
+    #[test]
+    fn ending_a_replaced_registration_leaves_the_new_one() {
+        let mut state = DaemonState::new(9847);
+        let mut session = test_pty_session("s1");
+        session.registration_id = 2;
+        state.sessions.insert("s1".to_string(), session);
+
+        assert!(remove_registered_session(&mut state, "s1", 1).is_none());
+        assert!(state.sessions.contains_key("s1"));
+        assert!(remove_registered_session(&mut state, "s1", 2).is_some());
+        assert!(remove_registered_session(&mut state, "s1", 2).is_none());
+    }
 }
@@ -2,15 +2,23 @@
 //!
 //! This module provides:
 //! - A scored CLI identity tracker
+//! - A coarse busy/idle activity state
 //! - ANSI-stripped prompt detection
 //! - Normalized waiting-state classification
+//! - User-defined wait patterns from `detection.toml`
 
+use crate::platform;
 use chrono::{DateTime, Utc};
+use regex_automata::meta::Regex;
+use serde::Deserialize;
 use std::collections::HashMap;
 use std::hash::{Hash, Hasher};
+use std::path::PathBuf;
+use std::sync::Arc;
 use strip_ansi_escapes::strip;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum CliType {
     Claude,
     Codex,
@@ -43,7 +51,8 @@ impl CliType {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum ApprovalModel {
     Numbered,
     YesNo,
@@ -51,7 +60,8 @@ pub enum ApprovalModel {
     None,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum WaitType {
     ToolApproval,
     PlanApproval,
@@ -70,6 +80,27 @@ impl WaitType {
     }
 }
 
+/// Coarse activity of a session, independent of wait-event detection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ActivityState {
+    Busy,
+    Idle,
+    Waiting,
+}
+
+impl ActivityState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ActivityState::Busy => "busy",
+            ActivityState::Idle => "idle",
+            ActivityState::Waiting => "waiting",
+        }
+    }
+}
+
+/// Output silence after which a session counts as idle even without a prompt.
+const ACTIVITY_QUIET_MS: i64 = 3000;
+
 #[derive(Debug, Clone)]
 pub struct WaitEvent {
     pub wait_type: WaitType,
@@ -78,12 +109,93 @@ pub struct WaitEvent {
     pub prompt_hash: u64,
 }
 
+/// A wait prompt from `detection.toml`. These are checked before the
+/// built-in heuristics, so new prompt wording is a config edit.
+#[derive(Debug, Clone)]
+pub struct CustomPattern {
+    regex: Regex,
+    wait_type: WaitType,
+    /// Only applies while the session is detected as this CLI.
+    cli_type: Option<CliType>,
+    approval_model: Option<ApprovalModel>,
+}
+
+#[derive(Deserialize)]
+struct RawPattern {
+    pattern: String,
+    wait_type: WaitType,
+    #[serde(default)]
+    cli_type: Option<CliType>,
+    #[serde(default)]
+    approval_model: Option<ApprovalModel>,
+}
+
+fn detection_file() -> PathBuf {
+    platform::config_dir().join("detection.toml")
+}
+
+/// Load user-defined wait patterns. Entries that don't parse or whose regex
+/// is invalid are logged and skipped.
+pub fn load_custom_patterns() -> Vec<CustomPattern> {
+    let path = detection_file();
+    let Ok(data) = std::fs::read_to_string(&path) else {
+        return Vec::new();
+    };
+    match toml::from_str::<toml::Table>(&data) {
+        Ok(table) => parse_custom_patterns(&table),
+        Err(e) => {
+            tracing::warn!(path = %path.display(), error = %e, "Ignoring unreadable detection patterns");
+            Vec::new()
+        }
+    }
+}
+
+fn parse_custom_patterns(table: &toml::Table) -> Vec<CustomPattern> {
+    let Some(entries) = table.get("patterns").and_then(|p| p.as_array()) else {
+        return Vec::new();
+    };
+    entries
+        .iter()
+        .enumerate()
+        .filter_map(|(index, entry)| {
+            let raw = match entry.clone().try_into::<RawPattern>() {
+                Ok(raw) => raw,
+                Err(e) => {
+                    tracing::warn!(index, error = %e, "Ignoring malformed detection pattern");
+                    return None;
+                }
+            };
+            match Regex::new(&raw.pattern) {
+                Ok(regex) => Some(CustomPattern {
+                    regex,
+                    wait_type: raw.wait_type,
+                    cli_type: raw.cli_type,
+                    approval_model: raw.approval_model,
+                }),
+                Err(e) => {
+                    tracing::warn!(
+                        index,
+                        pattern = %raw.pattern,
+                        error = %e,
+                        "Ignoring detection pattern with invalid regex"
+                    );
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
 #[derive(Debug, Clone)]
 pub struct CliTracker {
     scores: HashMap<CliType, i32>,
     current: CliType,
     confidence: u8,
     last_updated: DateTime<Utc>,
+    custom_patterns: Arc<Vec<CustomPattern>>,
+    /// Last output chunk ended on something that looks like a prompt.
+    at_prompt: bool,
+    last_output: DateTime<Utc>,
 }
 
 impl CliTracker {
@@ -100,6 +212,9 @@ impl CliTracker {
             current: CliType::Terminal,
             confidence: 1,
             last_updated: Utc::now(),
+            custom_patterns: Arc::default(),
+            at_prompt: false,
+            last_output: Utc::now(),
         }
     }
 
@@ -107,6 +222,17 @@ impl CliTracker {
         self.current
     }
 
+    /// Check `patterns` before the built-in heuristics.
+    pub fn with_custom_patterns(mut self, patterns: Arc<Vec<CustomPattern>>) -> Self {
+        self.custom_patterns = patterns;
+        self
+    }
+
+    /// User-defined patterns this session was started with.
+    pub fn custom_patterns(&self) -> Arc<Vec<CustomPattern>> {
+        Arc::clone(&self.custom_patterns)
+    }
+
     pub fn apply_signal(&mut self, cli: CliType, weight: i32) {
         let entry = self.scores.entry(cli).or_insert(0);
         *entry += weight;
@@ -128,6 +254,25 @@ impl CliTracker {
         }
     }
 
+    /// Record an ANSI-stripped output chunk for activity tracking.
+    pub fn observe_output(&mut self, text: &str, now: DateTime<Utc>) {
+        self.last_output = now;
+        self.at_prompt = ends_at_prompt(text);
+    }
+
+    /// `waiting` is whether the session has an outstanding wait event, which
+    /// takes precedence over plain busy/idle.
+    pub fn activity_state(&self, waiting: bool, now: DateTime<Utc>) -> ActivityState {
+        if waiting {
+            ActivityState::Waiting
+        } else if self.at_prompt || (now - self.last_output).num_milliseconds() >= ACTIVITY_QUIET_MS
+        {
+            ActivityState::Idle
+        } else {
+            ActivityState::Busy
+        }
+    }
+
     fn select_best(&mut self) {
         let mut best = self.current;
         let mut best_score = *self.scores.get(&best).unwrap_or(&0);
@@ -186,6 +331,23 @@ fn cli_from_output(text: &str) -> Option<CliType> {
     None
 }
 
+/// Whether output stops on a shell or REPL prompt (`$ `, `# `, `% `, `> `,
+/// `❯ `, ...), with the cursor left on that line.
+fn ends_at_prompt(text: &str) -> bool {
+    if text.ends_with('\n') || text.ends_with('\r') {
+        return false;
+    }
+    let last_line = text.rsplit(['\n', '\r']).next().unwrap_or(text);
+    let trimmed = last_line.trim_end();
+    if trimmed.is_empty() || trimmed.chars().count() > 200 {
+        return false;
+    }
+    matches!(
+        trimmed.chars().last(),
+        Some('$' | '#' | '%' | '>' | '❯' | '➜' | 'λ')
+    )
+}
+
 pub fn strip_ansi_and_normalize(input: &str) -> String {
     let stripped = strip(input.as_bytes());
     String::from_utf8_lossy(&stripped).to_string()
@@ -327,7 +489,11 @@ fn is_clarifying_question(text: &str, text_lower: &str) -> bool {
     false
 }
 
-pub fn detect_wait_event(input: &str, cli: CliType) -> Option<WaitEvent> {
+pub fn detect_wait_event(
+    input: &str,
+    cli: CliType,
+    custom_patterns: &[CustomPattern],
+) -> Option<WaitEvent> {
     let normalized = strip_ansi_and_normalize(input);
     let tail = tail_chars(&normalized, 1200);
     // Focus on the last few lines to avoid stale prompt matches
@@ -344,6 +510,23 @@ pub fn detect_wait_event(input: &str, cli: CliType) -> Option<WaitEvent> {
 
     let approval_model = detect_approval_model(&text_lower);
 
+    let custom = custom_patterns
+        .iter()
+        .find(|p| p.cli_type.map_or(true, |c| c == cli) && p.regex.is_match(tail_lines.as_str()));
+    if let Some(custom) = custom {
+        let prompt = tail_chars(&tail, 300);
+        let detected_model = match approval_model {
+            ApprovalModel::None => cli.default_approval_model(),
+            model => model,
+        };
+        return Some(WaitEvent {
+            wait_type: custom.wait_type,
+            approval_model: custom.approval_model.unwrap_or(detected_model),
+            prompt_hash: hash_prompt(&prompt),
+            prompt,
+        });
+    }
+
     if is_plan_approval_prompt(&text_lower, approval_model) {
         let prompt = tail_chars(&tail, 300);
         return Some(WaitEvent {
@@ -386,3 +569,75 @@ pub fn detect_wait_event(input: &str, cli: CliType) -> Option<WaitEvent> {
 
     None
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        detect_wait_event, parse_custom_patterns, ActivityState, ApprovalModel, CliTracker,
+        CliType, WaitType,
+    };
+    use chrono::{Duration, Utc};
+
+    #[test]
+    fn custom_patterns_skip_bad_entries_and_match_before_builtins() {
+        let table: toml::Table = toml::from_str(
+            r#"
+            [[patterns]]
+            pattern = 'Ship it\?'
+            wait_type = "tool_approval"
+            cli_type = "codex"
+            approval_model = "yes_no"
+
+            [[patterns]]
+            pattern = "(unclosed"
+            wait_type = "tool_approval"
+
+            [[patterns]]
+            pattern = "x"
+            wait_type = "not_a_wait_type"
+
+            [[patterns]]
+            pattern = "(?i)deploy to prod"
+            wait_type = "plan_approval"
+            "#,
+        )
+        .unwrap();
+        let patterns = parse_custom_patterns(&table);
+        assert_eq!(patterns.len(), 2);
+
+        let event = detect_wait_event("build ok\nShip it? ", CliType::Codex, &patterns)
+            .expect("custom match");
+        assert_eq!(event.wait_type, WaitType::ToolApproval);
+        assert_eq!(event.approval_model, ApprovalModel::YesNo);
+
+        // Scoped to Codex, so Claude only sees the built-in heuristics.
+        let event = detect_wait_event("build ok\nShip it?", CliType::Claude, &patterns)
+            .expect("builtin match");
+        assert_eq!(event.wait_type, WaitType::ClarifyingQuestion);
+
+        let event = detect_wait_event("Deploy to PROD", CliType::Terminal, &patterns)
+            .expect("unscoped match");
+        assert_eq!(event.wait_type, WaitType::PlanApproval);
+        assert_eq!(event.approval_model, ApprovalModel::None);
+    }
+
+    #[test]
+    fn activity_state_tracks_prompt_and_quiet_output() {
+        let mut tracker = CliTracker::new();
+        let now = Utc::now();
+
+        tracker.observe_output("Compiling foo v0.1.0\n", now);
+        assert_eq!(tracker.activity_state(false, now), ActivityState::Busy);
+        assert_eq!(
+            tracker.activity_state(false, now + Duration::seconds(5)),
+            ActivityState::Idle
+        );
+
+        tracker.observe_output("done\nuser@host:~/src$ ", now);
+        assert_eq!(tracker.activity_state(false, now), ActivityState::Idle);
+        assert_eq!(tracker.activity_state(true, now), ActivityState::Waiting);
+
+        tracker.observe_output("50% [=====>    ]\r", now);
+        assert_eq!(tracker.activity_state(false, now), ActivityState::Busy);
+    }
+}
@@ -0,0 +1,441 @@
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::{Component, Path, PathBuf};
+
+use flate2::read::GzDecoder;
+use walkdir::WalkDir;
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::protocol::{ArchiveFormat, FileSystemError};
+
+use super::operations::FileOperations;
+use super::path_utils;
+
+/// A directory packed into a temp file by `FileOperations::create_archive`.
+/// The file is removed when this is dropped.
+#[derive(Debug)]
+pub struct PreparedArchive {
+    /// Protocol path of the archived directory with the format's extension
+    pub name: String,
+    pub file_path: PathBuf,
+    pub size: u64,
+    /// Entries left out because a denied pattern matched them
+    pub skipped: usize,
+}
+
+/// Result of `FileOperations::extract_archive`.
+#[derive(Debug, Clone)]
+pub struct ExtractedArchive {
+    pub destination: String,
+    /// Protocol paths of the files written, in archive order
+    pub files: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ArchiveKind {
+    Zip,
+    TarGz,
+}
+
+/// One archive entry that passed validation, ready to be written.
+struct PlannedEntry {
+    target: PathBuf,
+    is_dir: bool,
+}
+
+impl Drop for PreparedArchive {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.file_path);
+    }
+}
+
+fn io_err(e: impl ToString) -> FileSystemError {
+    FileSystemError::IoError {
+        message: e.to_string(),
+    }
+}
+
+impl FileOperations {
+    /// Pack `path` into an archive in the temp directory. Denied files are
+    /// skipped, symlinks are not followed, and the combined size of the files
+    /// may not exceed `max_archive_size`.
+    pub async fn create_archive(
+        &self,
+        path: &str,
+        format: ArchiveFormat,
+    ) -> Result<PreparedArchive, FileSystemError> {
+        let dir = self.validator().validate_existing(path)?;
+        if !dir.is_dir() {
+            return Err(FileSystemError::NotADirectory {
+                path: path_utils::to_protocol_path(&dir),
+            });
+        }
+        let ops = self.clone();
+        tokio::task::spawn_blocking(move || match format {
+            ArchiveFormat::Zip => ops.write_zip(&dir),
+        })
+        .await
+        .map_err(io_err)?
+    }
+
+    fn write_zip(&self, dir: &Path) -> Result<PreparedArchive, FileSystemError> {
+        let source = path_utils::to_protocol_path(dir);
+        let max_size = self.config().max_archive_size;
+        let file_path =
+            std::env::temp_dir().join(format!("mobilecli-archive-{}.zip", uuid::Uuid::new_v4()));
+        let file = File::create(&file_path).map_err(io_err)?;
+        // Created before writing so an early return still removes the file.
+        let mut archive = PreparedArchive {
+            name: format!("{}.zip", source.trim_end_matches('/')),
+            file_path,
+            size: 0,
+            skipped: 0,
+        };
+
+        // Entries live under the directory's own name, as `zip -r` does.
+        let base = dir
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| "archive".to_string());
+        let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+        let mut zip = ZipWriter::new(file);
+        zip.add_directory(base.as_str(), options).map_err(io_err)?;
+
+        let mut total: u64 = 0;
+        let mut walker = WalkDir::new(dir)
+            .follow_links(false)
+            .min_depth(1)
+            .sort_by_file_name()
+            .into_iter();
+        while let Some(entry) = walker.next() {
+            let entry = entry.map_err(io_err)?;
+            let file_type = entry.file_type();
+            if self.validator().is_denied(entry.path()) {
+                archive.skipped += 1;
+                if file_type.is_dir() {
+                    walker.skip_current_dir();
+                }
+                continue;
+            }
+            let Ok(relative) = entry.path().strip_prefix(dir) else {
+                continue;
+            };
+            let name = relative
+                .components()
+                .map(|part| part.as_os_str().to_string_lossy())
+                .fold(base.clone(), |name, part| format!("{}/{}", name, part));
+            if file_type.is_dir() {
+                zip.add_directory(name, options).map_err(io_err)?;
+            } else if file_type.is_file() {
+                total += entry.metadata().map_err(io_err)?.len();
+                if total > max_size {
+                    return Err(FileSystemError::FileTooLarge {
+                        path: source,
+                        size: total,
+                        max_size,
+                    });
+                }
+                zip.start_file(name, options).map_err(io_err)?;
+                let mut input = File::open(entry.path()).map_err(io_err)?;
+                io::copy(&mut input, &mut zip).map_err(io_err)?;
+            }
+            // Symlinks and special files are left out.
+        }
+
+        let mut file = zip.finish().map_err(io_err)?;
+        file.flush().map_err(io_err)?;
+        archive.size = file.metadata().map_err(io_err)?.len();
+        Ok(archive)
+    }
+
+    /// Unpack a `.zip` or `.tar.gz` into `destination`. Every entry is checked
+    /// before anything is written: entries that climb out of `destination`,
+    /// land on a denied or read-only path, would replace an existing file, or
+    /// name the same file twice fail the whole extraction, and a write that
+    /// still fails removes everything written so far. Symlinks and other special entries are
+    /// skipped, and the unpacked size is capped at `max_archive_size`.
+    pub async fn extract_archive(
+        &self,
+        path: &str,
+        destination: &str,
+    ) -> Result<ExtractedArchive, FileSystemError> {
+        let source = self.validator().validate_existing(path)?;
+        if !source.is_file() {
+            return Err(FileSystemError::NotAFile {
+                path: path_utils::to_protocol_path(&source),
+            });
+        }
+        let destination = self.validator().resolve_new_path(destination, true)?;
+        if destination.exists() && !destination.is_dir() {
+            return Err(FileSystemError::NotADirectory {
+                path: path_utils::to_protocol_path(&destination),
+            });
+        }
+        let ops = self.clone();
+        tokio::task::spawn_blocking(move || ops.extract_blocking(&source, &destination))
+            .await
+            .map_err(io_err)?
+    }
+
+    fn extract_blocking(
+        &self,
+        source: &Path,
+        destination: &Path,
+    ) -> Result<ExtractedArchive, FileSystemError> {
+        let kind = detect_archive_kind(source)?;
+        let max_size = self.config().max_archive_size;
+
+        // First pass: read headers only and validate every entry.
+        let mut plan = Vec::new();
+        let mut total: u64 = 0;
+        let mut file_targets = HashSet::new();
+        let mut check = |name: &str, is_dir: bool, size: u64| -> Result<(), FileSystemError> {
+            let Some(target) = self.entry_target(destination, name)? else {
+                return Ok(());
+            };
+            if !is_dir {
+                // A second entry for the same file would fail mid-write.
+                if target.exists() || !file_targets.insert(target.clone()) {
+                    return Err(FileSystemError::AlreadyExists {
+                        path: path_utils::to_protocol_path(&target),
+                    });
+                }
+                total = total.saturating_add(size);
+                if total > max_size {
+                    return Err(FileSystemError::FileTooLarge {
+                        path: path_utils::to_protocol_path(source),
+                        size: total,
+                        max_size,
+                    });
+                }
+            }
+            plan.push(PlannedEntry { target, is_dir });
+            Ok(())
+        };
+        match kind {
+            ArchiveKind::Zip => {
+                let mut zip =
+                    ZipArchive::new(File::open(source).map_err(io_err)?).map_err(io_err)?;
+                for index in 0..zip.len() {
+                    let entry = zip.by_index(index).map_err(io_err)?;
+                    if entry.is_dir() {
+                        check(entry.name(), true, 0)?;
+                    } else if !zip_entry_is_symlink(entry.unix_mode()) {
+                        check(entry.name(), false, entry.size())?;
+                    }
+                }
+            }
+            ArchiveKind::TarGz => {
+                let mut tar = open_tar_gz(source)?;
+                for entry in tar.entries().map_err(io_err)? {
+                    let entry = entry.map_err(io_err)?;
+                    let name = entry.path().map_err(io_err)?.to_string_lossy().to_string();
+                    let entry_type = entry.header().entry_type();
+                    if entry_type.is_dir() {
+                        check(&name, true, 0)?;
+                    } else if entry_type.is_file() {
+                        check(&name, false, entry.size())?;
+                    }
+                }
+            }
+        }
+
+        // Second pass: write the planned entries. If it fails partway, take
+        // back what it wrote so a failed extraction leaves nothing behind.
+        let mut written = Vec::new();
+        let mut created_dirs = Vec::new();
+        if let Err(e) = self.unpack_planned(
+            kind,
+            source,
+            destination,
+            plan,
+            &mut written,
+            &mut created_dirs,
+        ) {
+            for file in written.iter().rev() {
+                let _ = std::fs::remove_file(file);
+            }
+            for dir in created_dirs.iter().rev() {
+                let _ = std::fs::remove_dir(dir);
+            }
+            return Err(e);
+        }
+
+        Ok(ExtractedArchive {
+            destination: path_utils::to_protocol_path(destination),
+            files: written
+                .iter()
+                .map(|file| path_utils::to_protocol_path(file))
+                .collect(),
+        })
+    }
+
+    /// Write the planned entries in archive order, recording each file as it
+    /// is created in `written` and each directory that did not exist yet in
+    /// `created_dirs`, parents first. Sizes are enforced again here because
+    /// archive headers can lie.
+    fn unpack_planned(
+        &self,
+        kind: ArchiveKind,
+        source: &Path,
+        destination: &Path,
+        plan: Vec<PlannedEntry>,
+        written: &mut Vec<PathBuf>,
+        created_dirs: &mut Vec<PathBuf>,
+    ) -> Result<(), FileSystemError> {
+        let max_size = self.config().max_archive_size;
+        create_dirs(destination, created_dirs)?;
+        let mut planned = plan.into_iter();
+        let mut budget = max_size;
+        let mut write = |reader: &mut dyn Read| -> Result<(), FileSystemError> {
+            let Some(entry) = planned.next() else {
+                return Ok(());
+            };
+            if entry.is_dir {
+                return create_dirs(&entry.target, created_dirs);
+            }
+            if let Some(parent) = entry.target.parent() {
+                create_dirs(parent, created_dirs)?;
+            }
+            let mut output = std::fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&entry.target)
+                .map_err(io_err)?;
+            written.push(entry.target);
+            let copied = io::copy(&mut reader.take(budget + 1), &mut output).map_err(io_err)?;
+            if copied > budget {
+                return Err(FileSystemError::FileTooLarge {
+                    path: path_utils::to_protocol_path(source),
+                    size: max_size + 1,
+                    max_size,
+                });
+            }
+            budget -= copied;
+            Ok(())
+        };
+        // The planner skipped entries whose path is just `destination`, so the
+        // same filter has to run again to keep both passes aligned.
+        let keeps = |name: &str| {
+            Path::new(name)
+                .components()
+                .any(|part| matches!(part, Component::Normal(_)))
+        };
+        match kind {
+            ArchiveKind::Zip => {
+                let mut zip =
+                    ZipArchive::new(File::open(source).map_err(io_err)?).map_err(io_err)?;
+                for index in 0..zip.len() {
+                    let mut entry = zip.by_index(index).map_err(io_err)?;
+                    let listed = entry.is_dir() || !zip_entry_is_symlink(entry.unix_mode());
+                    if listed && keeps(entry.name()) {
+                        write(&mut entry)?;
+                    }
+                }
+            }
+            ArchiveKind::TarGz => {
+                let mut tar = open_tar_gz(source)?;
+                for entry in tar.entries().map_err(io_err)? {
+                    let mut entry = entry.map_err(io_err)?;
+                    let name = entry.path().map_err(io_err)?.to_string_lossy().to_string();
+                    let entry_type = entry.header().entry_type();
+                    if (entry_type.is_dir() || entry_type.is_file()) && keeps(&name) {
+                        write(&mut entry)?;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Where archive entry `name` lands under `destination`, or `None` for an
+    /// entry naming `destination` itself. Absolute names and `..` components
+    /// are rejected outright (zip-slip), then the result goes through the
+    /// same checks as any other new path.
+    fn entry_target(
+        &self,
+        destination: &Path,
+        name: &str,
+    ) -> Result<Option<PathBuf>, FileSystemError> {
+        let mut target = destination.to_path_buf();
+        let mut depth = 0;
+        for component in Path::new(name).components() {
+            match component {
+                Component::Normal(part) => {
+                    target.push(part);
+                    depth += 1;
+                }
+                Component::CurDir => {}
+                Component::ParentDir | Component::RootDir | Component::Prefix(_) => {
+                    return Err(FileSystemError::PathTraversal {
+                        attempted_path: name.to_string(),
+                    });
+                }
+            }
+        }
+        if depth == 0 {
+            return Ok(None);
+        }
+        let target = self
+            .validator()
+            .resolve_new_path(&target.to_string_lossy(), true)?;
+        if !self.validator().is_writable(&target) {
+            return Err(FileSystemError::PermissionDenied {
+                path: path_utils::to_protocol_path(&target),
+                reason: "Path is read-only".to_string(),
+            });
+        }
+        Ok(Some(target))
+    }
+}
+
+/// Pick the archive format from the file name, falling back to magic bytes
+/// for uploads saved without an extension.
+fn detect_archive_kind(path: &Path) -> Result<ArchiveKind, FileSystemError> {
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    if name.ends_with(".zip") {
+        return Ok(ArchiveKind::Zip);
+    }
+    if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+        return Ok(ArchiveKind::TarGz);
+    }
+    let mut magic = [0u8; 4];
+    let read = File::open(path)
+        .and_then(|mut file| file.read(&mut magic))
+        .map_err(io_err)?;
+    match &magic[..read] {
+        [b'P', b'K', 3, 4] => Ok(ArchiveKind::Zip),
+        [0x1f, 0x8b, ..] => Ok(ArchiveKind::TarGz),
+        _ => Err(FileSystemError::IoError {
+            message: "Unsupported archive format (expected .zip or .tar.gz)".to_string(),
+        }),
+    }
+}
+
+/// `create_dir_all`, noting in `created` each directory that did not exist
+/// before, parents first.
+fn create_dirs(dir: &Path, created: &mut Vec<PathBuf>) -> Result<(), FileSystemError> {
+    let missing: Vec<PathBuf> = dir
+        .ancestors()
+        .take_while(|ancestor| !ancestor.exists())
+        .map(Path::to_path_buf)
+        .collect();
+    std::fs::create_dir_all(dir).map_err(io_err)?;
+    created.extend(missing.into_iter().rev());
+    Ok(())
+}
+
+fn open_tar_gz(path: &Path) -> Result<tar::Archive<GzDecoder<File>>, FileSystemError> {
+    let file = File::open(path).map_err(io_err)?;
+    Ok(tar::Archive::new(GzDecoder::new(file)))
+}
+
+fn zip_entry_is_symlink(mode: Option<u32>) -> bool {
+    mode.is_some_and(|mode| mode & 0o170000 == 0o120000)
+}
@@ -1,5 +1,15 @@
 use std::path::PathBuf;
 
+use super::security::PathGrants;
+
+/// Hard ceilings for limits set from the app or config.json.
+pub const MAX_READ_SIZE_LIMIT: u64 = 1024 * 1024 * 1024;
+pub const MAX_WRITE_SIZE_LIMIT: u64 = 1024 * 1024 * 1024;
+pub const MAX_LIST_ENTRIES_LIMIT: usize = 100_000;
+pub const MAX_SEARCH_RESULTS_LIMIT: u32 = 10_000;
+pub const MAX_SEARCH_FILE_SIZE_LIMIT: u64 = 256 * 1024 * 1024;
+pub const MAX_ARCHIVE_SIZE_LIMIT: u64 = 4 * 1024 * 1024 * 1024;
+
 /// Configuration for file system access
 #[derive(Debug, Clone)]
 pub struct FileSystemConfig {
@@ -9,6 +19,9 @@ pub struct FileSystemConfig {
     /// Denied file patterns (glob)
     pub denied_patterns: Vec<String>,
 
+    /// Per-root exceptions to `denied_patterns`
+    pub denied_pattern_overrides: Vec<DeniedPatternOverride>,
+
     /// Maximum file size for read operations (bytes)
     pub max_read_size: u64,
 
@@ -26,6 +39,37 @@ pub struct FileSystemConfig {
 
     /// Maximum search results
     pub max_search_results: u32,
+
+    /// Largest file whose content a search will scan (bytes); bigger files
+    /// can still match by name
+    pub max_search_file_size: u64,
+
+    /// Largest combined file size `DownloadArchive` will pack (bytes)
+    pub max_archive_size: u64,
+
+    /// Move the old file aside as `.bak` while replacing it, so a failed
+    /// rename can be rolled back. Without it writes still land atomically,
+    /// with one rename less per save.
+    pub atomic_backup: bool,
+
+    /// Default debounce for directory watches (ms). Shorter is more responsive
+    /// for live logs; longer collapses the event bursts editors emit on save.
+    pub watch_debounce_ms: u64,
+
+    /// Files outside `allowed_roots` that may be read for a short while.
+    /// Shared, so carry it over when the service is rebuilt.
+    pub path_grants: PathGrants,
+}
+
+/// Re-allows paths under one allowed root that a global denied pattern would
+/// otherwise block (e.g. `**/.env` inside a sandbox project).
+///
+/// Only `**/`-relative denied patterns can be lifted; anchored patterns such as
+/// the daemon's own config directory always stay denied.
+#[derive(Debug, Clone)]
+pub struct DeniedPatternOverride {
+    pub root: PathBuf,
+    pub allowed_patterns: Vec<String>,
 }
 
 impl Default for FileSystemConfig {
@@ -89,6 +133,7 @@ impl Default for FileSystemConfig {
         Self {
             allowed_roots,
             denied_patterns,
+            denied_pattern_overrides: Vec::new(),
             max_read_size: 50 * 1024 * 1024,
             max_write_size: 50 * 1024 * 1024,
             follow_symlinks: false,
@@ -103,6 +148,11 @@ impl Default for FileSystemConfig {
             ],
             max_list_entries: 10_000,
             max_search_results: 1_000,
+            max_search_file_size: 4 * 1024 * 1024,
+            max_archive_size: 512 * 1024 * 1024,
+            atomic_backup: true,
+            watch_debounce_ms: super::watcher::DEFAULT_DEBOUNCE_MS,
+            path_grants: PathGrants::default(),
         }
     }
 }
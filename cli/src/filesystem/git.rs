@@ -1,12 +1,127 @@
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::Match;
 use tokio::process::Command;
 
 use crate::protocol::GitStatus;
 
-pub async fn status_map_for_path(path: &Path) -> Option<HashMap<PathBuf, GitStatus>> {
-    let repo_root = find_repo_root(path).await?;
-    status_map(&repo_root).await
+/// How long a `git status` result is reused before shelling out again.
+pub const STATUS_CACHE_TTL: Duration = Duration::from_secs(2);
+
+type StatusMap = HashMap<PathBuf, GitStatus>;
+
+/// Short-lived `git status` cache keyed by repo root, so navigating around a
+/// large repo doesn't spawn a `git` process for every listing. Entries expire
+/// after the TTL or when the file watcher reports a change inside the repo.
+#[derive(Clone)]
+pub struct GitStatusCache {
+    entries: Arc<Mutex<HashMap<PathBuf, (Instant, StatusMap)>>>,
+    ttl: Duration,
+    refreshes: Arc<AtomicUsize>,
+}
+
+impl Default for GitStatusCache {
+    fn default() -> Self {
+        Self::new(STATUS_CACHE_TTL)
+    }
+}
+
+impl GitStatusCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            entries: Arc::new(Mutex::new(HashMap::new())),
+            ttl,
+            refreshes: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    pub async fn status_map_for_path(&self, path: &Path) -> Option<StatusMap> {
+        let repo_root = find_repo_root(path).await?;
+        if let Some(map) = self.fresh(&repo_root) {
+            return Some(map);
+        }
+
+        self.refreshes.fetch_add(1, Ordering::Relaxed);
+        let map = status_map(&repo_root).await?;
+        if let Ok(mut entries) = self.entries.lock() {
+            let ttl = self.ttl;
+            entries.retain(|_, (at, _)| at.elapsed() < ttl);
+            entries.insert(repo_root, (Instant::now(), map.clone()));
+        }
+        Some(map)
+    }
+
+    /// Drop cached status for any repo containing `changed`.
+    pub fn invalidate(&self, changed: &Path) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.retain(|repo_root, _| !changed.starts_with(repo_root));
+        }
+    }
+
+    /// Number of times `git status` has actually been run through this cache.
+    #[cfg(test)]
+    pub fn refresh_count(&self) -> usize {
+        self.refreshes.load(Ordering::Relaxed)
+    }
+
+    fn fresh(&self, repo_root: &Path) -> Option<StatusMap> {
+        let entries = self.entries.lock().ok()?;
+        let (at, map) = entries.get(repo_root)?;
+        (at.elapsed() < self.ttl).then(|| map.clone())
+    }
+}
+
+/// `.gitignore` rules that apply to the entries of one directory: the repo's
+/// `.git/info/exclude`, then every `.gitignore` from the repo root down.
+pub struct IgnoreRules {
+    /// Outermost first; deeper files take precedence.
+    layers: Vec<Gitignore>,
+}
+
+impl IgnoreRules {
+    /// Rules for `dir`, or `None` when it is not inside a repository.
+    pub async fn for_dir(dir: &Path) -> Option<Self> {
+        let root = find_repo_root(dir).await?;
+        let relative = dir.strip_prefix(&root).ok()?.to_path_buf();
+
+        let mut root_rules = GitignoreBuilder::new(&root);
+        // Missing files are fine; later lines win, so .gitignore beats exclude.
+        let _ = root_rules.add(root.join(".git").join("info").join("exclude"));
+        let _ = root_rules.add(root.join(".gitignore"));
+        let mut layers = vec![root_rules.build().ok()?];
+
+        let mut current = root;
+        for component in relative.components() {
+            current.push(component);
+            let file = current.join(".gitignore");
+            if tokio::fs::metadata(&file).await.is_ok_and(|m| m.is_file()) {
+                let mut builder = GitignoreBuilder::new(&current);
+                let _ = builder.add(file);
+                if let Ok(rules) = builder.build() {
+                    layers.push(rules);
+                }
+            }
+        }
+        Some(Self { layers })
+    }
+
+    /// Whether `path` itself matches an ignore rule (parents aren't consulted,
+    /// so browsing inside an ignored directory still lists its contents).
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        for layer in self.layers.iter().rev() {
+            match layer.matched(path, is_dir) {
+                Match::Ignore(_) => return true,
+                Match::Whitelist(_) => return false,
+                Match::None => {}
+            }
+        }
+        false
+    }
 }
 
 pub async fn status_for_path(path: &Path) -> Option<GitStatus> {
@@ -71,6 +186,17 @@ async fn status_map(repo_root: &Path) -> Option<HashMap<PathBuf, GitStatus>> {
     Some(map)
 }
 
+/// Whether `dir` is the root of its own repository.
+///
+/// Submodules and `--separate-git-dir` checkouts have a `.git` *file*
+/// (`gitdir: ...`) rather than a directory, so either counts.
+pub async fn is_repo_root(dir: &Path) -> bool {
+    // Use async metadata check instead of blocking exists()
+    tokio::fs::metadata(dir.join(".git")).await.is_ok()
+}
+
+/// Nearest enclosing repository, so files inside a submodule or nested repo
+/// are attributed to that repo rather than the outer one.
 async fn find_repo_root(path: &Path) -> Option<PathBuf> {
     let mut current = if path.is_dir() {
         path.to_path_buf()
@@ -79,11 +205,8 @@ async fn find_repo_root(path: &Path) -> Option<PathBuf> {
     };
 
     loop {
-        // Use async metadata check instead of blocking exists()
-        let git_path = current.join(".git");
-        match tokio::fs::metadata(&git_path).await {
-            Ok(meta) if meta.is_dir() => return Some(current),
-            _ => {}
+        if is_repo_root(&current).await {
+            return Some(current);
         }
         if !current.pop() {
             break;
@@ -1,15 +1,62 @@
+use crate::protocol::{IndentKind, IndentStyle, LineEnding};
+
 /// Detect MIME type from file content and extension
 pub fn detect_mime_type(buffer: &[u8], filename: &str) -> String {
-    if let Some(kind) = infer::get(buffer) {
+    let inferred = infer::get(buffer);
+    // `infer`'s text matchers (HTML, XML, any `#!` script) are weaker than
+    // the extension or the interpreter line, so only binary kinds win here.
+    if let Some(kind) = inferred.filter(|kind| kind.matcher_type() != infer::MatcherType::Text) {
         return kind.mime_type().to_string();
     }
     let guessed = guess_mime_from_extension(filename);
-    if guessed == "application/octet-stream" && is_probably_text(buffer) {
-        return "text/plain".to_string();
+    if guessed == "application/octet-stream" {
+        if let Some(mime) = mime_from_shebang(buffer) {
+            return mime.to_string();
+        }
+        if let Some(kind) = inferred {
+            return kind.mime_type().to_string();
+        }
+        if is_probably_text(buffer) {
+            return "text/plain".to_string();
+        }
     }
     guessed
 }
 
+/// Interpreter named by a script's `#!` line, without path or version
+/// (`#!/usr/bin/env python3.12` -> `python`).
+fn shebang_interpreter(buffer: &[u8]) -> Option<&str> {
+    let rest = buffer.strip_prefix(b"#!")?;
+    let line = rest.split(|&b| b == b'\n').next()?;
+    let line = std::str::from_utf8(line).ok()?;
+    let mut words = line.split_whitespace();
+    let mut interpreter = words.next()?.rsplit('/').next()?;
+    if interpreter == "env" {
+        // Skip `env` flags such as `-S`.
+        interpreter = words.find(|word| !word.starts_with('-'))?;
+    }
+    Some(interpreter.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.'))
+}
+
+/// MIME type for a script's interpreter line (`#!/usr/bin/env python3`,
+/// `#!/bin/bash`, ...), so extensionless scripts still get highlighted.
+fn mime_from_shebang(buffer: &[u8]) -> Option<&'static str> {
+    let mime = match shebang_interpreter(buffer)? {
+        "sh" | "bash" | "zsh" | "dash" | "ksh" | "ash" | "fish" => "text/x-shellscript",
+        "python" | "pypy" => "text/x-python",
+        "node" | "nodejs" | "deno" | "bun" => "application/javascript",
+        "ts-node" | "tsx" => "text/typescript",
+        "ruby" => "text/x-ruby",
+        "perl" => "text/x-perl",
+        "php" => "text/x-php",
+        "lua" | "luajit" => "text/x-lua",
+        "pwsh" => "text/x-powershell",
+        "Rscript" => "text/x-r",
+        _ => return None,
+    };
+    Some(mime)
+}
+
 /// Guess MIME type based on filename extension
 pub fn guess_mime_from_extension(filename: &str) -> String {
     let filename_lower = filename.to_lowercase();
@@ -49,7 +96,7 @@ pub fn guess_mime_from_extension(filename: &str) -> String {
     match ext.as_str() {
         "rs" => "text/x-rust",
         "py" => "text/x-python",
-        "js" => "application/javascript",
+        "js" | "mjs" | "cjs" => "application/javascript",
         "ts" => "text/typescript",
         "tsx" => "text/typescript-jsx",
         "jsx" => "text/javascript-jsx",
@@ -70,6 +117,16 @@ pub fn guess_mime_from_extension(filename: &str) -> String {
         "less" => "text/x-less",
         "xml" => "application/xml",
         "json" => "application/json",
+        "jsonc" | "json5" => "text/x-jsonc",
+        "vue" => "text/x-vue",
+        "svelte" => "text/x-svelte",
+        "astro" => "text/x-astro",
+        "zig" => "text/x-zig",
+        "nix" => "text/x-nix",
+        "lua" => "text/x-lua",
+        "proto" => "text/x-protobuf",
+        "graphql" | "gql" => "application/graphql",
+        "sql" => "application/sql",
         "yaml" | "yml" => "text/x-yaml",
         "toml" => "text/x-toml",
         "md" | "markdown" => "text/markdown",
@@ -97,6 +154,124 @@ pub fn guess_mime_from_extension(filename: &str) -> String {
     .to_string()
 }
 
+/// Highlighter language token for a text file, from its name and, for
+/// extensionless scripts, its `#!` line. `None` when the language is unknown
+/// or the file is plain text; callers skip this for binary content.
+pub fn detect_language(buffer: &[u8], filename: &str) -> Option<&'static str> {
+    language_from_filename(filename).or_else(|| {
+        let language = match shebang_interpreter(buffer)? {
+            "sh" | "bash" | "dash" | "ksh" | "ash" => "bash",
+            "zsh" => "zsh",
+            "fish" => "fish",
+            "python" | "pypy" => "python",
+            "node" | "nodejs" | "deno" | "bun" => "javascript",
+            "ts-node" | "tsx" => "typescript",
+            "ruby" => "ruby",
+            "perl" => "perl",
+            "php" => "php",
+            "lua" | "luajit" => "lua",
+            "pwsh" => "powershell",
+            "Rscript" => "r",
+            _ => return None,
+        };
+        Some(language)
+    })
+}
+
+/// Highlighter language token from a file name alone.
+pub fn language_from_filename(filename: &str) -> Option<&'static str> {
+    let name = std::path::Path::new(filename)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or(filename)
+        .to_lowercase();
+
+    let by_name = match name.as_str() {
+        "dockerfile" | "containerfile" => Some("dockerfile"),
+        "makefile" | "gnumakefile" => Some("makefile"),
+        "cmakelists.txt" => Some("cmake"),
+        "gemfile" | "rakefile" | "podfile" | "vagrantfile" => Some("ruby"),
+        "justfile" => Some("just"),
+        ".gitignore" | ".dockerignore" | ".npmignore" | ".prettierignore" | ".eslintignore" => {
+            Some("ignore")
+        }
+        ".editorconfig" | ".gitconfig" | ".npmrc" => Some("ini"),
+        ".bashrc" | ".bash_profile" | ".bash_aliases" | ".profile" => Some("bash"),
+        ".zshrc" | ".zshenv" | ".zprofile" => Some("zsh"),
+        _ => None,
+    };
+    if by_name.is_some() {
+        return by_name;
+    }
+    if name.starts_with(".env") {
+        return Some("dotenv");
+    }
+    if name.starts_with("dockerfile.") {
+        return Some("dockerfile");
+    }
+
+    let ext = name.rsplit_once('.').map(|(_, ext)| ext)?;
+    let language = match ext {
+        "rs" => "rust",
+        "py" | "pyi" => "python",
+        "js" | "mjs" | "cjs" => "javascript",
+        "jsx" => "jsx",
+        "ts" | "mts" | "cts" => "typescript",
+        "tsx" => "tsx",
+        "go" => "go",
+        "java" => "java",
+        "c" | "h" => "c",
+        "cpp" | "cc" | "cxx" | "hpp" | "hh" => "cpp",
+        "cs" => "csharp",
+        "rb" => "ruby",
+        "php" => "php",
+        "swift" => "swift",
+        "kt" | "kts" => "kotlin",
+        "scala" => "scala",
+        "dart" => "dart",
+        "ex" | "exs" => "elixir",
+        "erl" => "erlang",
+        "hs" => "haskell",
+        "ml" | "mli" => "ocaml",
+        "clj" | "cljs" => "clojure",
+        "r" => "r",
+        "pl" | "pm" => "perl",
+        "sh" | "bash" => "bash",
+        "zsh" => "zsh",
+        "fish" => "fish",
+        "ps1" | "psm1" => "powershell",
+        "html" | "htm" => "html",
+        "css" => "css",
+        "scss" | "sass" => "scss",
+        "less" => "less",
+        "xml" | "svg" | "plist" => "xml",
+        "json" => "json",
+        "jsonc" | "json5" => "jsonc",
+        "yaml" | "yml" => "yaml",
+        "toml" => "toml",
+        "ini" | "cfg" | "conf" => "ini",
+        "md" | "markdown" => "markdown",
+        "rst" => "rst",
+        "tex" => "latex",
+        "vue" => "vue",
+        "svelte" => "svelte",
+        "astro" => "astro",
+        "zig" => "zig",
+        "nix" => "nix",
+        "lua" => "lua",
+        "proto" => "protobuf",
+        "graphql" | "gql" => "graphql",
+        "sql" => "sql",
+        "tf" | "tfvars" | "hcl" => "hcl",
+        "diff" | "patch" => "diff",
+        "csv" => "csv",
+        "env" => "dotenv",
+        "dockerfile" => "dockerfile",
+        _ => return None,
+    };
+    Some(language)
+}
+
 /// Best-effort heuristic for deciding if a buffer is "text enough" to render as UTF-8.
 ///
 /// This is used to avoid treating common config files (dotfiles, extensionless files, etc)
@@ -138,12 +313,109 @@ pub fn is_probably_text(buffer: &[u8]) -> bool {
     control_count * 10 <= buffer.len()
 }
 
+/// Like `is_probably_text`, but for a prefix read from a larger file: a UTF-8
+/// sequence cut off at the end of the buffer is not counted against it.
+pub fn is_probably_text_prefix(buffer: &[u8]) -> bool {
+    match std::str::from_utf8(buffer) {
+        Err(e) if e.error_len().is_none() => is_probably_text(&buffer[..e.valid_up_to()]),
+        _ => is_probably_text(buffer),
+    }
+}
+
+/// Text hint from an extension-based MIME guess, without touching the file.
+///
+/// Returns `None` when the guess is inconclusive and the content has to be
+/// sniffed instead.
+pub fn text_hint_from_mime(mime: &str) -> Option<bool> {
+    if is_text_mime(mime) {
+        Some(true)
+    } else if mime == "application/octet-stream" {
+        None
+    } else {
+        Some(false)
+    }
+}
+
 /// Check if MIME type is text-based (safe to display as text)
 pub fn is_text_mime(mime: &str) -> bool {
     mime.starts_with("text/")
         || mime == "application/json"
         || mime == "application/javascript"
         || mime == "application/xml"
+        || mime == "application/graphql"
+        || mime == "application/sql"
         || mime.ends_with("+xml")
         || mime.ends_with("+json")
 }
+
+/// Line-ending convention of `text`, or `None` when it has no line break.
+pub fn detect_line_ending(text: &str) -> Option<LineEnding> {
+    let bytes = text.as_bytes();
+    let (mut lf, mut crlf) = (0usize, 0usize);
+    for (idx, _) in bytes.iter().enumerate().filter(|(_, &b)| b == b'\n') {
+        if idx > 0 && bytes[idx - 1] == b'\r' {
+            crlf += 1;
+        } else {
+            lf += 1;
+        }
+    }
+    match (lf, crlf) {
+        (0, 0) => None,
+        (_, 0) => Some(LineEnding::Lf),
+        (0, _) => Some(LineEnding::Crlf),
+        _ => Some(LineEnding::Mixed),
+    }
+}
+
+/// Lines sampled by `detect_indent`; enough to settle any real file's style.
+const INDENT_SAMPLE_LINES: usize = 1000;
+
+/// Indentation style of `text`: tabs when more lines start with a tab than
+/// with spaces, otherwise the most common step between the space indents of
+/// neighbouring lines. `None` when no sampled line is indented.
+pub fn detect_indent(text: &str) -> Option<IndentStyle> {
+    let (mut tab_lines, mut space_lines) = (0usize, 0usize);
+    let mut steps = [0usize; 9];
+    let mut previous = 0usize;
+    for line in text.lines().take(INDENT_SAMPLE_LINES) {
+        let content = line.trim_start_matches([' ', '\t']);
+        // Blank lines and block-comment continuations (` * ...`) say
+        // nothing about the indent step.
+        if content.is_empty() || content.starts_with('*') {
+            continue;
+        }
+        if line.starts_with('\t') {
+            tab_lines += 1;
+            continue;
+        }
+        let width = line.len() - line.trim_start_matches(' ').len();
+        if width > 0 {
+            space_lines += 1;
+        }
+        let step = width.abs_diff(previous);
+        if (1..steps.len()).contains(&step) {
+            steps[step] += 1;
+        }
+        previous = width;
+    }
+
+    if tab_lines == 0 && space_lines == 0 {
+        return None;
+    }
+    if tab_lines > space_lines {
+        return Some(IndentStyle {
+            kind: IndentKind::Tabs,
+            width: 1,
+        });
+    }
+    // Ties go to the smaller step.
+    let width = (1..steps.len())
+        .rev()
+        .max_by_key(|&step| steps[step])
+        .filter(|&step| steps[step] > 0)
+        .unwrap_or(4);
+    Some(IndentStyle {
+        kind: IndentKind::Spaces,
+        width: width as u8,
+    })
+}
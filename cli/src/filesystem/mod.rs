@@ -1,5 +1,6 @@
 //! File system service for MobileCLI
 
+pub mod archive;
 pub mod config;
 pub mod git;
 pub mod mime;
@@ -17,7 +18,8 @@ mod tests;
 use std::sync::Arc;
 
 use config::FileSystemConfig;
-use operations::FileOperations;
+use git::GitStatusCache;
+use operations::{ChunkedWrites, FileOperations};
 use search::FileSearch;
 use security::PathValidator;
 use watcher::FileWatcher;
@@ -27,16 +29,39 @@ pub struct FileSystemService {
     validator: Arc<PathValidator>,
     ops: FileOperations,
     search: FileSearch,
-    watcher: FileWatcher,
+    watcher: Arc<FileWatcher>,
 }
 
 impl FileSystemService {
     pub fn new(config: FileSystemConfig) -> Self {
+        // Listings read git status through this cache; the watcher invalidates it.
+        let watcher = FileWatcher::new(config.watch_debounce_ms, GitStatusCache::default());
+        Self::assemble(config, Arc::new(watcher), ChunkedWrites::default())
+    }
+
+    /// A service for `config` that keeps this one's live state: uploads in
+    /// progress and the watcher, with its watches, subscribers and replay
+    /// sequence.
+    pub fn rebuild(&self, config: FileSystemConfig) -> Self {
+        self.watcher.set_debounce_ms(config.watch_debounce_ms);
+        Self::assemble(
+            config,
+            self.watcher.clone(),
+            self.ops.chunked_writes().clone(),
+        )
+    }
+
+    fn assemble(
+        config: FileSystemConfig,
+        watcher: Arc<FileWatcher>,
+        chunked_writes: ChunkedWrites,
+    ) -> Self {
         let config = Arc::new(config);
         let validator = Arc::new(PathValidator::new(config.clone()));
-        let ops = FileOperations::new(validator.clone(), config.clone());
+        let ops = FileOperations::new(validator.clone(), config.clone())
+            .with_git_cache(watcher.git_cache().clone())
+            .with_chunked_writes(chunked_writes);
         let search = FileSearch::new(ops.clone());
-        let watcher = FileWatcher::new(250);
         Self {
             config,
             validator,
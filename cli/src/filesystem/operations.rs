@@ -1,24 +1,183 @@
+use std::collections::HashMap;
 use std::ffi::OsString;
+use std::net::SocketAddr;
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 use tokio::fs;
-use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
 
 use crate::protocol::{
-    FileContent, FileEncoding, FileEntry, FileSystemError, GitStatus, SortField, SortOrder,
+    ChecksumAlgorithm, FileContent, FileEncoding, FileEntry, FileLines, FileSystemError, GitStatus,
+    SortField, SortOrder,
 };
 use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use sha2::{Digest, Sha256};
 
 use super::config::FileSystemConfig;
+use super::git::{GitStatusCache, IgnoreRules};
 use super::mime;
 use super::path_utils;
 use super::platform;
 use super::security::PathValidator;
 
+/// Bytes read from the start of a file to guess whether it is text.
+const TEXT_PEEK_BYTES: usize = 4096;
+/// Files larger than this are not peeked while building listing entries.
+const TEXT_PEEK_MAX_FILE_SIZE: u64 = 16 * 1024 * 1024;
+/// Text files larger than this get no `line_count` in listing entries.
+const LINE_COUNT_MAX_FILE_SIZE: u64 = 256 * 1024;
+/// Bytes read from the start of an image to find its dimensions.
+const IMAGE_HEADER_PEEK_BYTES: u64 = 64 * 1024;
+/// Images larger than this are not probed for dimensions.
+const IMAGE_DIMENSIONS_MAX_FILE_SIZE: u64 = 32 * 1024 * 1024;
+
+/// Bytes returned by `binary_preview` when the caller doesn't say.
+const BINARY_PREVIEW_DEFAULT_BYTES: u64 = 4096;
+/// Most bytes `binary_preview` will return.
+pub const BINARY_PREVIEW_MAX_BYTES: u64 = 64 * 1024;
+
+/// Unfinished chunked writes idle for longer than this are discarded.
+const CHUNKED_WRITE_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// Filtering, sorting, and paging for `FileOperations::list_directory`.
+#[derive(Debug, Clone, Default)]
+pub struct ListOptions {
+    pub include_hidden: bool,
+    pub sort_by: Option<SortField>,
+    pub sort_order: Option<SortOrder>,
+    /// Glob applied to entry names before entries are built
+    pub name_filter: Option<String>,
+    /// Keep only files with these extensions; directories always pass
+    pub extensions: Option<Vec<String>>,
+    /// Index of the first entry to return, applied after sorting
+    pub offset: usize,
+    /// Page size; at least 1 and capped at `max_list_entries`
+    pub limit: Option<usize>,
+    /// Build minimal entries: no git status, permissions, MIME, or content peeks
+    pub compact: bool,
+    /// Drop `.gitignore`d files and mark ignored directories (repos only)
+    pub respect_gitignore: bool,
+    /// With `respect_gitignore`, drop ignored directories as well
+    pub hide_ignored_dirs: bool,
+}
+
+/// One page of a directory listing.
+#[derive(Debug, Clone)]
+pub struct DirectoryPage {
+    pub path: String,
+    pub entries: Vec<FileEntry>,
+    /// Size of the full (filtered, pre-paging) listing
+    pub total_count: usize,
+    /// The listing is larger than a single response can hold
+    pub truncated: bool,
+    pub has_more: bool,
+}
+
+/// Progress after one `FileOperations::write_file_chunk` call.
+#[derive(Debug, Clone)]
+pub struct ChunkProgress {
+    pub path: String,
+    /// Bytes received so far for this upload
+    pub bytes_written: u64,
+    pub is_complete: bool,
+}
+
+/// Result of `FileOperations::binary_preview`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BinaryPreview {
+    pub path: String,
+    pub hex: String,
+    pub ascii: String,
+    pub total_size: u64,
+    pub mime_type: Option<String>,
+}
+
+/// Result of `FileOperations::path_exists`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PathPresence {
+    pub path: String,
+    pub exists: bool,
+    pub is_directory: bool,
+    pub is_file: bool,
+}
+
+/// What a recursive `FileOperations::delete_path` would remove.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeletePreview {
+    pub path: String,
+    pub is_directory: bool,
+    /// Entries removed, the target itself included
+    pub entry_count: u64,
+    /// Combined size of the files among them
+    pub total_bytes: u64,
+}
+
+/// What a rename, copy or delete would do, from the `dry_run` variants.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DryRun {
+    /// Paths that would be removed, moved or created
+    pub affected_paths: Vec<String>,
+    /// Existing paths in the way; the real operation fails on these
+    pub conflicts: Vec<String>,
+    /// `affected_paths` stopped at `max_list_entries`
+    pub truncated: bool,
+}
+
+impl DryRun {
+    fn affect(&mut self, path: &Path, limit: usize) {
+        if self.affected_paths.len() < limit {
+            self.affected_paths.push(path_utils::to_protocol_path(path));
+        } else {
+            self.truncated = true;
+        }
+    }
+}
+
+/// A chunked write in progress, keyed by the client's upload id. Dropping
+/// it removes the temp file; a committed upload has already renamed it away.
+struct ChunkedWrite {
+    path: PathBuf,
+    temp_path: PathBuf,
+    total_chunks: u64,
+    next_index: u64,
+    written: u64,
+    touched: Instant,
+}
+
+impl Drop for ChunkedWrite {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.temp_path);
+    }
+}
+
+/// Chunked writes in progress, shared so they outlive a rebuild of the
+/// file system service.
+#[derive(Clone, Default)]
+pub struct ChunkedWrites(Arc<Mutex<HashMap<String, ChunkedWrite>>>);
+
+/// An open directory being listed by `FileOperations`.
+struct DirectoryCursor {
+    path: PathBuf,
+    read_dir: fs::ReadDir,
+    git_statuses: Option<std::collections::HashMap<PathBuf, GitStatus>>,
+    include_hidden: bool,
+    name_filter: Option<glob::Pattern>,
+    extensions: Option<std::collections::HashSet<String>>,
+    compact: bool,
+    ignore_rules: Option<IgnoreRules>,
+    hide_ignored_dirs: bool,
+}
+
 #[derive(Clone)]
 pub struct FileOperations {
     validator: std::sync::Arc<PathValidator>,
     config: std::sync::Arc<FileSystemConfig>,
+    git_cache: GitStatusCache,
+    chunked_writes: ChunkedWrites,
+    /// Client whose read grants apply to reads through this handle.
+    grantee: Option<SocketAddr>,
 }
 
 impl FileOperations {
@@ -26,7 +185,41 @@ impl FileOperations {
         validator: std::sync::Arc<PathValidator>,
         config: std::sync::Arc<FileSystemConfig>,
     ) -> Self {
-        Self { validator, config }
+        Self {
+            validator,
+            config,
+            git_cache: GitStatusCache::default(),
+            chunked_writes: ChunkedWrites::default(),
+            grantee: None,
+        }
+    }
+
+    /// Share a git status cache (e.g. the one the file watcher invalidates).
+    pub fn with_git_cache(mut self, git_cache: GitStatusCache) -> Self {
+        self.git_cache = git_cache;
+        self
+    }
+
+    /// Continue the chunked writes of an earlier `FileOperations`.
+    pub fn with_chunked_writes(mut self, chunked_writes: ChunkedWrites) -> Self {
+        self.chunked_writes = chunked_writes;
+        self
+    }
+
+    /// These operations on behalf of `client`, whose read grants then apply.
+    pub fn for_client(&self, client: SocketAddr) -> Self {
+        let mut ops = self.clone();
+        ops.grantee = Some(client);
+        ops
+    }
+
+    pub fn chunked_writes(&self) -> &ChunkedWrites {
+        &self.chunked_writes
+    }
+
+    #[cfg(test)]
+    pub fn git_cache(&self) -> &GitStatusCache {
+        &self.git_cache
     }
 
     pub fn validator(&self) -> &PathValidator {
@@ -39,12 +232,74 @@ impl FileOperations {
 
     /// List directory contents
     pub async fn list_directory(
+        &self,
+        path: &str,
+        options: &ListOptions,
+    ) -> Result<DirectoryPage, FileSystemError> {
+        let mut cursor = self.open_directory(path, options).await?;
+        let mut entries = Vec::new();
+        while let Some(entry) = self.next_directory_entry(&mut cursor).await? {
+            entries.push(entry);
+        }
+
+        sort_entries(
+            &mut entries,
+            options.sort_by.clone(),
+            options.sort_order.clone(),
+        );
+
+        // Page after sorting so offsets stay stable across requests.
+        let total_count = entries.len();
+        let truncated = total_count > self.config.max_list_entries;
+        let page_size = options
+            .limit
+            .unwrap_or(self.config.max_list_entries)
+            .min(self.config.max_list_entries)
+            .max(1);
+        let start = options.offset.min(total_count);
+        let end = start.saturating_add(page_size).min(total_count);
+        let entries: Vec<FileEntry> = entries.drain(start..end).collect();
+
+        Ok(DirectoryPage {
+            path: path_utils::to_protocol_path(&cursor.path),
+            entries,
+            total_count,
+            truncated,
+            has_more: end < total_count,
+        })
+    }
+
+    /// Stream directory entries to `sink` as each one is built.
+    ///
+    /// Entries arrive in directory order, unsorted and unpaged. Stops early if
+    /// the receiver goes away. Returns the protocol path and the number of
+    /// entries sent.
+    pub async fn list_directory_stream(
         &self,
         path: &str,
         include_hidden: bool,
-        sort_by: Option<SortField>,
-        sort_order: Option<SortOrder>,
-    ) -> Result<(String, Vec<FileEntry>, usize, bool), FileSystemError> {
+        sink: tokio::sync::mpsc::Sender<FileEntry>,
+    ) -> Result<(String, usize), FileSystemError> {
+        let options = ListOptions {
+            include_hidden,
+            ..Default::default()
+        };
+        let mut cursor = self.open_directory(path, &options).await?;
+        let mut sent = 0usize;
+        while let Some(entry) = self.next_directory_entry(&mut cursor).await? {
+            if sink.send(entry).await.is_err() {
+                break;
+            }
+            sent += 1;
+        }
+        Ok((path_utils::to_protocol_path(&cursor.path), sent))
+    }
+
+    async fn open_directory(
+        &self,
+        path: &str,
+        options: &ListOptions,
+    ) -> Result<DirectoryCursor, FileSystemError> {
         let path = self.validator.validate_existing(path)?;
 
         if !path.is_dir() {
@@ -53,23 +308,62 @@ impl FileOperations {
             });
         }
 
-        let mut entries = Vec::new();
+        let name_filter = options
+            .name_filter
+            .as_deref()
+            .map(str::trim)
+            .filter(|pattern| !pattern.is_empty())
+            .map(glob::Pattern::new)
+            .transpose()
+            .map_err(|e| FileSystemError::IoError {
+                message: e.to_string(),
+            })?;
+
         // Git status is best-effort; don't block listing on it
-        let git_statuses = tokio::time::timeout(
-            std::time::Duration::from_millis(500),
-            super::git::status_map_for_path(&path),
-        )
-        .await
-        .ok()
-        .flatten();
-        let mut read_dir = fs::read_dir(&path)
+        let git_statuses = if options.compact {
+            None
+        } else {
+            tokio::time::timeout(
+                std::time::Duration::from_millis(500),
+                self.git_cache.status_map_for_path(&path),
+            )
+            .await
+            .ok()
+            .flatten()
+        };
+        let read_dir = fs::read_dir(&path)
             .await
             .map_err(|e| FileSystemError::IoError {
                 message: e.to_string(),
             })?;
 
+        let ignore_rules = if options.respect_gitignore {
+            IgnoreRules::for_dir(&path).await
+        } else {
+            None
+        };
+
+        Ok(DirectoryCursor {
+            path,
+            read_dir,
+            git_statuses,
+            include_hidden: options.include_hidden,
+            name_filter,
+            extensions: extension_set(options.extensions.as_deref()),
+            compact: options.compact,
+            ignore_rules,
+            hide_ignored_dirs: options.hide_ignored_dirs,
+        })
+    }
+
+    /// Next entry of an open directory that passes the hidden/name/denied filters.
+    async fn next_directory_entry(
+        &self,
+        cursor: &mut DirectoryCursor,
+    ) -> Result<Option<FileEntry>, FileSystemError> {
         while let Some(entry) =
-            read_dir
+            cursor
+                .read_dir
                 .next_entry()
                 .await
                 .map_err(|e| FileSystemError::IoError {
@@ -79,35 +373,61 @@ impl FileOperations {
             let entry_path = entry.path();
             let name = entry.file_name().to_string_lossy().to_string();
 
-            if !include_hidden && platform::is_hidden(&entry_path) {
+            if !cursor.include_hidden && platform::is_hidden(&entry_path) {
+                continue;
+            }
+            if cursor
+                .name_filter
+                .as_ref()
+                .is_some_and(|pattern| !pattern.matches(&name))
+            {
                 continue;
             }
             if self.validator.is_denied(&entry_path) {
                 continue;
             }
-            let git_status = git_statuses
+            if cursor
+                .extensions
                 .as_ref()
-                .and_then(|map| map.get(&entry_path).cloned());
-            match self.build_file_entry(&entry_path, &name, git_status).await {
-                Ok(file_entry) => entries.push(file_entry),
-                Err(_) => continue,
+                .is_some_and(|exts| !exts.contains(&extension_of(&name)) && !entry_path.is_dir())
+            {
+                continue;
+            }
+            let file_type = entry.file_type().await.ok();
+            let mut ignored_dir = false;
+            if let Some(rules) = cursor.ignore_rules.as_ref() {
+                let is_dir = file_type.is_some_and(|t| t.is_dir());
+                if rules.is_ignored(&entry_path, is_dir) {
+                    if !is_dir || cursor.hide_ignored_dirs {
+                        continue;
+                    }
+                    ignored_dir = true;
+                }
+            }
+            if cursor.compact {
+                if let Ok(mut file_entry) = build_compact_entry(&entry_path, &name).await {
+                    if ignored_dir {
+                        file_entry.git_status = Some(GitStatus::Ignored);
+                    }
+                    return Ok(Some(file_entry));
+                }
+                continue;
+            }
+            let git_status = match cursor.git_statuses.as_ref() {
+                _ if ignored_dir => Some(GitStatus::Ignored),
+                // Inside a repo, child repos get their own marker instead of
+                // the outer repo's view of them (usually untracked).
+                Some(_) if entry_path.is_dir() && super::git::is_repo_root(&entry_path).await => {
+                    Some(GitStatus::Submodule)
+                }
+                Some(map) => map.get(&entry_path).cloned(),
+                None => None,
+            };
+            if let Ok(file_entry) = self.build_file_entry(&entry_path, &name, git_status).await {
+                return Ok(Some(file_entry));
             }
         }
-
-        sort_entries(&mut entries, sort_by, sort_order);
-
-        let total_count = entries.len();
-        let truncated = total_count > self.config.max_list_entries;
-        if truncated {
-            entries.truncate(self.config.max_list_entries);
-        }
-
-        Ok((
-            path_utils::to_protocol_path(&path),
-            entries,
-            total_count,
-            truncated,
-        ))
+        Ok(None)
     }
 
     /// Read file contents
@@ -117,8 +437,9 @@ impl FileOperations {
         offset: Option<u64>,
         length: Option<u64>,
         encoding: FileEncoding,
+        force_text: bool,
     ) -> Result<FileContent, FileSystemError> {
-        let path = self.validator.validate_existing(path)?;
+        let path = self.validator.validate_readable(path, self.grantee)?;
 
         if !path.is_file() {
             return Err(FileSystemError::NotAFile {
@@ -176,23 +497,35 @@ impl FileOperations {
 
         let mime_type = mime::detect_mime_type(&buffer, path.to_string_lossy().as_ref());
 
-        let (content, actual_encoding) = match encoding {
-            FileEncoding::Utf8 => {
-                // Treat as text if MIME says text or the buffer looks like text.
-                if mime::is_text_mime(&mime_type) || mime::is_probably_text(&buffer) {
-                    if let Some(text) = decode_text_buffer(&buffer) {
-                        (text, FileEncoding::Utf8)
-                    } else {
-                        (
-                            String::from_utf8_lossy(&buffer).to_string(),
-                            FileEncoding::Utf8,
-                        )
-                    }
-                } else {
-                    (BASE64.encode(&buffer), FileEncoding::Base64)
-                }
-            }
-            FileEncoding::Base64 => (BASE64.encode(&buffer), FileEncoding::Base64),
+        // Treat as text if MIME says text or the buffer looks like text. Content
+        // that `infer` recognised as a binary format is never sniffed further.
+        let looks_textual = mime::is_text_mime(&mime_type) || mime::is_probably_text(&buffer);
+        let decoded = if looks_textual || mime_type == "application/octet-stream" {
+            decode_text_buffer(&buffer)
+        } else {
+            None
+        };
+
+        let is_text = force_text || looks_textual || decoded.is_some();
+        let (content, actual_encoding, detected_encoding) = match (encoding, decoded) {
+            // The caller knows better than the heuristics: hand back text
+            // whatever the bytes look like.
+            _ if force_text => (
+                String::from_utf8_lossy(&buffer).to_string(),
+                FileEncoding::Utf8,
+                "UTF-8",
+            ),
+            (FileEncoding::Utf8, Some((text, detected))) => (text, FileEncoding::Utf8, detected),
+            (FileEncoding::Utf8, None) if looks_textual => (
+                String::from_utf8_lossy(&buffer).to_string(),
+                FileEncoding::Utf8,
+                "UTF-8",
+            ),
+            (_, decoded) => (
+                BASE64.encode(&buffer),
+                FileEncoding::Base64,
+                decoded.map_or("binary", |(_, detected)| detected),
+            ),
         };
 
         let modified = metadata
@@ -200,6 +533,19 @@ impl FileOperations {
             .map(|t| t.duration_since(std::time::UNIX_EPOCH).unwrap().as_millis() as u64)
             .unwrap_or(0);
 
+        let language = if is_text {
+            mime::detect_language(&buffer, path.to_string_lossy().as_ref()).map(str::to_string)
+        } else {
+            None
+        };
+        let (line_ending, indent) = match actual_encoding {
+            FileEncoding::Utf8 => (
+                mime::detect_line_ending(&content),
+                mime::detect_indent(&content),
+            ),
+            FileEncoding::Base64 => (None, None),
+        };
+
         Ok(FileContent {
             path: path_utils::to_protocol_path(&path),
             content,
@@ -212,9 +558,133 @@ impl FileOperations {
             } else {
                 None
             },
+            detected_encoding: detected_encoding.to_string(),
+            language,
+            line_ending,
+            indent,
+        })
+    }
+
+    /// Read the first `max_bytes` (default 4 KiB, capped at 64 KiB) of a file
+    /// for a hex view, whatever its type.
+    pub async fn binary_preview(
+        &self,
+        path: &str,
+        max_bytes: Option<u64>,
+    ) -> Result<BinaryPreview, FileSystemError> {
+        let path = self.validator.validate_readable(path, self.grantee)?;
+        if !path.is_file() {
+            return Err(FileSystemError::NotAFile {
+                path: path_utils::to_protocol_path(&path),
+            });
+        }
+        let io_err = |e: std::io::Error| FileSystemError::IoError {
+            message: e.to_string(),
+        };
+
+        let total_size = fs::metadata(&path).await.map_err(io_err)?.len();
+        let limit = max_bytes
+            .unwrap_or(BINARY_PREVIEW_DEFAULT_BYTES)
+            .min(BINARY_PREVIEW_MAX_BYTES);
+        let mut buffer = Vec::with_capacity(limit.min(total_size) as usize);
+        fs::File::open(&path)
+            .await
+            .map_err(io_err)?
+            .take(limit)
+            .read_to_end(&mut buffer)
+            .await
+            .map_err(io_err)?;
+
+        let hex = buffer.iter().map(|b| format!("{:02x}", b)).collect();
+        let ascii = buffer
+            .iter()
+            .map(|&b| {
+                if b.is_ascii_graphic() || b == b' ' {
+                    b as char
+                } else {
+                    '.'
+                }
+            })
+            .collect();
+        Ok(BinaryPreview {
+            path: path_utils::to_protocol_path(&path),
+            hex,
+            ascii,
+            total_size,
+            mime_type: infer::get(&buffer).map(|kind| kind.mime_type().to_string()),
         })
     }
 
+    /// Hash a file by streaming it through the hasher; returns the path,
+    /// lowercase hex digest, and size.
+    pub async fn file_checksum(
+        &self,
+        path: &str,
+        algorithm: ChecksumAlgorithm,
+    ) -> Result<(String, String, u64), FileSystemError> {
+        let path = self.validator.validate_readable(path, self.grantee)?;
+
+        if !path.is_file() {
+            return Err(FileSystemError::NotAFile {
+                path: path_utils::to_protocol_path(&path),
+            });
+        }
+
+        let size = fs::metadata(&path)
+            .await
+            .map_err(|e| FileSystemError::IoError {
+                message: e.to_string(),
+            })?
+            .len();
+        if size > self.config.max_read_size {
+            return Err(FileSystemError::FileTooLarge {
+                path: path_utils::to_protocol_path(&path),
+                size,
+                max_size: self.config.max_read_size,
+            });
+        }
+
+        let mut file = fs::File::open(&path)
+            .await
+            .map_err(|e| FileSystemError::IoError {
+                message: e.to_string(),
+            })?;
+        let mut md5_ctx = md5::Context::new();
+        let mut sha256 = Sha256::new();
+        let mut buffer = vec![0u8; 64 * 1024];
+        let mut hashed = 0u64;
+        loop {
+            let n = file
+                .read(&mut buffer)
+                .await
+                .map_err(|e| FileSystemError::IoError {
+                    message: e.to_string(),
+                })?;
+            if n == 0 {
+                break;
+            }
+            hashed += n as u64;
+            // The file may have grown since the size check.
+            if hashed > self.config.max_read_size {
+                return Err(FileSystemError::FileTooLarge {
+                    path: path_utils::to_protocol_path(&path),
+                    size: hashed,
+                    max_size: self.config.max_read_size,
+                });
+            }
+            match algorithm {
+                ChecksumAlgorithm::Md5 => md5_ctx.consume(&buffer[..n]),
+                ChecksumAlgorithm::Sha256 => sha256.update(&buffer[..n]),
+            }
+        }
+
+        let checksum = match algorithm {
+            ChecksumAlgorithm::Md5 => format!("{:x}", md5_ctx.compute()),
+            ChecksumAlgorithm::Sha256 => format!("{:x}", sha256.finalize()),
+        };
+        Ok((path_utils::to_protocol_path(&path), checksum, hashed))
+    }
+
     pub async fn read_file_chunk(
         &self,
         path: &str,
@@ -226,7 +696,7 @@ impl FileOperations {
                 message: "Chunk size must be greater than zero".to_string(),
             });
         }
-        let path = self.validator.validate_existing(path)?;
+        let path = self.validator.validate_readable(path, self.grantee)?;
 
         if !path.is_file() {
             return Err(FileSystemError::NotAFile {
@@ -305,6 +775,175 @@ impl FileOperations {
         ))
     }
 
+    /// md5 of every `chunk_size` chunk of a file, in the same form
+    /// `read_file_chunk` reports, so an interrupted download can be checked
+    /// and resumed. Returns the path, total size, and checksums.
+    pub async fn file_manifest(
+        &self,
+        path: &str,
+        chunk_size: u64,
+    ) -> Result<(String, u64, Vec<String>), FileSystemError> {
+        if chunk_size == 0 {
+            return Err(FileSystemError::IoError {
+                message: "Chunk size must be greater than zero".to_string(),
+            });
+        }
+        let path = self.validator.validate_readable(path, self.grantee)?;
+        if !path.is_file() {
+            return Err(FileSystemError::NotAFile {
+                path: path_utils::to_protocol_path(&path),
+            });
+        }
+        let io_err = |e: std::io::Error| FileSystemError::IoError {
+            message: e.to_string(),
+        };
+
+        let size = fs::metadata(&path).await.map_err(io_err)?.len();
+        if size > self.config.max_read_size {
+            return Err(FileSystemError::FileTooLarge {
+                path: path_utils::to_protocol_path(&path),
+                size,
+                max_size: self.config.max_read_size,
+            });
+        }
+
+        let mut file = fs::File::open(&path).await.map_err(io_err)?;
+        let mut checksums = Vec::new();
+        let mut buffer = Vec::with_capacity(chunk_size.min(size) as usize);
+        loop {
+            buffer.clear();
+            (&mut file)
+                .take(chunk_size)
+                .read_to_end(&mut buffer)
+                .await
+                .map_err(io_err)?;
+            // An empty file is still one (empty) chunk, as in `read_file_chunk`.
+            if buffer.is_empty() && !checksums.is_empty() {
+                break;
+            }
+            checksums.push(format!("{:x}", md5::compute(&buffer)));
+            if (buffer.len() as u64) < chunk_size {
+                break;
+            }
+        }
+        Ok((path_utils::to_protocol_path(&path), size, checksums))
+    }
+
+    /// Start following a file: returns its protocol path, the last
+    /// `from_end_bytes` (capped at `max_read_size`), and the current size,
+    /// which is the offset to pass to `read_appended` next.
+    pub async fn tail_file(
+        &self,
+        path: &str,
+        from_end_bytes: u64,
+    ) -> Result<(String, Vec<u8>, u64), FileSystemError> {
+        let path = self.validator.validate_readable(path, self.grantee)?;
+        if !path.is_file() {
+            return Err(FileSystemError::NotAFile {
+                path: path_utils::to_protocol_path(&path),
+            });
+        }
+        let size = file_len(&path).await?;
+        let len = from_end_bytes.min(self.config.max_read_size).min(size);
+        let data = read_range(&path, size - len, len).await?;
+        Ok((path_utils::to_protocol_path(&path), data, size))
+    }
+
+    /// Read up to `max_len` bytes appended after `offset`; returns the bytes
+    /// and the offset to continue from.
+    ///
+    /// A file that is now shorter than `offset` was truncated or rotated, so
+    /// reading restarts from the beginning.
+    pub async fn read_appended(
+        &self,
+        path: &str,
+        offset: u64,
+        max_len: u64,
+    ) -> Result<(Vec<u8>, u64), FileSystemError> {
+        let path = self.validator.validate_readable(path, self.grantee)?;
+        let size = file_len(&path).await?;
+        let offset = if offset > size { 0 } else { offset };
+        let data = read_range(&path, offset, max_len.min(size - offset)).await?;
+        let next = offset + data.len() as u64;
+        Ok((data, next))
+    }
+
+    /// Read an inclusive, 1-based line range.
+    ///
+    /// Lines are streamed rather than loading the whole file. Scanning stops at
+    /// `max_read_size` bytes; anything past that is reported as `truncated`.
+    pub async fn read_file_lines(
+        &self,
+        path: &str,
+        start_line: u64,
+        end_line: u64,
+    ) -> Result<FileLines, FileSystemError> {
+        if start_line == 0 || end_line < start_line {
+            return Err(FileSystemError::IoError {
+                message: "Invalid line range".to_string(),
+            });
+        }
+        let path = self.validator.validate_readable(path, self.grantee)?;
+
+        if !path.is_file() {
+            return Err(FileSystemError::NotAFile {
+                path: path_utils::to_protocol_path(&path),
+            });
+        }
+
+        let file = fs::File::open(&path)
+            .await
+            .map_err(|e| FileSystemError::IoError {
+                message: e.to_string(),
+            })?;
+        let mut reader = tokio::io::BufReader::new(file);
+
+        let mut lines = Vec::new();
+        let mut line = Vec::new();
+        let mut line_no = 0u64;
+        let mut bytes_scanned = 0u64;
+        let mut truncated = false;
+        loop {
+            if bytes_scanned >= self.config.max_read_size {
+                truncated = !reader
+                    .fill_buf()
+                    .await
+                    .map_err(|e| FileSystemError::IoError {
+                        message: e.to_string(),
+                    })?
+                    .is_empty();
+                break;
+            }
+            line.clear();
+            // Bounded so one huge line can't be buffered whole; a line cut at
+            // the cap is returned as far as it got and the result is truncated.
+            let remaining = self.config.max_read_size - bytes_scanned;
+            let read = (&mut reader)
+                .take(remaining)
+                .read_until(b'\n', &mut line)
+                .await
+                .map_err(|e| FileSystemError::IoError {
+                    message: e.to_string(),
+                })?;
+            if read == 0 {
+                break;
+            }
+            bytes_scanned += read as u64;
+            line_no += 1;
+            if line_no >= start_line && line_no <= end_line {
+                lines.push(decode_line(&line, line_no == 1));
+            }
+        }
+
+        Ok(FileLines {
+            path: path_utils::to_protocol_path(&path),
+            start_line,
+            lines,
+            total_lines: line_no,
+            truncated,
+        })
+    }
+
     /// Write file contents
     pub async fn write_file(
         &self,
@@ -312,7 +951,81 @@ impl FileOperations {
         content: &str,
         encoding: FileEncoding,
         create_parents: bool,
+        expected_modified: Option<u64>,
     ) -> Result<(), FileSystemError> {
+        let (path, bytes) = self
+            .prepare_write(path, content, encoding, create_parents)
+            .await?;
+
+        let temp_path = sibling_with_suffix(&path, &format!("tmp-{}", uuid::Uuid::new_v4()));
+
+        if let Err(e) = fs::write(&temp_path, &bytes).await {
+            return Err(map_not_a_directory_error(&path, e));
+        }
+
+        // Checked as late as possible so an edit landing while the temp file
+        // was written is still caught.
+        if let Some(expected) = expected_modified {
+            let current_modified = modified_ms(&path).await;
+            if current_modified != Some(expected) {
+                let _ = fs::remove_file(&temp_path).await;
+                return Err(FileSystemError::Conflict {
+                    path: path_utils::to_protocol_path(&path),
+                    current_modified,
+                });
+            }
+        }
+
+        replace_with_temp(&path, &temp_path, self.config.atomic_backup).await
+    }
+
+    /// Write a file that must not exist yet. The name is claimed atomically,
+    /// so of two writers racing for it one gets `AlreadyExists`.
+    pub async fn create_file(
+        &self,
+        path: &str,
+        content: &str,
+        encoding: FileEncoding,
+    ) -> Result<(), FileSystemError> {
+        let (path, bytes) = self.prepare_write(path, content, encoding, false).await?;
+
+        let mut file = match fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)
+            .await
+        {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                return Err(FileSystemError::AlreadyExists {
+                    path: path_utils::to_protocol_path(&path),
+                });
+            }
+            Err(e) => return Err(map_not_a_directory_error(&path, e)),
+        };
+        let written = match file.write_all(&bytes).await {
+            Ok(()) => file.sync_all().await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = written {
+            drop(file);
+            let _ = fs::remove_file(&path).await;
+            return Err(FileSystemError::IoError {
+                message: e.to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Checks shared by `write_file` and `create_file`: the resolved target
+    /// and the decoded bytes to put there.
+    async fn prepare_write(
+        &self,
+        path: &str,
+        content: &str,
+        encoding: FileEncoding,
+        create_parents: bool,
+    ) -> Result<(PathBuf, Vec<u8>), FileSystemError> {
         let path = self.validator.resolve_new_path(path, create_parents)?;
 
         if !self.validator.is_writable(&path) {
@@ -360,68 +1073,195 @@ impl FileOperations {
             path_utils::create_parent_dirs_safe(&path).await?;
         }
 
-        let temp_path = sibling_with_suffix(&path, &format!("tmp-{}", uuid::Uuid::new_v4()));
+        Ok((path, bytes))
+    }
 
-        if let Err(e) = fs::write(&temp_path, &bytes).await {
-            return Err(map_not_a_directory_error(&path, e));
+    /// Write one base64 chunk of a large file. Chunks are appended in order to
+    /// a temp file tracked by `upload_id`, and the last chunk replaces `path`
+    /// with the same backup/rollback as `write_file`. Chunk 0 (re)starts the
+    /// upload; resending the most recent chunk is acknowledged without
+    /// appending it twice.
+    pub async fn write_file_chunk(
+        &self,
+        path: &str,
+        upload_id: &str,
+        chunk_index: u64,
+        total_chunks: u64,
+        data: &str,
+        checksum: &str,
+    ) -> Result<ChunkProgress, FileSystemError> {
+        let path = self.validator.resolve_new_path(path, false)?;
+        let protocol_path = path_utils::to_protocol_path(&path);
+
+        if !self.validator.is_writable(&path) {
+            return Err(FileSystemError::PermissionDenied {
+                path: protocol_path,
+                reason: "Path is read-only".to_string(),
+            });
+        }
+        if path.is_dir() {
+            return Err(FileSystemError::NotAFile {
+                path: protocol_path,
+            });
         }
 
-        let mut backup_path = None;
-        if path.exists() {
-            let backup = sibling_with_suffix(&path, "bak");
-            let _ = fs::remove_file(&backup).await;
-            if let Err(e) = fs::rename(&path, &backup).await {
-                let _ = fs::remove_file(&temp_path).await;
-                let mapped = map_not_a_directory_error(&path, e);
-                return Err(match mapped {
-                    FileSystemError::IoError { message } => FileSystemError::IoError {
-                        message: format!("Failed to backup existing file: {}", message),
-                    },
-                    other => other,
+        let bytes = BASE64
+            .decode(data)
+            .map_err(|_| FileSystemError::InvalidEncoding {
+                path: protocol_path.clone(),
+            })?;
+        if !format!("{:x}", md5::compute(&bytes)).eq_ignore_ascii_case(checksum.trim()) {
+            return Err(FileSystemError::ChecksumMismatch {
+                path: protocol_path,
+                chunk_index,
+            });
+        }
+
+        if chunk_index == 0 {
+            self.expire_chunked_writes().await;
+            // A restarted upload replaces any earlier attempt with the same id.
+            drop(self.take_chunked_write(upload_id));
+            if total_chunks == 0 {
+                return Err(FileSystemError::UnexpectedChunk {
+                    path: protocol_path,
+                    expected_index: 0,
                 });
             }
-            backup_path = Some(backup);
-        }
-
-        if let Err(e) = fs::rename(&temp_path, &path).await {
-            let mut restore_error = None;
-            if let Some(ref backup) = backup_path {
-                if let Err(restore) = fs::rename(backup, &path).await {
-                    if let Err(copy_err) = fs::copy(backup, &path).await {
-                        restore_error = Some(format!(
-                            "Failed to restore backup at {}: {}; copy failed: {}",
-                            backup.display(),
-                            restore,
-                            copy_err
-                        ));
-                    } else {
-                        restore_error = Some(format!(
-                            "Restored from backup copy after rename failure; backup retained at {}",
-                            backup.display()
-                        ));
-                    }
-                }
+            self.check_chunked_size(&path, bytes.len() as u64)?;
+            if let Some(parent) = path.parent() {
+                path_utils::validate_parent_components(parent).await?;
             }
-            let _ = fs::remove_file(&temp_path).await;
-            let mapped = map_not_a_directory_error(&path, e);
-            return Err(match mapped {
-                FileSystemError::IoError { message } => {
-                    let message = if let Some(restore_error) = restore_error {
-                        format!("Failed to replace file: {}; {}", message, restore_error)
-                    } else {
-                        message
-                    };
-                    FileSystemError::IoError { message }
-                }
-                other => other,
+
+            let temp_path = sibling_with_suffix(&path, &format!("tmp-{}", uuid::Uuid::new_v4()));
+            if let Err(e) = fs::write(&temp_path, &bytes).await {
+                return Err(map_not_a_directory_error(&path, e));
+            }
+            let upload = ChunkedWrite {
+                path,
+                temp_path,
+                total_chunks,
+                next_index: 1,
+                written: bytes.len() as u64,
+                touched: Instant::now(),
+            };
+            return self.finish_chunk(upload_id, upload).await;
+        }
+
+        let Some(mut upload) = self.take_chunked_write(upload_id) else {
+            return Err(FileSystemError::UnexpectedChunk {
+                path: protocol_path,
+                expected_index: 0,
+            });
+        };
+        if upload.path != path
+            || upload.total_chunks != total_chunks
+            || chunk_index != upload.next_index
+        {
+            let expected_index = upload.next_index;
+            let is_resend = upload.path == path && chunk_index + 1 == upload.next_index;
+            let written = upload.written;
+            self.put_chunked_write(upload_id, upload);
+            if is_resend {
+                return Ok(ChunkProgress {
+                    path: protocol_path,
+                    bytes_written: written,
+                    is_complete: false,
+                });
+            }
+            return Err(FileSystemError::UnexpectedChunk {
+                path: protocol_path,
+                expected_index,
             });
         }
 
-        if let Some(backup) = backup_path {
-            let _ = fs::remove_file(backup).await;
-        }
+        let written = upload.written + bytes.len() as u64;
+        self.check_chunked_size(&path, written)?;
+        let appended = async {
+            let mut file = fs::OpenOptions::new()
+                .append(true)
+                .open(&upload.temp_path)
+                .await?;
+            file.write_all(&bytes).await?;
+            file.flush().await
+        }
+        .await;
+        if let Err(e) = appended {
+            return Err(FileSystemError::IoError {
+                message: e.to_string(),
+            });
+        }
+
+        upload.next_index += 1;
+        upload.written = written;
+        upload.touched = Instant::now();
+        self.finish_chunk(upload_id, upload).await
+    }
+
+    /// Commit the upload after its last chunk, or park it for the next one.
+    async fn finish_chunk(
+        &self,
+        upload_id: &str,
+        upload: ChunkedWrite,
+    ) -> Result<ChunkProgress, FileSystemError> {
+        let progress = ChunkProgress {
+            path: path_utils::to_protocol_path(&upload.path),
+            bytes_written: upload.written,
+            is_complete: upload.next_index >= upload.total_chunks,
+        };
+        if progress.is_complete {
+            replace_with_temp(&upload.path, &upload.temp_path, self.config.atomic_backup).await?;
+        } else {
+            self.put_chunked_write(upload_id, upload);
+        }
+        Ok(progress)
+    }
+
+    fn check_chunked_size(&self, path: &Path, size: u64) -> Result<(), FileSystemError> {
+        if size > self.config.max_write_size {
+            return Err(FileSystemError::FileTooLarge {
+                path: path_utils::to_protocol_path(path),
+                size,
+                max_size: self.config.max_write_size,
+            });
+        }
+        Ok(())
+    }
+
+    fn take_chunked_write(&self, upload_id: &str) -> Option<ChunkedWrite> {
+        self.chunked_writes
+            .0
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(upload_id)
+    }
 
-        Ok(())
+    fn put_chunked_write(&self, upload_id: &str, upload: ChunkedWrite) {
+        self.chunked_writes
+            .0
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(upload_id.to_string(), upload);
+    }
+
+    /// Drop abandoned chunked writes, and with them their temp files.
+    async fn expire_chunked_writes(&self) {
+        let expired: Vec<ChunkedWrite> = {
+            let mut writes = self
+                .chunked_writes
+                .0
+                .lock()
+                .unwrap_or_else(|e| e.into_inner());
+            let stale: Vec<String> = writes
+                .iter()
+                .filter(|(_, upload)| upload.touched.elapsed() > CHUNKED_WRITE_TTL)
+                .map(|(id, _)| id.clone())
+                .collect();
+            stale.iter().filter_map(|id| writes.remove(id)).collect()
+        };
+        // Removing the temp files blocks; keep it off the runtime threads.
+        if !expired.is_empty() {
+            let _ = tokio::task::spawn_blocking(move || drop(expired)).await;
+        }
     }
 
     /// Create directory
@@ -480,8 +1320,8 @@ impl FileOperations {
         Ok(())
     }
 
-    /// Delete file or directory
-    pub async fn delete_path(&self, path: &str, recursive: bool) -> Result<(), FileSystemError> {
+    /// Validation shared by `delete_path` and `delete_dry_run`.
+    async fn check_delete(&self, path: &str, recursive: bool) -> Result<PathBuf, FileSystemError> {
         let path = self.validator.validate_existing(path)?;
 
         if !self.validator.is_writable(&path) {
@@ -491,26 +1331,34 @@ impl FileOperations {
             });
         }
 
+        if path.is_dir() && !recursive {
+            let mut read_dir = fs::read_dir(&path)
+                .await
+                .map_err(|e| FileSystemError::IoError {
+                    message: e.to_string(),
+                })?;
+            if read_dir
+                .next_entry()
+                .await
+                .map_err(|e| FileSystemError::IoError {
+                    message: e.to_string(),
+                })?
+                .is_some()
+            {
+                return Err(FileSystemError::NotEmpty {
+                    path: path_utils::to_protocol_path(&path),
+                });
+            }
+        }
+        Ok(path)
+    }
+
+    /// Delete file or directory
+    pub async fn delete_path(&self, path: &str, recursive: bool) -> Result<(), FileSystemError> {
+        let path = self.check_delete(path, recursive).await?;
+
         if path.is_dir() {
             if !recursive {
-                let mut read_dir =
-                    fs::read_dir(&path)
-                        .await
-                        .map_err(|e| FileSystemError::IoError {
-                            message: e.to_string(),
-                        })?;
-                if read_dir
-                    .next_entry()
-                    .await
-                    .map_err(|e| FileSystemError::IoError {
-                        message: e.to_string(),
-                    })?
-                    .is_some()
-                {
-                    return Err(FileSystemError::NotEmpty {
-                        path: path_utils::to_protocol_path(&path),
-                    });
-                }
                 fs::remove_dir(&path)
                     .await
                     .map_err(|e| FileSystemError::IoError {
@@ -534,8 +1382,84 @@ impl FileOperations {
         Ok(())
     }
 
-    /// Rename file or directory
-    pub async fn rename_path(&self, old_path: &str, new_path: &str) -> Result<(), FileSystemError> {
+    /// List what `delete_path` would remove, without removing anything.
+    /// Symlinks are listed but not followed.
+    pub async fn delete_dry_run(
+        &self,
+        path: &str,
+        recursive: bool,
+    ) -> Result<DryRun, FileSystemError> {
+        let path = self.check_delete(path, recursive).await?;
+        let io_err = |e: std::io::Error| FileSystemError::IoError {
+            message: e.to_string(),
+        };
+        let limit = self.config.max_list_entries;
+
+        let mut dry_run = DryRun::default();
+        dry_run.affect(&path, limit);
+        let is_dir = fs::symlink_metadata(&path).await.map_err(io_err)?.is_dir();
+        let mut stack = if is_dir { vec![path] } else { Vec::new() };
+        while let Some(dir) = stack.pop() {
+            let mut read_dir = fs::read_dir(&dir).await.map_err(io_err)?;
+            while let Some(entry) = read_dir.next_entry().await.map_err(io_err)? {
+                if dry_run.truncated {
+                    return Ok(dry_run);
+                }
+                dry_run.affect(&entry.path(), limit);
+                if entry.file_type().await.map_err(io_err)?.is_dir() {
+                    stack.push(entry.path());
+                }
+            }
+        }
+        Ok(dry_run)
+    }
+
+    /// Count what deleting `path` recursively would remove, without removing
+    /// anything. Symlinks are counted as entries but not followed.
+    pub async fn delete_preview(&self, path: &str) -> Result<DeletePreview, FileSystemError> {
+        let path = self.validator.validate_existing(path)?;
+        let io_err = |e: std::io::Error| FileSystemError::IoError {
+            message: e.to_string(),
+        };
+
+        let root_meta = fs::symlink_metadata(&path).await.map_err(io_err)?;
+        let mut preview = DeletePreview {
+            path: path_utils::to_protocol_path(&path),
+            is_directory: root_meta.is_dir(),
+            entry_count: 1,
+            total_bytes: if root_meta.is_file() {
+                root_meta.len()
+            } else {
+                0
+            },
+        };
+        let mut stack = if root_meta.is_dir() {
+            vec![path]
+        } else {
+            Vec::new()
+        };
+        while let Some(dir) = stack.pop() {
+            let mut read_dir = fs::read_dir(&dir).await.map_err(io_err)?;
+            while let Some(entry) = read_dir.next_entry().await.map_err(io_err)? {
+                let meta = fs::symlink_metadata(entry.path()).await.map_err(io_err)?;
+                preview.entry_count += 1;
+                if meta.is_dir() {
+                    stack.push(entry.path());
+                } else if meta.is_file() {
+                    preview.total_bytes += meta.len();
+                }
+            }
+        }
+        Ok(preview)
+    }
+
+    /// Validation shared by `rename_path` and `rename_dry_run`, short of the
+    /// destination already existing.
+    fn check_rename(
+        &self,
+        old_path: &str,
+        new_path: &str,
+    ) -> Result<(PathBuf, PathBuf), FileSystemError> {
         let old_path = self.validator.validate_existing(old_path)?;
         let new_path = self.validator.resolve_new_path(new_path, false)?;
 
@@ -545,6 +1469,30 @@ impl FileOperations {
                 reason: "Path is read-only".to_string(),
             });
         }
+        Ok((old_path, new_path))
+    }
+
+    /// Report what `rename_path` would do: the path moved and, if the new
+    /// name is taken, the conflict that would make it fail.
+    pub fn rename_dry_run(
+        &self,
+        old_path: &str,
+        new_path: &str,
+    ) -> Result<DryRun, FileSystemError> {
+        let (old_path, new_path) = self.check_rename(old_path, new_path)?;
+        let mut dry_run = DryRun::default();
+        dry_run.affect(&old_path, usize::MAX);
+        if new_path.exists() {
+            dry_run
+                .conflicts
+                .push(path_utils::to_protocol_path(&new_path));
+        }
+        Ok(dry_run)
+    }
+
+    /// Rename file or directory
+    pub async fn rename_path(&self, old_path: &str, new_path: &str) -> Result<(), FileSystemError> {
+        let (old_path, new_path) = self.check_rename(old_path, new_path)?;
 
         if new_path.exists() {
             return Err(FileSystemError::AlreadyExists {
@@ -552,22 +1500,61 @@ impl FileOperations {
             });
         }
 
-        fs::rename(&old_path, &new_path)
-            .await
-            .map_err(|e| FileSystemError::IoError {
+        match fs::rename(&old_path, &new_path).await {
+            Ok(()) => Ok(()),
+            Err(e) if is_cross_device_error(&e) => self.move_by_copy(&old_path, &new_path).await,
+            Err(e) => Err(FileSystemError::IoError {
                 message: e.to_string(),
-            })?;
+            }),
+        }
+    }
 
-        Ok(())
+    /// Cross-filesystem fallback for `rename_path`: copy, then delete the source.
+    ///
+    /// A failed copy removes the partial destination it created, never one
+    /// that was already there; a failed delete leaves both copies in place
+    /// and says so.
+    pub(super) async fn move_by_copy(
+        &self,
+        old_path: &Path,
+        new_path: &Path,
+    ) -> Result<(), FileSystemError> {
+        let old = path_utils::to_protocol_path(old_path);
+        let new = path_utils::to_protocol_path(new_path);
+
+        let existed = fs::symlink_metadata(new_path).await.is_ok();
+        if let Err(e) = self.copy_path(&old, &new, true, true).await {
+            // `AlreadyExists` means someone else created it after the check.
+            if existed || matches!(e, FileSystemError::AlreadyExists { .. }) {
+                return Err(e);
+            }
+            if new_path.is_dir() {
+                let _ = fs::remove_dir_all(new_path).await;
+            } else if new_path.exists() {
+                let _ = fs::remove_file(new_path).await;
+            }
+            return Err(e);
+        }
+
+        self.delete_path(&old, true)
+            .await
+            .map_err(|e| FileSystemError::IoError {
+                message: format!(
+                    "Copied {} to {} but could not remove the original ({:?}); both copies remain",
+                    old, new, e
+                ),
+            })
     }
 
-    /// Copy file or directory
-    pub async fn copy_path(
+    /// Validation shared by `copy_path` and `copy_dry_run`, short of the
+    /// destination already existing and the per-entry checks of a
+    /// recursive copy.
+    async fn check_copy(
         &self,
         source: &str,
         destination: &str,
         recursive: bool,
-    ) -> Result<(), FileSystemError> {
+    ) -> Result<(PathBuf, PathBuf), FileSystemError> {
         let source = self.validator.validate_existing(source)?;
         let destination = self.validator.resolve_new_path(destination, recursive)?;
 
@@ -578,25 +1565,12 @@ impl FileOperations {
             });
         }
 
-        if destination.exists() {
-            return Err(FileSystemError::AlreadyExists {
-                path: path_utils::to_protocol_path(&destination),
-            });
-        }
-
         if source.is_dir() {
             if !recursive {
                 return Err(FileSystemError::NotADirectory {
                     path: path_utils::to_protocol_path(&source),
                 });
             }
-            copy_dir_recursive(
-                &source,
-                &destination,
-                self.validator.as_ref(),
-                self.config.as_ref(),
-            )
-            .await?;
         } else {
             let metadata = fs::metadata(&source)
                 .await
@@ -610,19 +1584,124 @@ impl FileOperations {
                     max_size: self.config.max_write_size,
                 });
             }
+        }
+        Ok((source, destination))
+    }
+
+    /// List the paths `copy_path` would create, running the same checks
+    /// (denied patterns, read-only paths, symlinks) without writing.
+    pub async fn copy_dry_run(
+        &self,
+        source: &str,
+        destination: &str,
+        recursive: bool,
+    ) -> Result<DryRun, FileSystemError> {
+        let (source, destination) = self.check_copy(source, destination, recursive).await?;
+        let mut dry_run = DryRun::default();
+        if destination.exists() {
+            dry_run
+                .conflicts
+                .push(path_utils::to_protocol_path(&destination));
+        }
+        if source.is_dir() {
+            let mut planned = Vec::new();
+            copy_dir_recursive(
+                &source,
+                &destination,
+                self.validator.as_ref(),
+                self.config.as_ref(),
+                false,
+                Some(&mut planned),
+            )
+            .await?;
+            for path in &planned {
+                dry_run.affect(path, self.config.max_list_entries);
+            }
+        } else {
+            dry_run.affect(&destination, usize::MAX);
+        }
+        Ok(dry_run)
+    }
+
+    /// Copy file or directory
+    pub async fn copy_path(
+        &self,
+        source: &str,
+        destination: &str,
+        recursive: bool,
+        preserve_metadata: bool,
+    ) -> Result<(), FileSystemError> {
+        let (source, destination) = self.check_copy(source, destination, recursive).await?;
+
+        if destination.exists() {
+            return Err(FileSystemError::AlreadyExists {
+                path: path_utils::to_protocol_path(&destination),
+            });
+        }
+
+        if source.is_dir() {
+            copy_dir_recursive(
+                &source,
+                &destination,
+                self.validator.as_ref(),
+                self.config.as_ref(),
+                preserve_metadata,
+                None,
+            )
+            .await?;
+        } else {
             fs::copy(&source, &destination)
                 .await
                 .map_err(|e| FileSystemError::IoError {
                     message: e.to_string(),
                 })?;
+            if preserve_metadata {
+                copy_metadata(&source, &destination);
+            }
         }
 
         Ok(())
     }
 
+    /// Report whether `path` exists. Traversal, out-of-root, and denied paths
+    /// are still errors; a missing in-root path is simply `exists: false`.
+    pub async fn path_exists(&self, path: &str) -> Result<PathPresence, FileSystemError> {
+        let resolved = match self.validator.resolve_new_path(path, true) {
+            Ok(resolved) => resolved,
+            // A file where a parent directory should be: nothing can exist below it.
+            Err(FileSystemError::NotADirectory { .. }) => {
+                return Ok(PathPresence {
+                    path: path.to_string(),
+                    exists: false,
+                    is_directory: false,
+                    is_file: false,
+                });
+            }
+            Err(e) => return Err(e),
+        };
+        let path = path_utils::to_protocol_path(&resolved);
+        match fs::metadata(&resolved).await {
+            Ok(metadata) => Ok(PathPresence {
+                path,
+                exists: true,
+                is_directory: metadata.is_dir(),
+                is_file: metadata.is_file(),
+            }),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(PathPresence {
+                path,
+                exists: false,
+                is_directory: false,
+                is_file: false,
+            }),
+            Err(e) => Err(FileSystemError::IoError {
+                message: e.to_string(),
+            }),
+        }
+    }
+
     /// Get file info
     pub async fn get_file_info(&self, path: &str) -> Result<FileEntry, FileSystemError> {
-        let path = self.validator.validate_existing(path)?;
+        let path = self.validator.validate_readable(path, self.grantee)?;
         let name = path
             .file_name()
             .and_then(|n| n.to_str())
@@ -668,10 +1747,7 @@ impl FileOperations {
             .modified()
             .map(|t| t.duration_since(std::time::UNIX_EPOCH).unwrap().as_millis() as u64)
             .unwrap_or(0);
-        let created = file_metadata
-            .created()
-            .ok()
-            .map(|t| t.duration_since(std::time::UNIX_EPOCH).unwrap().as_millis() as u64);
+        let created = platform::created_ms(path, &file_metadata);
 
         let is_hidden = platform::is_hidden(path);
         let permissions = Some(platform::format_permissions(&file_metadata));
@@ -682,6 +1758,30 @@ impl FileOperations {
             Some(mime::guess_mime_from_extension(name))
         };
 
+        let is_likely_text = match mime_type.as_deref() {
+            None => None,
+            Some(mime_type) => match mime::text_hint_from_mime(mime_type) {
+                Some(hint) => Some(hint),
+                None if size <= TEXT_PEEK_MAX_FILE_SIZE => peek_is_text(path).await,
+                None => None,
+            },
+        };
+
+        let line_count = if is_likely_text == Some(true) && size <= LINE_COUNT_MAX_FILE_SIZE {
+            count_lines(path).await
+        } else {
+            None
+        };
+
+        let image_dimensions = match mime_type.as_deref() {
+            Some(mime_type)
+                if mime_type.starts_with("image/") && size <= IMAGE_DIMENSIONS_MAX_FILE_SIZE =>
+            {
+                peek_image_dimensions(path).await
+            }
+            _ => None,
+        };
+
         let symlink_target = if is_symlink {
             std::fs::read_link(path)
                 .ok()
@@ -703,18 +1803,221 @@ impl FileOperations {
             permissions,
             symlink_target,
             git_status,
+            is_likely_text,
+            image_dimensions,
+            line_count,
+        })
+    }
+}
+
+/// Move a fully written temp file over `path`. With `backup`, the old contents
+/// are kept as a `.bak` until the rename succeeds and restored if it fails.
+async fn replace_with_temp(
+    path: &Path,
+    temp_path: &Path,
+    backup: bool,
+) -> Result<(), FileSystemError> {
+    let mut backup_path = None;
+    if backup && path.exists() {
+        let backup = sibling_with_suffix(path, "bak");
+        let _ = fs::remove_file(&backup).await;
+        if let Err(e) = fs::rename(path, &backup).await {
+            let _ = fs::remove_file(temp_path).await;
+            let mapped = map_not_a_directory_error(path, e);
+            return Err(match mapped {
+                FileSystemError::IoError { message } => FileSystemError::IoError {
+                    message: format!("Failed to backup existing file: {}", message),
+                },
+                other => other,
+            });
+        }
+        backup_path = Some(backup);
+    }
+
+    if let Err(e) = fs::rename(temp_path, path).await {
+        let mut restore_error = None;
+        if let Some(ref backup) = backup_path {
+            if let Err(restore) = fs::rename(backup, path).await {
+                if let Err(copy_err) = fs::copy(backup, path).await {
+                    restore_error = Some(format!(
+                        "Failed to restore backup at {}: {}; copy failed: {}",
+                        backup.display(),
+                        restore,
+                        copy_err
+                    ));
+                } else {
+                    restore_error = Some(format!(
+                        "Restored from backup copy after rename failure; backup retained at {}",
+                        backup.display()
+                    ));
+                }
+            }
+        }
+        let _ = fs::remove_file(temp_path).await;
+        let mapped = map_not_a_directory_error(path, e);
+        return Err(match mapped {
+            FileSystemError::IoError { message } => {
+                let message = if let Some(restore_error) = restore_error {
+                    format!("Failed to replace file: {}; {}", message, restore_error)
+                } else {
+                    message
+                };
+                FileSystemError::IoError { message }
+            }
+            other => other,
+        });
+    }
+
+    if let Some(backup) = backup_path {
+        let _ = fs::remove_file(backup).await;
+    }
+
+    Ok(())
+}
+
+/// Listing entry with only the fields a tree view needs: one `stat` (two for
+/// a symlink), no git, permission, MIME, or content work. A symlink whose
+/// target is missing is still listed, as a link.
+async fn build_compact_entry(path: &Path, name: &str) -> Result<FileEntry, FileSystemError> {
+    let link_metadata = fs::symlink_metadata(path)
+        .await
+        .map_err(|e| FileSystemError::IoError {
+            message: e.to_string(),
+        })?;
+    let is_symlink = link_metadata.file_type().is_symlink();
+    let metadata = if is_symlink {
+        fs::metadata(path).await.unwrap_or(link_metadata)
+    } else {
+        link_metadata
+    };
+    let is_directory = metadata.is_dir();
+    Ok(FileEntry {
+        name: name.to_string(),
+        path: path_utils::to_protocol_path(path),
+        is_directory,
+        is_symlink,
+        is_hidden: platform::is_hidden(path),
+        size: if is_directory { 0 } else { metadata.len() },
+        modified: metadata
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0),
+        created: None,
+        mime_type: None,
+        permissions: None,
+        symlink_target: None,
+        git_status: None,
+        is_likely_text: None,
+        image_dimensions: None,
+        line_count: None,
+    })
+}
+
+/// Modification time in ms since the epoch, as reported in `FileEntry`.
+async fn modified_ms(path: &Path) -> Option<u64> {
+    let modified = fs::metadata(path).await.ok()?.modified().ok()?;
+    modified
+        .duration_since(std::time::UNIX_EPOCH)
+        .ok()
+        .map(|d| d.as_millis() as u64)
+}
+
+async fn file_len(path: &Path) -> Result<u64, FileSystemError> {
+    fs::metadata(path)
+        .await
+        .map(|meta| meta.len())
+        .map_err(|e| FileSystemError::IoError {
+            message: e.to_string(),
         })
+}
+
+async fn read_range(path: &Path, offset: u64, len: u64) -> Result<Vec<u8>, FileSystemError> {
+    let io_err = |e: std::io::Error| FileSystemError::IoError {
+        message: e.to_string(),
+    };
+    let mut file = fs::File::open(path).await.map_err(io_err)?;
+    file.seek(std::io::SeekFrom::Start(offset))
+        .await
+        .map_err(io_err)?;
+    let mut buffer = Vec::with_capacity(len as usize);
+    file.take(len)
+        .read_to_end(&mut buffer)
+        .await
+        .map_err(io_err)?;
+    Ok(buffer)
+}
+
+/// Sniff the first few KB of a file for the `is_likely_text` listing hint.
+async fn peek_is_text(path: &Path) -> Option<bool> {
+    let mut file = fs::File::open(path).await.ok()?;
+    let mut buffer = vec![0u8; TEXT_PEEK_BYTES];
+    let bytes_read = file.read(&mut buffer).await.ok()?;
+    buffer.truncate(bytes_read);
+    Some(mime::is_probably_text_prefix(&buffer))
+}
+
+/// Count lines for the `line_count` listing hint. A last line without a
+/// trailing newline still counts.
+async fn count_lines(path: &Path) -> Option<u64> {
+    let file = fs::File::open(path).await.ok()?;
+    let mut buffer = Vec::new();
+    file.take(LINE_COUNT_MAX_FILE_SIZE)
+        .read_to_end(&mut buffer)
+        .await
+        .ok()?;
+    let newlines = buffer.iter().filter(|b| **b == b'\n').count() as u64;
+    let unterminated = buffer.last().is_some_and(|b| *b != b'\n');
+    Some(newlines + u64::from(unterminated))
+}
+
+/// Parse image dimensions from the file header for the listing metadata.
+async fn peek_image_dimensions(path: &Path) -> Option<(u32, u32)> {
+    let file = fs::File::open(path).await.ok()?;
+    let mut buffer = Vec::new();
+    file.take(IMAGE_HEADER_PEEK_BYTES)
+        .read_to_end(&mut buffer)
+        .await
+        .ok()?;
+    let size = imagesize::blob_size(&buffer).ok()?;
+    Some((
+        u32::try_from(size.width).ok()?,
+        u32::try_from(size.height).ok()?,
+    ))
+}
+
+/// `rename(2)` cannot move between mounts: EXDEV on Unix, ERROR_NOT_SAME_DEVICE
+/// on Windows.
+fn is_cross_device_error(e: &std::io::Error) -> bool {
+    if cfg!(windows) {
+        e.raw_os_error() == Some(17)
+    } else {
+        e.raw_os_error() == Some(18)
+    }
+}
+
+/// Decode one line for `read_file_lines`, dropping the line terminator and a
+/// leading UTF-8 BOM on the first line.
+fn decode_line(raw: &[u8], first: bool) -> String {
+    let mut raw = raw.strip_suffix(b"\n").unwrap_or(raw);
+    raw = raw.strip_suffix(b"\r").unwrap_or(raw);
+    if first {
+        raw = raw.strip_prefix(&[0xEF, 0xBB, 0xBF]).unwrap_or(raw);
     }
+    String::from_utf8_lossy(raw).into_owned()
 }
 
-fn decode_text_buffer(buffer: &[u8]) -> Option<String> {
+/// Decode a text buffer to UTF-8, returning the text and the name of the
+/// encoding it was decoded from.
+fn decode_text_buffer(buffer: &[u8]) -> Option<(String, &'static str)> {
     if let Ok(content) = std::str::from_utf8(buffer) {
-        return Some(content.to_string());
+        return Some((content.to_string(), "UTF-8"));
     }
 
     if buffer.starts_with(&[0xEF, 0xBB, 0xBF]) {
         if let Ok(content) = std::str::from_utf8(&buffer[3..]) {
-            return Some(content.to_string());
+            return Some((content.to_string(), "UTF-8"));
         }
     }
 
@@ -724,7 +2027,7 @@ fn decode_text_buffer(buffer: &[u8]) -> Option<String> {
             .map(|c| u16::from_le_bytes([c[0], c[1]]))
             .collect();
         if let Ok(content) = String::from_utf16(&utf16) {
-            return Some(content);
+            return Some((content, "UTF-16LE"));
         }
     }
 
@@ -734,11 +2037,29 @@ fn decode_text_buffer(buffer: &[u8]) -> Option<String> {
             .map(|c| u16::from_be_bytes([c[0], c[1]]))
             .collect();
         if let Ok(content) = String::from_utf16(&utf16) {
-            return Some(content);
+            return Some((content, "UTF-16BE"));
         }
     }
 
-    None
+    decode_legacy_text(buffer)
+}
+
+/// Sniff a legacy encoding (Windows-1252, Latin-1, Shift_JIS, ...) and decode it.
+fn decode_legacy_text(buffer: &[u8]) -> Option<(String, &'static str)> {
+    let mut detector = chardetng::EncodingDetector::new();
+    detector.feed(buffer, true);
+    let (detected, confident) = detector.guess_assess(None, true);
+
+    // A low-confidence guess over data with NUL bytes is almost always binary.
+    if !confident && buffer.contains(&0) {
+        return None;
+    }
+
+    let (text, had_errors) = detected.decode_without_bom_handling(buffer);
+    if had_errors || (!confident && !mime::is_probably_text(text.as_bytes())) {
+        return None;
+    }
+    Some((text.into_owned(), detected.name()))
 }
 
 fn sibling_with_suffix(path: &Path, suffix: &str) -> std::path::PathBuf {
@@ -807,7 +2128,20 @@ fn sort_entries(
     });
 }
 
-fn extension_of(name: &str) -> String {
+/// Normalized extension filter (lowercase, no leading dot). `None` when no
+/// usable extension was given, meaning "don't filter".
+pub(super) fn extension_set(
+    extensions: Option<&[String]>,
+) -> Option<std::collections::HashSet<String>> {
+    let set: std::collections::HashSet<String> = extensions?
+        .iter()
+        .map(|ext| ext.trim().trim_start_matches('.').to_lowercase())
+        .filter(|ext| !ext.is_empty())
+        .collect();
+    (!set.is_empty()).then_some(set)
+}
+
+pub(super) fn extension_of(name: &str) -> String {
     Path::new(name)
         .extension()
         .and_then(|e| e.to_str())
@@ -815,19 +2149,26 @@ fn extension_of(name: &str) -> String {
         .to_lowercase()
 }
 
+/// Copy `src` to `dst` entry by entry. With `plan`, nothing is written: the
+/// destination paths are collected there after passing the same checks.
 async fn copy_dir_recursive(
     src: &Path,
     dst: &Path,
     validator: &PathValidator,
     config: &FileSystemConfig,
+    preserve_metadata: bool,
+    mut plan: Option<&mut Vec<PathBuf>>,
 ) -> Result<(), FileSystemError> {
     let mut stack = vec![(src.to_path_buf(), dst.to_path_buf())];
+    // Directories in discovery order (parents before children), so their
+    // metadata can be applied bottom-up once every child has been written.
+    let mut copied_dirs = Vec::new();
 
     while let Some((current_src, current_dst)) = stack.pop() {
-        if validator.is_denied(&current_dst) {
-            return Err(FileSystemError::PermissionDenied {
+        if let Some(pattern) = validator.denied_pattern(&current_dst) {
+            return Err(FileSystemError::DeniedByPattern {
                 path: path_utils::to_protocol_path(&current_dst),
-                reason: "Destination matches denied pattern".to_string(),
+                pattern: pattern.to_string(),
             });
         }
         if !validator.is_writable(&current_dst) {
@@ -837,25 +2178,29 @@ async fn copy_dir_recursive(
             });
         }
 
-        // Check parent paths for files before creating directories
-        super::path_utils::create_parent_dirs_safe(&current_dst).await?;
-        fs::create_dir_all(&current_dst).await.map_err(|e| {
-            if let Some(20) = e.raw_os_error() {
-                // ENOTDIR error - a path component is a file
-                let mut current = std::path::PathBuf::new();
-                for component in current_dst.components() {
-                    current.push(component);
-                    if current.exists() && current.is_file() {
-                        return FileSystemError::NotADirectory {
-                            path: path_utils::to_protocol_path(&current),
-                        };
+        if let Some(plan) = plan.as_deref_mut() {
+            plan.push(current_dst.clone());
+        } else {
+            // Check parent paths for files before creating directories
+            super::path_utils::create_parent_dirs_safe(&current_dst).await?;
+            fs::create_dir_all(&current_dst).await.map_err(|e| {
+                if let Some(20) = e.raw_os_error() {
+                    // ENOTDIR error - a path component is a file
+                    let mut current = std::path::PathBuf::new();
+                    for component in current_dst.components() {
+                        current.push(component);
+                        if current.exists() && current.is_file() {
+                            return FileSystemError::NotADirectory {
+                                path: path_utils::to_protocol_path(&current),
+                            };
+                        }
                     }
                 }
-            }
-            FileSystemError::IoError {
-                message: e.to_string(),
-            }
-        })?;
+                FileSystemError::IoError {
+                    message: e.to_string(),
+                }
+            })?;
+        }
 
         let mut read_dir =
             fs::read_dir(&current_src)
@@ -875,10 +2220,10 @@ async fn copy_dir_recursive(
             let entry_path = entry.path();
             let dest_path = current_dst.join(entry.file_name());
 
-            if validator.is_denied(&entry_path) {
-                return Err(FileSystemError::PermissionDenied {
+            if let Some(pattern) = validator.denied_pattern(&entry_path) {
+                return Err(FileSystemError::DeniedByPattern {
                     path: path_utils::to_protocol_path(&entry_path),
-                    reason: "Source matches denied pattern".to_string(),
+                    pattern: pattern.to_string(),
                 });
             }
 
@@ -907,10 +2252,10 @@ async fn copy_dir_recursive(
             if meta.is_dir() {
                 stack.push((entry_path, dest_path));
             } else {
-                if validator.is_denied(&dest_path) {
-                    return Err(FileSystemError::PermissionDenied {
+                if let Some(pattern) = validator.denied_pattern(&dest_path) {
+                    return Err(FileSystemError::DeniedByPattern {
                         path: path_utils::to_protocol_path(&dest_path),
-                        reason: "Destination matches denied pattern".to_string(),
+                        pattern: pattern.to_string(),
                     });
                 }
                 if !validator.is_writable(&dest_path) {
@@ -919,14 +2264,48 @@ async fn copy_dir_recursive(
                         reason: "Destination is read-only".to_string(),
                     });
                 }
+                if let Some(plan) = plan.as_deref_mut() {
+                    plan.push(dest_path);
+                    continue;
+                }
                 fs::copy(&entry_path, &dest_path)
                     .await
                     .map_err(|e| FileSystemError::IoError {
                         message: e.to_string(),
                     })?;
+                if preserve_metadata {
+                    copy_metadata(&entry_path, &dest_path);
+                }
             }
         }
+
+        if preserve_metadata {
+            copied_dirs.push((current_src, current_dst));
+        }
+    }
+
+    for (dir_src, dir_dst) in copied_dirs.iter().rev() {
+        copy_metadata(dir_src, dir_dst);
     }
 
     Ok(())
 }
+
+/// Best-effort copy of access/modification times and Unix mode bits.
+///
+/// Attributes the platform or filesystem cannot set are skipped; the copied
+/// content is already in place, so this never fails the operation.
+fn copy_metadata(src: &Path, dst: &Path) {
+    let Ok(metadata) = std::fs::metadata(src) else {
+        return;
+    };
+    #[cfg(unix)]
+    if let Err(e) = std::fs::set_permissions(dst, metadata.permissions()) {
+        tracing::debug!(path = %dst.display(), "Could not preserve permissions: {}", e);
+    }
+    let atime = filetime::FileTime::from_last_access_time(&metadata);
+    let mtime = filetime::FileTime::from_last_modification_time(&metadata);
+    if let Err(e) = filetime::set_file_times(dst, atime, mtime) {
+        tracing::debug!(path = %dst.display(), "Could not preserve timestamps: {}", e);
+    }
+}
@@ -39,6 +39,74 @@ pub fn format_permissions(metadata: &std::fs::Metadata) -> String {
     if readonly { "r--" } else { "rw-" }.to_string()
 }
 
+/// Creation (birth) time in ms since the epoch, or `None` when the platform
+/// or filesystem doesn't record it.
+pub fn created_ms(path: &Path, metadata: &std::fs::Metadata) -> Option<u64> {
+    match metadata.created() {
+        Ok(time) => time
+            .duration_since(std::time::UNIX_EPOCH)
+            .ok()
+            .map(|d| d.as_millis() as u64),
+        Err(_) => birth_time_ms(path),
+    }
+}
+
+/// std only asks for birth time when it was built against a libc that wraps
+/// `statx` (glibc), so musl builds always got `None`. Ask the kernel directly.
+#[cfg(target_os = "linux")]
+pub(super) fn birth_time_ms(path: &Path) -> Option<u64> {
+    use std::os::unix::ffi::OsStrExt;
+
+    // Layout of `struct statx` (linux/stat.h) up to `stx_btime`, padded to
+    // the full 256 bytes the kernel writes.
+    #[repr(C)]
+    struct StatxTimestamp {
+        tv_sec: i64,
+        tv_nsec: u32,
+        _reserved: i32,
+    }
+    #[repr(C)]
+    struct Statx {
+        stx_mask: u32,
+        _head: [u32; 7],
+        _counts: [u64; 4],
+        _atime: StatxTimestamp,
+        stx_btime: StatxTimestamp,
+        _tail: [u64; 20],
+    }
+    const _: () = assert!(std::mem::size_of::<Statx>() == 256);
+    const STATX_BTIME: libc::c_uint = 0x0800;
+
+    let c_path = std::ffi::CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut buf = std::mem::MaybeUninit::<Statx>::zeroed();
+    // SAFETY: `c_path` is NUL-terminated and `buf` is a 256-byte, zeroed
+    // `struct statx` that outlives the call.
+    let rc = unsafe {
+        libc::syscall(
+            libc::SYS_statx,
+            libc::AT_FDCWD,
+            c_path.as_ptr(),
+            0,
+            STATX_BTIME,
+            buf.as_mut_ptr(),
+        )
+    };
+    if rc != 0 {
+        return None;
+    }
+    // SAFETY: the kernel filled the struct on success (and it started zeroed).
+    let statx = unsafe { buf.assume_init() };
+    if statx.stx_mask & STATX_BTIME == 0 || statx.stx_btime.tv_sec < 0 {
+        return None;
+    }
+    Some(statx.stx_btime.tv_sec as u64 * 1000 + u64::from(statx.stx_btime.tv_nsec) / 1_000_000)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub(super) fn birth_time_ms(_path: &Path) -> Option<u64> {
+    None
+}
+
 #[cfg(unix)]
 fn format_rwx(bits: u32) -> String {
     format!(
@@ -1,5 +1,8 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::io::Read;
 use std::path::Path;
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
 
 use ignore::WalkBuilder;
@@ -10,6 +13,76 @@ use super::operations::FileOperations;
 use super::path_utils;
 
 const MAX_CONTENT_MATCHES_PER_FILE: usize = 20;
+/// Leading bytes sniffed to tell text from binary before a content scan.
+const TEXT_SNIFF_BYTES: u64 = 1024;
+
+/// Fuzzy scoring: every matched character earns the base score; matches that
+/// continue a run or start a word earn the bonus; each skipped character
+/// between matches costs one point.
+const FUZZY_MATCH_SCORE: i64 = 16;
+const FUZZY_BONUS: i64 = 8;
+/// A name must average at least this much per query character to be kept,
+/// which drops matches scattered thinly across a long name.
+const FUZZY_MIN_SCORE_PER_CHAR: i64 = 8;
+
+/// Entries a fuzzy search scores before giving up on the rest of the tree.
+/// Fuzzy results are ranked over everything scanned, so the walk doesn't stop
+/// at `max_results`.
+pub const FUZZY_SCAN_LIMIT: usize = 100_000;
+
+/// Entries `FileSearch::recent_files` looks at before giving up on the rest
+/// of the tree.
+pub const RECENT_FILES_SCAN_LIMIT: usize = 50_000;
+/// Files `FileSearch::recent_files` returns when the caller sets no limit.
+pub const DEFAULT_RECENT_FILES: u32 = 50;
+
+/// Filtering and ordering for `FileSearch::search_files`.
+#[derive(Debug, Clone, Default)]
+pub struct SearchOptions {
+    /// Substring looked for inside each matching file
+    pub content_pattern: Option<String>,
+    pub max_depth: Option<u32>,
+    pub max_results: u32,
+    /// Order matches by content hit count (descending), then path
+    pub sort_by_relevance: bool,
+    /// Treat the pattern as a fuzzy query instead of a glob; matches are
+    /// scored and returned best first
+    pub fuzzy: bool,
+    /// Only match files with one of these extensions
+    pub extensions: Option<Vec<String>>,
+    /// Counters shared with the caller; also how a search is cancelled
+    pub control: Option<Arc<SearchControl>>,
+}
+
+/// Live state of a running search. Setting `cancelled` stops the walk at the
+/// next entry; whatever matched so far is still returned.
+#[derive(Debug, Default)]
+pub struct SearchControl {
+    pub scanned: AtomicUsize,
+    pub matched: AtomicUsize,
+    pub cancelled: AtomicBool,
+}
+
+impl SearchControl {
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+/// Matches from one search, plus why it stopped early (if it did).
+#[derive(Debug, Clone)]
+pub struct SearchOutcome {
+    pub path: String,
+    pub matches: Vec<SearchMatch>,
+    /// Hit `max_results` (for fuzzy searches, `FUZZY_SCAN_LIMIT`) or was
+    /// cancelled, so more or better matches may exist
+    pub truncated: bool,
+    pub cancelled: bool,
+}
 
 #[derive(Clone)]
 pub struct FileSearch {
@@ -25,15 +98,15 @@ impl FileSearch {
         &self,
         path: &str,
         pattern: &str,
-        content_pattern: Option<&str>,
-        max_depth: Option<u32>,
-        max_results: u32,
-    ) -> Result<(String, Vec<SearchMatch>, bool), FileSystemError> {
+        options: &SearchOptions,
+    ) -> Result<SearchOutcome, FileSystemError> {
         let root = self.ops.validator().validate_existing(path)?;
-        let max_read_size = self.ops.config().max_read_size;
+        let max_scan_size = self.ops.config().max_search_file_size;
+        let max_results = options.max_results;
+        let control = options.control.clone().unwrap_or_default();
 
         let walker = WalkBuilder::new(&root)
-            .max_depth(max_depth.map(|d| d as usize))
+            .max_depth(options.max_depth.map(|d| d as usize))
             .hidden(false)
             .git_ignore(true)
             .git_global(true)
@@ -41,24 +114,45 @@ impl FileSearch {
             .build_parallel();
 
         let matches = Arc::new(Mutex::new(Vec::new()));
-        let match_count = Arc::new(AtomicUsize::new(0));
-        let pattern = glob::Pattern::new(pattern).map_err(|e| FileSystemError::IoError {
-            message: e.to_string(),
-        })?;
+        let ranked = Arc::new(Mutex::new(TopMatches::new(max_results as usize)));
+        let scan_limited = AtomicBool::new(false);
+        let extensions = super::operations::extension_set(options.extensions.as_deref());
+        let matcher = if options.fuzzy {
+            NameMatcher::Fuzzy(pattern.to_lowercase().chars().collect())
+        } else {
+            NameMatcher::Glob(glob::Pattern::new(pattern).map_err(|e| {
+                FileSystemError::IoError {
+                    message: e.to_string(),
+                }
+            })?)
+        };
 
         walker.run(|| {
             let matches = Arc::clone(&matches);
-            let match_count = Arc::clone(&match_count);
-            let pattern = pattern.clone();
-            let content_pattern = content_pattern.map(|s| s.to_string());
+            let ranked = Arc::clone(&ranked);
+            let scan_limited = &scan_limited;
+            let control = Arc::clone(&control);
+            let matcher = &matcher;
+            let extensions = extensions.as_ref();
+            let content_pattern = options.content_pattern.clone();
 
             Box::new(move |entry| {
+                if control.is_cancelled() {
+                    return ignore::WalkState::Quit;
+                }
                 let entry = match entry {
                     Ok(e) => e,
                     Err(_) => return ignore::WalkState::Continue,
                 };
+                let scanned = control.scanned.fetch_add(1, Ordering::Relaxed);
+                let match_count = &control.matched;
 
-                if match_count.load(Ordering::Relaxed) >= max_results as usize {
+                if options.fuzzy {
+                    if scanned >= FUZZY_SCAN_LIMIT {
+                        scan_limited.store(true, Ordering::Relaxed);
+                        return ignore::WalkState::Quit;
+                    }
+                } else if match_count.load(Ordering::Relaxed) >= max_results as usize {
                     return ignore::WalkState::Quit;
                 }
 
@@ -68,10 +162,26 @@ impl FileSearch {
                     .map(|n| n.to_string_lossy().to_string())
                     .unwrap_or_default();
 
-                if !pattern.matches(&name) {
+                if extensions.is_some_and(|exts| {
+                    entry.file_type().is_some_and(|t| t.is_dir())
+                        || !exts.contains(&super::operations::extension_of(&name))
+                }) {
                     return ignore::WalkState::Continue;
                 }
 
+                let score = match matcher {
+                    NameMatcher::Glob(pattern) => {
+                        if !pattern.matches(&name) {
+                            return ignore::WalkState::Continue;
+                        }
+                        None
+                    }
+                    NameMatcher::Fuzzy(query) => match fuzzy_score(query, &name) {
+                        Some(score) => Some(score),
+                        None => return ignore::WalkState::Continue,
+                    },
+                };
+
                 // Validate per-entry to enforce allowlist + denied patterns and prevent following symlinks.
                 let canonical = match self
                     .ops
@@ -86,11 +196,20 @@ impl FileSearch {
                     return ignore::WalkState::Continue;
                 }
 
+                let protocol_path = path_utils::to_protocol_path(&canonical);
+                if let Some(score) = score {
+                    // Not worth building an entry that can't make the cut.
+                    if !ranked.lock().unwrap().admits(score, &protocol_path) {
+                        match_count.fetch_add(1, Ordering::Relaxed);
+                        return ignore::WalkState::Continue;
+                    }
+                }
+
                 let content_matches = if let Some(ref content_pat) = content_pattern {
                     if canonical.is_file() {
                         if let Ok(meta) = std::fs::metadata(&canonical) {
                             // Avoid loading huge files into memory during search.
-                            if meta.len() <= max_read_size {
+                            if meta.len() <= max_scan_size {
                                 search_file_content(&canonical, content_pat)
                             } else {
                                 None
@@ -106,6 +225,17 @@ impl FileSearch {
                 };
 
                 if let Ok(entry_info) = std::fs::metadata(&canonical) {
+                    if score.is_some() {
+                        match_count.fetch_add(1, Ordering::Relaxed);
+                        ranked.lock().unwrap().push(SearchMatch {
+                            path: protocol_path,
+                            entry: build_file_entry_sync(&canonical, &entry_info, &name),
+                            content_matches,
+                            score,
+                        });
+                        return ignore::WalkState::Continue;
+                    }
+
                     let mut reserved = false;
                     loop {
                         let current = match_count.load(Ordering::Relaxed);
@@ -132,9 +262,10 @@ impl FileSearch {
 
                     let file_entry = build_file_entry_sync(&canonical, &entry_info, &name);
                     matches.lock().unwrap().push(SearchMatch {
-                        path: path_utils::to_protocol_path(&canonical),
+                        path: protocol_path,
                         entry: file_entry,
                         content_matches,
+                        score,
                     });
                 }
 
@@ -142,15 +273,226 @@ impl FileSearch {
             })
         });
 
-        let matches = Arc::try_unwrap(matches).unwrap().into_inner().unwrap();
-        let truncated = matches.len() >= max_results as usize;
+        let cancelled = control.is_cancelled();
+        let (matches, truncated) = if options.fuzzy {
+            let ranked = Arc::try_unwrap(ranked).ok().unwrap().into_inner().unwrap();
+            (
+                ranked.into_best_first(),
+                cancelled || scan_limited.load(Ordering::Relaxed),
+            )
+        } else {
+            let mut matches = Arc::try_unwrap(matches).unwrap().into_inner().unwrap();
+            if options.sort_by_relevance {
+                sort_by_relevance_desc(&mut matches);
+            }
+            let truncated = cancelled || matches.len() >= max_results as usize;
+            (matches, truncated)
+        };
 
-        Ok((path_utils::to_protocol_path(&root), matches, truncated))
+        Ok(SearchOutcome {
+            path: path_utils::to_protocol_path(&root),
+            matches,
+            truncated,
+            cancelled,
+        })
     }
 }
 
+impl FileSearch {
+    /// The `limit` most recently modified files under `path`, newest first,
+    /// optionally only those modified at or after `since_ms`. Denied paths
+    /// are skipped and symlinks are not followed. The walk stops after
+    /// `RECENT_FILES_SCAN_LIMIT` entries; the flag says whether it did.
+    pub async fn recent_files(
+        &self,
+        path: &str,
+        limit: u32,
+        since_ms: Option<u64>,
+        respect_gitignore: bool,
+    ) -> Result<(String, Vec<FileEntry>, bool), FileSystemError> {
+        let root = self.ops.validator().validate_existing(path)?;
+        if !root.is_dir() {
+            return Err(FileSystemError::NotADirectory {
+                path: path_utils::to_protocol_path(&root),
+            });
+        }
+        let limit = limit.clamp(1, self.ops.config().max_search_results) as usize;
+
+        let walker = WalkBuilder::new(&root)
+            .hidden(false)
+            .git_ignore(respect_gitignore)
+            .git_global(respect_gitignore)
+            .git_exclude(respect_gitignore)
+            .build();
+
+        let mut files = Vec::new();
+        let mut truncated = false;
+        for (scanned, entry) in walker.enumerate() {
+            if scanned >= RECENT_FILES_SCAN_LIMIT {
+                truncated = true;
+                break;
+            }
+            let Ok(entry) = entry else { continue };
+            if !entry.file_type().is_some_and(|t| t.is_file())
+                || self.ops.validator().is_denied(entry.path())
+            {
+                continue;
+            }
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+            let modified = metadata
+                .modified()
+                .ok()
+                .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+                .map_or(0, |d| d.as_millis() as u64);
+            if since_ms.is_some_and(|since| modified < since) {
+                continue;
+            }
+            files.push((modified, entry.into_path(), metadata));
+        }
+
+        files.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+        files.truncate(limit);
+        let entries = files
+            .iter()
+            .map(|(_, path, metadata)| {
+                let name = path
+                    .file_name()
+                    .map(|n| n.to_string_lossy().to_string())
+                    .unwrap_or_default();
+                build_file_entry_sync(path, metadata, &name)
+            })
+            .collect();
+        Ok((path_utils::to_protocol_path(&root), entries, truncated))
+    }
+}
+
+/// The best `limit` fuzzy matches seen so far: highest score first, then
+/// path.
+struct TopMatches {
+    limit: usize,
+    /// Max-heap on `rank`, so the worst kept match is on top.
+    heap: BinaryHeap<Ranked>,
+}
+
+struct Ranked(SearchMatch);
+
+impl Ranked {
+    fn rank(&self) -> (Reverse<i64>, &str) {
+        (Reverse(self.0.score.unwrap_or(0)), self.0.path.as_str())
+    }
+}
+
+impl PartialEq for Ranked {
+    fn eq(&self, other: &Self) -> bool {
+        self.rank() == other.rank()
+    }
+}
+
+impl Eq for Ranked {}
+
+impl PartialOrd for Ranked {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Ranked {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.rank().cmp(&other.rank())
+    }
+}
+
+impl TopMatches {
+    fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            heap: BinaryHeap::new(),
+        }
+    }
+
+    /// Whether a match with this score and path would be kept.
+    fn admits(&self, score: i64, path: &str) -> bool {
+        if self.heap.len() < self.limit {
+            return true;
+        }
+        self.heap
+            .peek()
+            .is_some_and(|worst| (Reverse(score), path) < worst.rank())
+    }
+
+    fn push(&mut self, m: SearchMatch) {
+        self.heap.push(Ranked(m));
+        if self.heap.len() > self.limit {
+            self.heap.pop();
+        }
+    }
+
+    fn into_best_first(self) -> Vec<SearchMatch> {
+        self.heap
+            .into_sorted_vec()
+            .into_iter()
+            .map(|ranked| ranked.0)
+            .collect()
+    }
+}
+
+enum NameMatcher {
+    Glob(glob::Pattern),
+    /// Lowercased query characters
+    Fuzzy(Vec<char>),
+}
+
+/// Case-insensitive subsequence match of `query` against `name`, scored in
+/// the spirit of Skim: runs and word starts (after `.`, `_`, `-`, a space, or
+/// a lower-to-upper case change) score higher, gaps lower. `None` when the
+/// query isn't a subsequence or the match is too weak to be useful.
+fn fuzzy_score(query: &[char], name: &str) -> Option<i64> {
+    if query.is_empty() {
+        return None;
+    }
+    let chars: Vec<char> = name.chars().collect();
+    let mut score = 0;
+    let mut next = 0;
+    let mut last_match: Option<usize> = None;
+    for &wanted in query {
+        let found = (next..chars.len()).find(|&i| chars[i].to_lowercase().eq([wanted]))?;
+        score += FUZZY_MATCH_SCORE;
+        let word_start = found == 0
+            || matches!(chars[found - 1], '.' | '_' | '-' | ' ')
+            || (chars[found - 1].is_lowercase() && chars[found].is_uppercase());
+        match last_match {
+            Some(prev) if prev + 1 == found => score += FUZZY_BONUS,
+            _ if word_start => score += FUZZY_BONUS,
+            _ => {}
+        }
+        score -= (found - next) as i64;
+        last_match = Some(found);
+        next = found + 1;
+    }
+    (score >= FUZZY_MIN_SCORE_PER_CHAR * query.len() as i64).then_some(score)
+}
+
+/// Most content hits first; ties (and name-only matches) fall back to path
+/// order so results are stable across the parallel walk.
+fn sort_by_relevance_desc(matches: &mut [SearchMatch]) {
+    let hits = |m: &SearchMatch| m.content_matches.as_ref().map_or(0, Vec::len);
+    matches.sort_by(|a, b| hits(b).cmp(&hits(a)).then_with(|| a.path.cmp(&b.path)));
+}
+
 fn search_file_content(path: &Path, pattern: &str) -> Option<Vec<ContentMatch>> {
-    let data = std::fs::read(path).ok()?;
+    let mut file = std::fs::File::open(path).ok()?;
+    let mut data = Vec::new();
+    (&mut file)
+        .take(TEXT_SNIFF_BYTES)
+        .read_to_end(&mut data)
+        .ok()?;
+    // Binaries only yield lossy garbage matches; don't read past the sniff.
+    if !super::mime::is_probably_text_prefix(&data) {
+        return None;
+    }
+    file.read_to_end(&mut data).ok()?;
     let text = String::from_utf8_lossy(&data);
 
     let mut matches = Vec::new();
@@ -183,10 +525,17 @@ fn build_file_entry_sync(path: &Path, metadata: &std::fs::Metadata, name: &str)
         .modified()
         .map(|t| t.duration_since(std::time::UNIX_EPOCH).unwrap().as_millis() as u64)
         .unwrap_or(0);
-    let created = metadata
-        .created()
-        .ok()
-        .map(|t| t.duration_since(std::time::UNIX_EPOCH).unwrap().as_millis() as u64);
+    let created = super::platform::created_ms(path, metadata);
+
+    let mime_type = if is_directory {
+        None
+    } else {
+        Some(super::mime::guess_mime_from_extension(name))
+    };
+    // Search results skip the content peek; the extension hint is free.
+    let is_likely_text = mime_type
+        .as_deref()
+        .and_then(super::mime::text_hint_from_mime);
 
     FileEntry {
         name: name.to_string(),
@@ -197,13 +546,12 @@ fn build_file_entry_sync(path: &Path, metadata: &std::fs::Metadata, name: &str)
         size,
         modified,
         created,
-        mime_type: if is_directory {
-            None
-        } else {
-            Some(super::mime::guess_mime_from_extension(name))
-        },
+        mime_type,
         permissions: Some(super::platform::format_permissions(metadata)),
         symlink_target: None,
         git_status: None,
+        is_likely_text,
+        image_dimensions: None,
+        line_count: None,
     }
 }
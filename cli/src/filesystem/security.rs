@@ -1,4 +1,8 @@
+use std::collections::HashMap;
+use std::net::SocketAddr;
 use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 use glob_match::glob_match;
 use path_jail::Jail;
@@ -8,10 +12,42 @@ use crate::protocol::FileSystemError;
 use super::config::FileSystemConfig;
 use super::path_utils;
 
+/// Single files outside the allowed roots that one client may read until its
+/// grant expires. Denied patterns still apply to them, and they are never
+/// writable.
+#[derive(Debug, Clone, Default)]
+pub struct PathGrants(Arc<Mutex<HashMap<(SocketAddr, PathBuf), Instant>>>);
+
+impl PathGrants {
+    pub fn grant(&self, owner: SocketAddr, canonical: PathBuf, ttl: Duration) -> Instant {
+        let expires_at = Instant::now() + ttl;
+        let mut grants = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        grants.retain(|_, expiry| *expiry > Instant::now());
+        grants.insert((owner, canonical), expires_at);
+        expires_at
+    }
+
+    pub fn is_granted(&self, owner: SocketAddr, canonical: &Path) -> bool {
+        let grants = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        grants
+            .get(&(owner, canonical.to_path_buf()))
+            .is_some_and(|expiry| *expiry > Instant::now())
+    }
+
+    /// Drop every grant `owner` holds, e.g. when it disconnects.
+    pub fn revoke_all(&self, owner: SocketAddr) {
+        let mut grants = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        grants.retain(|(granted_to, _), _| *granted_to != owner);
+    }
+}
+
 /// Validates and sanitizes paths before any file operation
 pub struct PathValidator {
     config: std::sync::Arc<FileSystemConfig>,
     jails: Vec<Jail>,
+    /// Normalized override roots (as configured and canonicalized) with their
+    /// normalized allow patterns.
+    overrides: Vec<(Vec<String>, Vec<String>)>,
     symlink_cache: std::sync::Mutex<std::collections::HashMap<PathBuf, bool>>,
 }
 
@@ -22,15 +58,83 @@ impl PathValidator {
             .iter()
             .filter_map(|root| Jail::new(root).ok())
             .collect();
+        let overrides = config
+            .denied_pattern_overrides
+            .iter()
+            .map(|entry| {
+                let mut roots = vec![normalize_for_match(&entry.root)];
+                if let Ok(canonical) = entry.root.canonicalize() {
+                    roots.push(normalize_for_match(&canonical));
+                }
+                let patterns = entry
+                    .allowed_patterns
+                    .iter()
+                    .map(|pattern| normalize_pattern_for_match(pattern))
+                    .collect();
+                (roots, patterns)
+            })
+            .collect();
         Self {
             config,
             jails,
+            overrides,
             symlink_cache: std::sync::Mutex::new(std::collections::HashMap::new()),
         }
     }
 
     /// Validate a path that must already exist
     pub fn validate_existing(&self, path: &str) -> Result<PathBuf, FileSystemError> {
+        self.validate_existing_inner(path, None)
+    }
+
+    /// Like `validate_existing`, but also accepts a file `grantee` holds a
+    /// live read grant for. Only read operations use this.
+    pub fn validate_readable(
+        &self,
+        path: &str,
+        grantee: Option<SocketAddr>,
+    ) -> Result<PathBuf, FileSystemError> {
+        self.validate_existing_inner(path, grantee)
+    }
+
+    /// Let `owner` read `path`, a single existing file, via
+    /// `validate_readable` for `ttl` even if it is outside every allowed root.
+    pub fn grant_read(
+        &self,
+        path: &str,
+        owner: SocketAddr,
+        ttl: Duration,
+    ) -> Result<PathBuf, FileSystemError> {
+        let path = Path::new(path);
+        if !path.is_absolute() || contains_parent_dir(path) {
+            return Err(FileSystemError::PathTraversal {
+                attempted_path: path_utils::to_protocol_path(path),
+            });
+        }
+        if !self.config.follow_symlinks && self.contains_symlink(path) {
+            return Err(FileSystemError::PermissionDenied {
+                path: path_utils::to_protocol_path(path),
+                reason: "Symlinked paths are not allowed".to_string(),
+            });
+        }
+        let canonical = path.canonicalize().map_err(|e| FileSystemError::IoError {
+            message: e.to_string(),
+        })?;
+        if !canonical.is_file() {
+            return Err(FileSystemError::NotAFile {
+                path: path_utils::to_protocol_path(&canonical),
+            });
+        }
+        self.ensure_not_denied(&canonical)?;
+        self.config.path_grants.grant(owner, canonical.clone(), ttl);
+        Ok(canonical)
+    }
+
+    fn validate_existing_inner(
+        &self,
+        path: &str,
+        grantee: Option<SocketAddr>,
+    ) -> Result<PathBuf, FileSystemError> {
         let path = Path::new(path);
 
         if !path.is_absolute() || contains_parent_dir(path) {
@@ -52,7 +156,11 @@ impl PathValidator {
             message: e.to_string(),
         })?;
 
-        self.ensure_allowed(&canonical)?;
+        let granted =
+            grantee.is_some_and(|owner| self.config.path_grants.is_granted(owner, &canonical));
+        if !granted {
+            self.ensure_allowed(&canonical)?;
+        }
         self.ensure_not_denied(&canonical)?;
 
         Ok(canonical)
@@ -156,20 +264,15 @@ impl PathValidator {
 
     /// Check if path matches denied patterns
     pub fn is_denied(&self, path: &Path) -> bool {
-        let normalized = normalize_for_match(path);
-        self.config
-            .denied_patterns
-            .iter()
-            .any(|pattern| glob_match(&normalize_pattern_for_match(pattern), &normalized))
+        self.denied_pattern(path).is_some()
     }
 
     fn ensure_allowed(&self, path: &Path) -> Result<(), FileSystemError> {
         let is_allowed = self.jails.iter().any(|jail| jail.contains(path).is_ok());
 
         if !is_allowed {
-            return Err(FileSystemError::PermissionDenied {
+            return Err(FileSystemError::OutsideAllowedRoots {
                 path: path_utils::to_protocol_path(path),
-                reason: "Path is outside allowed directories".to_string(),
             });
         }
 
@@ -177,18 +280,37 @@ impl PathValidator {
     }
 
     fn ensure_not_denied(&self, path: &Path) -> Result<(), FileSystemError> {
-        let normalized = normalize_for_match(path);
-        for pattern in &self.config.denied_patterns {
-            if glob_match(&normalize_pattern_for_match(pattern), &normalized) {
-                return Err(FileSystemError::PermissionDenied {
-                    path: path_utils::to_protocol_path(path),
-                    reason: format!("Path matches denied pattern: {}", pattern),
-                });
-            }
+        if let Some(pattern) = self.denied_pattern(path) {
+            return Err(FileSystemError::DeniedByPattern {
+                path: path_utils::to_protocol_path(path),
+                pattern: pattern.to_string(),
+            });
         }
         Ok(())
     }
 
+    /// First denied pattern that blocks `path` after per-root overrides.
+    pub fn denied_pattern(&self, path: &Path) -> Option<&str> {
+        let normalized = normalize_for_match(path);
+        self.config
+            .denied_patterns
+            .iter()
+            .find(|pattern| {
+                glob_match(&normalize_pattern_for_match(pattern), &normalized)
+                    && !(pattern.starts_with("**/") && self.is_overridden(&normalized))
+            })
+            .map(String::as_str)
+    }
+
+    fn is_overridden(&self, normalized: &str) -> bool {
+        self.overrides.iter().any(|(roots, patterns)| {
+            roots.iter().any(|root| is_under_root(normalized, root))
+                && patterns
+                    .iter()
+                    .any(|pattern| glob_match(pattern, normalized))
+        })
+    }
+
     fn contains_symlink(&self, path: &Path) -> bool {
         let mut current = PathBuf::new();
         for component in path.components() {
@@ -223,6 +345,14 @@ fn contains_parent_dir(path: &Path) -> bool {
     path.components().any(|c| matches!(c, Component::ParentDir))
 }
 
+fn is_under_root(normalized: &str, root: &str) -> bool {
+    let root = root.trim_end_matches('/');
+    normalized == root
+        || normalized
+            .strip_prefix(root)
+            .is_some_and(|rest| rest.starts_with('/'))
+}
+
 fn normalize_for_match(path: &Path) -> String {
     normalize_match_text(&path.to_string_lossy())
 }
@@ -1,12 +1,15 @@
 use std::sync::Arc;
 
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
 use tempfile::TempDir;
 
-use crate::protocol::{SortField, SortOrder};
+use crate::protocol::{ArchiveFormat, ChecksumAlgorithm, FileSystemError, SortField, SortOrder};
 
 use super::config::FileSystemConfig;
-use super::operations::FileOperations;
+use super::operations::{FileOperations, ListOptions};
+use super::search::{FileSearch, SearchControl, SearchOptions};
 use super::security::PathValidator;
+use super::FileSystemService;
 
 fn temp_root(temp: &TempDir) -> std::path::PathBuf {
     temp.path()
@@ -71,6 +74,72 @@ fn test_path_validation_allows_project_mobilecli_upload_cache() {
     assert!(!validator.is_denied(&upload_path));
 }
 
+#[test]
+fn test_denied_pattern_override_only_applies_under_its_root() {
+    let temp = TempDir::new().unwrap();
+    let root = temp_root(&temp);
+    let sandbox = root.join("sandbox");
+    let other = root.join("other");
+    std::fs::create_dir_all(&sandbox).unwrap();
+    std::fs::create_dir_all(&other).unwrap();
+    std::fs::write(sandbox.join(".env"), "KEY=value").unwrap();
+    std::fs::write(sandbox.join("id_rsa"), "key").unwrap();
+    std::fs::write(other.join(".env"), "KEY=value").unwrap();
+
+    let config = Arc::new(FileSystemConfig {
+        allowed_roots: vec![sandbox.clone(), other.clone()],
+        denied_pattern_overrides: vec![super::config::DeniedPatternOverride {
+            root: sandbox.clone(),
+            allowed_patterns: vec!["**/.env".to_string()],
+        }],
+        ..Default::default()
+    });
+    let validator = PathValidator::new(config);
+
+    assert!(validator
+        .validate_existing(&sandbox.join(".env").to_string_lossy())
+        .is_ok());
+    assert!(validator
+        .validate_existing(&other.join(".env").to_string_lossy())
+        .is_err());
+    assert!(validator
+        .validate_existing(&sandbox.join("id_rsa").to_string_lossy())
+        .is_err());
+}
+
+#[test]
+fn test_path_validation_distinguishes_outside_roots_from_denied() {
+    let temp = TempDir::new().unwrap();
+    let root = temp_root(&temp);
+    let allowed = root.join("allowed");
+    std::fs::create_dir_all(&allowed).unwrap();
+    std::fs::write(allowed.join(".env"), "KEY=value").unwrap();
+    std::fs::write(root.join("outside.txt"), "content").unwrap();
+
+    let config = Arc::new(FileSystemConfig {
+        allowed_roots: vec![allowed.clone()],
+        ..Default::default()
+    });
+    let validator = PathValidator::new(config);
+
+    match validator
+        .validate_existing(&root.join("outside.txt").to_string_lossy())
+        .unwrap_err()
+    {
+        crate::protocol::FileSystemError::OutsideAllowedRoots { .. } => {}
+        other => panic!("expected OutsideAllowedRoots, got: {:?}", other),
+    }
+    match validator
+        .validate_existing(&allowed.join(".env").to_string_lossy())
+        .unwrap_err()
+    {
+        crate::protocol::FileSystemError::DeniedByPattern { pattern, .. } => {
+            assert_eq!(pattern, "**/.env");
+        }
+        other => panic!("expected DeniedByPattern, got: {:?}", other),
+    }
+}
+
 #[tokio::test]
 async fn test_list_directory_sorts_directories_first() {
     let temp = TempDir::new().unwrap();
@@ -87,15 +156,16 @@ async fn test_list_directory_sorts_directories_first() {
     let validator = Arc::new(PathValidator::new(config.clone()));
     let ops = FileOperations::new(validator, config);
 
-    let (_path, entries, _total, _truncated) = ops
-        .list_directory(
-            &root.to_string_lossy(),
-            false,
-            Some(SortField::Name),
-            Some(SortOrder::Asc),
-        )
+    let options = ListOptions {
+        sort_by: Some(SortField::Name),
+        sort_order: Some(SortOrder::Asc),
+        ..Default::default()
+    };
+    let entries = ops
+        .list_directory(&root.to_string_lossy(), &options)
         .await
-        .unwrap();
+        .unwrap()
+        .entries;
 
     assert!(entries.len() >= 4);
     assert!(entries[0].is_directory);
@@ -104,9 +174,14 @@ async fn test_list_directory_sorts_directories_first() {
 }
 
 #[tokio::test]
-async fn test_write_file_fails_when_parent_is_file() {
+async fn test_list_directory_applies_name_filter() {
     let temp = TempDir::new().unwrap();
     let root = temp_root(&temp);
+    for name in ["app.log", "db.log", "notes.txt", "README.md"] {
+        std::fs::write(root.join(name), "x").unwrap();
+    }
+    std::fs::create_dir(root.join("logs")).unwrap();
+
     let config = Arc::new(FileSystemConfig {
         allowed_roots: vec![root.clone()],
         ..Default::default()
@@ -114,178 +189,2163 @@ async fn test_write_file_fails_when_parent_is_file() {
     let validator = Arc::new(PathValidator::new(config.clone()));
     let ops = FileOperations::new(validator, config);
 
-    // Create a file
-    let file_path = root.join("existing_file.txt");
-    std::fs::write(&file_path, "content").unwrap();
+    let mut options = ListOptions {
+        sort_by: Some(SortField::Name),
+        sort_order: Some(SortOrder::Asc),
+        name_filter: Some("*.log".to_string()),
+        ..Default::default()
+    };
+    let page = ops
+        .list_directory(&root.to_string_lossy(), &options)
+        .await
+        .unwrap();
 
-    // Try to write a file with the existing file in the parent path
-    let invalid_path = file_path.join("should_fail.txt");
-    let result = ops
-        .write_file(
-            &invalid_path.to_string_lossy(),
-            "test content",
-            crate::protocol::FileEncoding::Utf8,
-            true, // create_parents = true
-        )
-        .await;
+    let names: Vec<&str> = page.entries.iter().map(|e| e.name.as_str()).collect();
+    assert_eq!(names, vec!["app.log", "db.log"]);
+    assert_eq!(page.total_count, 2);
+    assert!(!page.truncated);
 
-    // Should fail with NotADirectory error
-    assert!(result.is_err());
-    match result.unwrap_err() {
-        crate::protocol::FileSystemError::NotADirectory { path } => {
-            assert_eq!(path, super::path_utils::to_protocol_path(&file_path));
-        }
-        e => panic!("Expected NotADirectory error, got: {:?}", e),
-    }
+    options.name_filter = Some("[*.log".to_string());
+    assert!(ops
+        .list_directory(&root.to_string_lossy(), &options)
+        .await
+        .is_err());
 }
 
 #[tokio::test]
-async fn test_write_file_overwrite_existing_file_succeeds() {
+async fn test_extension_filter_keeps_directories_in_listings_only() {
     let temp = TempDir::new().unwrap();
     let root = temp_root(&temp);
+    for name in ["main.rs", "LIB.RS", "index.ts", "notes.txt", "Makefile"] {
+        std::fs::write(root.join(name), "x").unwrap();
+    }
+    std::fs::create_dir(root.join("src")).unwrap();
+    std::fs::write(root.join("src/mod.rs"), "x").unwrap();
+
     let config = Arc::new(FileSystemConfig {
         allowed_roots: vec![root.clone()],
         ..Default::default()
     });
     let validator = Arc::new(PathValidator::new(config.clone()));
     let ops = FileOperations::new(validator, config);
+    let extensions = Some(vec![".rs".to_string(), "TS".to_string()]);
 
-    let file_path = root.join("overwrite.txt");
-    let path = file_path.to_string_lossy().to_string();
+    let options = ListOptions {
+        sort_by: Some(SortField::Name),
+        sort_order: Some(SortOrder::Asc),
+        extensions: extensions.clone(),
+        ..Default::default()
+    };
+    let page = ops
+        .list_directory(&root.to_string_lossy(), &options)
+        .await
+        .unwrap();
+    let names: Vec<&str> = page.entries.iter().map(|e| e.name.as_str()).collect();
+    assert_eq!(names, vec!["src", "index.ts", "LIB.RS", "main.rs"]);
+
+    let search = FileSearch::new(ops);
+    let options = SearchOptions {
+        max_results: 100,
+        extensions,
+        sort_by_relevance: true,
+        ..Default::default()
+    };
+    let outcome = search
+        .search_files(&root.to_string_lossy(), "*", &options)
+        .await
+        .unwrap();
+    let mut names: Vec<&str> = outcome
+        .matches
+        .iter()
+        .map(|m| m.entry.name.as_str())
+        .collect();
+    names.sort_unstable();
+    assert_eq!(names, vec!["LIB.RS", "index.ts", "main.rs", "mod.rs"]);
+}
+
+#[tokio::test]
+async fn test_list_directory_pages_after_sorting() {
+    let temp = TempDir::new().unwrap();
+    let root = temp_root(&temp);
+    for i in 0..5 {
+        std::fs::write(root.join(format!("file{}.txt", i)), "x").unwrap();
+    }
+
+    let config = Arc::new(FileSystemConfig {
+        allowed_roots: vec![root.clone()],
+        ..Default::default()
+    });
+    let validator = Arc::new(PathValidator::new(config.clone()));
+    let ops = FileOperations::new(validator, config);
 
-    ops.write_file(&path, "first", crate::protocol::FileEncoding::Utf8, true)
+    let mut options = ListOptions {
+        sort_by: Some(SortField::Name),
+        sort_order: Some(SortOrder::Asc),
+        offset: 2,
+        limit: Some(2),
+        ..Default::default()
+    };
+    let page = ops
+        .list_directory(&root.to_string_lossy(), &options)
         .await
         .unwrap();
-    ops.write_file(&path, "second", crate::protocol::FileEncoding::Utf8, true)
+    let names: Vec<&str> = page.entries.iter().map(|e| e.name.as_str()).collect();
+    assert_eq!(names, vec!["file2.txt", "file3.txt"]);
+    assert_eq!(page.total_count, 5);
+    assert!(page.has_more);
+
+    options.offset = 4;
+    let last = ops
+        .list_directory(&root.to_string_lossy(), &options)
         .await
         .unwrap();
+    assert_eq!(last.entries.len(), 1);
+    assert!(!last.has_more);
 
-    let final_content = std::fs::read_to_string(&file_path).unwrap();
-    assert_eq!(final_content, "second");
+    // An empty page with `has_more` would never let a client advance.
+    options.offset = 0;
+    options.limit = Some(0);
+    let first = ops
+        .list_directory(&root.to_string_lossy(), &options)
+        .await
+        .unwrap();
+    let names: Vec<&str> = first.entries.iter().map(|e| e.name.as_str()).collect();
+    assert_eq!(names, vec!["file0.txt"]);
+    assert!(first.has_more);
 }
 
 #[tokio::test]
-async fn test_create_directory_respects_read_only_patterns() {
+async fn test_list_directory_stream_sends_each_entry() {
     let temp = TempDir::new().unwrap();
     let root = temp_root(&temp);
+    for name in ["a.txt", "b.txt", ".hidden", ".env"] {
+        std::fs::write(root.join(name), "x").unwrap();
+    }
+
     let config = Arc::new(FileSystemConfig {
         allowed_roots: vec![root.clone()],
-        read_only_patterns: vec![format!("{}/**", root.display())],
         ..Default::default()
     });
     let validator = Arc::new(PathValidator::new(config.clone()));
     let ops = FileOperations::new(validator, config);
 
-    let dir_path = root.join("newdir");
-    let result = ops
-        .create_directory(&dir_path.to_string_lossy(), true)
-        .await;
+    let (entry_tx, mut entry_rx) = tokio::sync::mpsc::channel(1);
+    let path = root.to_string_lossy().to_string();
+    let listing =
+        tokio::spawn(async move { ops.list_directory_stream(&path, true, entry_tx).await });
 
-    assert!(result.is_err());
-    match result.unwrap_err() {
-        crate::protocol::FileSystemError::PermissionDenied { .. } => {}
-        e => panic!("Expected PermissionDenied, got: {:?}", e),
+    let mut names = Vec::new();
+    while let Some(entry) = entry_rx.recv().await {
+        names.push(entry.name);
     }
+    names.sort();
+    let (_path, total) = listing.await.unwrap().unwrap();
+
+    // `.env` is filtered by the default denylist.
+    assert_eq!(names, vec![".hidden", "a.txt", "b.txt"]);
+    assert_eq!(total, 3);
 }
 
 #[tokio::test]
-async fn test_copy_path_respects_read_only_patterns() {
+async fn test_git_status_cache_reuses_recent_status() {
     let temp = TempDir::new().unwrap();
     let root = temp_root(&temp);
-    let src = root.join("src.txt");
-    std::fs::write(&src, "hi").unwrap();
+    let initialized = std::process::Command::new("git")
+        .arg("init")
+        .arg("-q")
+        .arg(&root)
+        .status()
+        .is_ok_and(|status| status.success());
+    if !initialized {
+        return;
+    }
+    std::fs::write(root.join("new.txt"), "x").unwrap();
 
     let config = Arc::new(FileSystemConfig {
         allowed_roots: vec![root.clone()],
-        read_only_patterns: vec![format!("{}/**", root.display())],
         ..Default::default()
     });
     let validator = Arc::new(PathValidator::new(config.clone()));
     let ops = FileOperations::new(validator, config);
+    let path = root.to_string_lossy().to_string();
 
-    let dest = root.join("dest.txt");
-    let result = ops
-        .copy_path(&src.to_string_lossy(), &dest.to_string_lossy(), false)
-        .await;
+    let first = ops
+        .list_directory(&path, &ListOptions::default())
+        .await
+        .unwrap();
+    let second = ops
+        .list_directory(&path, &ListOptions::default())
+        .await
+        .unwrap();
 
-    assert!(result.is_err());
-    match result.unwrap_err() {
-        crate::protocol::FileSystemError::PermissionDenied { .. } => {}
-        e => panic!("Expected PermissionDenied, got: {:?}", e),
+    assert_eq!(ops.git_cache().refresh_count(), 1);
+    let status = |page: &super::operations::DirectoryPage| {
+        page.entries
+            .iter()
+            .find(|e| e.name == "new.txt")
+            .and_then(|e| e.git_status.clone())
+    };
+    assert!(matches!(
+        status(&first),
+        Some(crate::protocol::GitStatus::Untracked)
+    ));
+    assert!(matches!(
+        status(&second),
+        Some(crate::protocol::GitStatus::Untracked)
+    ));
+
+    ops.git_cache().invalidate(&root.join("new.txt"));
+    ops.list_directory(&path, &ListOptions::default())
+        .await
+        .unwrap();
+    assert_eq!(ops.git_cache().refresh_count(), 2);
+}
+
+#[tokio::test]
+async fn test_git_status_uses_nearest_repo_for_nested_checkouts() {
+    let temp = TempDir::new().unwrap();
+    let root = temp_root(&temp);
+    let outer = root.join("outer");
+    let nested = outer.join("nested");
+    std::fs::create_dir_all(&nested).unwrap();
+
+    let git = |args: &[&std::ffi::OsStr]| {
+        std::process::Command::new("git")
+            .args(args)
+            .status()
+            .is_ok_and(|status| status.success())
+    };
+    // A separate git dir leaves a `.git` file in the checkout, like a submodule.
+    let separate_git_dir = root.join("nested-gitdir");
+    if !git(&["init".as_ref(), "-q".as_ref(), outer.as_os_str()])
+        || !git(&[
+            "init".as_ref(),
+            "-q".as_ref(),
+            "--separate-git-dir".as_ref(),
+            separate_git_dir.as_os_str(),
+            nested.as_os_str(),
+        ])
+    {
+        return;
     }
+    assert!(nested.join(".git").is_file());
+    std::fs::write(nested.join("inner.txt"), "x").unwrap();
+
+    let config = Arc::new(FileSystemConfig {
+        allowed_roots: vec![root.clone()],
+        ..Default::default()
+    });
+    let validator = Arc::new(PathValidator::new(config.clone()));
+    let ops = FileOperations::new(validator, config);
+
+    let outer_listing = ops
+        .list_directory(&outer.to_string_lossy(), &ListOptions::default())
+        .await
+        .unwrap();
+    let nested_entry = outer_listing
+        .entries
+        .iter()
+        .find(|e| e.name == "nested")
+        .unwrap();
+    assert!(matches!(
+        nested_entry.git_status,
+        Some(crate::protocol::GitStatus::Submodule)
+    ));
+
+    let nested_listing = ops
+        .list_directory(&nested.to_string_lossy(), &ListOptions::default())
+        .await
+        .unwrap();
+    let inner = nested_listing
+        .entries
+        .iter()
+        .find(|e| e.name == "inner.txt")
+        .unwrap();
+    assert!(matches!(
+        inner.git_status,
+        Some(crate::protocol::GitStatus::Untracked)
+    ));
 }
 
-#[cfg(unix)]
-#[test]
-fn test_path_validation_blocks_symlink_paths_when_disabled() {
-    use std::os::unix::fs::symlink;
+#[tokio::test]
+async fn test_build_file_entry_sets_text_hint() {
+    let temp = TempDir::new().unwrap();
+    let root = temp_root(&temp);
+    let config = Arc::new(FileSystemConfig {
+        allowed_roots: vec![root.clone()],
+        ..Default::default()
+    });
+    let validator = Arc::new(PathValidator::new(config.clone()));
+    let ops = FileOperations::new(validator, config);
+
+    std::fs::write(root.join("notes"), "plain text without an extension\n").unwrap();
+    std::fs::write(root.join("blob"), [0u8, 1, 2, 3, 0, 255]).unwrap();
+    std::fs::write(root.join("main.rs"), "fn main() {}\n").unwrap();
+    std::fs::create_dir(root.join("dir")).unwrap();
 
+    let hint = |name: &'static str| {
+        let ops = ops.clone();
+        let path = root.join(name);
+        async move {
+            ops.build_file_entry(&path, name, None)
+                .await
+                .unwrap()
+                .is_likely_text
+        }
+    };
+    assert_eq!(hint("notes").await, Some(true));
+    assert_eq!(hint("blob").await, Some(false));
+    assert_eq!(hint("main.rs").await, Some(true));
+    assert_eq!(hint("dir").await, None);
+}
+
+#[tokio::test]
+async fn test_build_file_entry_counts_lines_of_text_files() {
     let temp = TempDir::new().unwrap();
     let root = temp_root(&temp);
-    let real_dir = root.join("real");
-    std::fs::create_dir_all(&real_dir).unwrap();
+    let config = Arc::new(FileSystemConfig {
+        allowed_roots: vec![root.clone()],
+        ..Default::default()
+    });
+    let validator = Arc::new(PathValidator::new(config.clone()));
+    let ops = FileOperations::new(validator, config);
 
-    let link_dir = root.join("link");
-    symlink(&real_dir, &link_dir).unwrap();
+    std::fs::write(root.join("three.txt"), "a\nb\nc\n").unwrap();
+    std::fs::write(root.join("open.txt"), "a\nb").unwrap();
+    std::fs::write(root.join("empty.txt"), "").unwrap();
+    std::fs::write(root.join("blob"), [0u8, 1, 2, 3, 0, 255]).unwrap();
 
-    let file_path = real_dir.join("file.txt");
-    std::fs::write(&file_path, "content").unwrap();
+    let lines = |name: &'static str| {
+        let ops = ops.clone();
+        let path = root.join(name);
+        async move {
+            ops.build_file_entry(&path, name, None)
+                .await
+                .unwrap()
+                .line_count
+        }
+    };
+    assert_eq!(lines("three.txt").await, Some(3));
+    assert_eq!(lines("open.txt").await, Some(2));
+    assert_eq!(lines("empty.txt").await, Some(0));
+    assert_eq!(lines("blob").await, None);
+}
 
+#[tokio::test]
+async fn test_compact_listing_skips_expensive_fields() {
+    let temp = TempDir::new().unwrap();
+    let root = temp_root(&temp);
     let config = Arc::new(FileSystemConfig {
-        allowed_roots: vec![root],
-        follow_symlinks: false,
+        allowed_roots: vec![root.clone()],
         ..Default::default()
     });
-    let validator = PathValidator::new(config);
+    let validator = Arc::new(PathValidator::new(config.clone()));
+    let ops = FileOperations::new(validator, config);
 
-    let via_link = link_dir.join("file.txt");
-    let err = validator
-        .validate_existing(&via_link.to_string_lossy())
-        .unwrap_err();
+    std::fs::write(root.join("notes.txt"), "hello").unwrap();
+    std::fs::create_dir(root.join("dir")).unwrap();
 
-    match err {
-        crate::protocol::FileSystemError::PermissionDenied { .. } => {}
-        other => panic!("expected PermissionDenied, got: {:?}", other),
-    }
+    let options = ListOptions {
+        sort_by: Some(SortField::Name),
+        sort_order: Some(SortOrder::Asc),
+        compact: true,
+        ..Default::default()
+    };
+    let page = ops
+        .list_directory(&root.to_string_lossy(), &options)
+        .await
+        .unwrap();
+    let names: Vec<&str> = page.entries.iter().map(|e| e.name.as_str()).collect();
+    assert_eq!(names, vec!["dir", "notes.txt"]);
+
+    let file = &page.entries[1];
+    assert_eq!(file.size, 5);
+    assert!(file.modified > 0);
+    assert!(!file.is_directory);
+    assert!(file.permissions.is_none());
+    assert!(file.mime_type.is_none());
+    assert!(file.is_likely_text.is_none());
+    assert!(page.entries[0].is_directory);
 }
 
 #[cfg(unix)]
 #[tokio::test]
-async fn test_copy_directory_blocks_symlink_entries() {
-    use std::os::unix::fs::symlink;
-
+async fn test_compact_listing_keeps_broken_symlinks() {
     let temp = TempDir::new().unwrap();
     let root = temp_root(&temp);
+    let config = Arc::new(FileSystemConfig {
+        allowed_roots: vec![root.clone()],
+        ..Default::default()
+    });
+    let validator = Arc::new(PathValidator::new(config.clone()));
+    let ops = FileOperations::new(validator, config);
 
-    let src_dir = root.join("srcdir");
-    std::fs::create_dir_all(&src_dir).unwrap();
-    std::fs::write(src_dir.join("a.txt"), "a").unwrap();
+    std::fs::create_dir(root.join("dir")).unwrap();
+    std::os::unix::fs::symlink(root.join("gone"), root.join("dangling")).unwrap();
+    std::os::unix::fs::symlink(root.join("dir"), root.join("dir-link")).unwrap();
 
-    // Symlink inside the directory should be rejected for recursive copy.
-    let target = root.join("real_target.txt");
-    std::fs::write(&target, "target").unwrap();
-    symlink(&target, src_dir.join("link.txt")).unwrap();
+    let options = ListOptions {
+        sort_by: Some(SortField::Name),
+        sort_order: Some(SortOrder::Asc),
+        compact: true,
+        ..Default::default()
+    };
+    let page = ops
+        .list_directory(&root.to_string_lossy(), &options)
+        .await
+        .unwrap();
+    let dangling = page
+        .entries
+        .iter()
+        .find(|e| e.name == "dangling")
+        .expect("broken symlink listed");
+    assert!(dangling.is_symlink);
+    assert!(!dangling.is_directory);
+    let dir_link = page.entries.iter().find(|e| e.name == "dir-link").unwrap();
+    assert!(dir_link.is_symlink);
+    assert!(dir_link.is_directory);
+}
 
+#[tokio::test]
+async fn test_listing_respects_gitignore_when_requested() {
+    let temp = TempDir::new().unwrap();
+    let root = temp_root(&temp);
     let config = Arc::new(FileSystemConfig {
         allowed_roots: vec![root.clone()],
-        follow_symlinks: false,
         ..Default::default()
     });
     let validator = Arc::new(PathValidator::new(config.clone()));
     let ops = FileOperations::new(validator, config);
 
-    let dest_dir = root.join("destdir");
-    let result = ops
-        .copy_path(
-            &src_dir.to_string_lossy(),
-            &dest_dir.to_string_lossy(),
-            true,
-        )
-        .await;
+    // Matching only needs a `.git` marker, not a real repository.
+    std::fs::create_dir(root.join(".git")).unwrap();
+    std::fs::write(root.join(".gitignore"), "node_modules/\n*.log\n").unwrap();
+    std::fs::create_dir(root.join("node_modules")).unwrap();
+    std::fs::write(root.join("debug.log"), "x").unwrap();
+    std::fs::write(root.join("main.js"), "x").unwrap();
+    std::fs::create_dir(root.join("logs")).unwrap();
+    std::fs::write(root.join("logs/.gitignore"), "!keep.log\n").unwrap();
+    std::fs::write(root.join("logs/keep.log"), "x").unwrap();
+    std::fs::write(root.join("logs/drop.log"), "x").unwrap();
 
-    assert!(result.is_err());
-    match result.unwrap_err() {
-        crate::protocol::FileSystemError::PermissionDenied { .. } => {}
-        e => panic!("Expected PermissionDenied, got: {:?}", e),
-    }
+    let list = |dir: std::path::PathBuf, hide_ignored_dirs: bool| {
+        let ops = ops.clone();
+        async move {
+            let options = ListOptions {
+                sort_by: Some(SortField::Name),
+                sort_order: Some(SortOrder::Asc),
+                respect_gitignore: true,
+                hide_ignored_dirs,
+                ..Default::default()
+            };
+            ops.list_directory(&dir.to_string_lossy(), &options)
+                .await
+                .unwrap()
+                .entries
+        }
+    };
+
+    let entries = list(root.clone(), false).await;
+    let names: Vec<&str> = entries.iter().map(|e| e.name.as_str()).collect();
+    assert_eq!(names, vec!["logs", "node_modules", "main.js"]);
+    assert!(matches!(
+        entries[1].git_status,
+        Some(crate::protocol::GitStatus::Ignored)
+    ));
+
+    let entries = list(root.clone(), true).await;
+    let names: Vec<&str> = entries.iter().map(|e| e.name.as_str()).collect();
+    assert_eq!(names, vec!["logs", "main.js"]);
+
+    let entries = list(root.join("logs"), false).await;
+    let names: Vec<&str> = entries.iter().map(|e| e.name.as_str()).collect();
+    assert_eq!(names, vec!["keep.log"]);
+}
+
+#[test]
+fn test_created_time_matches_std_when_both_available() {
+    let temp = TempDir::new().unwrap();
+    let path = temp_root(&temp).join("new.txt");
+    std::fs::write(&path, "x").unwrap();
+    let metadata = std::fs::metadata(&path).unwrap();
+
+    let now_ms = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64;
+    // Filesystems without birth times legitimately report None.
+    if let Some(created) = super::platform::created_ms(&path, &metadata) {
+        assert!(created <= now_ms && now_ms - created < 60_000);
+    }
+    if let Ok(std_created) = metadata.created() {
+        let std_ms = std_created
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as u64;
+        assert_eq!(super::platform::created_ms(&path, &metadata), Some(std_ms));
+        // The direct statx fallback agrees with std where std has it.
+        if cfg!(target_os = "linux") {
+            assert_eq!(super::platform::birth_time_ms(&path), Some(std_ms));
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_build_file_entry_reads_image_dimensions() {
+    let temp = TempDir::new().unwrap();
+    let root = temp_root(&temp);
+    let config = Arc::new(FileSystemConfig {
+        allowed_roots: vec![root.clone()],
+        ..Default::default()
+    });
+    let validator = Arc::new(PathValidator::new(config.clone()));
+    let ops = FileOperations::new(validator, config);
+
+    // PNG signature followed by an IHDR chunk for a 640x480 image.
+    let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+    png.extend_from_slice(&640u32.to_be_bytes());
+    png.extend_from_slice(&480u32.to_be_bytes());
+    png.extend_from_slice(&[8, 6, 0, 0, 0]);
+    std::fs::write(root.join("shot.png"), &png).unwrap();
+    std::fs::write(root.join("broken.png"), b"not an image").unwrap();
+    std::fs::write(root.join("notes.txt"), "text\n").unwrap();
+
+    let dims = |name: &'static str| {
+        let ops = ops.clone();
+        let path = root.join(name);
+        async move {
+            ops.build_file_entry(&path, name, None)
+                .await
+                .unwrap()
+                .image_dimensions
+        }
+    };
+    assert_eq!(dims("shot.png").await, Some((640, 480)));
+    assert_eq!(dims("broken.png").await, None);
+    assert_eq!(dims("notes.txt").await, None);
+}
+
+#[tokio::test]
+async fn test_path_exists_reports_missing_without_error() {
+    let temp = TempDir::new().unwrap();
+    let root = temp_root(&temp);
+    let config = Arc::new(FileSystemConfig {
+        allowed_roots: vec![root.clone()],
+        ..Default::default()
+    });
+    let validator = Arc::new(PathValidator::new(config.clone()));
+    let ops = FileOperations::new(validator, config);
+
+    std::fs::write(root.join("file.txt"), "x").unwrap();
+    std::fs::create_dir(root.join("dir")).unwrap();
+    let check = |rel: &str| {
+        let ops = ops.clone();
+        let path = root.join(rel).to_string_lossy().to_string();
+        async move { ops.path_exists(&path).await }
+    };
+
+    let file = check("file.txt").await.unwrap();
+    assert!(file.exists && file.is_file && !file.is_directory);
+    let dir = check("dir").await.unwrap();
+    assert!(dir.exists && dir.is_directory && !dir.is_file);
+    assert!(!check("missing/deeper.txt").await.unwrap().exists);
+    assert!(!check("file.txt/child").await.unwrap().exists);
+
+    let outside = temp_root(&TempDir::new().unwrap()).join("x");
+    assert!(matches!(
+        ops.path_exists(&outside.to_string_lossy()).await,
+        Err(FileSystemError::OutsideAllowedRoots { .. })
+    ));
+    let traversal = format!("{}/../etc", root.to_string_lossy());
+    assert!(matches!(
+        ops.path_exists(&traversal).await,
+        Err(FileSystemError::PathTraversal { .. })
+    ));
+}
+
+#[tokio::test]
+async fn test_write_file_chunk_assembles_in_order() {
+    let temp = TempDir::new().unwrap();
+    let root = temp_root(&temp);
+    let config = Arc::new(FileSystemConfig {
+        allowed_roots: vec![root.clone()],
+        ..Default::default()
+    });
+    let validator = Arc::new(PathValidator::new(config.clone()));
+    let ops = FileOperations::new(validator, config);
+
+    let target = root.join("big.txt");
+    std::fs::write(&target, "old contents").unwrap();
+    let target_str = target.to_string_lossy().to_string();
+    let chunk = |data: &[u8]| (BASE64.encode(data), format!("{:x}", md5::compute(data)));
+
+    let (data, sum) = chunk(b"hello ");
+    let progress = ops
+        .write_file_chunk(&target_str, "u1", 0, 3, &data, &sum)
+        .await
+        .unwrap();
+    assert_eq!(progress.bytes_written, 6);
+    assert!(!progress.is_complete);
+
+    // A corrupted chunk is rejected and can be resent.
+    let (data, _) = chunk(b"big ");
+    let err = ops
+        .write_file_chunk(&target_str, "u1", 1, 3, &data, "0000")
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        FileSystemError::ChecksumMismatch { chunk_index: 1, .. }
+    ));
+
+    // Skipping ahead reports the chunk the daemon expects next.
+    let (data, sum) = chunk(b"world");
+    let err = ops
+        .write_file_chunk(&target_str, "u1", 2, 3, &data, &sum)
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        FileSystemError::UnexpectedChunk {
+            expected_index: 1,
+            ..
+        }
+    ));
+
+    let (data, sum) = chunk(b"big ");
+    ops.write_file_chunk(&target_str, "u1", 1, 3, &data, &sum)
+        .await
+        .unwrap();
+    // Resending the last chunk does not append it twice.
+    let resent = ops
+        .write_file_chunk(&target_str, "u1", 1, 3, &data, &sum)
+        .await
+        .unwrap();
+    assert_eq!(resent.bytes_written, 10);
+    assert_eq!(std::fs::read_to_string(&target).unwrap(), "old contents");
+
+    let (data, sum) = chunk(b"world");
+    let done = ops
+        .write_file_chunk(&target_str, "u1", 2, 3, &data, &sum)
+        .await
+        .unwrap();
+    assert!(done.is_complete);
+    assert_eq!(std::fs::read_to_string(&target).unwrap(), "hello big world");
+
+    let leftovers: Vec<_> = std::fs::read_dir(&root)
+        .unwrap()
+        .map(|e| e.unwrap().file_name())
+        .collect();
+    assert_eq!(leftovers, vec![std::ffi::OsString::from("big.txt")]);
+}
+
+#[tokio::test]
+async fn test_service_rebuild_keeps_uploads_and_watcher() {
+    let temp = TempDir::new().unwrap();
+    let root = temp_root(&temp);
+    let config = || FileSystemConfig {
+        allowed_roots: vec![root.clone()],
+        ..Default::default()
+    };
+    let service = FileSystemService::new(config());
+    let target = root.join("big.txt").to_string_lossy().to_string();
+    let chunk = |data: &[u8]| (BASE64.encode(data), format!("{:x}", md5::compute(data)));
+
+    let (data, sum) = chunk(b"hello ");
+    service
+        .ops()
+        .write_file_chunk(&target, "u1", 0, 2, &data, &sum)
+        .await
+        .unwrap();
+    let rebuilt = service.rebuild(config());
+    // Watches, subscribers and replay sequence numbers carry over.
+    assert!(std::ptr::eq(service.watcher(), rebuilt.watcher()));
+    drop(service);
+    let service = rebuilt;
+    let (data, sum) = chunk(b"world");
+    let done = service
+        .ops()
+        .write_file_chunk(&target, "u1", 1, 2, &data, &sum)
+        .await
+        .unwrap();
+    assert!(done.is_complete);
+    assert_eq!(std::fs::read_to_string(&target).unwrap(), "hello world");
+
+    // An upload still in progress when the service goes away leaves no temp file.
+    let (data, sum) = chunk(b"partial");
+    let other = root.join("other.txt").to_string_lossy().to_string();
+    service
+        .ops()
+        .write_file_chunk(&other, "u2", 0, 2, &data, &sum)
+        .await
+        .unwrap();
+    assert_eq!(std::fs::read_dir(&root).unwrap().count(), 2);
+    drop(service);
+    let leftovers: Vec<_> = std::fs::read_dir(&root)
+        .unwrap()
+        .map(|e| e.unwrap().file_name())
+        .collect();
+    assert_eq!(leftovers, vec![std::ffi::OsString::from("big.txt")]);
+}
+
+#[tokio::test]
+async fn test_write_file_fails_when_parent_is_file() {
+    let temp = TempDir::new().unwrap();
+    let root = temp_root(&temp);
+    let config = Arc::new(FileSystemConfig {
+        allowed_roots: vec![root.clone()],
+        ..Default::default()
+    });
+    let validator = Arc::new(PathValidator::new(config.clone()));
+    let ops = FileOperations::new(validator, config);
+
+    // Create a file
+    let file_path = root.join("existing_file.txt");
+    std::fs::write(&file_path, "content").unwrap();
+
+    // Try to write a file with the existing file in the parent path
+    let invalid_path = file_path.join("should_fail.txt");
+    let result = ops
+        .write_file(
+            &invalid_path.to_string_lossy(),
+            "test content",
+            crate::protocol::FileEncoding::Utf8,
+            true, // create_parents = true
+            None,
+        )
+        .await;
+
+    // Should fail with NotADirectory error
+    assert!(result.is_err());
+    match result.unwrap_err() {
+        crate::protocol::FileSystemError::NotADirectory { path } => {
+            assert_eq!(path, super::path_utils::to_protocol_path(&file_path));
+        }
+        e => panic!("Expected NotADirectory error, got: {:?}", e),
+    }
+}
+
+#[tokio::test]
+async fn test_write_file_overwrite_existing_file_succeeds() {
+    let temp = TempDir::new().unwrap();
+    let root = temp_root(&temp);
+    let config = Arc::new(FileSystemConfig {
+        allowed_roots: vec![root.clone()],
+        ..Default::default()
+    });
+    let validator = Arc::new(PathValidator::new(config.clone()));
+    let ops = FileOperations::new(validator, config);
+
+    let file_path = root.join("overwrite.txt");
+    let path = file_path.to_string_lossy().to_string();
+
+    ops.write_file(
+        &path,
+        "first",
+        crate::protocol::FileEncoding::Utf8,
+        true,
+        None,
+    )
+    .await
+    .unwrap();
+    ops.write_file(
+        &path,
+        "second",
+        crate::protocol::FileEncoding::Utf8,
+        true,
+        None,
+    )
+    .await
+    .unwrap();
+
+    let final_content = std::fs::read_to_string(&file_path).unwrap();
+    assert_eq!(final_content, "second");
+}
+
+#[tokio::test]
+async fn test_create_file_never_replaces_an_existing_file() {
+    use crate::protocol::FileEncoding::Utf8;
+
+    let temp = TempDir::new().unwrap();
+    let root = temp_root(&temp);
+    let config = Arc::new(FileSystemConfig {
+        allowed_roots: vec![root.clone()],
+        ..Default::default()
+    });
+    let validator = Arc::new(PathValidator::new(config.clone()));
+    let ops = FileOperations::new(validator, config);
+
+    let file_path = root.join("upload.txt");
+    let path = file_path.to_string_lossy().to_string();
+
+    ops.create_file(&path, "first", Utf8).await.unwrap();
+    let err = ops.create_file(&path, "second", Utf8).await.unwrap_err();
+    assert!(matches!(err, FileSystemError::AlreadyExists { .. }));
+    assert_eq!(std::fs::read_to_string(&file_path).unwrap(), "first");
+}
+
+#[tokio::test]
+async fn test_write_file_without_backup_replaces_in_place() {
+    use crate::protocol::FileEncoding::Utf8;
+
+    let temp = TempDir::new().unwrap();
+    let root = temp_root(&temp);
+    let config = Arc::new(FileSystemConfig {
+        allowed_roots: vec![root.clone()],
+        atomic_backup: false,
+        ..Default::default()
+    });
+    let validator = Arc::new(PathValidator::new(config.clone()));
+    let ops = FileOperations::new(validator, config);
+
+    let file_path = root.join("big.log");
+    let path = file_path.to_string_lossy().to_string();
+    std::fs::write(&file_path, "first").unwrap();
+
+    ops.write_file(&path, "second", Utf8, false, None)
+        .await
+        .unwrap();
+
+    assert_eq!(std::fs::read_to_string(&file_path).unwrap(), "second");
+    // Neither a backup nor the temp file is left behind.
+    assert_eq!(std::fs::read_dir(&root).unwrap().count(), 1);
+}
+
+#[tokio::test]
+async fn test_write_file_detects_conflicting_edits() {
+    use crate::protocol::FileEncoding::Utf8;
+
+    let temp = TempDir::new().unwrap();
+    let root = temp_root(&temp);
+    let config = Arc::new(FileSystemConfig {
+        allowed_roots: vec![root.clone()],
+        ..Default::default()
+    });
+    let validator = Arc::new(PathValidator::new(config.clone()));
+    let ops = FileOperations::new(validator, config);
+
+    let file_path = root.join("shared.txt");
+    let path = file_path.to_string_lossy().to_string();
+    std::fs::write(&file_path, "original").unwrap();
+    let read_modified = ops.get_file_info(&path).await.unwrap().modified;
+
+    // Someone else saves in between; push the mtime well past the one read.
+    std::fs::write(&file_path, "theirs").unwrap();
+    let later = filetime::FileTime::from_unix_time((read_modified / 1000) as i64 + 5, 0);
+    filetime::set_file_mtime(&file_path, later).unwrap();
+
+    let err = ops
+        .write_file(&path, "mine", Utf8, false, Some(read_modified))
+        .await
+        .unwrap_err();
+    match err {
+        FileSystemError::Conflict {
+            current_modified, ..
+        } => assert_ne!(current_modified, Some(read_modified)),
+        e => panic!("Expected Conflict, got: {:?}", e),
+    }
+    assert_eq!(std::fs::read_to_string(&file_path).unwrap(), "theirs");
+    assert_eq!(std::fs::read_dir(&root).unwrap().count(), 1);
+
+    let current = ops.get_file_info(&path).await.unwrap().modified;
+    ops.write_file(&path, "mine", Utf8, false, Some(current))
+        .await
+        .unwrap();
+    assert_eq!(std::fs::read_to_string(&file_path).unwrap(), "mine");
+}
+
+#[tokio::test]
+async fn test_read_file_decodes_windows_1252_text() {
+    let temp = TempDir::new().unwrap();
+    let root = temp_root(&temp);
+    let config = Arc::new(FileSystemConfig {
+        allowed_roots: vec![root.clone()],
+        ..Default::default()
+    });
+    let validator = Arc::new(PathValidator::new(config.clone()));
+    let ops = FileOperations::new(validator, config);
+
+    let file_path = root.join("legacy.dat");
+    // Accented French text encoded as Windows-1252.
+    std::fs::write(
+        &file_path,
+        b"Caf\xe9 cr\xe8me br\xfbl\xe9e, na\xefve fa\xe7ade\n",
+    )
+    .unwrap();
+
+    let file = ops
+        .read_file(
+            &file_path.to_string_lossy(),
+            None,
+            None,
+            crate::protocol::FileEncoding::Utf8,
+            false,
+        )
+        .await
+        .unwrap();
+    assert!(matches!(file.encoding, crate::protocol::FileEncoding::Utf8));
+    assert_eq!(
+        file.content,
+        "Caf\u{e9} cr\u{e8}me br\u{fb}l\u{e9}e, na\u{ef}ve fa\u{e7}ade\n"
+    );
+    assert_eq!(file.detected_encoding, "windows-1252");
+
+    let utf8_path = root.join("plain.txt");
+    std::fs::write(&utf8_path, "hello\n").unwrap();
+    let file = ops
+        .read_file(
+            &utf8_path.to_string_lossy(),
+            None,
+            None,
+            crate::protocol::FileEncoding::Utf8,
+            false,
+        )
+        .await
+        .unwrap();
+    assert_eq!(file.detected_encoding, "UTF-8");
+}
+
+#[tokio::test]
+async fn test_read_file_reports_line_endings_and_indent() {
+    use crate::protocol::{FileEncoding, IndentKind, IndentStyle, LineEnding};
+
+    let temp = TempDir::new().unwrap();
+    let root = temp_root(&temp);
+    let config = Arc::new(FileSystemConfig {
+        allowed_roots: vec![root.clone()],
+        ..Default::default()
+    });
+    let validator = Arc::new(PathValidator::new(config.clone()));
+    let ops = FileOperations::new(validator, config);
+
+    let cases = [
+        (
+            "two.yaml",
+            &b"a:\n  b:\n    c: 1\n  d: 2\n"[..],
+            Some(LineEnding::Lf),
+            Some(IndentStyle {
+                kind: IndentKind::Spaces,
+                width: 2,
+            }),
+        ),
+        (
+            "tabs.go",
+            &b"func main() {\r\n\tif x {\r\n\t\ty()\r\n\t}\r\n}\r\n"[..],
+            Some(LineEnding::Crlf),
+            Some(IndentStyle {
+                kind: IndentKind::Tabs,
+                width: 1,
+            }),
+        ),
+        (
+            "mixed.txt",
+            &b"one\r\ntwo\nthree"[..],
+            Some(LineEnding::Mixed),
+            None,
+        ),
+        (
+            "logo.png",
+            &b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"[..],
+            None,
+            None,
+        ),
+    ];
+    for (name, bytes, line_ending, indent) in cases {
+        let file_path = root.join(name);
+        std::fs::write(&file_path, bytes).unwrap();
+        let file = ops
+            .read_file(
+                &file_path.to_string_lossy(),
+                None,
+                None,
+                FileEncoding::Utf8,
+                false,
+            )
+            .await
+            .unwrap();
+        assert_eq!(file.line_ending, line_ending, "{name}");
+        assert_eq!(file.indent, indent, "{name}");
+    }
+}
+
+#[tokio::test]
+async fn test_read_file_lines_returns_inclusive_range() {
+    let temp = TempDir::new().unwrap();
+    let root = temp_root(&temp);
+    let config = Arc::new(FileSystemConfig {
+        allowed_roots: vec![root.clone()],
+        ..Default::default()
+    });
+    let validator = Arc::new(PathValidator::new(config.clone()));
+    let ops = FileOperations::new(validator, config);
+
+    let file_path = root.join("lines.txt");
+    std::fs::write(&file_path, "one\r\ntwo\nthree\nfour\n").unwrap();
+    let path = file_path.to_string_lossy().to_string();
+
+    let range = ops.read_file_lines(&path, 2, 3).await.unwrap();
+    assert_eq!(range.start_line, 2);
+    assert_eq!(range.lines, vec!["two".to_string(), "three".to_string()]);
+    assert_eq!(range.total_lines, 4);
+    assert!(!range.truncated);
+
+    let past_end = ops.read_file_lines(&path, 4, 10).await.unwrap();
+    assert_eq!(past_end.lines, vec!["four".to_string()]);
+
+    assert!(ops.read_file_lines(&path, 0, 1).await.is_err());
+    assert!(ops.read_file_lines(&path, 3, 2).await.is_err());
+}
+
+#[tokio::test]
+async fn test_read_file_lines_marks_truncated_past_max_read_size() {
+    let temp = TempDir::new().unwrap();
+    let root = temp_root(&temp);
+    let config = Arc::new(FileSystemConfig {
+        allowed_roots: vec![root.clone()],
+        max_read_size: 8,
+        ..Default::default()
+    });
+    let validator = Arc::new(PathValidator::new(config.clone()));
+    let ops = FileOperations::new(validator, config);
+
+    let file_path = root.join("big.txt");
+    std::fs::write(&file_path, "aaa\nbbb\nccc\nddd\n").unwrap();
+
+    let range = ops
+        .read_file_lines(&file_path.to_string_lossy(), 1, 100)
+        .await
+        .unwrap();
+    assert_eq!(range.lines, vec!["aaa".to_string(), "bbb".to_string()]);
+    assert_eq!(range.total_lines, 2);
+    assert!(range.truncated);
+}
+
+#[tokio::test]
+async fn test_read_file_lines_caps_a_single_oversized_line() {
+    let temp = TempDir::new().unwrap();
+    let root = temp_root(&temp);
+    let config = Arc::new(FileSystemConfig {
+        allowed_roots: vec![root.clone()],
+        max_read_size: 8,
+        ..Default::default()
+    });
+    let validator = Arc::new(PathValidator::new(config.clone()));
+    let ops = FileOperations::new(validator, config);
+
+    let file_path = root.join("minified.js");
+    std::fs::write(&file_path, "x".repeat(1000)).unwrap();
+
+    let range = ops
+        .read_file_lines(&file_path.to_string_lossy(), 1, 1)
+        .await
+        .unwrap();
+    assert_eq!(range.lines, vec!["x".repeat(8)]);
+    assert_eq!(range.total_lines, 1);
+    assert!(range.truncated);
+}
+
+#[tokio::test]
+async fn test_tail_file_reads_appends_and_resets_after_truncation() {
+    let temp = TempDir::new().unwrap();
+    let root = temp_root(&temp);
+    let config = Arc::new(FileSystemConfig {
+        allowed_roots: vec![root.clone()],
+        ..Default::default()
+    });
+    let validator = Arc::new(PathValidator::new(config.clone()));
+    let ops = FileOperations::new(validator, config);
+
+    let file_path = root.join("build.log");
+    std::fs::write(&file_path, "line one\nline two\n").unwrap();
+    let path = file_path.to_string_lossy().to_string();
+
+    let (_, data, size) = ops.tail_file(&path, 9).await.unwrap();
+    assert_eq!(data, b"line two\n");
+    assert_eq!(size, 18);
+
+    std::fs::write(&file_path, "line one\nline two\nline three\n").unwrap();
+    let (appended, offset) = ops.read_appended(&path, size, 4).await.unwrap();
+    assert_eq!(appended, b"line");
+    let (appended, offset) = ops.read_appended(&path, offset, 1024).await.unwrap();
+    assert_eq!(appended, b" three\n");
+    let (appended, _) = ops.read_appended(&path, offset, 1024).await.unwrap();
+    assert!(appended.is_empty());
+
+    std::fs::write(&file_path, "new\n").unwrap();
+    let (appended, offset) = ops.read_appended(&path, offset, 1024).await.unwrap();
+    assert_eq!(appended, b"new\n");
+    assert_eq!(offset, 4);
+}
+
+#[tokio::test]
+async fn test_file_checksum_streams_md5_and_sha256() {
+    let temp = TempDir::new().unwrap();
+    let root = temp_root(&temp);
+    let config = Arc::new(FileSystemConfig {
+        allowed_roots: vec![root.clone()],
+        ..Default::default()
+    });
+    let validator = Arc::new(PathValidator::new(config.clone()));
+    let ops = FileOperations::new(validator, config);
+
+    let file_path = root.join("hello.txt");
+    std::fs::write(&file_path, "hello world").unwrap();
+    let path = file_path.to_string_lossy().to_string();
+
+    let (_, md5, size) = ops
+        .file_checksum(&path, ChecksumAlgorithm::Md5)
+        .await
+        .unwrap();
+    assert_eq!(md5, "5eb63bbbe01eeed093cb22bb8f5acdc3");
+    assert_eq!(size, 11);
+
+    let (_, sha256, _) = ops
+        .file_checksum(&path, ChecksumAlgorithm::Sha256)
+        .await
+        .unwrap();
+    assert_eq!(
+        sha256,
+        "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9"
+    );
+
+    assert!(ops
+        .file_checksum(&root.to_string_lossy(), ChecksumAlgorithm::Md5)
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn test_move_by_copy_matches_rename_result() {
+    let temp = TempDir::new().unwrap();
+    let root = temp_root(&temp);
+    let config = Arc::new(FileSystemConfig {
+        allowed_roots: vec![root.clone()],
+        ..Default::default()
+    });
+    let validator = Arc::new(PathValidator::new(config.clone()));
+    let ops = FileOperations::new(validator, config);
+
+    for name in ["renamed", "copied"] {
+        let src = root.join(format!("{}-src", name));
+        std::fs::create_dir_all(src.join("nested")).unwrap();
+        std::fs::write(src.join("a.txt"), "a").unwrap();
+        std::fs::write(src.join("nested").join("b.txt"), "b").unwrap();
+    }
+
+    ops.rename_path(
+        &root.join("renamed-src").to_string_lossy(),
+        &root.join("renamed-dst").to_string_lossy(),
+    )
+    .await
+    .unwrap();
+    ops.move_by_copy(&root.join("copied-src"), &root.join("copied-dst"))
+        .await
+        .unwrap();
+
+    for name in ["renamed", "copied"] {
+        let dst = root.join(format!("{}-dst", name));
+        assert!(!root.join(format!("{}-src", name)).exists());
+        assert_eq!(std::fs::read_to_string(dst.join("a.txt")).unwrap(), "a");
+        assert_eq!(
+            std::fs::read_to_string(dst.join("nested").join("b.txt")).unwrap(),
+            "b"
+        );
+    }
+
+    // A destination that is already there is never cleaned up.
+    let src = root.join("keep-src.txt");
+    let dst = root.join("keep-dst.txt");
+    std::fs::write(&src, "new").unwrap();
+    std::fs::write(&dst, "precious").unwrap();
+    let err = ops.move_by_copy(&src, &dst).await.unwrap_err();
+    assert!(matches!(err, FileSystemError::AlreadyExists { .. }));
+    assert_eq!(std::fs::read_to_string(&dst).unwrap(), "precious");
+    assert_eq!(std::fs::read_to_string(&src).unwrap(), "new");
+}
+
+#[tokio::test]
+async fn test_create_directory_respects_read_only_patterns() {
+    let temp = TempDir::new().unwrap();
+    let root = temp_root(&temp);
+    let config = Arc::new(FileSystemConfig {
+        allowed_roots: vec![root.clone()],
+        read_only_patterns: vec![format!("{}/**", root.display())],
+        ..Default::default()
+    });
+    let validator = Arc::new(PathValidator::new(config.clone()));
+    let ops = FileOperations::new(validator, config);
+
+    let dir_path = root.join("newdir");
+    let result = ops
+        .create_directory(&dir_path.to_string_lossy(), true)
+        .await;
+
+    assert!(result.is_err());
+    match result.unwrap_err() {
+        crate::protocol::FileSystemError::PermissionDenied { .. } => {}
+        e => panic!("Expected PermissionDenied, got: {:?}", e),
+    }
+}
+
+#[tokio::test]
+async fn test_copy_path_respects_read_only_patterns() {
+    let temp = TempDir::new().unwrap();
+    let root = temp_root(&temp);
+    let src = root.join("src.txt");
+    std::fs::write(&src, "hi").unwrap();
+
+    let config = Arc::new(FileSystemConfig {
+        allowed_roots: vec![root.clone()],
+        read_only_patterns: vec![format!("{}/**", root.display())],
+        ..Default::default()
+    });
+    let validator = Arc::new(PathValidator::new(config.clone()));
+    let ops = FileOperations::new(validator, config);
+
+    let dest = root.join("dest.txt");
+    let result = ops
+        .copy_path(
+            &src.to_string_lossy(),
+            &dest.to_string_lossy(),
+            false,
+            false,
+        )
+        .await;
+
+    assert!(result.is_err());
+    match result.unwrap_err() {
+        crate::protocol::FileSystemError::PermissionDenied { .. } => {}
+        e => panic!("Expected PermissionDenied, got: {:?}", e),
+    }
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_copy_path_preserves_metadata_recursively() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let temp = TempDir::new().unwrap();
+    let root = temp_root(&temp);
+    let src = root.join("src");
+    std::fs::create_dir_all(src.join("nested")).unwrap();
+    let script = src.join("nested").join("run.sh");
+    std::fs::write(&script, "#!/bin/sh\n").unwrap();
+    std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o750)).unwrap();
+    std::fs::set_permissions(src.join("nested"), std::fs::Permissions::from_mode(0o700)).unwrap();
+
+    let old = filetime::FileTime::from_unix_time(1_600_000_000, 0);
+    filetime::set_file_mtime(&script, old).unwrap();
+    filetime::set_file_mtime(src.join("nested"), old).unwrap();
+
+    let config = Arc::new(FileSystemConfig {
+        allowed_roots: vec![root.clone()],
+        ..Default::default()
+    });
+    let validator = Arc::new(PathValidator::new(config.clone()));
+    let ops = FileOperations::new(validator, config);
+
+    let dest = root.join("dest");
+    ops.copy_path(&src.to_string_lossy(), &dest.to_string_lossy(), true, true)
+        .await
+        .unwrap();
+
+    for path in [dest.join("nested"), dest.join("nested").join("run.sh")] {
+        let metadata = std::fs::metadata(&path).unwrap();
+        assert_eq!(
+            filetime::FileTime::from_last_modification_time(&metadata),
+            old,
+            "mtime not preserved for {}",
+            path.display()
+        );
+    }
+    let mode = |path: std::path::PathBuf| std::fs::metadata(path).unwrap().permissions().mode();
+    assert_eq!(mode(dest.join("nested")) & 0o777, 0o700);
+    assert_eq!(mode(dest.join("nested").join("run.sh")) & 0o777, 0o750);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_path_validation_blocks_symlink_paths_when_disabled() {
+    use std::os::unix::fs::symlink;
+
+    let temp = TempDir::new().unwrap();
+    let root = temp_root(&temp);
+    let real_dir = root.join("real");
+    std::fs::create_dir_all(&real_dir).unwrap();
+
+    let link_dir = root.join("link");
+    symlink(&real_dir, &link_dir).unwrap();
+
+    let file_path = real_dir.join("file.txt");
+    std::fs::write(&file_path, "content").unwrap();
+
+    let config = Arc::new(FileSystemConfig {
+        allowed_roots: vec![root],
+        follow_symlinks: false,
+        ..Default::default()
+    });
+    let validator = PathValidator::new(config);
+
+    let via_link = link_dir.join("file.txt");
+    let err = validator
+        .validate_existing(&via_link.to_string_lossy())
+        .unwrap_err();
+
+    match err {
+        crate::protocol::FileSystemError::PermissionDenied { .. } => {}
+        other => panic!("expected PermissionDenied, got: {:?}", other),
+    }
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_copy_directory_blocks_symlink_entries() {
+    use std::os::unix::fs::symlink;
+
+    let temp = TempDir::new().unwrap();
+    let root = temp_root(&temp);
+
+    let src_dir = root.join("srcdir");
+    std::fs::create_dir_all(&src_dir).unwrap();
+    std::fs::write(src_dir.join("a.txt"), "a").unwrap();
+
+    // Symlink inside the directory should be rejected for recursive copy.
+    let target = root.join("real_target.txt");
+    std::fs::write(&target, "target").unwrap();
+    symlink(&target, src_dir.join("link.txt")).unwrap();
+
+    let config = Arc::new(FileSystemConfig {
+        allowed_roots: vec![root.clone()],
+        follow_symlinks: false,
+        ..Default::default()
+    });
+    let validator = Arc::new(PathValidator::new(config.clone()));
+    let ops = FileOperations::new(validator, config);
+
+    let dest_dir = root.join("destdir");
+    let result = ops
+        .copy_path(
+            &src_dir.to_string_lossy(),
+            &dest_dir.to_string_lossy(),
+            true,
+            false,
+        )
+        .await;
+
+    assert!(result.is_err());
+    match result.unwrap_err() {
+        crate::protocol::FileSystemError::PermissionDenied { .. } => {}
+        e => panic!("Expected PermissionDenied, got: {:?}", e),
+    }
+}
+
+#[tokio::test]
+async fn test_recent_files_newest_first_within_window() {
+    let temp = TempDir::new().unwrap();
+    let root = temp_root(&temp);
+    let config = Arc::new(FileSystemConfig {
+        allowed_roots: vec![root.clone()],
+        ..Default::default()
+    });
+    let validator = Arc::new(PathValidator::new(config.clone()));
+    let search = FileSearch::new(FileOperations::new(validator, config));
+
+    std::fs::create_dir_all(root.join(".git")).unwrap();
+    std::fs::create_dir_all(root.join("src/deep")).unwrap();
+    std::fs::write(root.join(".gitignore"), "build.log\n").unwrap();
+    let files = [
+        ("old.txt", 1_000),
+        ("src/lib.rs", 3_000),
+        ("src/deep/mod.rs", 4_000),
+        ("build.log", 5_000),
+        (".env", 6_000),
+    ];
+    for (name, secs) in files {
+        let path = root.join(name);
+        std::fs::write(&path, name).unwrap();
+        filetime::set_file_mtime(&path, filetime::FileTime::from_unix_time(secs, 0)).unwrap();
+    }
+    let gitignore = filetime::FileTime::from_unix_time(2_000, 0);
+    filetime::set_file_mtime(root.join(".gitignore"), gitignore).unwrap();
+
+    let (_, entries, truncated) = search
+        .recent_files(&root.to_string_lossy(), 3, None, false)
+        .await
+        .unwrap();
+    assert!(!truncated);
+    let names: Vec<&str> = entries.iter().map(|e| e.name.as_str()).collect();
+    // `.env` is denied by default.
+    assert_eq!(names, ["build.log", "mod.rs", "lib.rs"]);
+
+    let (_, entries, _) = search
+        .recent_files(&root.to_string_lossy(), 10, Some(2_500_000), true)
+        .await
+        .unwrap();
+    let names: Vec<&str> = entries.iter().map(|e| e.name.as_str()).collect();
+    assert_eq!(names, ["mod.rs", "lib.rs"]);
+}
+
+#[tokio::test]
+async fn test_search_files_sorts_by_relevance_when_requested() {
+    let temp = TempDir::new().unwrap();
+    let root = temp_root(&temp);
+    let config = Arc::new(FileSystemConfig {
+        allowed_roots: vec![root.clone()],
+        ..Default::default()
+    });
+    let validator = Arc::new(PathValidator::new(config.clone()));
+    let search = FileSearch::new(FileOperations::new(validator, config));
+
+    std::fs::write(root.join("one.txt"), "todo\n").unwrap();
+    std::fs::write(root.join("three.txt"), "todo\ntodo\ntodo\n").unwrap();
+    std::fs::write(root.join("b_two.txt"), "todo\ntodo\n").unwrap();
+    std::fs::write(root.join("a_two.txt"), "todo\ntodo\n").unwrap();
+    std::fs::write(root.join("none.txt"), "nothing here\n").unwrap();
+
+    let options = SearchOptions {
+        content_pattern: Some("todo".to_string()),
+        max_results: 100,
+        sort_by_relevance: true,
+        ..Default::default()
+    };
+    let outcome = search
+        .search_files(&root.to_string_lossy(), "*.txt", &options)
+        .await
+        .unwrap();
+    assert!(!outcome.truncated);
+    let names: Vec<&str> = outcome
+        .matches
+        .iter()
+        .map(|m| m.entry.name.as_str())
+        .collect();
+    assert_eq!(
+        names,
+        ["three.txt", "a_two.txt", "b_two.txt", "one.txt", "none.txt"]
+    );
+}
+
+#[tokio::test]
+async fn test_search_files_stops_when_cancelled() {
+    let temp = TempDir::new().unwrap();
+    let root = temp_root(&temp);
+    let config = Arc::new(FileSystemConfig {
+        allowed_roots: vec![root.clone()],
+        ..Default::default()
+    });
+    let validator = Arc::new(PathValidator::new(config.clone()));
+    let search = FileSearch::new(FileOperations::new(validator, config));
+    for i in 0..10 {
+        std::fs::write(root.join(format!("file{i}.txt")), "x").unwrap();
+    }
+
+    let control = Arc::new(SearchControl::default());
+    control.cancel();
+    let options = SearchOptions {
+        max_results: 100,
+        control: Some(control.clone()),
+        ..Default::default()
+    };
+    let outcome = search
+        .search_files(&root.to_string_lossy(), "*.txt", &options)
+        .await
+        .unwrap();
+    assert!(outcome.cancelled);
+    assert!(outcome.truncated);
+    assert!(outcome.matches.is_empty());
+    assert_eq!(
+        control.scanned.load(std::sync::atomic::Ordering::Relaxed),
+        0
+    );
+}
+
+#[tokio::test]
+async fn test_search_files_fuzzy_ranks_by_score() {
+    let temp = TempDir::new().unwrap();
+    let root = temp_root(&temp);
+    let config = Arc::new(FileSystemConfig {
+        allowed_roots: vec![root.clone()],
+        ..Default::default()
+    });
+    let validator = Arc::new(PathValidator::new(config.clone()));
+    let search = FileSearch::new(FileOperations::new(validator, config));
+    std::fs::write(root.join("config.yaml"), "").unwrap();
+    std::fs::write(root.join("cfg.yml"), "").unwrap();
+    std::fs::write(root.join("README.md"), "").unwrap();
+    std::fs::write(root.join("c-is-for-fig-and-yellow-melon.txt"), "").unwrap();
+    // Every query character is there, but too far apart to be a useful hit.
+    let scattered = "cfgyml"
+        .chars()
+        .map(|c| format!("xxxxxxxxxx{c}"))
+        .collect::<String>();
+    std::fs::write(root.join(scattered), "").unwrap();
+
+    let options = SearchOptions {
+        max_results: 100,
+        fuzzy: true,
+        ..Default::default()
+    };
+    let outcome = search
+        .search_files(&root.to_string_lossy(), "cfgyml", &options)
+        .await
+        .unwrap();
+    let names: Vec<&str> = outcome
+        .matches
+        .iter()
+        .map(|m| m.entry.name.as_str())
+        .collect();
+    assert_eq!(
+        names,
+        [
+            "cfg.yml",
+            "config.yaml",
+            "c-is-for-fig-and-yellow-melon.txt"
+        ]
+    );
+    assert!(outcome.matches[0].score > outcome.matches[1].score);
+}
+
+#[tokio::test]
+async fn test_search_files_fuzzy_keeps_the_best_matches_past_max_results() {
+    let temp = TempDir::new().unwrap();
+    let root = temp_root(&temp);
+    let config = Arc::new(FileSystemConfig {
+        allowed_roots: vec![root.clone()],
+        ..Default::default()
+    });
+    let validator = Arc::new(PathValidator::new(config.clone()));
+    let search = FileSearch::new(FileOperations::new(validator, config));
+    for i in 0..40 {
+        let dir = root.join(format!("d{i}"));
+        std::fs::create_dir(&dir).unwrap();
+        std::fs::write(dir.join(format!("config-{i}.yaml")), "").unwrap();
+    }
+    std::fs::write(root.join("d17").join("cfg.yml"), "").unwrap();
+
+    let options = SearchOptions {
+        max_results: 1,
+        fuzzy: true,
+        ..Default::default()
+    };
+    let outcome = search
+        .search_files(&root.to_string_lossy(), "cfgyml", &options)
+        .await
+        .unwrap();
+    let names: Vec<&str> = outcome
+        .matches
+        .iter()
+        .map(|m| m.entry.name.as_str())
+        .collect();
+    assert_eq!(names, ["cfg.yml"]);
+    assert!(!outcome.truncated);
+}
+
+#[tokio::test]
+async fn test_content_search_skips_binaries_and_oversized_files() {
+    let temp = TempDir::new().unwrap();
+    let root = temp_root(&temp);
+    let config = Arc::new(FileSystemConfig {
+        allowed_roots: vec![root.clone()],
+        max_search_file_size: 64,
+        ..Default::default()
+    });
+    let validator = Arc::new(PathValidator::new(config.clone()));
+    let search = FileSearch::new(FileOperations::new(validator, config));
+
+    std::fs::write(root.join("notes.txt"), "needle\n").unwrap();
+    let mut blob = vec![0u8, 159, 146, 150, 0, 1, 2];
+    blob.extend_from_slice(b"\nneedle\n");
+    std::fs::write(root.join("blob.bin"), blob).unwrap();
+    std::fs::write(
+        root.join("big.txt"),
+        format!("{}\nneedle\n", "x".repeat(100)),
+    )
+    .unwrap();
+
+    let options = SearchOptions {
+        content_pattern: Some("needle".to_string()),
+        max_results: 100,
+        ..Default::default()
+    };
+    let outcome = search
+        .search_files(&root.to_string_lossy(), "*", &options)
+        .await
+        .unwrap();
+    let with_hits: Vec<&str> = outcome
+        .matches
+        .iter()
+        .filter(|m| m.content_matches.is_some())
+        .map(|m| m.entry.name.as_str())
+        .collect();
+    assert_eq!(with_hits, ["notes.txt"]);
+}
+
+#[tokio::test]
+async fn test_delete_preview_counts_tree_without_deleting() {
+    let temp = TempDir::new().unwrap();
+    let root = temp_root(&temp);
+    let config = Arc::new(FileSystemConfig {
+        allowed_roots: vec![root.clone()],
+        ..Default::default()
+    });
+    let validator = Arc::new(PathValidator::new(config.clone()));
+    let ops = FileOperations::new(validator, config);
+
+    let dir = root.join("build");
+    std::fs::create_dir_all(dir.join("nested/deeper")).unwrap();
+    std::fs::write(dir.join("a.o"), [0u8; 100]).unwrap();
+    std::fs::write(dir.join("nested/b.o"), [0u8; 20]).unwrap();
+    std::fs::write(dir.join("nested/deeper/c.o"), [0u8; 3]).unwrap();
+
+    let preview = ops.delete_preview(&dir.to_string_lossy()).await.unwrap();
+    assert!(preview.is_directory);
+    // build, a.o, nested, b.o, deeper, c.o
+    assert_eq!(preview.entry_count, 6);
+    assert_eq!(preview.total_bytes, 123);
+    assert!(dir.join("nested/deeper/c.o").exists());
+
+    let file = ops
+        .delete_preview(&dir.join("a.o").to_string_lossy())
+        .await
+        .unwrap();
+    assert!(!file.is_directory);
+    assert_eq!((file.entry_count, file.total_bytes), (1, 100));
+}
+
+#[tokio::test]
+async fn test_dry_runs_validate_without_touching_files() {
+    let temp = TempDir::new().unwrap();
+    let root = temp_root(&temp);
+    let config = Arc::new(FileSystemConfig {
+        allowed_roots: vec![root.clone()],
+        ..Default::default()
+    });
+    let validator = Arc::new(PathValidator::new(config.clone()));
+    let ops = FileOperations::new(validator, config);
+
+    let src = root.join("src");
+    std::fs::create_dir_all(src.join("nested")).unwrap();
+    std::fs::write(src.join("a.txt"), "a").unwrap();
+    std::fs::write(src.join("nested/b.txt"), "b").unwrap();
+    std::fs::write(root.join("taken.txt"), "x").unwrap();
+    let p = |path: &std::path::Path| super::path_utils::to_protocol_path(path);
+    let s = |path: &std::path::Path| path.to_string_lossy().to_string();
+
+    let copy = ops
+        .copy_dry_run(&s(&src), &s(&root.join("copy")), true)
+        .await
+        .unwrap();
+    let mut affected = copy.affected_paths.clone();
+    affected.sort();
+    assert_eq!(
+        affected,
+        [
+            p(&root.join("copy")),
+            p(&root.join("copy/a.txt")),
+            p(&root.join("copy/nested")),
+            p(&root.join("copy/nested/b.txt")),
+        ]
+    );
+    assert!(copy.conflicts.is_empty());
+    assert!(!root.join("copy").exists());
+
+    let copy = ops
+        .copy_dry_run(&s(&src.join("a.txt")), &s(&root.join("taken.txt")), false)
+        .await
+        .unwrap();
+    assert_eq!(copy.conflicts, [p(&root.join("taken.txt"))]);
+
+    let rename = ops
+        .rename_dry_run(&s(&src), &s(&root.join("taken.txt")))
+        .unwrap();
+    assert_eq!(rename.affected_paths, [p(&src)]);
+    assert_eq!(rename.conflicts, [p(&root.join("taken.txt"))]);
+
+    let delete = ops.delete_dry_run(&s(&src), true).await.unwrap();
+    assert_eq!(delete.affected_paths.len(), 4);
+    assert!(src.join("nested/b.txt").exists());
+    assert!(matches!(
+        ops.delete_dry_run(&s(&src), false).await,
+        Err(FileSystemError::NotEmpty { .. })
+    ));
+}
+
+#[tokio::test]
+async fn test_create_archive_skips_denied_and_enforces_cap() {
+    use std::io::Read;
+
+    let temp = TempDir::new().unwrap();
+    let root = temp_root(&temp);
+    let dir = root.join("dist");
+    std::fs::create_dir_all(dir.join("assets")).unwrap();
+    std::fs::write(dir.join("index.html"), "<html></html>").unwrap();
+    std::fs::write(dir.join("assets/app.js"), "console.log(1)").unwrap();
+    std::fs::write(dir.join(".env"), "SECRET=1").unwrap();
+
+    let config = Arc::new(FileSystemConfig {
+        allowed_roots: vec![root.clone()],
+        ..Default::default()
+    });
+    let validator = Arc::new(PathValidator::new(config.clone()));
+    let ops = FileOperations::new(validator, config);
+
+    let archive = ops
+        .create_archive(&dir.to_string_lossy(), ArchiveFormat::Zip)
+        .await
+        .unwrap();
+    assert!(archive.name.ends_with("/dist.zip"));
+    assert_eq!(archive.skipped, 1);
+    let file = std::fs::File::open(&archive.file_path).unwrap();
+    assert_eq!(file.metadata().unwrap().len(), archive.size);
+    let mut zip = zip::ZipArchive::new(file).unwrap();
+    let mut names: Vec<String> = zip.file_names().map(str::to_string).collect();
+    names.sort();
+    assert_eq!(
+        names,
+        vec![
+            "dist/",
+            "dist/assets/",
+            "dist/assets/app.js",
+            "dist/index.html"
+        ]
+    );
+    let mut content = String::new();
+    zip.by_name("dist/assets/app.js")
+        .unwrap()
+        .read_to_string(&mut content)
+        .unwrap();
+    assert_eq!(content, "console.log(1)");
+    let temp_file = archive.file_path.clone();
+    drop(archive);
+    assert!(!temp_file.exists());
+
+    let config = Arc::new(FileSystemConfig {
+        allowed_roots: vec![root.clone()],
+        max_archive_size: 20,
+        ..Default::default()
+    });
+    let validator = Arc::new(PathValidator::new(config.clone()));
+    let ops = FileOperations::new(validator, config);
+    let err = ops
+        .create_archive(&dir.to_string_lossy(), ArchiveFormat::Zip)
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        FileSystemError::FileTooLarge { max_size: 20, .. }
+    ));
+}
+
+#[tokio::test]
+async fn test_extract_archive_unpacks_and_rejects_zip_slip() {
+    use std::io::Write;
+
+    let temp = TempDir::new().unwrap();
+    let root = temp_root(&temp);
+    let config = Arc::new(FileSystemConfig {
+        allowed_roots: vec![root.clone()],
+        ..Default::default()
+    });
+    let validator = Arc::new(PathValidator::new(config.clone()));
+    let ops = FileOperations::new(validator, config);
+
+    let tarball = root.join("site.tar.gz");
+    {
+        let gz = flate2::write::GzEncoder::new(
+            std::fs::File::create(&tarball).unwrap(),
+            flate2::Compression::default(),
+        );
+        let mut tar = tar::Builder::new(gz);
+        let mut header = tar::Header::new_gnu();
+        header.set_size(5);
+        header.set_mode(0o644);
+        header.set_cksum();
+        tar.append_data(&mut header, "docs/readme.md", &b"hello"[..])
+            .unwrap();
+        tar.into_inner().unwrap().finish().unwrap();
+    }
+    let dest = root.join("out");
+    let extracted = ops
+        .extract_archive(&tarball.to_string_lossy(), &dest.to_string_lossy())
+        .await
+        .unwrap();
+    assert_eq!(extracted.files.len(), 1);
+    assert!(extracted.files[0].ends_with("out/docs/readme.md"));
+    assert_eq!(
+        std::fs::read_to_string(dest.join("docs/readme.md")).unwrap(),
+        "hello"
+    );
+
+    // Extracting again would overwrite readme.md.
+    let err = ops
+        .extract_archive(&tarball.to_string_lossy(), &dest.to_string_lossy())
+        .await
+        .unwrap_err();
+    assert!(matches!(err, FileSystemError::AlreadyExists { .. }));
+
+    let evil = root.join("evil.zip");
+    {
+        let mut zip = zip::ZipWriter::new(std::fs::File::create(&evil).unwrap());
+        let options = zip::write::FileOptions::default();
+        zip.start_file("ok.txt", options).unwrap();
+        zip.write_all(b"fine").unwrap();
+        zip.start_file("../escaped.txt", options).unwrap();
+        zip.write_all(b"nope").unwrap();
+        zip.finish().unwrap();
+    }
+    let dest = root.join("unzipped");
+    let err = ops
+        .extract_archive(&evil.to_string_lossy(), &dest.to_string_lossy())
+        .await
+        .unwrap_err();
+    assert!(matches!(err, FileSystemError::PathTraversal { .. }));
+    assert!(!dest.join("ok.txt").exists());
+    assert!(!root.join("escaped.txt").exists());
+
+    let secrets = root.join("secrets.zip");
+    {
+        let mut zip = zip::ZipWriter::new(std::fs::File::create(&secrets).unwrap());
+        zip.start_file("app/.env", zip::write::FileOptions::default())
+            .unwrap();
+        zip.write_all(b"TOKEN=1").unwrap();
+        zip.finish().unwrap();
+    }
+    let err = ops
+        .extract_archive(&secrets.to_string_lossy(), &dest.to_string_lossy())
+        .await
+        .unwrap_err();
+    assert!(matches!(err, FileSystemError::DeniedByPattern { .. }));
+
+    let zip_of = |name: &str, entries: &[&str]| {
+        let path = root.join(name);
+        let mut zip = zip::ZipWriter::new(std::fs::File::create(&path).unwrap());
+        for entry in entries {
+            zip.start_file(*entry, zip::write::FileOptions::default())
+                .unwrap();
+            zip.write_all(b"data").unwrap();
+        }
+        zip.finish().unwrap();
+        path.to_string_lossy().to_string()
+    };
+
+    // The same file twice is refused before anything is written.
+    let dest = root.join("dupes");
+    let err = ops
+        .extract_archive(
+            &zip_of("dupes.zip", &["a.txt", "b.txt", "./a.txt"]),
+            &dest.to_string_lossy(),
+        )
+        .await
+        .unwrap_err();
+    assert!(matches!(err, FileSystemError::AlreadyExists { .. }));
+    assert!(!dest.exists());
+
+    // A file that the next entry needs as a directory only fails while
+    // writing; what was already written is removed again.
+    let dest = root.join("clash");
+    let err = ops
+        .extract_archive(
+            &zip_of("clash.zip", &["lib/a", "lib/a/b"]),
+            &dest.to_string_lossy(),
+        )
+        .await
+        .unwrap_err();
+    assert!(matches!(err, FileSystemError::IoError { .. }));
+    assert!(!dest.exists());
+}
+
+#[test]
+fn test_detect_mime_type_uses_shebang_for_extensionless_scripts() {
+    use super::mime::detect_mime_type;
+
+    assert_eq!(
+        detect_mime_type(b"#!/usr/bin/env python3\nprint(1)\n", "manage"),
+        "text/x-python"
+    );
+    assert_eq!(
+        detect_mime_type(b"#!/usr/bin/env -S node --no-warnings\n", "cli"),
+        "application/javascript"
+    );
+    assert_eq!(
+        detect_mime_type(b"#!/bin/bash\necho hi\n", "deploy"),
+        "text/x-shellscript"
+    );
+    // The extension still wins over the interpreter line.
+    assert_eq!(
+        detect_mime_type(b"#!/usr/bin/env python\n", "tool.py"),
+        "text/x-python"
+    );
+    assert_eq!(
+        detect_mime_type(b"export default {}\n", "vite.config.mjs"),
+        "application/javascript"
+    );
+    assert_eq!(
+        detect_mime_type(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR", "logo.sql"),
+        "image/png"
+    );
+}
+
+#[tokio::test]
+async fn test_read_file_reports_language_hint() {
+    let temp = TempDir::new().unwrap();
+    let root = temp_root(&temp);
+    let config = Arc::new(FileSystemConfig {
+        allowed_roots: vec![root.clone()],
+        ..Default::default()
+    });
+    let validator = Arc::new(PathValidator::new(config.clone()));
+    let ops = FileOperations::new(validator, config);
+
+    let read = |name: &str, content: &[u8]| {
+        let path = root.join(name);
+        std::fs::write(&path, content).unwrap();
+        let ops = ops.clone();
+        async move {
+            ops.read_file(
+                &path.to_string_lossy(),
+                None,
+                None,
+                Default::default(),
+                false,
+            )
+            .await
+            .unwrap()
+            .language
+        }
+    };
+
+    assert_eq!(
+        read("Dockerfile", b"FROM alpine\n").await.as_deref(),
+        Some("dockerfile")
+    );
+    assert_eq!(
+        read("main.tf", b"resource {}\n").await.as_deref(),
+        Some("hcl")
+    );
+    assert_eq!(
+        read("run", b"#!/usr/bin/env python3\n").await.as_deref(),
+        Some("python")
+    );
+    assert_eq!(read("notes.txt", b"hello\n").await, None);
+    assert_eq!(
+        read("image.rs", b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR").await,
+        None
+    );
+}
+
+#[tokio::test]
+async fn test_read_file_force_text_skips_binary_detection() {
+    let temp = TempDir::new().unwrap();
+    let root = temp_root(&temp);
+    let config = Arc::new(FileSystemConfig {
+        allowed_roots: vec![root.clone()],
+        ..Default::default()
+    });
+    let validator = Arc::new(PathValidator::new(config.clone()));
+    let ops = FileOperations::new(validator, config);
+
+    // Starts with a PNG signature, so `infer` calls it an image.
+    let path = root.join("device.bin");
+    std::fs::write(&path, b"\x89PNG\r\n\x1a\nmode=fast\n").unwrap();
+    let path = path.to_string_lossy();
+
+    let file = ops
+        .read_file(&path, None, None, Default::default(), false)
+        .await
+        .unwrap();
+    assert!(matches!(
+        file.encoding,
+        crate::protocol::FileEncoding::Base64
+    ));
+
+    let file = ops
+        .read_file(
+            &path,
+            None,
+            None,
+            crate::protocol::FileEncoding::Base64,
+            true,
+        )
+        .await
+        .unwrap();
+    assert!(matches!(file.encoding, crate::protocol::FileEncoding::Utf8));
+    assert_eq!(file.content, "\u{fffd}PNG\r\n\u{1a}\nmode=fast\n");
+}
+
+#[tokio::test]
+async fn test_read_grant_allows_single_file_outside_roots_read_only() {
+    let temp = TempDir::new().unwrap();
+    let base = temp_root(&temp);
+    let root = base.join("project");
+    let outside = base.join("logs");
+    std::fs::create_dir_all(&root).unwrap();
+    std::fs::create_dir_all(&outside).unwrap();
+    let log = outside.join("syslog");
+    let other = outside.join("auth.log");
+    std::fs::write(&log, "boot ok\n").unwrap();
+    std::fs::write(&other, "secret\n").unwrap();
+    std::fs::write(outside.join("token.txt"), "x").unwrap();
+
+    let config = Arc::new(FileSystemConfig {
+        allowed_roots: vec![root.clone()],
+        ..Default::default()
+    });
+    let validator = Arc::new(PathValidator::new(config.clone()));
+    let client: std::net::SocketAddr = "192.168.1.20:50000".parse().unwrap();
+    let bystander: std::net::SocketAddr = "192.168.1.21:50000".parse().unwrap();
+    let shared = FileOperations::new(validator.clone(), config.clone());
+    let ops = shared.for_client(client);
+    let log_path = log.to_string_lossy().to_string();
+
+    let read = ops
+        .read_file(&log_path, None, None, Default::default(), false)
+        .await;
+    assert!(matches!(
+        read,
+        Err(FileSystemError::OutsideAllowedRoots { .. })
+    ));
+
+    validator
+        .grant_read(&log_path, client, std::time::Duration::from_secs(60))
+        .unwrap();
+    let file = ops
+        .read_file(&log_path, None, None, Default::default(), false)
+        .await
+        .unwrap();
+    assert_eq!(file.content, "boot ok\n");
+
+    // Only for the client it was granted to.
+    for other_ops in [shared.for_client(bystander), shared.clone()] {
+        assert!(matches!(
+            other_ops
+                .read_file(&log_path, None, None, Default::default(), false)
+                .await,
+            Err(FileSystemError::OutsideAllowedRoots { .. })
+        ));
+    }
+
+    // Only that file, and only for reading.
+    assert!(ops
+        .read_file(
+            &other.to_string_lossy(),
+            None,
+            None,
+            Default::default(),
+            false
+        )
+        .await
+        .is_err());
+    assert!(ops
+        .write_file(&log_path, "x", Default::default(), false, None)
+        .await
+        .is_err());
+    assert!(ops.delete_path(&log_path, false).await.is_err());
+
+    // Directories and denied files can't be granted.
+    assert!(validator
+        .grant_read(
+            &outside.to_string_lossy(),
+            client,
+            std::time::Duration::from_secs(60)
+        )
+        .is_err());
+    assert!(matches!(
+        validator.grant_read(
+            &outside.join("token.txt").to_string_lossy(),
+            client,
+            std::time::Duration::from_secs(60)
+        ),
+        Err(FileSystemError::DeniedByPattern { .. })
+    ));
+
+    // Grants expire.
+    validator
+        .grant_read(&other.to_string_lossy(), client, std::time::Duration::ZERO)
+        .unwrap();
+    assert!(ops
+        .read_file(
+            &other.to_string_lossy(),
+            None,
+            None,
+            Default::default(),
+            false
+        )
+        .await
+        .is_err());
+
+    // A disconnecting client's grants go with it.
+    config.path_grants.revoke_all(client);
+    assert!(ops
+        .read_file(&log_path, None, None, Default::default(), false)
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn test_binary_preview_caps_bytes_and_reports_type() {
+    let temp = TempDir::new().unwrap();
+    let root = temp_root(&temp);
+    let config = Arc::new(FileSystemConfig {
+        allowed_roots: vec![root.clone()],
+        ..Default::default()
+    });
+    let validator = Arc::new(PathValidator::new(config.clone()));
+    let ops = FileOperations::new(validator, config);
+
+    let path = root.join("logo.png");
+    let mut data = b"\x89PNG\r\n\x1a\n".to_vec();
+    data.resize(200_000, 0xab);
+    std::fs::write(&path, &data).unwrap();
+    let path = path.to_string_lossy();
+
+    let preview = ops.binary_preview(&path, Some(10)).await.unwrap();
+    assert_eq!(preview.hex, "89504e470d0a1a0aabab");
+    assert_eq!(preview.ascii, ".PNG......");
+    assert_eq!(preview.total_size, 200_000);
+    assert_eq!(preview.mime_type.as_deref(), Some("image/png"));
+
+    let preview = ops.binary_preview(&path, Some(u64::MAX)).await.unwrap();
+    assert_eq!(
+        preview.ascii.len() as u64,
+        super::operations::BINARY_PREVIEW_MAX_BYTES
+    );
+}
+
+#[tokio::test]
+async fn test_file_manifest_matches_read_file_chunk_checksums() {
+    let temp = TempDir::new().unwrap();
+    let root = temp_root(&temp);
+    let config = Arc::new(FileSystemConfig {
+        allowed_roots: vec![root.clone()],
+        ..Default::default()
+    });
+    let validator = Arc::new(PathValidator::new(config.clone()));
+    let ops = FileOperations::new(validator, config);
+
+    let path = root.join("video.bin");
+    let data: Vec<u8> = (0..2500u32).map(|i| (i % 251) as u8).collect();
+    std::fs::write(&path, &data).unwrap();
+    let path = path.to_string_lossy();
+
+    let (_, size, checksums) = ops.file_manifest(&path, 1000).await.unwrap();
+    assert_eq!(size, 2500);
+    assert_eq!(checksums.len(), 3);
+    for (index, expected) in checksums.iter().enumerate() {
+        let (_, total_chunks, _, _, _, checksum, _) = ops
+            .read_file_chunk(&path, index as u64, 1000)
+            .await
+            .unwrap();
+        assert_eq!(total_chunks, 3);
+        assert_eq!(&checksum, expected);
+    }
+
+    // Exact multiples don't grow a trailing empty chunk; empty files have one.
+    let (_, _, checksums) = ops.file_manifest(&path, 500).await.unwrap();
+    assert_eq!(checksums.len(), 5);
+    let empty = root.join("empty.bin");
+    std::fs::write(&empty, b"").unwrap();
+    let (_, _, checksums) = ops
+        .file_manifest(&empty.to_string_lossy(), 500)
+        .await
+        .unwrap();
+    assert_eq!(checksums, vec![format!("{:x}", md5::compute(b""))]);
 }
@@ -1,28 +1,186 @@
-use std::path::PathBuf;
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
 
 use dashmap::{DashMap, DashSet};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::Match;
 use notify::RecursiveMode;
 use notify_debouncer_mini::{new_debouncer, DebouncedEvent, DebouncedEventKind, Debouncer};
 use tokio::sync::broadcast;
 
+use crate::filesystem::git::GitStatusCache;
 use crate::filesystem::path_utils;
 use crate::protocol::{ChangeType, FileChanged, FileSystemError};
 
+/// Default delay for coalescing bursts of change events into one batch.
+pub const DEFAULT_DEBOUNCE_MS: u64 = 250;
+/// Bounds for configured and per-watch debounce intervals.
+pub const MIN_DEBOUNCE_MS: u64 = 10;
+pub const MAX_DEBOUNCE_MS: u64 = 10_000;
+/// Recent events kept per watched directory for clients catching up.
+pub const REPLAY_BUFFER_LEN: usize = 256;
+
+/// Clamp a requested debounce interval into the supported range.
+pub fn clamp_debounce_ms(ms: u64) -> u64 {
+    ms.clamp(MIN_DEBOUNCE_MS, MAX_DEBOUNCE_MS)
+}
+
 pub struct FileWatcher {
     watchers: DashMap<String, Debouncer<notify::RecommendedWatcher>>,
     known_paths: std::sync::Arc<DashSet<String>>,
     event_tx: broadcast::Sender<FileChanged>,
-    debounce_ms: u64,
+    debounce_ms: AtomicU64,
+    git_cache: GitStatusCache,
+    replay: Arc<Mutex<ReplayBuffer>>,
+}
+
+/// Sequence-numbered recent events per watched directory. Sequence numbers
+/// are shared across directories and only ever increase.
+#[derive(Default)]
+struct ReplayBuffer {
+    last_seq: u64,
+    events: HashMap<String, VecDeque<FileChanged>>,
+}
+
+impl ReplayBuffer {
+    /// Number `change` and keep it for the watched directory `key`.
+    fn record(&mut self, key: &str, change: &mut FileChanged) {
+        self.last_seq += 1;
+        change.seq = Some(self.last_seq);
+        let buffer = self.events.entry(key.to_string()).or_default();
+        if buffer.len() == REPLAY_BUFFER_LEN {
+            buffer.pop_front();
+        }
+        buffer.push_back(change.clone());
+    }
+}
+
+/// Ignore rules for one watched directory: `.gitignore` and `.mobilecliignore`
+/// files from the directory up to its repository root, nearest first, plus
+/// the repository's `.git/info/exclude`. Outside a repository only the
+/// directory's own files count. Built once when the watch starts.
+struct IgnoreRules {
+    layers: Vec<Gitignore>,
+}
+
+impl IgnoreRules {
+    fn for_dir(dir: &Path) -> Self {
+        let repo_root = dir.ancestors().find(|d| d.join(".git").exists());
+        let mut layers = Vec::new();
+        for d in dir.ancestors() {
+            let mut builder = GitignoreBuilder::new(d);
+            let is_root = repo_root.map_or(true, |root| root == d);
+            if repo_root == Some(d) {
+                builder.add(d.join(".git").join("info").join("exclude"));
+            }
+            // Later files win, so `.mobilecliignore` can re-include paths.
+            for name in [".gitignore", ".mobilecliignore"] {
+                let file = d.join(name);
+                if file.is_file() {
+                    builder.add(file);
+                }
+            }
+            if let Ok(layer) = builder.build() {
+                if !layer.is_empty() {
+                    layers.push(layer);
+                }
+            }
+            if is_root {
+                break;
+            }
+        }
+        Self { layers }
+    }
+
+    /// Whether `path` (inside the watched directory) is ignored. `.git`
+    /// itself always is; a path that no longer exists is matched both as a
+    /// file and as a directory.
+    fn is_ignored(&self, path: &Path) -> bool {
+        if path.file_name().is_some_and(|name| name == ".git") {
+            return true;
+        }
+        if path.exists() {
+            return self.matches(path, path.is_dir());
+        }
+        self.matches(path, false) || self.matches(path, true)
+    }
+
+    fn matches(&self, path: &Path, is_dir: bool) -> bool {
+        for layer in &self.layers {
+            if !path.starts_with(layer.path()) {
+                continue;
+            }
+            match layer.matched_path_or_any_parents(path, is_dir) {
+                Match::Ignore(_) => return true,
+                Match::Whitelist(_) => return false,
+                Match::None => {}
+            }
+        }
+        false
+    }
+}
+
+/// Buffered events after a client's last-seen sequence number.
+#[derive(Debug, Clone, Default)]
+pub struct Replay {
+    pub events: Vec<FileChanged>,
+    /// Events after `since_seq` were already dropped from the buffer, so the
+    /// client has to re-list the directory.
+    pub incomplete: bool,
 }
 
 impl FileWatcher {
-    pub fn new(debounce_ms: u64) -> Self {
+    pub fn new(debounce_ms: u64, git_cache: GitStatusCache) -> Self {
         let (event_tx, _) = broadcast::channel(1024);
         Self {
             watchers: DashMap::new(),
             known_paths: std::sync::Arc::new(DashSet::new()),
             event_tx,
-            debounce_ms,
+            debounce_ms: AtomicU64::new(debounce_ms),
+            git_cache,
+            replay: Arc::default(),
+        }
+    }
+
+    /// The git status cache this watcher invalidates on changes.
+    pub fn git_cache(&self) -> &GitStatusCache {
+        &self.git_cache
+    }
+
+    /// Change the debounce interval used by watches started from now on.
+    pub fn set_debounce_ms(&self, debounce_ms: u64) {
+        self.debounce_ms.store(debounce_ms, Ordering::Relaxed);
+    }
+
+    /// Events for the watched directory `path` with a sequence number above
+    /// `since_seq`, oldest first. Call before re-watching `path`, so a
+    /// directory nobody was watching is reported as incomplete.
+    pub fn events_since(&self, path: &str, since_seq: u64) -> Replay {
+        let replay = self.replay.lock().unwrap_or_else(|e| e.into_inner());
+        if since_seq > replay.last_seq {
+            // A number handed out before the daemon restarted.
+            return Replay {
+                incomplete: true,
+                ..Replay::default()
+            };
+        }
+        let Some(events) = replay.events.get(path) else {
+            // Either nothing changed, or nothing was watching.
+            return Replay {
+                incomplete: since_seq < replay.last_seq && !self.watchers.contains_key(path),
+                ..Replay::default()
+            };
+        };
+        let oldest = events.front().and_then(|event| event.seq).unwrap_or(0);
+        Replay {
+            events: events
+                .iter()
+                .filter(|event| event.seq.is_some_and(|seq| seq > since_seq))
+                .cloned()
+                .collect(),
+            incomplete: events.len() == REPLAY_BUFFER_LEN && oldest > since_seq + 1,
         }
     }
 
@@ -30,14 +188,26 @@ impl FileWatcher {
         self.event_tx.subscribe()
     }
 
-    pub fn watch(&self, path: &str) -> Result<(), FileSystemError> {
+    /// Start watching `path`. `debounce_ms` overrides the watcher default for
+    /// this directory; it only applies when the watch is first created, since
+    /// clients watching the same directory share one debouncer. Every event is
+    /// flagged `ignored` by the directory's `IgnoreRules` and recorded for
+    /// replay; each client decides whether to skip the ignored ones.
+    pub fn watch(&self, path: &str, debounce_ms: Option<u64>) -> Result<(), FileSystemError> {
         if self.watchers.contains_key(path) {
             return Ok(());
         }
+        let debounce_ms = clamp_debounce_ms(
+            debounce_ms.unwrap_or_else(|| self.debounce_ms.load(Ordering::Relaxed)),
+        );
 
         let path_buf = PathBuf::from(path);
         let event_tx = self.event_tx.clone();
         let known_paths = self.known_paths.clone();
+        let git_cache = self.git_cache.clone();
+        let replay = self.replay.clone();
+        let watch_key = path.to_string();
+        let ignore_rules = IgnoreRules::for_dir(&path_buf);
 
         known_paths.insert(path_utils::to_protocol_path(&path_buf));
         if let Ok(entries) = std::fs::read_dir(&path_buf) {
@@ -47,11 +217,17 @@ impl FileWatcher {
         }
 
         let mut debouncer = new_debouncer(
-            std::time::Duration::from_millis(self.debounce_ms),
+            std::time::Duration::from_millis(debounce_ms),
             move |res: Result<Vec<DebouncedEvent>, notify::Error>| {
                 if let Ok(events) = res {
                     for event in events {
-                        let change = classify_event(&event, &known_paths);
+                        git_cache.invalidate(&event.path);
+                        let mut change = classify_event(&event, &known_paths);
+                        change.ignored = ignore_rules.is_ignored(&event.path);
+                        replay
+                            .lock()
+                            .unwrap_or_else(|e| e.into_inner())
+                            .record(&watch_key, &mut change);
                         let _ = event_tx.send(change);
                     }
                 }
@@ -74,6 +250,11 @@ impl FileWatcher {
 
     pub fn unwatch(&self, path: &str) -> Result<(), FileSystemError> {
         self.watchers.remove(path);
+        self.replay
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .events
+            .remove(path);
         Ok(())
     }
 }
@@ -92,6 +273,8 @@ fn classify_event(event: &DebouncedEvent, known_paths: &DashSet<String>) -> File
         path,
         change_type,
         new_entry: None,
+        seq: None,
+        ignored: false,
     }
 }
 
@@ -108,3 +291,94 @@ fn classify_known_path(path: &str, exists: bool, known_paths: &DashSet<String>)
         ChangeType::Deleted
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn change(path: &str) -> FileChanged {
+        FileChanged {
+            path: path.to_string(),
+            change_type: ChangeType::Modified,
+            new_entry: None,
+            seq: None,
+            ignored: false,
+        }
+    }
+
+    fn record(watcher: &FileWatcher, key: &str, path: &str) {
+        watcher
+            .replay
+            .lock()
+            .unwrap()
+            .record(key, &mut change(path));
+    }
+
+    #[test]
+    fn events_since_replays_only_newer_events_for_the_directory() {
+        let watcher = FileWatcher::new(DEFAULT_DEBOUNCE_MS, GitStatusCache::default());
+        record(&watcher, "/a", "/a/one");
+        record(&watcher, "/b", "/b/other");
+        record(&watcher, "/a", "/a/two");
+
+        let replay = watcher.events_since("/a", 1);
+        assert!(!replay.incomplete);
+        let paths: Vec<_> = replay.events.iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(paths, vec!["/a/two"]);
+        assert_eq!(replay.events[0].seq, Some(3));
+
+        assert!(watcher.events_since("/a", 3).events.is_empty());
+        // Numbers from before a daemon restart can't be trusted.
+        assert!(watcher.events_since("/a", 99).incomplete);
+    }
+
+    #[test]
+    fn events_since_flags_overflowed_buffer() {
+        let watcher = FileWatcher::new(DEFAULT_DEBOUNCE_MS, GitStatusCache::default());
+        for i in 0..REPLAY_BUFFER_LEN + 10 {
+            record(&watcher, "/a", &format!("/a/{i}"));
+        }
+
+        let replay = watcher.events_since("/a", 5);
+        assert!(replay.incomplete);
+        assert_eq!(replay.events.len(), REPLAY_BUFFER_LEN);
+
+        let replay = watcher.events_since("/a", 10);
+        assert!(!replay.incomplete);
+        assert_eq!(replay.events.len(), REPLAY_BUFFER_LEN);
+    }
+
+    #[test]
+    fn ignore_rules_follow_gitignore_up_to_the_repo_root() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let root = temp.path().canonicalize().unwrap();
+        std::fs::create_dir_all(root.join(".git").join("info")).unwrap();
+        std::fs::write(root.join(".git/info/exclude"), "*.swp\n").unwrap();
+        std::fs::write(root.join(".gitignore"), "target/\n*.log\n").unwrap();
+        let app = root.join("app");
+        std::fs::create_dir_all(app.join("target")).unwrap();
+        std::fs::create_dir_all(app.join("node_modules")).unwrap();
+        std::fs::write(app.join(".mobilecliignore"), "node_modules/\n!keep.log\n").unwrap();
+
+        let rules = IgnoreRules::for_dir(&app);
+        assert!(rules.is_ignored(&app.join("target")));
+        assert!(rules.is_ignored(&app.join("node_modules")));
+        assert!(rules.is_ignored(&app.join("debug.log")));
+        assert!(rules.is_ignored(&app.join(".main.rs.swp")));
+        assert!(!rules.is_ignored(&app.join("keep.log")));
+        assert!(!rules.is_ignored(&app.join("main.rs")));
+        // Deleted directories still match directory-only patterns.
+        std::fs::remove_dir(app.join("target")).unwrap();
+        assert!(rules.is_ignored(&app.join("target")));
+        assert!(rules.is_ignored(&root.join(".git")));
+    }
+
+    #[test]
+    fn events_since_flags_unwatched_directory_with_unseen_changes() {
+        let watcher = FileWatcher::new(DEFAULT_DEBOUNCE_MS, GitStatusCache::default());
+        record(&watcher, "/b", "/b/one");
+
+        assert!(watcher.events_since("/a", 0).incomplete);
+        assert!(!watcher.events_since("/a", 1).incomplete);
+    }
+}
@@ -245,7 +245,7 @@ async fn run_linked_mode(
                     Some(Ok(Message::Text(text))) => {
                         if let Ok(msg) = serde_json::from_str::<ServerMessage>(&text) {
                             match msg {
-                                ServerMessage::PtyBytes { session_id: sid, data } if sid == session_id => {
+                                ServerMessage::PtyBytes { session_id: sid, data, .. } if sid == session_id => {
                                     if let Ok(bytes) = BASE64.decode(&data) {
                                         let mut stdout = io::stdout();
                                         let _ = stdout.write_all(&bytes);
@@ -11,6 +11,7 @@
 
 mod auth;
 mod autostart;
+mod bookmarks;
 mod daemon;
 mod detection;
 mod filesystem;
@@ -19,13 +20,14 @@ mod platform;
 mod protocol;
 mod pty_wrapper;
 mod qr;
+mod screen;
 mod session;
 mod setup;
 mod shell_hook;
 mod tmux;
 mod uninstall;
 
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
 use colored::Colorize;
 use std::process::ExitCode;
 
@@ -41,6 +43,44 @@ struct Cli {
 
     #[command(flatten)]
     run_args: Option<RunArgs>,
+
+    /// Named daemon instance; each has its own pid/port files, config, and
+    /// autostart service (default: the unnamed instance)
+    #[arg(long, global = true, value_parser = parse_instance_name)]
+    instance: Option<String>,
+
+    /// Log output format (also settable with MOBILECLI_LOG_FORMAT)
+    #[arg(long, global = true, value_enum)]
+    log_format: Option<LogFormat>,
+}
+
+/// Environment variable selecting the log format for this and child processes.
+const LOG_FORMAT_ENV: &str = "MOBILECLI_LOG_FORMAT";
+
+#[derive(Debug, Clone, Copy, Default, clap::ValueEnum)]
+enum LogFormat {
+    /// Human-readable lines
+    #[default]
+    Text,
+    /// One JSON object per event
+    Json,
+}
+
+impl LogFormat {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Text => "text",
+            Self::Json => "json",
+        }
+    }
+}
+
+fn parse_instance_name(value: &str) -> Result<String, String> {
+    if platform::is_valid_instance_name(value) {
+        Ok(value.to_string())
+    } else {
+        Err("use up to 32 ASCII letters, digits, '-' or '_'".to_string())
+    }
 }
 
 /// Arguments for running a command with mobile streaming
@@ -89,6 +129,14 @@ enum Commands {
         /// Port to listen on
         #[arg(short, long, default_value_t = daemon::DEFAULT_PORT)]
         port: u16,
+        /// IP address for the mobile listener (overrides `bind_address` in
+        /// the config; loopback is always bound for the desktop)
+        #[arg(long)]
+        bind: Option<std::net::IpAddr>,
+        /// Maximum concurrent sessions, 0 for unlimited (default: 64, or
+        /// MOBILECLI_MAX_SESSIONS)
+        #[arg(long)]
+        max_sessions: Option<usize>,
     },
     /// Stop the background daemon
     Stop,
@@ -124,6 +172,13 @@ enum CredentialCommand {
         /// Credential ID to revoke
         credential_id: String,
     },
+    /// Grant a scope that pairing leaves out (fs:config)
+    Grant {
+        /// Credential ID to grant the scope to
+        credential_id: String,
+        /// Scope to grant
+        scope: String,
+    },
 }
 
 #[tokio::main]
@@ -134,6 +189,20 @@ async fn main() -> ExitCode {
         colored::control::set_virtual_terminal(true).ok();
     }
 
+    let cli = Cli::parse();
+
+    // `--log-format` is exported like `--instance` so a background daemon we
+    // spawn logs in the same format.
+    if let Some(format) = cli.log_format {
+        std::env::set_var(LOG_FORMAT_ENV, format.as_str());
+    }
+    let log_format = cli.log_format.unwrap_or_else(|| {
+        std::env::var(LOG_FORMAT_ENV)
+            .ok()
+            .and_then(|value| LogFormat::from_str(&value, true).ok())
+            .unwrap_or_default()
+    });
+
     // Initialize tracing.
     //
     // Default to warn so the foreground UX (bare `mobilecli`, `mobilecli claude`,
@@ -145,12 +214,25 @@ async fn main() -> ExitCode {
     // the user's perspective.
     let env_filter = tracing_subscriber::EnvFilter::try_from_default_env()
         .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("mobilecli=warn"));
-    tracing_subscriber::fmt()
-        .with_env_filter(env_filter)
-        .with_target(false)
-        .init();
+    match log_format {
+        LogFormat::Text => tracing_subscriber::fmt()
+            .with_env_filter(env_filter)
+            .with_target(false)
+            .init(),
+        // One JSON object per line with span/event fields as attributes, for
+        // Loki/ELK-style ingestion.
+        LogFormat::Json => tracing_subscriber::fmt()
+            .json()
+            .with_env_filter(env_filter)
+            .with_current_span(false)
+            .init(),
+    }
 
-    let cli = Cli::parse();
+    // Exported so the background daemon and any wrappers we spawn resolve the
+    // same instance without threading the name through every call.
+    if let Some(instance) = &cli.instance {
+        std::env::set_var(platform::INSTANCE_ENV, instance);
+    }
 
     // Handle --setup flag (shortcut for setup subcommand)
     if let Some(ref run_args) = cli.run_args {
@@ -193,13 +275,17 @@ async fn main() -> ExitCode {
                     ExitCode::FAILURE
                 }
             },
-            Commands::Daemon { port } => {
+            Commands::Daemon {
+                port,
+                bind,
+                max_sessions,
+            } => {
                 if daemon::is_running() {
                     eprintln!("{}", "Daemon is already running".yellow());
                     return ExitCode::FAILURE;
                 }
                 println!("{} Starting daemon on port {}...", "▶".green(), port);
-                match daemon::run(*port).await {
+                match daemon::run(*port, *bind, *max_sessions).await {
                     Ok(_) => ExitCode::SUCCESS,
                     Err(e) => {
                         eprintln!("{}: {}", "Daemon error".red().bold(), e);
@@ -338,9 +424,8 @@ async fn start_daemon_background() -> std::io::Result<()> {
     let exe = std::env::current_exe()?;
 
     // Create log file for daemon stderr (cross-platform config directory)
-    let log_dir = platform::config_dir();
-    std::fs::create_dir_all(&log_dir)?;
-    let log_file = std::fs::File::create(log_dir.join("daemon.log"))?;
+    std::fs::create_dir_all(platform::config_dir())?;
+    let log_file = std::fs::File::create(daemon::log_file())?;
 
     // Spawn daemon as background process with stderr logged for debugging
     let mut cmd = Command::new(&exe);
@@ -544,6 +629,10 @@ fn handle_credentials(command: &CredentialCommand) -> Result<(), Box<dyn std::er
     match command {
         CredentialCommand::List => list_credentials(),
         CredentialCommand::Revoke { credential_id } => revoke_credential(credential_id),
+        CredentialCommand::Grant {
+            credential_id,
+            scope,
+        } => grant_credential_scope(credential_id, scope),
     }
 }
 
@@ -577,6 +666,7 @@ fn list_credentials() -> Result<(), Box<dyn std::error::Error>> {
         println!("  {} {}", "Status:".bold(), status);
         println!("  {} {}", "Created:".bold(), credential.created_at);
         println!("  {} {}", "Last used:".bold(), last_used);
+        println!("  {} {}", "Scopes:".bold(), credential.scopes.join(", "));
         if let Some(revoked_at) = &credential.revoked_at {
             println!("  {} {}", "Revoked:".bold(), revoked_at);
         }
@@ -605,3 +695,39 @@ fn revoke_credential(credential_id: &str) -> Result<(), Box<dyn std::error::Erro
 
     Ok(())
 }
+
+fn grant_credential_scope(
+    credential_id: &str,
+    scope: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if !auth::EXPLICIT_SCOPES.contains(&scope) {
+        return Err(format!(
+            "Unknown scope: {} (grantable: {})",
+            scope,
+            auth::EXPLICIT_SCOPES.join(", ")
+        )
+        .into());
+    }
+
+    let mut config = setup::load_config().ok_or("No MobileCLI config found")?;
+    let Some(credential) = config
+        .credentials
+        .iter_mut()
+        .find(|c| c.credential_id == credential_id && c.is_active())
+    else {
+        return Err(format!("Credential not found or revoked: {}", credential_id).into());
+    };
+
+    if credential.grant_scope(scope) {
+        setup::save_config(&config)?;
+        println!("{} Granted {} to {}", "✓".green(), scope, credential_id);
+        println!(
+            "  {}",
+            "The device picks it up the next time it connects.".dimmed()
+        );
+    } else {
+        println!("{} Credential already has {}", "•".dimmed(), scope);
+    }
+
+    Ok(())
+}
@@ -20,7 +20,47 @@ pub fn home_dir() -> Option<PathBuf> {
     dirs_next::home_dir()
 }
 
-/// Get the MobileCLI config directory.
+/// Environment variable naming the daemon instance. `--instance` sets it so
+/// every child process (daemon, wrappers) resolves the same instance.
+pub const INSTANCE_ENV: &str = "MOBILECLI_INSTANCE";
+
+/// Named daemon instance for this process, or `None` for the default one.
+pub fn instance_name() -> Option<String> {
+    std::env::var(INSTANCE_ENV)
+        .ok()
+        .filter(|name| is_valid_instance_name(name))
+}
+
+/// Instance names end up in file, unit, and task names, so keep them to a
+/// short run of ASCII letters, digits, `-`, and `_`.
+pub fn is_valid_instance_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 32
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// `<stem>.<ext>` for the default instance, `<stem>-<name>.<ext>` otherwise.
+pub fn instance_file_name(stem: &str, ext: &str) -> String {
+    match instance_name() {
+        Some(name) => format!("{}-{}.{}", stem, name, ext),
+        None => format!("{}.{}", stem, ext),
+    }
+}
+
+/// Get the MobileCLI config directory for the current instance.
+///
+/// The default instance uses `base_config_dir()` directly; a named instance
+/// gets its own `instances/<name>` subdirectory for config, sessions, and logs.
+pub fn config_dir() -> PathBuf {
+    match instance_name() {
+        Some(name) => base_config_dir().join("instances").join(name),
+        None => base_config_dir(),
+    }
+}
+
+/// Get the top-level MobileCLI config directory shared by all instances.
 ///
 /// Returns:
 /// - Linux/macOS: `~/.mobilecli`
@@ -28,7 +68,7 @@ pub fn home_dir() -> Option<PathBuf> {
 ///
 /// Note: We use a dot-prefix directory on all platforms for consistency.
 /// On Windows, this won't be hidden by default, but keeps paths predictable.
-pub fn config_dir() -> PathBuf {
+pub fn base_config_dir() -> PathBuf {
     if let Some(home) = home_dir() {
         return home.join(".mobilecli");
     }
@@ -186,6 +226,16 @@ mod tests {
         assert!(dir.ends_with(".mobilecli") || dir.ends_with("mobilecli"));
     }
 
+    #[test]
+    fn test_instance_names_are_file_safe() {
+        assert!(is_valid_instance_name("work"));
+        assert!(is_valid_instance_name("team_a-2"));
+        assert!(!is_valid_instance_name(""));
+        assert!(!is_valid_instance_name("../etc"));
+        assert!(!is_valid_instance_name("a b"));
+        assert!(!is_valid_instance_name(&"x".repeat(33)));
+    }
+
     #[test]
     fn test_default_shell() {
         let shell = default_shell();
@@ -68,10 +68,19 @@ pub enum ClientMessage {
     },
     Hello {
         client_version: String,
+        /// Highest protocol version the app understands; absent means 1
+        #[serde(default)]
+        protocol_version: Option<u32>,
         #[serde(default)]
         sender_id: Option<String>,
         #[serde(default)]
         client_capabilities: Option<u32>,
+        /// Opt in to live PTY output as binary frames (see `encode_binary_pty_frame`)
+        #[serde(default)]
+        binary_pty: bool,
+        /// `Some(false)` strips mouse-tracking sequences from live PTY output
+        #[serde(default)]
+        mouse_capable: Option<bool>,
     },
     Subscribe {
         session_id: String,
@@ -83,6 +92,21 @@ pub enum ClientMessage {
     Unsubscribe {
         session_id: String,
     },
+    /// `Subscribe` to every current session, for dashboard views. Sessions
+    /// started later still need their own `Subscribe`.
+    SubscribeAll {
+        #[serde(default)]
+        client_capabilities: Option<u32>,
+    },
+    /// `Unsubscribe` from every session this client is subscribed to
+    UnsubscribeAll,
+    /// Replay a session's recent history and start its live output in one
+    /// step, so nothing is lost or repeated between the two
+    AttachSession {
+        session_id: String,
+        #[serde(default)]
+        history_bytes: Option<usize>,
+    },
     SendInput {
         session_id: String,
         text: String,
@@ -91,6 +115,21 @@ pub enum ClientMessage {
         #[serde(default)]
         client_msg_id: Option<String>,
     },
+    /// Send the same input to every subscribed session matching `filter`
+    /// (all subscribed sessions without one), interpreted as `SendInput` would
+    BroadcastInput {
+        text: String,
+        #[serde(default)]
+        raw: bool,
+        #[serde(default)]
+        filter: Option<BroadcastFilter>,
+    },
+    /// Paste text; wrapped in bracketed-paste markers when the foreground
+    /// program has bracketed paste enabled, so newlines don't run early
+    PasteInput {
+        session_id: String,
+        text: String,
+    },
     /// Resize PTY - mobile sends terminal dimensions
     PtyResize {
         session_id: String,
@@ -110,17 +149,44 @@ pub enum ClientMessage {
     },
     /// Heartbeat ping
     Ping,
+    /// Graceful disconnect: the daemon releases views and watches, replies
+    /// with `GoodbyeAck`, then closes the socket
+    Goodbye {
+        #[serde(default)]
+        reason: Option<String>,
+    },
     /// Request list of available sessions
     GetSessions,
+    /// Sessions grouped by project directory, most recently active first
+    GetSessionsByProject {
+        request_id: String,
+    },
+    /// Request daemon diagnostics (version, uptime, counts)
+    GetDaemonStatus {
+        request_id: String,
+    },
+    /// Where the daemon keeps its config, pid, port, sessions and log files
+    GetPaths {
+        request_id: String,
+    },
     /// Rename a session
     RenameSession {
         session_id: String,
         new_name: String,
     },
+    /// Drop a session's scrollback without touching the running program
+    ClearHistory {
+        session_id: String,
+    },
     /// Close/kill a session
     CloseSession {
         session_id: String,
     },
+    /// Stop streaming a tmux session without killing it. The tmux server keeps
+    /// running so the session can be reattached from a desktop terminal.
+    DetachSession {
+        session_id: String,
+    },
     /// Register push notification token
     RegisterPushToken {
         token: String,
@@ -131,6 +197,27 @@ pub enum ClientMessage {
     UnregisterPushToken {
         token: String,
     },
+    /// Send a canned push to this device's registered tokens to verify setup
+    TestNotification {
+        request_id: String,
+    },
+    /// Recent commands typed into a session, newest first
+    GetInputHistory {
+        session_id: String,
+        #[serde(default)]
+        limit: Option<usize>,
+    },
+    /// Recent wait-state notifications, newest first
+    GetNotificationHistory {
+        request_id: String,
+        #[serde(default)]
+        limit: Option<usize>,
+    },
+    /// Clear a session's waiting state (resolved on the desktop) without
+    /// sending any input
+    DismissWaiting {
+        session_id: String,
+    },
     /// Tool approval response from mobile
     ToolApproval {
         session_id: String,
@@ -142,6 +229,13 @@ pub enum ClientMessage {
         #[serde(default)]
         max_bytes: Option<usize>,
     },
+    /// The session's current screen as plain text, rendered at `cols` x
+    /// `rows`, for preview cards
+    GetSessionScreen {
+        session_id: String,
+        cols: u16,
+        rows: u16,
+    },
     /// Spawn a new session from mobile
     SpawnSession {
         command: String,
@@ -149,6 +243,28 @@ pub enum ClientMessage {
         args: Vec<String>,
         #[serde(default)]
         name: Option<String>,
+        /// Absolute path, or a path relative to `base` (or to the first
+        /// approved root when `base` is omitted).
+        #[serde(default)]
+        working_dir: Option<String>,
+        /// Base directory for a relative `working_dir`.
+        #[serde(default)]
+        base: Option<String>,
+        /// Extra environment variables for the spawned CLI, as `[key, value]`
+        /// pairs. They apply to this spawn only and are never persisted, so a
+        /// later spawn or a restored session does not inherit them.
+        #[serde(default)]
+        env: Vec<(String, String)>,
+    },
+    /// Run an allowed command once, without a terminal, and reply with its
+    /// output as `CommandOutput`
+    RunCommand {
+        request_id: String,
+        command: String,
+        #[serde(default)]
+        args: Vec<String>,
+        /// Absolute path inside the approved roots; the first approved root
+        /// when omitted
         #[serde(default)]
         working_dir: Option<String>,
     },
@@ -162,6 +278,38 @@ pub enum ClientMessage {
         sort_by: Option<SortField>,
         #[serde(default)]
         sort_order: Option<SortOrder>,
+        /// Glob applied to entry names before listing (e.g. `*.log`)
+        #[serde(default)]
+        name_filter: Option<String>,
+        /// Only list files with one of these extensions (`"rs"` or `".rs"`,
+        /// any case); directories are always listed
+        #[serde(default)]
+        extensions: Option<Vec<String>>,
+        /// Index of the first entry to return, after sorting
+        #[serde(default)]
+        offset: Option<usize>,
+        /// Page size (at least 1, capped at the daemon's `max_list_entries`)
+        #[serde(default)]
+        limit: Option<usize>,
+        /// Skip git status, permissions, MIME, and content peeks; entries carry
+        /// only name, path, type, size, and modified time
+        #[serde(default)]
+        compact: bool,
+        /// Inside a git repo, drop files matched by `.gitignore`. Ignored
+        /// directories stay listed with `git_status: ignored`...
+        #[serde(default)]
+        respect_gitignore: bool,
+        /// ...unless this is also set, which drops them too
+        #[serde(default)]
+        hide_ignored_dirs: bool,
+    },
+    /// List a directory entry-by-entry (`directory_entry` messages, then
+    /// `directory_listing_done`) for directories too large to batch
+    ListDirectoryStream {
+        request_id: String,
+        path: String,
+        #[serde(default)]
+        include_hidden: bool,
     },
     ReadFile {
         request_id: String,
@@ -172,6 +320,10 @@ pub enum ClientMessage {
         length: Option<u64>,
         #[serde(default)]
         encoding: FileEncoding,
+        /// Return UTF-8 text even when the file looks binary; invalid bytes
+        /// become U+FFFD
+        #[serde(default)]
+        force_text: bool,
     },
     WriteFile {
         request_id: String,
@@ -181,6 +333,26 @@ pub enum ClientMessage {
         encoding: FileEncoding,
         #[serde(default)]
         create_parents: bool,
+        /// `modified` (ms) of the file as last read; the write fails with
+        /// `Conflict` if it has changed since
+        #[serde(default)]
+        expected_modified: Option<u64>,
+    },
+    /// Check whether a path exists without treating "missing" as an error
+    PathExists {
+        request_id: String,
+        path: String,
+    },
+    /// Write a large file in order-preserving base64 chunks. Each chunk carries
+    /// its md5 `checksum`; the last one moves the file into place atomically.
+    WriteFileChunk {
+        request_id: String,
+        path: String,
+        upload_id: String,
+        chunk_index: u64,
+        total_chunks: u64,
+        data: String,
+        checksum: String,
     },
     CreateDirectory {
         request_id: String,
@@ -193,11 +365,21 @@ pub enum ClientMessage {
         path: String,
         #[serde(default)]
         recursive: bool,
+        /// Token from a `DeletePreview`, confirming a recursive delete when
+        /// the daemon requires confirmation
+        #[serde(default)]
+        token: Option<String>,
+        /// Validate and answer with `DryRunResult` instead of deleting
+        #[serde(default)]
+        dry_run: bool,
     },
     RenamePath {
         request_id: String,
         old_path: String,
         new_path: String,
+        /// Validate and answer with `DryRunResult` instead of renaming
+        #[serde(default)]
+        dry_run: bool,
     },
     CopyPath {
         request_id: String,
@@ -205,6 +387,18 @@ pub enum ClientMessage {
         destination: String,
         #[serde(default)]
         recursive: bool,
+        /// Carry over modification times and Unix mode bits
+        #[serde(default)]
+        preserve_metadata: bool,
+        /// Validate and answer with `DryRunResult` instead of copying
+        #[serde(default)]
+        dry_run: bool,
+    },
+    /// Unpack a `.zip` or `.tar.gz` into `destination`
+    ExtractArchive {
+        request_id: String,
+        path: String,
+        destination: String,
     },
     GetFileInfo {
         request_id: String,
@@ -220,10 +414,48 @@ pub enum ClientMessage {
         max_depth: Option<u32>,
         #[serde(default)]
         max_results: Option<u32>,
+        /// Order matches by content hit count (descending), then path
+        #[serde(default)]
+        sort_by_relevance: bool,
+        /// Match names fuzzily ("cfgyml" finds "config.yaml"), best first
+        #[serde(default)]
+        fuzzy: bool,
+        /// Only return files with one of these extensions
+        #[serde(default)]
+        extensions: Option<Vec<String>>,
+    },
+    /// Most recently modified files under `path`, newest first
+    GetRecentFiles {
+        request_id: String,
+        path: String,
+        #[serde(default)]
+        limit: Option<u32>,
+        /// Only files modified at or after this time (ms since the epoch)
+        #[serde(default)]
+        since_ms: Option<u64>,
+        /// Skip files and directories matched by `.gitignore`
+        #[serde(default)]
+        respect_gitignore: bool,
+    },
+    /// Stop a running request (currently `SearchFiles`) early
+    CancelRequest {
+        request_id: String,
     },
     WatchDirectory {
         request_id: String,
         path: String,
+        /// Debounce for this directory's events (ms); the daemon default
+        /// otherwise. Only honored by the first client to watch the directory.
+        #[serde(default)]
+        debounce_ms: Option<u64>,
+        /// Last `FileChanged::seq` seen before a reconnect; buffered events
+        /// after it are sent right after the reply
+        #[serde(default)]
+        since_seq: Option<u64>,
+        /// Drop events for paths ignored by `.gitignore` or `.mobilecliignore`,
+        /// for this client only
+        #[serde(default)]
+        respect_ignore: bool,
     },
     UnwatchDirectory {
         request_id: String,
@@ -235,6 +467,43 @@ pub enum ClientMessage {
     GetAllowedRoots {
         request_id: String,
     },
+    /// Current filesystem limits and allowed roots
+    GetFsConfig {
+        request_id: String,
+    },
+    /// Re-read config.json into the running daemon (what SIGHUP does on
+    /// Unix). Only accepted from loopback clients.
+    ReloadConfig {
+        request_id: String,
+    },
+    /// Change filesystem limits; omitted fields keep their value. Applied
+    /// immediately and saved to config.json.
+    SetFsConfig {
+        request_id: String,
+        limits: FsLimits,
+    },
+    /// Let this connection read one file outside the allowed roots for a few
+    /// minutes
+    GrantPathOnce {
+        request_id: String,
+        path: String,
+    },
+    /// List bookmarked directories
+    GetBookmarks {
+        request_id: String,
+    },
+    /// Bookmark a directory inside the allowed roots, or relabel an existing
+    /// bookmark. The label defaults to the directory name.
+    AddBookmark {
+        request_id: String,
+        path: String,
+        #[serde(default)]
+        label: Option<String>,
+    },
+    RemoveBookmark {
+        request_id: String,
+        path: String,
+    },
     ReadFileChunk {
         request_id: String,
         path: String,
@@ -242,6 +511,59 @@ pub enum ClientMessage {
         #[serde(default)]
         chunk_size: Option<u64>,
     },
+    /// Per-chunk checksums for resuming an interrupted `ReadFileChunk` download
+    GetFileManifest {
+        request_id: String,
+        path: String,
+        #[serde(default)]
+        chunk_size: Option<u64>,
+    },
+    /// Pack a directory into an archive and send it back as `FileChunk`s
+    DownloadArchive {
+        request_id: String,
+        path: String,
+        #[serde(default)]
+        format: ArchiveFormat,
+        #[serde(default)]
+        chunk_size: Option<u64>,
+    },
+    /// List files previously uploaded to a session's project, newest first
+    ListUploads {
+        request_id: String,
+        session_id: String,
+    },
+    /// Follow a growing file like `tail -f`: the last `from_end_bytes` arrive
+    /// as a `FileChunk`, then appends as `FileAppended` until `UntailFile`.
+    TailFile {
+        request_id: String,
+        path: String,
+        #[serde(default)]
+        from_end_bytes: u64,
+    },
+    /// Stop a `TailFile` subscription, identified by its request id
+    UntailFile {
+        request_id: String,
+    },
+    /// Hash a whole file, e.g. to verify a chunked download
+    GetFileChecksum {
+        request_id: String,
+        path: String,
+        algorithm: ChecksumAlgorithm,
+    },
+    /// First bytes of a file as hex and printable ASCII, for a hex viewer
+    GetBinaryPreview {
+        request_id: String,
+        path: String,
+        #[serde(default)]
+        max_bytes: Option<u64>,
+    },
+    /// Read an inclusive, 1-based line range (for the code viewer)
+    ReadFileLines {
+        request_id: String,
+        path: String,
+        start_line: u64,
+        end_line: u64,
+    },
     UploadFile {
         request_id: String,
         session_id: String,
@@ -249,9 +571,26 @@ pub enum ClientMessage {
         content_base64: String,
         #[serde(default)]
         mime_type: Option<String>,
+        /// Reuse an earlier upload with identical content instead of writing a copy
+        #[serde(default)]
+        dedup: bool,
+    },
+    /// Upload a file into any writable directory, without a session. The
+    /// name is sanitized like `UploadFile`, and gets a " (n)" suffix rather
+    /// than replacing an existing file.
+    UploadToPath {
+        request_id: String,
+        destination_dir: String,
+        file_name: String,
+        content_base64: String,
     },
 }
 
+/// Wire protocol version advertised in `Welcome`. Bump it when adding
+/// `ServerMessage` variants older apps cannot parse, and list those variants
+/// in `ServerMessage::min_protocol_version`.
+pub const PROTOCOL_VERSION: u32 = 2;
+
 /// Messages sent from server to mobile client
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
@@ -264,6 +603,9 @@ pub enum ServerMessage {
     },
     Welcome {
         server_version: String,
+        /// See `PROTOCOL_VERSION`; absent from daemons that predate it
+        #[serde(default)]
+        protocol_version: u32,
         #[serde(skip_serializing_if = "Option::is_none")]
         device_id: Option<String>,
         #[serde(skip_serializing_if = "Option::is_none")]
@@ -281,6 +623,16 @@ pub enum ServerMessage {
     PtyBytes {
         session_id: String,
         data: String, // base64 encoded
+        /// Per-session live sequence number; a jump of more than one means
+        /// output was dropped. Absent on replay and control frames.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        seq: Option<u64>,
+    },
+    /// `SendInput` with a `client_msg_id` was delivered (or was a duplicate
+    /// of one already delivered), so the app can stop retrying it
+    InputAck {
+        session_id: String,
+        client_msg_id: String,
     },
     /// Session info
     SessionInfo {
@@ -294,6 +646,36 @@ pub enum ServerMessage {
     Sessions {
         sessions: Vec<SessionListItem>,
     },
+    /// Reply to `GetSessionsByProject`
+    SessionsByProject {
+        request_id: String,
+        groups: Vec<ProjectSessions>,
+    },
+    /// Daemon diagnostics for the mobile health panel
+    DaemonStatus {
+        request_id: String,
+        version: String,
+        uptime_seconds: u64,
+        session_count: usize,
+        /// Session cap; 0 means unlimited
+        max_sessions: usize,
+        connected_clients: usize,
+        push_token_count: usize,
+        port: u16,
+    },
+    /// Reply to `GetPaths`, as native paths on the daemon's machine
+    Paths {
+        request_id: String,
+        config_dir: String,
+        pid_file: String,
+        port_file: String,
+        sessions_file: String,
+        log_file: String,
+    },
+    /// A new session registered (sent alongside the `sessions` refresh)
+    SessionStarted {
+        session: SessionListItem,
+    },
     /// Session ended
     SessionEnded {
         session_id: String,
@@ -304,10 +686,21 @@ pub enum ServerMessage {
         session_id: String,
         new_name: String,
     },
+    /// A session's scrollback was cleared; reset the terminal view
+    HistoryCleared {
+        session_id: String,
+    },
     /// Session closed by client request
     SessionClosed {
         session_id: String,
     },
+    /// tmux session detached from the daemon but still alive; reattach with
+    /// `tmux -L <tmux_socket> attach -t <tmux_session>`.
+    SessionDetached {
+        session_id: String,
+        tmux_socket: String,
+        tmux_session: String,
+    },
     /// Acknowledge subscription with session metadata
     SubscribeAck {
         session_id: String,
@@ -353,6 +746,12 @@ pub enum ServerMessage {
         data: String, // base64 encoded bytes
         timestamp_ms: u64,
     },
+    /// Whether the session's foreground program echoes input; the app should
+    /// only echo keystrokes optimistically while this is true
+    EchoMode {
+        session_id: String,
+        local_echo: bool,
+    },
     /// PTY resized confirmation
     PtyResized {
         session_id: String,
@@ -371,6 +770,8 @@ pub enum ServerMessage {
     },
     /// Heartbeat pong
     Pong,
+    /// Client state was released after `Goodbye`; the socket closes next
+    GoodbyeAck,
     /// Session is waiting for user input (tool approval, question, etc.)
     WaitingForInput {
         session_id: String,
@@ -390,6 +791,23 @@ pub enum ServerMessage {
         data: String, // base64 encoded
         total_bytes: usize,
     },
+    /// Reply to `GetSessionScreen`: one line per row, trailing blanks trimmed
+    SessionScreen {
+        session_id: String,
+        cols: u16,
+        rows: u16,
+        lines: Vec<String>,
+        cursor_row: u16,
+        cursor_col: u16,
+    },
+    /// Reply to `AttachSession`: history up to and including live chunk
+    /// `seq`; live `PtyBytes` for the session continue from `seq + 1`
+    SessionAttached {
+        session_id: String,
+        data: String, // base64 encoded
+        total_bytes: usize,
+        seq: u64,
+    },
     /// Result of spawning a new session
     SpawnResult {
         success: bool,
@@ -398,13 +816,40 @@ pub enum ServerMessage {
         #[serde(skip_serializing_if = "Option::is_none")]
         error: Option<String>,
     },
+    /// Reply to `RunCommand`. `exit_code` is absent when the command was
+    /// refused, could not start, timed out, or was killed by a signal.
+    CommandOutput {
+        request_id: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        exit_code: Option<i32>,
+        stdout: String,
+        stderr: String,
+        /// Output past the cap was dropped
+        truncated: bool,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
+    },
     // ---- File system responses ----
     DirectoryListing {
         request_id: String,
         path: String,
         entries: Vec<FileEntry>,
+        /// Number of entries in the full listing, before paging
         total_count: usize,
         truncated: bool,
+        /// More entries exist after this page
+        has_more: bool,
+    },
+    /// One entry of a streamed directory listing
+    DirectoryEntry {
+        request_id: String,
+        entry: FileEntry,
+    },
+    /// End of a streamed directory listing
+    DirectoryListingDone {
+        request_id: String,
+        path: String,
+        total_count: usize,
     },
     FileContent {
         request_id: String,
@@ -416,6 +861,28 @@ pub enum ServerMessage {
         modified: u64,
         #[serde(skip_serializing_if = "Option::is_none")]
         truncated_at: Option<u64>,
+        /// Source encoding the content was decoded from (e.g. `UTF-8`,
+        /// `windows-1252`), or `binary` when it could not be read as text.
+        detected_encoding: String,
+        /// Syntax highlighting hint, independent of `mime_type`
+        #[serde(skip_serializing_if = "Option::is_none")]
+        language: Option<String>,
+        /// Line endings to keep when saving; absent for base64 content
+        #[serde(skip_serializing_if = "Option::is_none")]
+        line_ending: Option<LineEnding>,
+        /// Indentation style to keep when editing; absent for base64 content
+        #[serde(skip_serializing_if = "Option::is_none")]
+        indent: Option<IndentStyle>,
+    },
+    FileLines {
+        request_id: String,
+        path: String,
+        start_line: u64,
+        lines: Vec<String>,
+        total_lines: u64,
+        /// Set when the file is larger than `max_read_size`; `total_lines`
+        /// then only counts lines within the readable prefix.
+        truncated: bool,
     },
     FileInfo {
         request_id: String,
@@ -428,6 +895,10 @@ pub enum ServerMessage {
         path: String,
         #[serde(skip_serializing_if = "Option::is_none")]
         message: Option<String>,
+        /// The file as written, for `write_file`; its `modified` is the
+        /// `expected_modified` for the next save
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        entry: Option<FileEntry>,
     },
     OperationError {
         request_id: String,
@@ -441,12 +912,54 @@ pub enum ServerMessage {
         path: String,
         matches: Vec<SearchMatch>,
         truncated: bool,
+        /// The search was stopped by `CancelRequest`
+        #[serde(default)]
+        cancelled: bool,
+    },
+    /// Reply to `GetRecentFiles`
+    RecentFiles {
+        request_id: String,
+        path: String,
+        entries: Vec<FileEntry>,
+        /// The walk stopped at its entry cap, so older parts of the tree may
+        /// hold newer files
+        truncated: bool,
+    },
+    /// What a recursive `DeletePath` would remove; resend the request with
+    /// `token` to go ahead
+    DeletePreview {
+        request_id: String,
+        path: String,
+        entry_count: u64,
+        total_bytes: u64,
+        token: String,
+    },
+    /// What a `dry_run` rename, copy or delete would do; nothing was changed
+    DryRunResult {
+        request_id: String,
+        operation: String,
+        /// Paths that would be removed, moved or created
+        affected_paths: Vec<String>,
+        /// Existing paths the operation would refuse to overwrite
+        conflicts: Vec<String>,
+        /// `affected_paths` was cut off at `max_list_entries`
+        truncated: bool,
+    },
+    /// Periodic update while a `SearchFiles` walk is running
+    SearchProgress {
+        request_id: String,
+        scanned: usize,
+        matched: usize,
     },
     FileChanged {
         path: String,
         change_type: ChangeType,
         #[serde(skip_serializing_if = "Option::is_none")]
         new_entry: Option<FileEntry>,
+        /// Watcher sequence number; pass the last one seen as `since_seq`
+        /// when re-watching after a reconnect
+        #[serde(skip_serializing_if = "Option::is_none")]
+        seq: Option<u64>,
     },
     HomeDirectory {
         request_id: String,
@@ -456,6 +969,37 @@ pub enum ServerMessage {
         request_id: String,
         roots: Vec<String>,
     },
+    /// Reply to `GetFsConfig` and `SetFsConfig`
+    FsConfig {
+        request_id: String,
+        allowed_roots: Vec<String>,
+        limits: FsLimits,
+    },
+    /// Reply to `GrantPathOnce`: `path` is readable until `expires_at`
+    PathGranted {
+        request_id: String,
+        path: String,
+        expires_at: String,
+    },
+    /// Reply to `BroadcastInput`: how many sessions received the input
+    BroadcastResult {
+        delivered: usize,
+    },
+    /// Reply to `GetInputHistory`
+    InputHistory {
+        session_id: String,
+        entries: Vec<String>,
+    },
+    /// Reply to `GetNotificationHistory`
+    NotificationHistory {
+        request_id: String,
+        notifications: Vec<NotificationRecord>,
+    },
+    /// Reply to `GetBookmarks`, `AddBookmark`, and `RemoveBookmark`
+    Bookmarks {
+        request_id: String,
+        bookmarks: Vec<Bookmark>,
+    },
     FileChunk {
         request_id: String,
         path: String,
@@ -466,9 +1010,137 @@ pub enum ServerMessage {
         checksum: String,
         is_last: bool,
     },
+    /// Reply to `ExtractArchive`
+    ArchiveExtracted {
+        request_id: String,
+        path: String,
+        destination: String,
+        files: Vec<String>,
+    },
+    /// Reply to `PathExists`
+    PathExistsResult {
+        request_id: String,
+        path: String,
+        exists: bool,
+        is_directory: bool,
+        is_file: bool,
+    },
+    /// Progress for one `WriteFileChunk`; `is_complete` once the file is in place
+    FileChunkWritten {
+        request_id: String,
+        path: String,
+        upload_id: String,
+        chunk_index: u64,
+        total_chunks: u64,
+        bytes_written: u64,
+        is_complete: bool,
+    },
+    /// Reply to `ListUploads`
+    UploadList {
+        request_id: String,
+        uploads: Vec<FileEntry>,
+    },
+    /// Bytes appended to a file followed with `TailFile` (base64)
+    FileAppended {
+        request_id: String,
+        path: String,
+        data: String,
+    },
+    FileChecksum {
+        request_id: String,
+        path: String,
+        algorithm: ChecksumAlgorithm,
+        checksum: String,
+        size: u64,
+    },
+    /// Reply to `GetFileManifest`: `checksums[i]` is the md5 `FileChunk`
+    /// reports for chunk `i`
+    FileManifest {
+        request_id: String,
+        path: String,
+        total_size: u64,
+        total_chunks: u64,
+        chunk_size: u64,
+        checksums: Vec<String>,
+    },
+    /// Reply to `GetBinaryPreview`. `hex` has two lowercase digits per byte
+    /// and `ascii` one character per byte, `.` for anything unprintable.
+    BinaryPreview {
+        request_id: String,
+        path: String,
+        hex: String,
+        ascii: String,
+        total_size: u64,
+        /// Type recognised from magic bytes, if any
+        #[serde(skip_serializing_if = "Option::is_none")]
+        mime_type: Option<String>,
+    },
+}
+
+impl ServerMessage {
+    /// Oldest protocol version whose apps understand this message. Later
+    /// additions match on themselves here and return the version that
+    /// introduced them; everything else is part of version 1.
+    pub fn min_protocol_version(&self) -> u32 {
+        match self {
+            ServerMessage::SessionStarted { .. }
+            | ServerMessage::EchoMode { .. }
+            | ServerMessage::HistoryCleared { .. }
+            | ServerMessage::InputAck { .. } => 2,
+            _ => 1,
+        }
+    }
+}
+
+/// A user's favorite directory, shared across paired devices.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Bookmark {
+    pub path: String,
+    pub label: String,
+    pub created_at: String,
+}
+
+/// Filesystem limits the app can view and change. Replies fill in every
+/// field; in `SetFsConfig`, `None` leaves a limit unchanged.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FsLimits {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_read_size: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_write_size: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_list_entries: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_search_results: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_search_file_size: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub watch_debounce_ms: Option<u64>,
+}
+
+/// A wait-state notification the daemon sent (or tried to send) while the
+/// user may have been away.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotificationRecord {
+    pub timestamp: String,
+    pub session_id: String,
+    pub session_name: String,
+    pub wait_type: String,
+    pub title: String,
+    /// Tail of the prompt that triggered the notification
+    pub prompt: String,
 }
 
 /// Session list item for GetSessions response
+/// Sessions sharing a `project_path`, most recently active first.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProjectSessions {
+    pub project_path: String,
+    /// Latest output or input across the group (RFC 3339)
+    pub last_activity: String,
+    pub sessions: Vec<SessionListItem>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SessionListItem {
     pub session_id: String,
@@ -482,6 +1154,26 @@ pub struct SessionListItem {
     /// Runtime backend for this session (`pty` or `tmux`).
     #[serde(skip_serializing_if = "Option::is_none")]
     pub runtime: Option<String>,
+    /// `busy`, `idle` (at a prompt or quiet), or `waiting` (wait event pending)
+    #[serde(default)]
+    pub activity_state: String,
+}
+
+/// Which sessions a `BroadcastInput` reaches; set fields must all match.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BroadcastFilter {
+    /// As in `SessionListItem::cli_type` (`claude`, `codex`, ...)
+    #[serde(default)]
+    pub cli_type: Option<String>,
+    #[serde(default)]
+    pub project_path: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ArchiveFormat {
+    #[default]
+    Zip,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -500,6 +1192,37 @@ pub enum SortOrder {
     Desc,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChecksumAlgorithm {
+    Md5,
+    Sha256,
+}
+
+/// Line-ending convention of a text file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LineEnding {
+    Lf,
+    Crlf,
+    Mixed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IndentKind {
+    Spaces,
+    Tabs,
+}
+
+/// Indentation of a text file: one level is `width` spaces, or one tab
+/// (`width` 1)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IndentStyle {
+    pub kind: IndentKind,
+    pub width: u8,
+}
+
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, Default)]
 #[serde(rename_all = "snake_case")]
 pub enum FileEncoding {
@@ -526,6 +1249,17 @@ pub struct FileEntry {
     pub symlink_target: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub git_status: Option<GitStatus>,
+    /// Whether the file is likely viewable as text. `None` for directories and
+    /// for files too large to peek at cheaply.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub is_likely_text: Option<bool>,
+    /// `(width, height)` in pixels for image files, read from the header.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image_dimensions: Option<(u32, u32)>,
+    /// Number of lines in a likely-text file. `None` for other entries and
+    /// for text files too large to count cheaply.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line_count: Option<u64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -538,6 +1272,28 @@ pub struct FileContent {
     pub modified: u64,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub truncated_at: Option<u64>,
+    pub detected_encoding: String,
+    /// Highlighter language token (`rust`, `dockerfile`, `hcl`, ...); `None`
+    /// for binary content and unknown types
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    /// Line endings of the text content; `None` for base64 content and text
+    /// without a line break
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub line_ending: Option<LineEnding>,
+    /// Indentation of the text content; `None` for base64 content and text
+    /// without indented lines
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub indent: Option<IndentStyle>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileLines {
+    pub path: String,
+    pub start_line: u64,
+    pub lines: Vec<String>,
+    pub total_lines: u64,
+    pub truncated: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -548,6 +1304,18 @@ pub enum GitStatus {
     Deleted,
     Untracked,
     Ignored,
+    /// Root of a submodule or nested repository inside the listed repo
+    Submodule,
+}
+
+impl GitStatus {
+    /// Lowest protocol version whose apps can deserialize this status.
+    pub fn min_protocol_version(&self) -> u32 {
+        match self {
+            GitStatus::Submodule => 2,
+            _ => 1,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -556,6 +1324,9 @@ pub struct SearchMatch {
     pub entry: FileEntry,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub content_matches: Option<Vec<ContentMatch>>,
+    /// Fuzzy match score (higher is better); only set for fuzzy searches
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub score: Option<i64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -572,6 +1343,12 @@ pub struct FileChanged {
     pub change_type: ChangeType,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub new_entry: Option<FileEntry>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seq: Option<u64>,
+    /// Matched the watched directory's ignore rules: skipped for clients that
+    /// watch with `respect_ignore`, still seen by everyone else and file tails
+    #[serde(skip)]
+    pub ignored: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -593,6 +1370,15 @@ pub enum FileSystemError {
         path: String,
         reason: String,
     },
+    /// Path is not under any allowed root ("add this folder").
+    OutsideAllowedRoots {
+        path: String,
+    },
+    /// Path matches a protected pattern ("this file is protected").
+    DeniedByPattern {
+        path: String,
+        pattern: String,
+    },
     PathTraversal {
         attempted_path: String,
     },
@@ -619,6 +1405,26 @@ pub enum FileSystemError {
     InvalidEncoding {
         path: String,
     },
+    /// A `WriteFileChunk` chunk did not match its md5; resend it.
+    ChecksumMismatch {
+        path: String,
+        chunk_index: u64,
+    },
+    /// A `WriteFileChunk` arrived out of order or for an unknown upload.
+    UnexpectedChunk {
+        path: String,
+        expected_index: u64,
+    },
+    /// `WriteFile` with `expected_modified`: the file changed (or was
+    /// removed, `current_modified: None`) since the client read it.
+    Conflict {
+        path: String,
+        current_modified: Option<u64>,
+    },
+    /// A request field was out of range or malformed; nothing was changed.
+    InvalidArgument {
+        message: String,
+    },
     OperationCancelled,
     RateLimited {
         retry_after_ms: u64,
@@ -716,10 +1522,58 @@ impl ConnectionInfo {
     }
 }
 
+/// Encode live PTY output as a binary WebSocket frame payload.
+///
+/// Layout: one byte session id length, the session id bytes, then the raw PTY
+/// bytes. Returns `None` when the session id does not fit in the length byte,
+/// in which case callers fall back to a JSON `PtyBytes` message.
+pub fn encode_binary_pty_frame(session_id: &str, data: &[u8]) -> Option<Vec<u8>> {
+    let id_len = u8::try_from(session_id.len()).ok()?;
+    let mut frame = Vec::with_capacity(1 + session_id.len() + data.len());
+    frame.push(id_len);
+    frame.extend_from_slice(session_id.as_bytes());
+    frame.extend_from_slice(data);
+    Some(frame)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn binary_pty_frame_prefixes_session_id() {
+        let frame = encode_binary_pty_frame("abc", b"\x1b[0mhi").unwrap();
+        assert_eq!(frame[0], 3);
+        assert_eq!(&frame[1..4], b"abc");
+        assert_eq!(&frame[4..], b"\x1b[0mhi");
+
+        let long_id = "x".repeat(256);
+        assert!(encode_binary_pty_frame(&long_id, b"data").is_none());
+    }
+
+    #[test]
+    fn operation_success_omits_missing_entry() {
+        let msg = ServerMessage::OperationSuccess {
+            request_id: "r1".to_string(),
+            operation: "delete_path".to_string(),
+            path: "/tmp/a".to_string(),
+            message: None,
+            entry: None,
+        };
+        let json = serde_json::to_value(&msg).unwrap();
+        assert!(json.get("entry").is_none());
+
+        // Replies from older daemons still parse.
+        let parsed: ServerMessage = serde_json::from_str(
+            r#"{"type":"operation_success","request_id":"r1","operation":"write_file","path":"/tmp/a"}"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            parsed,
+            ServerMessage::OperationSuccess { entry: None, .. }
+        ));
+    }
+
     #[test]
     fn compact_qr_includes_auth_v2_pairing_fields() {
         let info = ConnectionInfo {
@@ -25,6 +25,13 @@ use thiserror::Error;
 use tokio::sync::mpsc;
 use tokio_tungstenite::{connect_async, tungstenite::Message};
 
+/// How often the wrapper pings the daemon so idle sessions are not mistaken
+/// for dead ones by the stale-session reaper.
+const HEARTBEAT_INTERVAL: std::time::Duration = std::time::Duration::from_secs(15);
+/// How often the PTY's termios is checked for echo changes. A single ioctl,
+/// kept short so a password prompt disables mobile local echo promptly.
+const ECHO_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(100);
+
 #[derive(Error, Debug)]
 pub enum WrapError {
     #[error("Command not found: {0}")]
@@ -478,6 +485,45 @@ fn setup_tmux_session(options: TmuxSessionOptions<'_>) -> Result<(), WrapError>
     Ok(())
 }
 
+/// Detach this wrapper's own tmux client (the `tmux attach` it spawned, found
+/// by pid), leaving the tmux server and its panes running for a later
+/// `tmux attach`. Other clients attached to the session stay connected.
+fn detach_tmux_client(ctx: &TmuxContext, client_pid: Option<u32>) -> Result<(), WrapError> {
+    let pid = client_pid.ok_or_else(|| WrapError::Pty("tmux client pid is unknown".to_string()))?;
+    let output = tmux_base_command(&ctx.socket_name)
+        .args(["list-clients", "-t", &ctx.session_name])
+        .args(["-F", "#{client_pid} #{client_name}"])
+        .output()
+        .map_err(|e| WrapError::Pty(format!("Failed to run tmux (list-clients): {e}")))?;
+    let listing = String::from_utf8_lossy(&output.stdout);
+    let client = own_tmux_client(&listing, pid)
+        .ok_or_else(|| WrapError::Pty("This session's tmux client is not attached".to_string()))?;
+    let mut cmd = tmux_base_command(&ctx.socket_name);
+    cmd.arg("detach-client").arg("-t").arg(client);
+    run_tmux_checked(&mut cmd, "detach-client")
+}
+
+/// Name of the client with `pid` in `list-clients -F '#{client_pid} #{client_name}'`
+/// output.
+fn own_tmux_client(listing: &str, pid: u32) -> Option<&str> {
+    listing.lines().find_map(|line| {
+        let (client_pid, name) = line.split_once(' ')?;
+        (client_pid.parse::<u32>().ok()? == pid).then_some(name)
+    })
+}
+
+/// Answer to a daemon `detach` request: `detached`, or `detach_failed` with
+/// the reason, in which case the session stays attached and registered.
+fn detach_reply(result: &Result<(), WrapError>) -> serde_json::Value {
+    match result {
+        Ok(()) => serde_json::json!({ "type": "detached" }),
+        Err(err) => serde_json::json!({
+            "type": "detach_failed",
+            "message": err.to_string(),
+        }),
+    }
+}
+
 fn cleanup_tmux_session(ctx: &TmuxContext) {
     let mut cmd = tmux_base_command(&ctx.socket_name);
     cmd.arg("kill-server");
@@ -630,6 +676,14 @@ pub async fn run_wrapped(config: WrapConfig) -> Result<i32, WrapError> {
         Some(Ok(Message::Text(text))) => {
             tracing::info!("Received response from daemon: {}", text);
             if let Ok(msg) = serde_json::from_str::<serde_json::Value>(&text) {
+                if msg["type"].as_str() == Some("error") {
+                    let message = msg["message"].as_str().unwrap_or("registration refused");
+                    tracing::error!("Daemon refused registration: {}", message);
+                    return Err(WrapError::DaemonConnection(format!(
+                        "Daemon refused registration: {}",
+                        message
+                    )));
+                }
                 if msg["type"].as_str() != Some("registered") {
                     tracing::error!("Unexpected response type from daemon: {:?}", msg["type"]);
                     return Err(WrapError::DaemonConnection(
@@ -776,10 +830,8 @@ pub async fn run_wrapped(config: WrapConfig) -> Result<i32, WrapError> {
         WrapError::Pty(e.to_string())
     })?;
 
-    tracing::info!(
-        "PTY command spawned successfully, PID: {:?}",
-        child.process_id()
-    );
+    let child_pid = child.process_id();
+    tracing::info!("PTY command spawned successfully, PID: {:?}", child_pid);
 
     // Drop the slave - we communicate through the master
     drop(pair.slave);
@@ -903,6 +955,8 @@ pub async fn run_wrapped(config: WrapConfig) -> Result<i32, WrapError> {
     let mut saved_local_size: Option<(u16, u16)> = None;
     let mut last_applied_pty_size: Option<(u16, u16)> = Some((cols, rows));
     let mut exit_code: i32 = 0;
+    // Set when mobile detached a tmux session: the tmux server must survive.
+    let mut detached = false;
 
     // Listen for SIGWINCH (terminal resize) on Unix so we can forward new
     // dimensions to the child PTY. Without this, resizing the desktop terminal
@@ -912,6 +966,12 @@ pub async fn run_wrapped(config: WrapConfig) -> Result<i32, WrapError> {
         tokio::signal::unix::signal(tokio::signal::unix::SignalKind::window_change())
             .expect("failed to register SIGWINCH handler");
 
+    let mut heartbeat = tokio::time::interval(HEARTBEAT_INTERVAL);
+    heartbeat.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    let mut echo_poll = tokio::time::interval(ECHO_POLL_INTERVAL);
+    echo_poll.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    let mut last_echo: Option<bool> = None;
+
     loop {
         // Helper future that resolves on SIGWINCH (unix) or never (other platforms).
         let sigwinch_fut = async {
@@ -926,6 +986,23 @@ pub async fn run_wrapped(config: WrapConfig) -> Result<i32, WrapError> {
         };
 
         tokio::select! {
+            // Liveness ping for the daemon's stale-session reaper
+            _ = heartbeat.tick() => {
+                let msg = serde_json::json!({ "type": "heartbeat" });
+                let _ = ws_tx.send(Message::Text(msg.to_string())).await;
+            }
+
+            // Termios ECHO of the foreground program, so mobile drops
+            // optimistic local echo as soon as a password prompt appears
+            _ = echo_poll.tick() => {
+                let echo = pty_echo_enabled(&*master);
+                if echo.is_some() && echo != last_echo {
+                    last_echo = echo;
+                    let msg = serde_json::json!({ "type": "echo_mode", "local_echo": echo });
+                    let _ = ws_tx.send(Message::Text(msg.to_string())).await;
+                }
+            }
+
             // Desktop terminal resize (SIGWINCH)
             _ = sigwinch_fut => {
                 let (new_cols, new_rows) = get_terminal_size();
@@ -1142,6 +1219,27 @@ pub async fn run_wrapped(config: WrapConfig) -> Result<i32, WrapError> {
                                         let _ = ws_tx.send(Message::Text(resized_msg.to_string())).await;
                                     }
                                 }
+                                Some("detach") => {
+                                    // The daemon keeps the session registered until it
+                                    // hears back, and only reports it detached on success.
+                                    let result = match &tmux_context {
+                                        Some(ctx) => detach_tmux_client(ctx, child_pid),
+                                        None => Err(WrapError::Pty(
+                                            "Session is not running under tmux".to_string(),
+                                        )),
+                                    };
+                                    let reply = detach_reply(&result);
+                                    let _ = ws_tx.send(Message::Text(reply.to_string())).await;
+                                    match result {
+                                        Ok(()) => {
+                                            detached = true;
+                                            break;
+                                        }
+                                        Err(err) => {
+                                            tracing::warn!(error = %err, "Failed to detach tmux client");
+                                        }
+                                    }
+                                }
                                 _ => {}
                             }
                         }
@@ -1177,12 +1275,15 @@ pub async fn run_wrapped(config: WrapConfig) -> Result<i32, WrapError> {
     // Cleanup
     running.store(false, Ordering::SeqCst);
 
-    // Notify daemon that the session ended (so mobile closes it promptly)
-    let msg = serde_json::json!({
-        "type": "session_ended",
-        "exit_code": exit_code,
-    });
-    let _ = ws_tx.send(Message::Text(msg.to_string())).await;
+    // Notify daemon that the session ended (so mobile closes it promptly).
+    // A detached session has not ended; the daemon already knows.
+    if !detached {
+        let msg = serde_json::json!({
+            "type": "session_ended",
+            "exit_code": exit_code,
+        });
+        let _ = ws_tx.send(Message::Text(msg.to_string())).await;
+    }
 
     // Close WebSocket
     let _ = ws_tx.close().await;
@@ -1191,7 +1292,9 @@ pub async fn run_wrapped(config: WrapConfig) -> Result<i32, WrapError> {
     let _ = reader_handle.join();
 
     if let Some(ctx) = &tmux_context {
-        cleanup_tmux_session(ctx);
+        if !detached {
+            cleanup_tmux_session(ctx);
+        }
     }
 
     // Reset terminal state after tmux teardown. Tmux with mouse mode enabled
@@ -1213,7 +1316,13 @@ pub async fn run_wrapped(config: WrapConfig) -> Result<i32, WrapError> {
 
     // Print exit message
     println!();
-    if exit_code == 0 {
+    if let (true, Some(ctx)) = (detached, &tmux_context) {
+        println!(
+            "{} Session detached from your phone; reattach with {}",
+            "•".yellow(),
+            format!("tmux -L {} attach -t {}", ctx.socket_name, ctx.session_name).cyan()
+        );
+    } else if exit_code == 0 {
         println!("{} Session ended", "✓".green());
     } else if exit_code == 130 {
         println!("{} Session interrupted", "•".yellow());
@@ -1224,6 +1333,26 @@ pub async fn run_wrapped(config: WrapConfig) -> Result<i32, WrapError> {
     Ok(exit_code)
 }
 
+/// Whether the PTY line discipline currently echoes input. Under tmux this is
+/// the tmux client's raw-mode PTY, so it reads as off (the safe answer).
+#[cfg(unix)]
+fn pty_echo_enabled(master: &(dyn portable_pty::MasterPty + Send)) -> Option<bool> {
+    use nix::sys::termios::{self, LocalFlags};
+    use std::os::fd::BorrowedFd;
+
+    let fd = master.as_raw_fd()?;
+    // SAFETY: the fd belongs to `master`, which outlives this call.
+    let fd = unsafe { BorrowedFd::borrow_raw(fd) };
+    termios::tcgetattr(fd)
+        .ok()
+        .map(|t| t.local_flags.contains(LocalFlags::ECHO))
+}
+
+#[cfg(not(unix))]
+fn pty_echo_enabled(_master: &(dyn portable_pty::MasterPty + Send)) -> Option<bool> {
+    None
+}
+
 /// Set up raw terminal mode for proper input handling
 #[cfg(unix)]
 fn setup_raw_mode() -> Option<nix::sys::termios::Termios> {
@@ -1264,12 +1393,49 @@ fn restore_terminal_mode(_original: Option<()>) {}
 #[cfg(test)]
 mod tests {
     use super::{
-        cleanup_tmux_session, parse_bool_env_flag, parse_tmux_mouse_mode, resolve_resize_reason,
-        resolve_runtime_mode, sanitize_tmux_token, setup_tmux_session, tmux_base_command,
-        RuntimeMode, TmuxContext, TmuxMouseMode, TmuxSessionOptions,
+        cleanup_tmux_session, detach_reply, own_tmux_client, parse_bool_env_flag,
+        parse_tmux_mouse_mode, pty_echo_enabled, resolve_resize_reason, resolve_runtime_mode,
+        sanitize_tmux_token, setup_tmux_session, tmux_base_command, RuntimeMode, TmuxContext,
+        TmuxMouseMode, TmuxSessionOptions, WrapError,
     };
     use crate::protocol::PtyResizeReason;
 
+    #[test]
+    fn own_tmux_client_ignores_other_attached_clients() {
+        let listing = "4242 /dev/pts/7\n977 /dev/pts/3\n";
+        assert_eq!(own_tmux_client(listing, 977), Some("/dev/pts/3"));
+        assert_eq!(own_tmux_client(listing, 4242), Some("/dev/pts/7"));
+        assert_eq!(own_tmux_client(listing, 97), None);
+    }
+
+    #[test]
+    fn detach_reply_reports_failure_with_reason() {
+        assert_eq!(detach_reply(&Ok(()))["type"], "detached");
+        let failed = detach_reply(&Err(WrapError::Pty("no client".to_string())));
+        assert_eq!(failed["type"], "detach_failed");
+        assert!(failed["message"].as_str().unwrap().contains("no client"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn echo_state_follows_pty_termios() {
+        use nix::sys::termios::{self, LocalFlags, SetArg};
+        use portable_pty::{native_pty_system, PtySize};
+        use std::os::fd::BorrowedFd;
+
+        let pair = native_pty_system()
+            .openpty(PtySize::default())
+            .expect("openpty");
+        assert_eq!(pty_echo_enabled(&*pair.master), Some(true));
+
+        let fd = pair.master.as_raw_fd().expect("master fd");
+        let fd = unsafe { BorrowedFd::borrow_raw(fd) };
+        let mut attrs = termios::tcgetattr(fd).expect("tcgetattr");
+        attrs.local_flags.remove(LocalFlags::ECHO);
+        termios::tcsetattr(fd, SetArg::TCSANOW, &attrs).expect("tcsetattr");
+        assert_eq!(pty_echo_enabled(&*pair.master), Some(false));
+    }
+
     #[test]
     fn wrapper_reason_resolves_restore_from_zero_dimensions() {
         assert_eq!(
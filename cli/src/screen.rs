@@ -0,0 +1,91 @@
+//! Headless screen rendering
+//!
+//! Replays terminal output through a `vt100` emulator and returns the visible
+//! grid as plain text, enough for session preview cards. Colours and other
+//! attributes are dropped; scroll regions, the alternate screen and wide
+//! characters behave as they would in a real terminal.
+
+/// Largest grid a client may ask for in either direction.
+pub const MAX_SCREEN_DIMENSION: u16 = 500;
+
+/// The visible screen after replaying some output.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenderedScreen {
+    /// One entry per row, trailing blanks trimmed.
+    pub lines: Vec<String>,
+    pub cursor_row: u16,
+    pub cursor_col: u16,
+}
+
+/// Replay `bytes` on a blank `cols` x `rows` screen. Dimensions are clamped
+/// to `1..=MAX_SCREEN_DIMENSION`.
+pub fn render(bytes: &[u8], cols: u16, rows: u16) -> RenderedScreen {
+    let cols = cols.clamp(1, MAX_SCREEN_DIMENSION);
+    let rows = rows.clamp(1, MAX_SCREEN_DIMENSION);
+    let mut parser = vt100::Parser::new(rows, cols, 0);
+    parser.process(bytes);
+    let screen = parser.screen();
+    let (cursor_row, cursor_col) = screen.cursor_position();
+    RenderedScreen {
+        lines: screen
+            .rows(0, cols)
+            .map(|line| line.trim_end().to_string())
+            .collect(),
+        cursor_row,
+        cursor_col,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_only_the_visible_screen() {
+        let screen = render(b"one\r\ntwo\r\nthree\r\nfour", 10, 3);
+        assert_eq!(screen.lines, vec!["two", "three", "four"]);
+        assert_eq!((screen.cursor_row, screen.cursor_col), (2, 4));
+    }
+
+    #[test]
+    fn applies_cursor_moves_and_erases() {
+        let screen = render(b"\x1b[31mhello world\x1b[0m\x1b[1;7H\x1b[Kthere", 20, 2);
+        assert_eq!(screen.lines, vec!["hello there", ""]);
+
+        let screen = render(b"hello\r\nworld\x1b[H\x1b[2J", 20, 2);
+        assert_eq!(screen.lines, vec!["", ""]);
+    }
+
+    #[test]
+    fn wraps_long_lines_and_clamps_dimensions() {
+        let screen = render(b"abcdef", 4, 2);
+        assert_eq!(screen.lines, vec!["abcd", "ef"]);
+
+        let screen = render(b"x", 0, 0);
+        assert_eq!(screen.lines, vec!["x"]);
+    }
+
+    #[test]
+    fn renders_alternate_screen_with_a_scroll_region() {
+        // A pager-style app: switch to the alternate screen, pin a status
+        // line by limiting scrolling to rows 1-3, then scroll past it.
+        let mut output = b"shell prompt\r\n".to_vec();
+        output.extend_from_slice(b"\x1b[?1049h\x1b[H\x1b[2J");
+        output.extend_from_slice(b"\x1b[1;3r\x1b[4;1Hstatus\x1b[1;1H");
+        output.extend_from_slice(b"a\r\nb\r\nc\r\nd\r\ne");
+        let screen = render(&output, 20, 4);
+        assert_eq!(screen.lines, vec!["c", "d", "e", "status"]);
+        assert_eq!((screen.cursor_row, screen.cursor_col), (2, 1));
+
+        // Leaving the alternate screen brings the shell back untouched.
+        output.extend_from_slice(b"\x1b[r\x1b[?1049l");
+        let screen = render(&output, 20, 4);
+        assert_eq!(screen.lines, vec!["shell prompt", "", "", ""]);
+    }
+
+    #[test]
+    fn wide_characters_take_two_cells() {
+        let screen = render("日本x".as_bytes(), 4, 2);
+        assert_eq!(screen.lines, vec!["日本", "x"]);
+    }
+}
@@ -7,6 +7,7 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::PathBuf;
+use std::sync::Mutex;
 
 /// Session info stored in the sessions file
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -22,7 +23,7 @@ pub struct SessionInfo {
 }
 
 /// Get the sessions file path (cross-platform)
-fn sessions_file() -> PathBuf {
+pub fn sessions_file() -> PathBuf {
     platform::config_dir().join("sessions.json")
 }
 
@@ -56,6 +57,72 @@ pub fn save_sessions(sessions: &[SessionInfo]) -> std::io::Result<()> {
     fs::write(path, data)
 }
 
+/// A user's rename, keyed by what survives a daemon restart. Session ids are
+/// regenerated, so the project directory and command stand in for identity.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SessionNameOverride {
+    project_path: String,
+    command: String,
+    name: String,
+}
+
+/// Serializes load-modify-save cycles of the session names file.
+static SESSION_NAMES_LOCK: Mutex<()> = Mutex::new(());
+
+fn session_names_file() -> PathBuf {
+    platform::config_dir().join("session_names.json")
+}
+
+fn load_session_names() -> Vec<SessionNameOverride> {
+    fs::read_to_string(session_names_file())
+        .ok()
+        .and_then(|data| serde_json::from_str(&data).ok())
+        .unwrap_or_default()
+}
+
+/// The name the user last gave a session of `command` in `project_path`.
+pub fn saved_session_name(project_path: &str, command: &str) -> Option<String> {
+    if project_path.is_empty() {
+        return None;
+    }
+    load_session_names()
+        .into_iter()
+        .find(|o| o.project_path == project_path && o.command == command)
+        .map(|o| o.name)
+}
+
+/// Remember a rename so the session keeps it after a daemon restart.
+pub fn save_session_name(project_path: &str, command: &str, name: &str) -> std::io::Result<()> {
+    if project_path.is_empty() {
+        return Ok(());
+    }
+    let _guard = SESSION_NAMES_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let mut names = load_session_names();
+    upsert_session_name(&mut names, project_path, command, name);
+    ensure_config_dir()?;
+    let data = serde_json::to_string_pretty(&names)?;
+    fs::write(session_names_file(), data)
+}
+
+fn upsert_session_name(
+    names: &mut Vec<SessionNameOverride>,
+    project_path: &str,
+    command: &str,
+    name: &str,
+) {
+    match names
+        .iter_mut()
+        .find(|o| o.project_path == project_path && o.command == command)
+    {
+        Some(existing) => existing.name = name.to_string(),
+        None => names.push(SessionNameOverride {
+            project_path: project_path.to_string(),
+            command: command.to_string(),
+            name: name.to_string(),
+        }),
+    }
+}
+
 /// Check if a process is still alive (cross-platform via platform module)
 ///
 /// Uses kill(pid, 0) signal test on Unix, Windows API on Windows.
@@ -70,3 +137,20 @@ pub fn list_active_sessions() -> Vec<SessionInfo> {
         .filter(|s| is_process_alive(s.pid))
         .collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::upsert_session_name;
+
+    #[test]
+    fn renames_are_keyed_by_project_and_command() {
+        let mut names = Vec::new();
+        upsert_session_name(&mut names, "/work/api", "claude", "API agent");
+        upsert_session_name(&mut names, "/work/api", "bash", "API shell");
+        upsert_session_name(&mut names, "/work/api", "claude", "Backend");
+
+        assert_eq!(names.len(), 2);
+        assert_eq!(names[0].name, "Backend");
+        assert_eq!(names[1].name, "API shell");
+    }
+}
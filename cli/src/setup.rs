@@ -31,9 +31,19 @@ pub struct Config {
     pub connection_mode: ConnectionMode,
     pub tailscale_ip: Option<String>,
     pub local_ip: Option<String>,
+    /// Interface for the mobile listener, overriding the address derived from
+    /// the connection mode. Loopback is always bound for the desktop.
+    pub bind_address: Option<std::net::IpAddr>,
+    /// CIDR ranges (or single IPs) allowed to open mobile connections.
+    /// Empty allows every address.
+    pub allowed_client_cidrs: Vec<String>,
+    /// Reverse proxies (CIDR ranges or single IPs) whose `Forwarded` /
+    /// `X-Forwarded-For` headers name the real client. Empty trusts none.
+    pub trusted_proxies: Vec<String>,
     pub auth_version: u8,
     pub credentials: Vec<AuthCredential>,
     pub filesystem: FileSystemAccessConfig,
+    pub spawn: SpawnConfig,
 }
 
 /// Configuration for mobile filesystem access.
@@ -45,6 +55,31 @@ pub struct FileSystemAccessConfig {
     pub whole_home_enabled: bool,
     /// Destructive operations such as delete/rename/copy-overwrite are allowed.
     pub destructive_operations: bool,
+    /// Recursive directory deletes need a preview-then-confirm round trip.
+    pub confirm_recursive_delete: bool,
+    /// Allowed root -> glob patterns re-allowed under that root despite the
+    /// global denylist (e.g. `**/.env` in a sandbox project).
+    pub denied_pattern_overrides: std::collections::BTreeMap<String, Vec<String>>,
+    /// Debounce for directory watch events in ms; the daemon default if unset.
+    pub watch_debounce_ms: Option<u64>,
+    /// Largest file content search scans, in bytes; the daemon default if unset.
+    pub search_max_file_size: Option<u64>,
+    /// Size and count limits; the daemon defaults if unset.
+    pub max_read_size: Option<u64>,
+    pub max_write_size: Option<u64>,
+    pub max_list_entries: Option<usize>,
+    pub max_search_results: Option<u32>,
+    /// Largest directory `DownloadArchive` packs, in bytes; the daemon default if unset.
+    pub max_archive_size: Option<u64>,
+    /// Keep a `.bak` rollback copy while a write replaces a file.
+    pub atomic_backup: bool,
+}
+
+/// Configuration for sessions spawned from mobile.
+#[derive(Debug, Clone, Default)]
+pub struct SpawnConfig {
+    /// Extra command names mobile may spawn, on top of the built-in profiles.
+    pub allowed_commands: Vec<String>,
 }
 
 impl Default for FileSystemAccessConfig {
@@ -68,6 +103,16 @@ impl Default for FileSystemAccessConfig {
             allowed_roots,
             whole_home_enabled: false,
             destructive_operations: false,
+            confirm_recursive_delete: false,
+            denied_pattern_overrides: std::collections::BTreeMap::new(),
+            watch_debounce_ms: None,
+            search_max_file_size: None,
+            max_read_size: None,
+            max_write_size: None,
+            max_list_entries: None,
+            max_search_results: None,
+            max_archive_size: None,
+            atomic_backup: true,
         }
     }
 }
@@ -83,9 +128,13 @@ impl Default for Config {
             connection_mode: ConnectionMode::Local,
             tailscale_ip: None,
             local_ip: None,
+            bind_address: None,
+            allowed_client_cidrs: Vec::new(),
+            trusted_proxies: Vec::new(),
             auth_version: auth::AUTH_VERSION,
             credentials: Vec::new(),
             filesystem: FileSystemAccessConfig::default(),
+            spawn: SpawnConfig::default(),
         }
     }
 }
@@ -163,6 +212,7 @@ pub fn load_config() -> Option<Config> {
         .unwrap_or_default();
 
     let filesystem = parse_filesystem_config(json.get("filesystem"));
+    let spawn = parse_spawn_config(json.get("spawn"));
 
     let config = Config {
         config_version: json
@@ -191,17 +241,49 @@ pub fn load_config() -> Option<Config> {
             .get("local_ip")
             .and_then(|v| v.as_str())
             .map(|s| s.to_string()),
+        bind_address: parse_bind_address(json.get("bind_address")),
+        allowed_client_cidrs: parse_address_ranges(json.get("allowed_client_cidrs")),
+        trusted_proxies: parse_address_ranges(json.get("trusted_proxies")),
         auth_version: json
             .get("auth_version")
             .and_then(|v| v.as_u64())
             .unwrap_or(auth::AUTH_VERSION as u64) as u8,
         credentials,
         filesystem,
+        spawn,
     };
 
     Some(config)
 }
 
+fn parse_address_ranges(value: Option<&serde_json::Value>) -> Vec<String> {
+    value
+        .and_then(|v| v.as_array())
+        .map(|ranges| {
+            ranges
+                .iter()
+                .filter_map(|v| v.as_str())
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn parse_bind_address(value: Option<&serde_json::Value>) -> Option<std::net::IpAddr> {
+    let raw = value?.as_str()?.trim();
+    if raw.is_empty() {
+        return None;
+    }
+    match raw.parse() {
+        Ok(ip) => Some(ip),
+        Err(_) => {
+            tracing::warn!(bind_address = %raw, "Ignoring bind_address that is not an IP address");
+            None
+        }
+    }
+}
+
 fn parse_filesystem_config(value: Option<&serde_json::Value>) -> FileSystemAccessConfig {
     let mut config = FileSystemAccessConfig::default();
     let mut allowed_roots_seen = false;
@@ -223,6 +305,48 @@ fn parse_filesystem_config(value: Option<&serde_json::Value>) -> FileSystemAcces
         {
             config.destructive_operations = enabled;
         }
+        if let Some(enabled) = value
+            .get("confirm_recursive_delete")
+            .and_then(|v| v.as_bool())
+        {
+            config.confirm_recursive_delete = enabled;
+        }
+        if let Some(enabled) = value.get("atomic_backup").and_then(|v| v.as_bool()) {
+            config.atomic_backup = enabled;
+        }
+        if let Some(overrides) = value
+            .get("denied_pattern_overrides")
+            .and_then(|v| v.as_object())
+        {
+            config.denied_pattern_overrides = overrides
+                .iter()
+                .filter(|(root, _)| !root.trim().is_empty())
+                .filter_map(|(root, patterns)| {
+                    let patterns: Vec<String> = patterns
+                        .as_array()?
+                        .iter()
+                        .filter_map(|v| v.as_str())
+                        .map(str::trim)
+                        .filter(|s| !s.is_empty())
+                        .map(|s| s.to_string())
+                        .collect();
+                    (!patterns.is_empty()).then(|| (root.clone(), patterns))
+                })
+                .collect();
+        }
+        if let Some(ms) = value.get("watch_debounce_ms").and_then(|v| v.as_u64()) {
+            config.watch_debounce_ms = Some(ms);
+        }
+        if let Some(bytes) = value.get("search_max_file_size").and_then(|v| v.as_u64()) {
+            config.search_max_file_size = Some(bytes);
+        }
+        let limit = |key: &str| value.get(key).and_then(|v| v.as_u64());
+        config.max_read_size = limit("max_read_size");
+        config.max_write_size = limit("max_write_size");
+        config.max_list_entries = limit("max_list_entries").map(|n| n as usize);
+        config.max_search_results =
+            limit("max_search_results").map(|n| n.min(u64::from(u32::MAX)) as u32);
+        config.max_archive_size = limit("max_archive_size");
     }
     if !allowed_roots_seen && config.allowed_roots.is_empty() {
         config.allowed_roots = FileSystemAccessConfig::default().allowed_roots;
@@ -230,6 +354,32 @@ fn parse_filesystem_config(value: Option<&serde_json::Value>) -> FileSystemAcces
     config
 }
 
+fn parse_spawn_config(value: Option<&serde_json::Value>) -> SpawnConfig {
+    let allowed_commands = value
+        .and_then(|v| v.get("allowed_commands"))
+        .and_then(|v| v.as_array())
+        .map(|commands| {
+            commands
+                .iter()
+                .filter_map(|v| v.as_str())
+                .map(str::trim)
+                .filter(|s| is_valid_spawn_command_name(s))
+                .map(|s| s.to_string())
+                .collect()
+        })
+        .unwrap_or_default();
+    SpawnConfig { allowed_commands }
+}
+
+/// Allowlisted commands are bare executable names resolved through PATH.
+fn is_valid_spawn_command_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+        && !name.starts_with('.')
+}
+
 /// Save configuration
 pub fn save_config(config: &Config) -> io::Result<()> {
     let config_path = get_config_path();
@@ -253,12 +403,28 @@ pub fn save_config(config: &Config) -> io::Result<()> {
         "connection_mode": mode_str,
         "tailscale_ip": &config.tailscale_ip,
         "local_ip": &config.local_ip,
+        "bind_address": config.bind_address.map(|ip| ip.to_string()),
+        "allowed_client_cidrs": &config.allowed_client_cidrs,
+        "trusted_proxies": &config.trusted_proxies,
         "auth_version": config.auth_version,
         "credentials": &config.credentials,
         "filesystem": {
             "allowed_roots": &config.filesystem.allowed_roots,
             "whole_home_enabled": config.filesystem.whole_home_enabled,
             "destructive_operations": config.filesystem.destructive_operations,
+            "confirm_recursive_delete": config.filesystem.confirm_recursive_delete,
+            "denied_pattern_overrides": &config.filesystem.denied_pattern_overrides,
+            "watch_debounce_ms": config.filesystem.watch_debounce_ms,
+            "search_max_file_size": config.filesystem.search_max_file_size,
+            "max_read_size": config.filesystem.max_read_size,
+            "max_write_size": config.filesystem.max_write_size,
+            "max_list_entries": config.filesystem.max_list_entries,
+            "max_search_results": config.filesystem.max_search_results,
+            "max_archive_size": config.filesystem.max_archive_size,
+            "atomic_backup": config.filesystem.atomic_backup,
+        },
+        "spawn": {
+            "allowed_commands": &config.spawn.allowed_commands,
         },
     });
 
@@ -776,7 +942,7 @@ pub fn run_setup_wizard() -> io::Result<Config> {
 
 #[cfg(test)]
 mod tests {
-    use super::parse_filesystem_config;
+    use super::{parse_bind_address, parse_filesystem_config, parse_spawn_config};
 
     #[test]
     fn explicit_empty_allowed_roots_stays_deny_all() {
@@ -792,4 +958,96 @@ mod tests {
         assert!(!config.whole_home_enabled);
         assert!(!config.destructive_operations);
     }
+
+    #[test]
+    fn denied_pattern_overrides_parse_per_root() {
+        let value = serde_json::json!({
+            "allowed_roots": ["/work/sandbox"],
+            "denied_pattern_overrides": {
+                "/work/sandbox": ["**/.env", " ", 3],
+                "/work/empty": [],
+                "": ["**/.env"],
+            },
+        });
+
+        let config = parse_filesystem_config(Some(&value));
+
+        assert_eq!(config.denied_pattern_overrides.len(), 1);
+        assert_eq!(
+            config.denied_pattern_overrides["/work/sandbox"],
+            vec!["**/.env".to_string()]
+        );
+    }
+
+    #[test]
+    fn watch_debounce_parses_when_numeric() {
+        let parse = |v: serde_json::Value| parse_filesystem_config(Some(&v)).watch_debounce_ms;
+        assert_eq!(
+            parse(serde_json::json!({ "watch_debounce_ms": 50 })),
+            Some(50)
+        );
+        assert_eq!(
+            parse(serde_json::json!({ "watch_debounce_ms": "fast" })),
+            None
+        );
+        assert_eq!(parse(serde_json::json!({})), None);
+    }
+
+    #[test]
+    fn recursive_delete_confirmation_is_opt_in() {
+        assert!(!parse_filesystem_config(Some(&serde_json::json!({}))).confirm_recursive_delete);
+        let value = serde_json::json!({ "confirm_recursive_delete": true });
+        assert!(parse_filesystem_config(Some(&value)).confirm_recursive_delete);
+    }
+
+    #[test]
+    fn atomic_backup_is_on_unless_disabled() {
+        assert!(parse_filesystem_config(Some(&serde_json::json!({}))).atomic_backup);
+        let value = serde_json::json!({ "atomic_backup": false });
+        assert!(!parse_filesystem_config(Some(&value)).atomic_backup);
+    }
+
+    #[test]
+    fn search_max_file_size_parses_when_numeric() {
+        let parse = |v: serde_json::Value| parse_filesystem_config(Some(&v)).search_max_file_size;
+        assert_eq!(
+            parse(serde_json::json!({ "search_max_file_size": 1048576 })),
+            Some(1048576)
+        );
+        assert_eq!(
+            parse(serde_json::json!({ "search_max_file_size": "1MB" })),
+            None
+        );
+    }
+
+    #[test]
+    fn bind_address_must_parse_as_ip() {
+        let parse = |v: serde_json::Value| parse_bind_address(Some(&v));
+        assert_eq!(
+            parse(serde_json::json!(" 100.64.0.7 ")),
+            Some("100.64.0.7".parse().unwrap())
+        );
+        assert_eq!(
+            parse(serde_json::json!("::1")),
+            Some("::1".parse().unwrap())
+        );
+        assert_eq!(parse(serde_json::json!("tailscale0")), None);
+        assert_eq!(parse(serde_json::json!("")), None);
+        assert_eq!(parse_bind_address(None), None);
+    }
+
+    #[test]
+    fn spawn_allowlist_keeps_only_bare_command_names() {
+        let value = serde_json::json!({
+            "allowed_commands": ["aider", " cursor-agent ", "llm", "/usr/bin/evil", "a b", "..", 7],
+        });
+
+        let config = parse_spawn_config(Some(&value));
+
+        assert_eq!(
+            config.allowed_commands,
+            vec!["aider", "cursor-agent", "llm"]
+        );
+        assert!(parse_spawn_config(None).allowed_commands.is_empty());
+    }
 }
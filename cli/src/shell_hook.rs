@@ -7,6 +7,8 @@
 //! - bash  (~/.bashrc, ~/.bash_profile on macOS)
 //! - zsh   (~/.zshrc)
 //! - fish  (~/.config/fish/config.fish)
+//! - nushell (~/.config/nushell/config.nu)
+//! - elvish (~/.config/elvish/rc.elv)
 //! - PowerShell ($PROFILE)
 //! - cmd.exe (Registry AutoRun) — prints manual instructions only
 //!
@@ -18,7 +20,7 @@
 use crate::platform;
 use clap::Subcommand;
 use colored::Colorize;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 // ── Sentinel markers ──────────────────────────────────────────────────────────
 
@@ -29,14 +31,20 @@ const BEGIN_MARKER_PS: &str = "# >>> mobilecli auto-launch >>>";
 const END_MARKER_PS: &str = "# <<< mobilecli auto-launch <<<";
 
 // ── Shell snippets ────────────────────────────────────────────────────────────
-
-fn bash_snippet() -> String {
+//
+// Each snippet launches `mobilecli` from PATH, or the given executable when the
+// hook has been pinned to one because PATH resolves to a stale binary.
+
+fn bash_snippet(exe: Option<&Path>) -> String {
+    let program = exe
+        .map(|p| format!("'{}'", p.display().to_string().replace('\'', r"'\''")))
+        .unwrap_or_else(|| "mobilecli".to_string());
     format!(
         r#"{BEGIN_MARKER}
 # Automatically launch mobilecli in interactive terminals.
 # To disable: export MOBILECLI_NO_AUTO_LAUNCH=1  (or run: mobilecli autolaunch uninstall)
-if [ -z "$MOBILECLI_NO_AUTO_LAUNCH" ] && [ -z "$MOBILECLI_SESSION" ] && [ -t 0 ] && command -v mobilecli >/dev/null 2>&1; then
-  exec mobilecli
+if [ -z "$MOBILECLI_NO_AUTO_LAUNCH" ] && [ -z "$MOBILECLI_SESSION" ] && [ -t 0 ] && command -v {program} >/dev/null 2>&1; then
+  exec {program}
 fi
 {END_MARKER}"#,
         BEGIN_MARKER = BEGIN_MARKER,
@@ -44,13 +52,19 @@ fi
     )
 }
 
-fn fish_snippet() -> String {
+fn fish_snippet(exe: Option<&Path>) -> String {
+    let program = exe
+        .map(|p| {
+            let escaped = p.display().to_string().replace('\\', r"\\");
+            format!("'{}'", escaped.replace('\'', r"\'"))
+        })
+        .unwrap_or_else(|| "mobilecli".to_string());
     format!(
         r#"{BEGIN_MARKER}
 # Automatically launch mobilecli in interactive terminals.
 # To disable: set -gx MOBILECLI_NO_AUTO_LAUNCH 1  (or run: mobilecli autolaunch uninstall)
-if not set -q MOBILECLI_NO_AUTO_LAUNCH; and not set -q MOBILECLI_SESSION; and isatty stdin; and command -v mobilecli >/dev/null 2>&1
-    exec mobilecli
+if not set -q MOBILECLI_NO_AUTO_LAUNCH; and not set -q MOBILECLI_SESSION; and isatty stdin; and command -v {program} >/dev/null 2>&1
+    exec {program}
 end
 {END_MARKER}"#,
         BEGIN_MARKER = BEGIN_MARKER,
@@ -58,13 +72,58 @@ end
     )
 }
 
-fn powershell_snippet() -> String {
+fn nushell_snippet(exe: Option<&Path>) -> String {
+    let (found, program) = match exe {
+        Some(p) => {
+            let escaped = p.display().to_string().replace('\\', r"\\");
+            let quoted = format!("\"{}\"", escaped.replace('"', "\\\""));
+            (format!("({} | path exists)", quoted), quoted)
+        }
+        None => (
+            "((which mobilecli | length) > 0)".to_string(),
+            "mobilecli".to_string(),
+        ),
+    };
+    format!(
+        r#"{BEGIN_MARKER}
+# Automatically launch mobilecli in interactive terminals.
+# To disable: $env.MOBILECLI_NO_AUTO_LAUNCH = "1"  (or run: mobilecli autolaunch uninstall)
+if ("MOBILECLI_NO_AUTO_LAUNCH" not-in $env) and ("MOBILECLI_SESSION" not-in $env) and $nu.is-interactive and {found} {{
+    exec {program}
+}}
+{END_MARKER}"#,
+        BEGIN_MARKER = BEGIN_MARKER,
+        END_MARKER = END_MARKER,
+    )
+}
+
+fn elvish_snippet(exe: Option<&Path>) -> String {
+    let program = exe
+        .map(|p| format!("'{}'", p.display().to_string().replace('\'', "''")))
+        .unwrap_or_else(|| "mobilecli".to_string());
+    format!(
+        r#"{BEGIN_MARKER}
+# Automatically launch mobilecli in interactive terminals.
+# To disable: set-env MOBILECLI_NO_AUTO_LAUNCH 1  (or run: mobilecli autolaunch uninstall)
+if (and (not (has-env MOBILECLI_NO_AUTO_LAUNCH)) (not (has-env MOBILECLI_SESSION)) (has-external {program})) {{
+  exec {program}
+}}
+{END_MARKER}"#,
+        BEGIN_MARKER = BEGIN_MARKER,
+        END_MARKER = END_MARKER,
+    )
+}
+
+fn powershell_snippet(exe: Option<&Path>) -> String {
+    let program = exe
+        .map(|p| format!("'{}'", p.display().to_string().replace('\'', "''")))
+        .unwrap_or_else(|| "mobilecli".to_string());
     format!(
         r#"{BEGIN_MARKER}
 # Automatically launch mobilecli in interactive terminals.
 # To disable: $env:MOBILECLI_NO_AUTO_LAUNCH = "1"  (or run: mobilecli autolaunch uninstall)
-if (-not $env:MOBILECLI_NO_AUTO_LAUNCH -and -not $env:MOBILECLI_SESSION -and [Environment]::UserInteractive -and (Get-Command mobilecli -ErrorAction SilentlyContinue)) {{
-    & mobilecli
+if (-not $env:MOBILECLI_NO_AUTO_LAUNCH -and -not $env:MOBILECLI_SESSION -and [Environment]::UserInteractive -and (Get-Command {program} -ErrorAction SilentlyContinue)) {{
+    & {program}
     exit $LASTEXITCODE
 }}
 {END_MARKER}"#,
@@ -83,6 +142,13 @@ pub enum ShellHookCommand {
     Uninstall,
     /// Show whether the hook is installed and for which shell(s)
     Status,
+    /// Check installed hooks for outdated snippets or a stale `mobilecli` on PATH
+    Doctor {
+        /// Rewrite outdated hook blocks with the current snippet, pinning them
+        /// to this binary when `mobilecli` on PATH is a different one
+        #[arg(long)]
+        repair: bool,
+    },
 }
 
 pub fn run(cmd: ShellHookCommand) -> Result<(), Box<dyn std::error::Error>> {
@@ -90,6 +156,7 @@ pub fn run(cmd: ShellHookCommand) -> Result<(), Box<dyn std::error::Error>> {
         ShellHookCommand::Install => install(),
         ShellHookCommand::Uninstall => uninstall(),
         ShellHookCommand::Status => status(),
+        ShellHookCommand::Doctor { repair } => doctor(repair),
     }
 }
 
@@ -99,7 +166,7 @@ fn install() -> Result<(), Box<dyn std::error::Error>> {
     let targets = detect_shell_targets();
     if targets.is_empty() {
         eprintln!(
-            "{} Could not detect your shell. Set $SHELL or run this inside bash/zsh/fish/PowerShell.",
+            "{} Could not detect your shell. Set $SHELL or run this inside bash/zsh/fish/nushell/elvish/PowerShell.",
             "✗".red()
         );
         print_cmd_instructions();
@@ -108,7 +175,7 @@ fn install() -> Result<(), Box<dyn std::error::Error>> {
 
     let mut any_installed = false;
     for target in &targets {
-        match install_into(target) {
+        match install_into(target, None) {
             Ok(true) => {
                 println!(
                     "{} Installed auto-launch hook into {}",
@@ -226,12 +293,163 @@ fn status() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+// ── Doctor ────────────────────────────────────────────────────────────────────
+
+fn doctor(repair: bool) -> Result<(), Box<dyn std::error::Error>> {
+    // The hook runs whatever `mobilecli` resolves to on PATH, so a reinstall to
+    // a different location leaves it launching the old binary (or nothing).
+    let current = std::env::current_exe()
+        .ok()
+        .and_then(|p| p.canonicalize().ok());
+    let on_path = which::which("mobilecli")
+        .ok()
+        .and_then(|p| p.canonicalize().ok());
+
+    let mut hooked = 0usize;
+    let mut problems = 0usize;
+
+    for target in &all_possible_targets() {
+        if !target.path.exists() {
+            continue;
+        }
+        let content = std::fs::read_to_string(&target.path).unwrap_or_default();
+        let Some(block) = installed_block(&content) else {
+            continue;
+        };
+        hooked += 1;
+
+        let pin = hook_pin(on_path.as_deref(), current.as_deref());
+        if block.trim_end() == (target.snippet)(pin).trim_end() {
+            println!(
+                "{} {} hook is up to date ({})",
+                "✓".green(),
+                target.shell_name,
+                target.path.display().to_string().dimmed()
+            );
+            continue;
+        }
+
+        problems += 1;
+        match pin {
+            Some(exe) => println!(
+                "{} {} hook in {} would not launch this binary ({})",
+                "!".yellow(),
+                target.shell_name,
+                target.path.display().to_string().cyan(),
+                exe.display().to_string().cyan()
+            ),
+            None => println!(
+                "{} {} hook in {} was written by a different mobilecli version",
+                "!".yellow(),
+                target.shell_name,
+                target.path.display().to_string().cyan()
+            ),
+        }
+        if repair {
+            remove_from(target)?;
+            install_into(target, pin)?;
+            match pin {
+                Some(exe) => println!(
+                    "  {} Rewrote the hook block to launch {}",
+                    "✓".green(),
+                    exe.display()
+                ),
+                None => println!("  {} Rewrote the hook block", "✓".green()),
+            }
+        } else {
+            println!("  Fix: {}", "mobilecli autolaunch doctor --repair".dimmed());
+        }
+    }
+
+    if hooked == 0 {
+        println!("{} Auto-launch: not installed", "○".dimmed());
+        println!("  Run {} to enable", "mobilecli autolaunch install".cyan());
+        return Ok(());
+    }
+
+    match (on_path, current) {
+        (None, Some(current)) => {
+            println!(
+                "{} `mobilecli` is not on PATH; hooks must be pinned to {}",
+                "!".yellow(),
+                current.display().to_string().cyan()
+            );
+            println!(
+                "  To launch from PATH instead, add {} to PATH",
+                current
+                    .parent()
+                    .map(|p| p.display().to_string())
+                    .unwrap_or_default()
+                    .cyan()
+            );
+        }
+        (Some(on_path), Some(current)) if on_path != current => {
+            println!(
+                "{} `mobilecli` on PATH is {} but this binary is {}",
+                "!".yellow(),
+                on_path.display().to_string().cyan(),
+                current.display().to_string().cyan()
+            );
+            println!(
+                "  To launch from PATH instead, remove the stale binary or put {} earlier on PATH",
+                current
+                    .parent()
+                    .map(|p| p.display().to_string())
+                    .unwrap_or_default()
+                    .cyan()
+            );
+        }
+        (Some(on_path), _) => {
+            println!(
+                "{} `mobilecli` on PATH resolves to {}",
+                "✓".green(),
+                on_path.display().to_string().dimmed()
+            );
+        }
+        (None, None) => {}
+    }
+
+    if problems == 0 {
+        println!();
+        println!("{}", "No problems found.".green());
+    }
+
+    Ok(())
+}
+
+/// The executable a hook should launch directly instead of looking up
+/// `mobilecli` on PATH: this binary, when PATH is missing it or resolves to a
+/// different (stale) one. `None` means PATH is fine, or this binary's own
+/// location is unknown.
+fn hook_pin<'a>(on_path: Option<&Path>, current: Option<&'a Path>) -> Option<&'a Path> {
+    current.filter(|current| on_path != Some(*current))
+}
+
+/// Extract the sentinel-wrapped hook block (markers included) from a file.
+fn installed_block(content: &str) -> Option<String> {
+    let mut block = String::new();
+    let mut inside = false;
+    for line in content.lines() {
+        if !inside && (line.trim() == BEGIN_MARKER || line.trim() == BEGIN_MARKER_PS) {
+            inside = true;
+        }
+        if inside {
+            block.push_str(line);
+            block.push('\n');
+            if line.trim() == END_MARKER || line.trim() == END_MARKER_PS {
+                return Some(block);
+            }
+        }
+    }
+    inside.then_some(block)
+}
+
 // ── Shell target detection ────────────────────────────────────────────────────
 
 struct ShellTarget {
     shell_name: &'static str,
     path: PathBuf,
-    snippet: String,
+    snippet: fn(Option<&Path>) -> String,
 }
 
 /// Detect which shell the user is running and return the target rc file(s).
@@ -249,7 +467,7 @@ fn detect_shell_targets() -> Vec<ShellTarget> {
             targets.push(ShellTarget {
                 shell_name: "bash",
                 path: home.join(".bashrc"),
-                snippet: bash_snippet(),
+                snippet: bash_snippet,
             });
             // macOS login shells read .bash_profile, not .bashrc.
             // Add to .bash_profile as well if we're on macOS.
@@ -258,7 +476,7 @@ fn detect_shell_targets() -> Vec<ShellTarget> {
                 targets.push(ShellTarget {
                     shell_name: "bash (login)",
                     path: home.join(".bash_profile"),
-                    snippet: bash_snippet(),
+                    snippet: bash_snippet,
                 });
             }
         }
@@ -266,14 +484,28 @@ fn detect_shell_targets() -> Vec<ShellTarget> {
             targets.push(ShellTarget {
                 shell_name: "zsh",
                 path: home.join(".zshrc"),
-                snippet: bash_snippet(), // zsh uses the same POSIX syntax
+                snippet: bash_snippet, // zsh uses the same POSIX syntax
             });
         }
         "fish" => {
             targets.push(ShellTarget {
                 shell_name: "fish",
                 path: home.join(".config/fish/config.fish"),
-                snippet: fish_snippet(),
+                snippet: fish_snippet,
+            });
+        }
+        "nu" | "nushell" => {
+            targets.push(ShellTarget {
+                shell_name: "nushell",
+                path: home.join(".config/nushell/config.nu"),
+                snippet: nushell_snippet,
+            });
+        }
+        "elvish" => {
+            targets.push(ShellTarget {
+                shell_name: "elvish",
+                path: home.join(".config/elvish/rc.elv"),
+                snippet: elvish_snippet,
             });
         }
         "powershell" | "pwsh" => {
@@ -281,7 +513,7 @@ fn detect_shell_targets() -> Vec<ShellTarget> {
                 targets.push(ShellTarget {
                     shell_name: "PowerShell",
                     path: profile,
-                    snippet: powershell_snippet(),
+                    snippet: powershell_snippet,
                 });
             }
         }
@@ -292,13 +524,13 @@ fn detect_shell_targets() -> Vec<ShellTarget> {
                 targets.push(ShellTarget {
                     shell_name: "zsh",
                     path: home.join(".zshrc"),
-                    snippet: bash_snippet(),
+                    snippet: bash_snippet,
                 });
             } else if home.join(".bashrc").exists() {
                 targets.push(ShellTarget {
                     shell_name: "bash",
                     path: home.join(".bashrc"),
-                    snippet: bash_snippet(),
+                    snippet: bash_snippet,
                 });
             }
         }
@@ -318,27 +550,37 @@ fn all_possible_targets() -> Vec<ShellTarget> {
         ShellTarget {
             shell_name: "bash",
             path: home.join(".bashrc"),
-            snippet: bash_snippet(),
+            snippet: bash_snippet,
         },
         ShellTarget {
             shell_name: "bash (login)",
             path: home.join(".bash_profile"),
-            snippet: bash_snippet(),
+            snippet: bash_snippet,
         },
         ShellTarget {
             shell_name: "bash (profile)",
             path: home.join(".profile"),
-            snippet: bash_snippet(),
+            snippet: bash_snippet,
         },
         ShellTarget {
             shell_name: "zsh",
             path: home.join(".zshrc"),
-            snippet: bash_snippet(),
+            snippet: bash_snippet,
         },
         ShellTarget {
             shell_name: "fish",
             path: home.join(".config/fish/config.fish"),
-            snippet: fish_snippet(),
+            snippet: fish_snippet,
+        },
+        ShellTarget {
+            shell_name: "nushell",
+            path: home.join(".config/nushell/config.nu"),
+            snippet: nushell_snippet,
+        },
+        ShellTarget {
+            shell_name: "elvish",
+            path: home.join(".config/elvish/rc.elv"),
+            snippet: elvish_snippet,
         },
     ];
 
@@ -346,7 +588,7 @@ fn all_possible_targets() -> Vec<ShellTarget> {
         targets.push(ShellTarget {
             shell_name: "PowerShell",
             path: profile,
-            snippet: powershell_snippet(),
+            snippet: powershell_snippet,
         });
     }
 
@@ -407,8 +649,10 @@ fn powershell_profile_path(home: &std::path::Path) -> Option<PathBuf> {
 
 // ── File manipulation ─────────────────────────────────────────────────────────
 
-/// Install the snippet into the target file. Returns Ok(true) if written, Ok(false) if already present.
-fn install_into(target: &ShellTarget) -> std::io::Result<bool> {
+/// Install the snippet into the target file, launching `exe` instead of the
+/// `mobilecli` on PATH when given. Returns Ok(true) if written, Ok(false) if
+/// already present.
+fn install_into(target: &ShellTarget, exe: Option<&Path>) -> std::io::Result<bool> {
     // Read existing content (or empty if file doesn't exist yet).
     let existing = if target.path.exists() {
         std::fs::read_to_string(&target.path)?
@@ -434,7 +678,7 @@ fn install_into(target: &ShellTarget) -> std::io::Result<bool> {
     if !content.is_empty() {
         content.push('\n');
     }
-    content.push_str(&target.snippet);
+    content.push_str(&(target.snippet)(exe));
     content.push('\n');
 
     std::fs::write(&target.path, content)?;
@@ -502,9 +746,68 @@ pub fn install_quiet() -> bool {
     let targets = detect_shell_targets();
     let mut any_installed = false;
     for target in &targets {
-        if let Ok(true) = install_into(target) {
+        if let Ok(true) = install_into(target, None) {
             any_installed = true;
         }
     }
     any_installed
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        bash_snippet, fish_snippet, hook_pin, install_into, installed_block, remove_from,
+        ShellTarget,
+    };
+    use std::path::Path;
+
+    #[test]
+    fn hook_is_pinned_only_when_path_does_not_resolve_to_this_binary() {
+        let current = Path::new("/opt/new/mobilecli");
+        let stale = Path::new("/usr/local/bin/mobilecli");
+
+        assert_eq!(hook_pin(Some(current), Some(current)), None);
+        assert_eq!(hook_pin(Some(stale), Some(current)), Some(current));
+        assert_eq!(hook_pin(None, Some(current)), Some(current));
+        assert_eq!(hook_pin(Some(stale), None), None);
+        assert_eq!(hook_pin(None, None), None);
+    }
+
+    #[test]
+    fn pinned_snippets_quote_the_executable() {
+        let exe = Path::new("/home/me/it's here/mobilecli");
+        let bash = bash_snippet(Some(exe));
+        assert!(bash.contains(r"exec '/home/me/it'\''s here/mobilecli'"));
+        assert!(bash.contains(r"command -v '/home/me/it'\''s here/mobilecli'"));
+        let fish = fish_snippet(Some(exe));
+        assert!(fish.contains(r"exec '/home/me/it\'s here/mobilecli'"));
+        assert!(bash_snippet(None).contains("exec mobilecli\n"));
+    }
+
+    #[test]
+    fn repair_replaces_a_stale_block_and_keeps_the_rest_of_the_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = ShellTarget {
+            shell_name: "bash",
+            path: dir.path().join(".bashrc"),
+            snippet: bash_snippet,
+        };
+        std::fs::write(&target.path, "alias ll='ls -l'\n").unwrap();
+        assert!(install_into(&target, None).unwrap());
+        assert!(!install_into(&target, None).unwrap());
+
+        let exe = Path::new("/opt/new/mobilecli");
+        let content = std::fs::read_to_string(&target.path).unwrap();
+        let block = installed_block(&content).unwrap();
+        assert_ne!(block.trim_end(), bash_snippet(Some(exe)).trim_end());
+
+        assert!(remove_from(&target).unwrap());
+        assert!(install_into(&target, Some(exe)).unwrap());
+        let content = std::fs::read_to_string(&target.path).unwrap();
+        assert!(content.starts_with("alias ll='ls -l'\n"));
+        assert_eq!(
+            installed_block(&content).unwrap().trim_end(),
+            bash_snippet(Some(exe)).trim_end()
+        );
+    }
+}